@@ -0,0 +1,129 @@
+//! Benchmarks `transform_html` on a generated page of about 5 MB, heavy in the
+//! head, meta, noscript, style and script elements it always strips, and with
+//! only_main_content on a page whose footer holds 2,000 nodes. A small page with
+//! include_tags and exclude_tags selectors measures the per-call overhead, such as
+//! selector compilation, that dominates on short documents.
+//!
+//! The pages are built here rather than checked in. To compare revisions, run
+//! `cargo bench --bench transform_html -- --save-baseline before` on the old one and
+//! `cargo bench --bench transform_html -- --baseline before` on the new one.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use firecrawl_rs::{transform_html, TransformHtmlOptions};
+use serde_json::json;
+
+const FIXTURE_BYTES: usize = 5 * 1024 * 1024;
+
+/// A news-style page of at least `size` bytes with scripts, styles and meta tags
+/// spread through the body, as on ad- and tracking-heavy sites.
+fn fixture_page(size: usize) -> String {
+  let mut html = String::with_capacity(size + 4096);
+  html.push_str("<!DOCTYPE html><html><head><title>Fixture</title>");
+  for i in 0..200 {
+    html.push_str(&format!(
+      "<meta name=\"m{i}\" content=\"v{i}\"><link rel=\"preload\" href=\"/a{i}.js\">\
+       <style>.c{i} {{ color: red; }}</style><script>window.t{i} = {i};</script>"
+    ));
+  }
+  html.push_str("</head><body><nav><a href=\"/\">Home</a><a href=\"/news\">News</a></nav>");
+
+  let mut i = 0;
+  while html.len() < size {
+    html.push_str(&format!(
+      "<article class=\"c{n}\"><h2>Story {i}</h2>\
+       <meta itemprop=\"position\" content=\"{i}\">\
+       <p>Paragraph one of story {i}, with <a href=\"/s/{i}\">a link</a> and <b>bold</b> text.</p>\
+       <script>track({i});</script><noscript><img src=\"/px/{i}.gif\"></noscript>\
+       <p>Paragraph two of story {i} runs a little longer to carry more text content.</p>\
+       <style>.s{i} {{ margin: 0; }}</style>\
+       <aside><div class=\"ad\">Ad {i}</div><script>ads.push({i});</script></aside></article>",
+      n = i % 200
+    ));
+    i += 1;
+  }
+
+  html.push_str("<footer><p>Footer</p></footer></body></html>");
+  html
+}
+
+/// A short article followed by a footer of `nodes` elements: link lists nested in
+/// columns, the shape that makes main-content removal rescan the same subtrees.
+fn footer_page(nodes: usize) -> String {
+  let mut html = String::from(
+    "<html><body><main><article><h1>Title</h1><p>Article text.</p></article></main><footer>",
+  );
+  for column in 0..nodes / 20 {
+    html.push_str("<div class=\"col\"><nav><ul>");
+    for link in 0..6 {
+      html.push_str(&format!(
+        "<li><a href=\"/f/{column}/{link}\"><span>Link {link}</span></a></li>"
+      ));
+    }
+    html.push_str("</ul></nav></div>");
+  }
+  html.push_str("<div class=\"swoogo-logo\">Logo</div></footer></body></html>");
+  html
+}
+
+const SMALL_PAGE: &str = r#"<html><head><title>Small</title></head><body>
+<header><nav><a href="/">Home</a></nav></header>
+<main><article><h1>Title</h1><p class="lead">Lead <a href="/more">more</a></p>
+<img src="/a.png" srcset="/a.png 1x, /a@2x.png 2x"><div class="share">Share</div></article></main>
+<footer><p>Footer</p></footer></body></html>"#;
+
+fn options(html: &str, exclude_tags: &[&str], only_main_content: bool) -> TransformHtmlOptions {
+  serde_json::from_value(json!({
+    "html": html,
+    "url": "https://example.com/news/",
+    "exclude_tags": exclude_tags,
+    "only_main_content": only_main_content,
+  }))
+  .expect("benchmark options are valid TransformHtmlOptions")
+}
+
+fn bench_transform_html(c: &mut Criterion) {
+  let html = fixture_page(FIXTURE_BYTES);
+  let runtime = tokio::runtime::Runtime::new().expect("tokio runtime starts");
+
+  let mut group = c.benchmark_group("transform_html_5mb");
+  group.sample_size(10);
+  group.bench_function("default", |b| {
+    b.iter(|| {
+      runtime
+        .block_on(transform_html(options(&html, &[], false)))
+        .unwrap()
+    })
+  });
+  group.bench_function("exclude_tag_names", |b| {
+    b.iter(|| {
+      runtime
+        .block_on(transform_html(options(
+          &html,
+          &["nav", "aside", "footer"],
+          false,
+        )))
+        .unwrap()
+    })
+  });
+  group.finish();
+
+  c.bench_function("transform_html_small_selectors", |b| {
+    b.iter(|| {
+      let mut opts = options(SMALL_PAGE, &[".share", "article > div"], true);
+      opts.include_tags = vec!["main".to_string(), "p.lead".to_string()];
+      runtime.block_on(transform_html(opts)).unwrap()
+    })
+  });
+
+  let html = footer_page(2000);
+  c.bench_function("transform_html_footer_2000_nodes", |b| {
+    b.iter(|| {
+      runtime
+        .block_on(transform_html(options(&html, &[], true)))
+        .unwrap()
+    })
+  });
+}
+
+criterion_group!(benches, bench_transform_html);
+criterion_main!(benches);
@@ -0,0 +1,106 @@
+//! Charset detection and transcoding for raw HTML response bodies.
+//!
+//! The encoding is taken from the byte order mark, then the `charset` parameter of the
+//! Content-Type header, then a `<meta charset>` or http-equiv Content-Type declaration
+//! in the first 1024 bytes. Without any of those, bytes that are valid UTF-8 are read
+//! as UTF-8 and anything else as windows-1252, as browsers do.
+
+use std::sync::LazyLock;
+
+use encoding_rs::{Encoding, UTF_8, WINDOWS_1252};
+use regex::bytes::Regex;
+
+/// How far into the document `<meta>` charset declarations are looked for.
+const META_PRESCAN_BYTES: usize = 1024;
+
+static META_CHARSET_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+  Regex::new(r#"(?i)<meta\s[^>]*?charset\s*=\s*["']?\s*([a-z0-9_:.+-]+)"#)
+    .expect("META_CHARSET_REGEX is a valid static regex pattern")
+});
+
+/// The encoding named by the `charset` parameter of a Content-Type header value.
+fn header_encoding(content_type: &str) -> Option<&'static Encoding> {
+  content_type.split(';').skip(1).find_map(|param| {
+    let (name, value) = param.split_once('=')?;
+    if !name.trim().eq_ignore_ascii_case("charset") {
+      return None;
+    }
+    Encoding::for_label(value.trim().trim_matches(['"', '\'']).as_bytes())
+  })
+}
+
+/// The encoding declared by a `<meta>` tag near the start of the document. A
+/// declared UTF-16 can't be right for a document that was read as ASCII, so like
+/// browsers this yields UTF-8 instead.
+fn meta_encoding(bytes: &[u8]) -> Option<&'static Encoding> {
+  let head = &bytes[..bytes.len().min(META_PRESCAN_BYTES)];
+  META_CHARSET_REGEX
+    .captures_iter(head)
+    .find_map(|x| Encoding::for_label(&x[1]))
+    .map(Encoding::output_encoding)
+}
+
+/// Decode an HTML response body, returning the text and the encoding it was read with.
+pub(crate) fn decode_html(bytes: &[u8], content_type: Option<&str>) -> (String, &'static Encoding) {
+  let (encoding, bom_length) = match Encoding::for_bom(bytes) {
+    Some(x) => x,
+    None => {
+      let encoding = content_type
+        .and_then(header_encoding)
+        .or_else(|| meta_encoding(bytes))
+        .unwrap_or_else(|| {
+          if std::str::from_utf8(bytes).is_ok() {
+            UTF_8
+          } else {
+            WINDOWS_1252
+          }
+        });
+      (encoding, 0)
+    }
+  };
+
+  let (text, _) = encoding.decode_without_bom_handling(&bytes[bom_length..]);
+  (text.into_owned(), encoding)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_decode_html() {
+    // "Привет" in windows-1251, declared only in the header.
+    let cp1251 = b"<p>\xcf\xf0\xe8\xe2\xe5\xf2</p>";
+    let (text, encoding) = decode_html(cp1251, Some("text/html; Charset=\"windows-1251\""));
+    assert_eq!(text, "<p>Привет</p>");
+    assert_eq!(encoding.name(), "windows-1251");
+
+    // "日本" in Shift_JIS, declared by http-equiv.
+    let sjis = b"<html><head><meta http-equiv=\"Content-Type\" content=\"text/html; charset=shift_jis\"></head><body>\x93\xfa\x96\x7b</body></html>";
+    let (text, encoding) = decode_html(sjis, Some("text/html"));
+    assert!(text.contains("<body>日本</body>"));
+    assert_eq!(encoding.name(), "Shift_JIS");
+
+    // "中文" in GBK, declared by meta charset.
+    let (text, encoding) = decode_html(b"<meta charset='gbk'><p>\xd6\xd0\xce\xc4</p>", None);
+    assert_eq!(text, "<meta charset='gbk'><p>中文</p>");
+    assert_eq!(encoding.name(), "GBK");
+
+    // A BOM beats the header, and is stripped.
+    let (text, encoding) = decode_html(
+      b"\xef\xbb\xbf<p>\xc3\xa9</p>",
+      Some("text/html; charset=iso-8859-1"),
+    );
+    assert_eq!(text, "<p>é</p>");
+    assert_eq!(encoding.name(), "UTF-8");
+
+    // Without a declaration, invalid UTF-8 falls back to windows-1252.
+    let (text, encoding) = decode_html(b"<p>caf\xe9</p>", None);
+    assert_eq!(text, "<p>café</p>");
+    assert_eq!(encoding.name(), "windows-1252");
+    assert_eq!(
+      decode_html("<p>café</p>".as_bytes(), None).1.name(),
+      "UTF-8"
+    );
+  }
+}
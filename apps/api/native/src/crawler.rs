@@ -735,6 +735,372 @@ pub async fn process_sitemap(xml_content: String) -> Result<SitemapProcessingRes
   res.map_err(|e| Error::new(Status::GenericFailure, format!("Parse sitemap error: {e}")))
 }
 
+#[derive(Default)]
+#[napi(object)]
+pub struct SitemapParseOptions {
+  /// Stop after this many URLs and child sitemaps. Defaults to 50,000, the limit of
+  /// the sitemap protocol.
+  pub max_urls: Option<u32>,
+  /// Whether the input is gzip-compressed. Detected from the gzip magic bytes when
+  /// not set.
+  pub gzip: Option<bool>,
+}
+
+#[derive(Serialize, Debug, Default)]
+#[napi(object)]
+pub struct SitemapUrlDetails {
+  pub loc: String,
+  pub lastmod: Option<String>,
+  pub changefreq: Option<String>,
+  pub priority: Option<f64>,
+}
+
+#[derive(Serialize, Debug, Default)]
+#[napi(object)]
+pub struct SitemapParseResult {
+  /// Whether the document is a sitemap index, whose `sitemaps` should be fetched next.
+  pub is_index: bool,
+  pub urls: Vec<SitemapUrlDetails>,
+  /// Child sitemap URLs of a sitemap index.
+  pub sitemaps: Vec<String>,
+  /// Whether parsing stopped at `max_urls`.
+  pub truncated: bool,
+}
+
+const DEFAULT_MAX_SITEMAP_URLS: u32 = 50_000;
+/// Longest value kept for a single sitemap field; the rest is dropped.
+const MAX_SITEMAP_FIELD_LEN: usize = 8 * 1024;
+
+#[derive(Clone, Copy, PartialEq)]
+enum SitemapField {
+  Loc,
+  Lastmod,
+  Changefreq,
+  Priority,
+}
+
+fn _parse_sitemap<R: std::io::BufRead>(
+  input: R,
+  max_urls: usize,
+) -> std::result::Result<SitemapParseResult, String> {
+  use quick_xml::events::Event;
+
+  let mut reader = quick_xml::Reader::from_reader(input);
+  reader.trim_text(true);
+
+  let mut out = SitemapParseResult::default();
+  let mut buf = Vec::new();
+  let mut root_seen = false;
+  // Element depth: 1 for the root, 2 for `<url>`/`<sitemap>`, 3 for their fields.
+  let mut depth: usize = 0;
+  let mut entry: Option<SitemapUrlDetails> = None;
+  let mut field: Option<SitemapField> = None;
+  let mut text = String::new();
+
+  loop {
+    buf.clear();
+    let event = reader
+      .read_event_into(&mut buf)
+      .map_err(|e| format!("Invalid XML at byte {}: {e}", reader.buffer_position()))?;
+    match event {
+      Event::Start(e) => {
+        depth += 1;
+        // Namespace prefixes are ignored, so documents without the sitemap namespace
+        // or with a custom prefix parse the same.
+        let name = e.local_name();
+        match depth {
+          1 => {
+            root_seen = true;
+            out.is_index = match name.as_ref() {
+              b"urlset" => false,
+              b"sitemapindex" => true,
+              _ => return Err("Not a sitemap: root must be urlset or sitemapindex".to_string()),
+            };
+          }
+          2 if matches!(name.as_ref(), b"url" | b"sitemap") => {
+            entry = Some(SitemapUrlDetails::default());
+          }
+          // Extensions such as `<image:image><image:loc>` sit deeper and are skipped.
+          3 if entry.is_some() => {
+            field = match name.as_ref() {
+              b"loc" => Some(SitemapField::Loc),
+              b"lastmod" => Some(SitemapField::Lastmod),
+              b"changefreq" => Some(SitemapField::Changefreq),
+              b"priority" => Some(SitemapField::Priority),
+              _ => None,
+            };
+            text.clear();
+          }
+          _ => {}
+        }
+      }
+      Event::Text(e) if field.is_some() => {
+        let value = e.unescape().map_err(|e| e.to_string())?;
+        if text.len() + value.len() <= MAX_SITEMAP_FIELD_LEN {
+          text.push_str(&value);
+        }
+      }
+      Event::CData(e) if field.is_some() => {
+        let value = String::from_utf8_lossy(&e);
+        if text.len() + value.len() <= MAX_SITEMAP_FIELD_LEN {
+          text.push_str(&value);
+        }
+      }
+      Event::End(_) => {
+        if depth == 3 {
+          if let (Some(current), Some(f)) = (entry.as_mut(), field.take()) {
+            let value = text.trim().to_string();
+            match f {
+              SitemapField::Loc => current.loc = value,
+              SitemapField::Lastmod => current.lastmod = Some(value).filter(|x| !x.is_empty()),
+              SitemapField::Changefreq => {
+                current.changefreq = Some(value.to_ascii_lowercase()).filter(|x| !x.is_empty())
+              }
+              SitemapField::Priority => current.priority = value.parse().ok(),
+            }
+          }
+        } else if depth == 2 {
+          if let Some(finished) = entry.take().filter(|x| !x.loc.is_empty()) {
+            if out.urls.len() + out.sitemaps.len() >= max_urls {
+              out.truncated = true;
+              break;
+            }
+            if out.is_index {
+              out.sitemaps.push(finished.loc);
+            } else {
+              out.urls.push(finished);
+            }
+          }
+        }
+        depth = depth.saturating_sub(1);
+      }
+      Event::Eof => break,
+      _ => {}
+    }
+  }
+
+  if !root_seen {
+    return Err("Not a sitemap: no root element".to_string());
+  }
+  Ok(out)
+}
+
+/// Parse a sitemap or sitemap index, optionally gzip-compressed, without building a
+/// tree of the document, so large sitemaps parse in bounded memory.
+#[napi]
+pub async fn parse_sitemap(
+  xml: Buffer,
+  options: Option<SitemapParseOptions>,
+) -> Result<SitemapParseResult> {
+  let xml = xml.to_vec();
+  let res = task::spawn_blocking(move || {
+    let options = options.unwrap_or_default();
+    let max_urls = options.max_urls.unwrap_or(DEFAULT_MAX_SITEMAP_URLS) as usize;
+    let bytes = xml.as_slice();
+    let gzip = options
+      .gzip
+      .unwrap_or_else(|| bytes.starts_with(&[0x1f, 0x8b]));
+
+    if gzip {
+      let decoder = flate2::read::GzDecoder::new(bytes);
+      _parse_sitemap(std::io::BufReader::new(decoder), max_urls)
+    } else {
+      _parse_sitemap(bytes, max_urls)
+    }
+  })
+  .await
+  .map_err(|e| {
+    napi::Error::new(
+      napi::Status::GenericFailure,
+      format!("parse_sitemap join error: {e}"),
+    )
+  })?;
+
+  res.map_err(|e| Error::new(Status::GenericFailure, format!("Parse sitemap error: {e}")))
+}
+
+#[derive(Default)]
+#[napi(object)]
+pub struct NormalizeUrlOptions {
+  /// Query parameter names to remove, compared case-insensitively. A trailing `*`
+  /// matches any name with that prefix. Replaces `DEFAULT_STRIPPED_PARAMS`.
+  pub strip_params: Option<Vec<String>>,
+  /// Sort the remaining query parameters by name. Defaults to true.
+  pub sort_query: Option<bool>,
+  /// Drop the fragment. Defaults to true.
+  pub drop_fragment: Option<bool>,
+  /// Remove trailing slashes from paths other than `/`. Defaults to false, or to true
+  /// with `aggressive`.
+  pub remove_trailing_slash: Option<bool>,
+  /// Normalize harder for crawl deduplication: also strip `AGGRESSIVE_STRIPPED_PARAMS`,
+  /// which some sites use for more than tracking, and remove trailing slashes unless
+  /// `remove_trailing_slash` is false. Defaults to false.
+  pub aggressive: Option<bool>,
+}
+
+/// Tracking and session parameters removed by `normalize_url` by default.
+const DEFAULT_STRIPPED_PARAMS: [&str; 18] = [
+  "utm_*",
+  "fbclid",
+  "gclid",
+  "dclid",
+  "gbraid",
+  "wbraid",
+  "msclkid",
+  "mc_eid",
+  "mc_cid",
+  "yclid",
+  "igshid",
+  "twclid",
+  "ttclid",
+  "_ga",
+  "_gl",
+  "_hsenc",
+  "phpsessid",
+  "jsessionid",
+];
+
+/// Parameters removed by `normalize_url` only with `aggressive`. They are mostly
+/// referral tracking, but functional on some sites, such as GitLab's `?ref=`.
+const AGGRESSIVE_STRIPPED_PARAMS: [&str; 2] = ["ref", "source"];
+
+static PATH_PARAMETER_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+  Regex::new(r";([^/;=]+)=[^/;]*").expect("PATH_PARAMETER_REGEX is a valid static regex pattern")
+});
+
+fn is_stripped_param<S: AsRef<str>>(name: &str, patterns: &[S]) -> bool {
+  let name = name.to_ascii_lowercase();
+  patterns.iter().any(|x| {
+    let pattern = x.as_ref().to_ascii_lowercase();
+    match pattern.strip_suffix('*') {
+      Some(prefix) => name.starts_with(prefix),
+      None => name == pattern,
+    }
+  })
+}
+
+/// Uppercase the hex digits of percent escapes and decode escaped unreserved
+/// characters (`A-Z a-z 0-9 - . _ ~`), as described in RFC 3986 section 6.2.2.
+fn normalize_percent_encoding(value: &str) -> String {
+  let bytes = value.as_bytes();
+  let mut out = String::with_capacity(value.len());
+  let mut i = 0;
+
+  while i < bytes.len() {
+    let decoded = (bytes[i] == b'%')
+      .then(|| value.get(i + 1..i + 3))
+      .flatten()
+      .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+    match decoded {
+      Some(b) if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b'_' | b'~') => {
+        out.push(b as char);
+        i += 3;
+      }
+      Some(b) => {
+        out.push_str(&format!("%{b:02X}"));
+        i += 3;
+      }
+      None => {
+        let c = value[i..].chars().next().unwrap_or_default();
+        out.push(c);
+        i += c.len_utf8();
+      }
+    }
+  }
+
+  out
+}
+
+/// Normalize a URL for deduplication. Parsing already lowercases the scheme and
+/// host, converts IDN hosts to punycode, drops default ports and resolves dot
+/// segments; on top of that, tracking parameters (also as `;name=value` path
+/// parameters) are removed, percent escapes are normalized and the query is sorted.
+pub(crate) fn _normalize_url(
+  url: &str,
+  options: &NormalizeUrlOptions,
+) -> std::result::Result<String, String> {
+  let mut url = Url::parse(url.trim()).map_err(|e| e.to_string())?;
+  if url.cannot_be_a_base() {
+    return Ok(url.to_string());
+  }
+
+  let aggressive = options.aggressive.unwrap_or(false);
+  let strip = |name: &str| {
+    let listed = match &options.strip_params {
+      Some(params) => is_stripped_param(name, params),
+      None => is_stripped_param(name, &DEFAULT_STRIPPED_PARAMS),
+    };
+    listed || (aggressive && is_stripped_param(name, &AGGRESSIVE_STRIPPED_PARAMS))
+  };
+
+  let path = PATH_PARAMETER_REGEX.replace_all(url.path(), |caps: &regex::Captures| {
+    if strip(&caps[1]) {
+      String::new()
+    } else {
+      caps[0].to_string()
+    }
+  });
+  let mut path = normalize_percent_encoding(&path);
+  if options.remove_trailing_slash.unwrap_or(aggressive) && path != "/" {
+    path.truncate(path.trim_end_matches('/').len().max(1));
+  }
+  url.set_path(&path);
+
+  if let Some(query) = url.query() {
+    let mut params: Vec<String> = query
+      .split('&')
+      .filter(|x| !x.is_empty() && !strip(x.split('=').next().unwrap_or_default()))
+      .map(normalize_percent_encoding)
+      .collect();
+    if options.sort_query.unwrap_or(true) {
+      // Stable, so repeated parameters keep their relative order.
+      params.sort_by(|a, b| a.split('=').next().cmp(&b.split('=').next()));
+    }
+    let query = params.join("&");
+    url.set_query((!query.is_empty()).then_some(query.as_str()));
+  }
+
+  if options.drop_fragment.unwrap_or(true) {
+    url.set_fragment(None);
+  } else if let Some(fragment) = url.fragment() {
+    let fragment = normalize_percent_encoding(fragment);
+    url.set_fragment(Some(&fragment));
+  }
+
+  Ok(url.to_string())
+}
+
+/// Normalize a URL for deduplication: lowercase scheme and host, drop default ports,
+/// resolve dot segments, strip tracking parameters, normalize percent escapes, sort
+/// the query and, depending on `options`, drop the fragment and trailing slashes.
+#[napi]
+pub fn normalize_url(url: String, options: Option<NormalizeUrlOptions>) -> Result<String> {
+  _normalize_url(&url, &options.unwrap_or_default())
+    .map_err(|e| Error::new(Status::InvalidArg, format!("Normalize URL error: {e}")))
+}
+
+/// Like `normalize_url` for many URLs at once. URLs that fail to parse map to `null`.
+#[napi]
+pub async fn normalize_urls(
+  urls: Vec<String>,
+  options: Option<NormalizeUrlOptions>,
+) -> Result<Vec<Option<String>>> {
+  task::spawn_blocking(move || {
+    let options = options.unwrap_or_default();
+    urls
+      .iter()
+      .map(|x| _normalize_url(x, &options).ok())
+      .collect()
+  })
+  .await
+  .map_err(|e| {
+    napi::Error::new(
+      napi::Status::GenericFailure,
+      format!("normalize_urls join error: {e}"),
+    )
+  })
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
@@ -1050,4 +1416,170 @@ mod tests {
     assert!(!is_file("page"));
     assert!(!is_file("directory/"));
   }
+
+  #[test]
+  fn test_normalize_url() {
+    let defaults = NormalizeUrlOptions::default();
+    let normalize = |url: &str| _normalize_url(url, &defaults).unwrap();
+
+    assert_eq!(
+      normalize(
+        "HTTPS://Example.COM:443/a/./b/../c?utm_source=x&b=2&UTM_Medium=y&a=1&fbclid=z#top"
+      ),
+      "https://example.com/a/c?a=1&b=2"
+    );
+    assert_eq!(
+      normalize("http://example.com:80/?gclid=1&msclkid=2&mc_eid=3"),
+      "http://example.com/"
+    );
+    assert_eq!(
+      normalize("https://example.com/cart;jsessionid=ABC123?item=1"),
+      "https://example.com/cart?item=1"
+    );
+    assert_eq!(
+      normalize("https://example.com/blog/?source=rss&ref=home&page=2"),
+      "https://example.com/blog/?page=2&ref=home&source=rss"
+    );
+    assert_eq!(normalize("https://example.com"), "https://example.com/");
+    // Repeated parameters keep their order.
+    assert_eq!(
+      normalize("https://example.com/?tag=b&id=1&tag=a"),
+      "https://example.com/?id=1&tag=b&tag=a"
+    );
+
+    // IDN hosts become punycode.
+    assert_eq!(
+      normalize("https://Bücher.example/Straße"),
+      "https://xn--bcher-kva.example/Stra%C3%9Fe"
+    );
+
+    // Percent escapes are uppercased, escaped unreserved characters are decoded.
+    assert_eq!(
+      normalize("https://example.com/%7euser/a%2fb?q=%e2%82%ac&r=%41"),
+      "https://example.com/~user/a%2Fb?q=%E2%82%AC&r=A"
+    );
+    assert_eq!(
+      normalize("https://example.com/%7Euser/a%2Fb?q=%E2%82%AC&r=A"),
+      normalize("https://example.com/%7euser/a%2fb?q=%e2%82%ac&r=%41")
+    );
+
+    let options = NormalizeUrlOptions {
+      strip_params: Some(vec!["ref".to_string(), "s_*".to_string()]),
+      sort_query: Some(false),
+      drop_fragment: Some(false),
+      remove_trailing_slash: Some(true),
+      aggressive: None,
+    };
+    assert_eq!(
+      _normalize_url(
+        "https://example.com/docs//?z=1&ref=home&utm_source=x&s_kwcid=2#Sec%3a1",
+        &options
+      )
+      .unwrap(),
+      "https://example.com/docs?z=1&utm_source=x#Sec%3A1"
+    );
+    assert_eq!(
+      _normalize_url("https://example.com/", &options).unwrap(),
+      "https://example.com/"
+    );
+
+    let aggressive = NormalizeUrlOptions {
+      aggressive: Some(true),
+      ..Default::default()
+    };
+    assert_eq!(
+      _normalize_url(
+        "https://example.com/blog/?source=rss&ref=home&page=2",
+        &aggressive
+      )
+      .unwrap(),
+      "https://example.com/blog?page=2"
+    );
+    assert_eq!(
+      _normalize_url("https://example.com", &aggressive).unwrap(),
+      "https://example.com/"
+    );
+    let keep_slash = NormalizeUrlOptions {
+      remove_trailing_slash: Some(false),
+      aggressive: Some(true),
+      ..Default::default()
+    };
+    assert_eq!(
+      _normalize_url("https://example.com/blog/?ref=home", &keep_slash).unwrap(),
+      "https://example.com/blog/"
+    );
+
+    assert!(_normalize_url("/relative", &defaults).is_err());
+  }
+
+  #[test]
+  fn test_parse_sitemap_streaming() {
+    let urlset = "\u{feff}<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<urlset>
+  <url>
+    <loc> https://example.com/a?x=1&amp;y=2 </loc>
+    <lastmod>2024-05-01</lastmod>
+    <changefreq>Weekly</changefreq>
+    <priority>0.8</priority>
+  </url>
+  <url><loc><![CDATA[https://example.com/b]]></loc><priority>high</priority></url>
+  <url><lastmod>2024-01-01</lastmod></url>
+  <url><loc>https://example.com/c</loc>
+    <image:image><image:loc>https://example.com/c.png</image:loc></image:image></url>
+</urlset>";
+
+    let result = _parse_sitemap(urlset.as_bytes(), 100).unwrap();
+    assert!(!result.is_index && !result.truncated);
+    type Entry<'a> = (&'a str, Option<&'a str>, Option<&'a str>, Option<f64>);
+    let urls: Vec<Entry> = result
+      .urls
+      .iter()
+      .map(|x| {
+        (
+          x.loc.as_str(),
+          x.lastmod.as_deref(),
+          x.changefreq.as_deref(),
+          x.priority,
+        )
+      })
+      .collect();
+    assert_eq!(
+      urls,
+      vec![
+        (
+          "https://example.com/a?x=1&y=2",
+          Some("2024-05-01"),
+          Some("weekly"),
+          Some(0.8)
+        ),
+        ("https://example.com/b", None, None, None),
+        ("https://example.com/c", None, None, None),
+      ]
+    );
+
+    let capped = _parse_sitemap(urlset.as_bytes(), 2).unwrap();
+    assert_eq!(capped.urls.len(), 2);
+    assert!(capped.truncated);
+
+    let index = r#"<sm:sitemapindex xmlns:sm="http://www.sitemaps.org/schemas/sitemap/0.9">
+  <sm:sitemap><sm:loc>https://example.com/sitemap-1.xml.gz</sm:loc><sm:lastmod>2024-05-01</sm:lastmod></sm:sitemap>
+  <sm:sitemap><sm:loc>https://example.com/sitemap-2.xml.gz</sm:loc></sm:sitemap>
+</sm:sitemapindex>"#;
+    let mut compressed = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    std::io::Write::write_all(&mut compressed, index.as_bytes()).unwrap();
+    let compressed = compressed.finish().unwrap();
+    let decoder = std::io::BufReader::new(flate2::read::GzDecoder::new(&compressed[..]));
+    let result = _parse_sitemap(decoder, 100).unwrap();
+    assert!(result.is_index);
+    assert_eq!(
+      result.sitemaps,
+      vec![
+        "https://example.com/sitemap-1.xml.gz",
+        "https://example.com/sitemap-2.xml.gz"
+      ]
+    );
+
+    assert!(_parse_sitemap("<html><body></body></html>".as_bytes(), 100).is_err());
+    assert!(_parse_sitemap("".as_bytes(), 100).is_err());
+  }
 }
@@ -1,20 +1,35 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
 
-use std::sync::LazyLock;
+use std::panic::AssertUnwindSafe;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, LazyLock, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
 
-use kuchikiki::{iter::NodeEdge, parse_html, traits::TendrilSink, NodeRef};
+use kuchikiki::{
+  iter::{NodeEdge, NodeIterator},
+  parse_html,
+  traits::TendrilSink,
+  ElementData, Node, NodeDataRef, NodeRef, Selectors,
+};
+use napi::bindgen_prelude::{AsyncTask, Buffer, External, ToNapiValue, TypeName};
+use napi::{Env, Task};
 use napi_derive::napi;
 use nodesig::{get_node_signature, SignatureMode};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use tokio::task;
+use sha2::{Digest, Sha256};
+use tokio::task::{self, JoinSet};
 use url::Url;
 
 static URL_REGEX: LazyLock<Regex> =
   LazyLock::new(|| Regex::new(r#"url\(['"]?([^'")]+)['"]?\)"#).expect("URL_REGEX is a valid static regex pattern"));
 
-use crate::utils::to_napi_err;
+use crate::charset::decode_html;
+use crate::crawler::{_normalize_url, NormalizeUrlOptions};
+use crate::readability;
 
 fn _extract_base_href_from_document(
   document: &NodeRef,
@@ -24,13 +39,26 @@ fn _extract_base_href_from_document(
     .select("base[href]")
     .map_err(|_| "Failed to select base href".to_string())?
     .next()
-    .and_then(|base| base.attributes.borrow().get("href").map(|x| x.to_string()))
+    .and_then(|base| {
+      base
+        .attributes
+        .borrow()
+        .get("href")
+        .map(|x| x.trim().to_string())
+    })
+    .filter(|base| !base.is_empty())
   {
-    if let Ok(base) = url.join(&base) {
-      return Ok(base.to_string());
+    if let Ok(mut base) = url.join(&base) {
+      // javascript: and data: URLs can't act as a document base.
+      if !matches!(base.scheme(), "javascript" | "data") {
+        base.set_fragment(None);
+        return Ok(base.to_string());
+      }
     }
   }
 
+  let mut url = url.clone();
+  url.set_fragment(None);
   Ok(url.to_string())
 }
 
@@ -38,7 +66,7 @@ fn _extract_base_href(
   html: &str,
   url: &str,
 ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
-  let document = parse_html().one(html);
+  let document = _parse_html(html)?;
   let url = Url::parse(url)?;
   _extract_base_href_from_document(&document, &url)
 }
@@ -55,43 +83,344 @@ pub async fn extract_base_href(html: String, url: String) -> napi::Result<String
       )
     })?;
 
-  res.map_err(to_napi_err)
+  res.map_err(_to_napi_err)
 }
 
-/// Extract all links from HTML document.
-#[napi]
-pub async fn extract_links(html: Option<String>) -> napi::Result<Vec<String>> {
-  task::spawn_blocking(move || {
-    let html = match html {
-      Some(h) => h,
-      None => return Ok(Vec::new()),
-    };
+/// Returned when a `timeout_ms` deadline passes before the work is done.
+#[derive(Debug)]
+pub(crate) struct TimeoutError(u32);
 
-    let document = parse_html().one(html.as_str());
+impl std::fmt::Display for TimeoutError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "Timed out after {} ms", self.0)
+  }
+}
 
-    let anchors: Vec<_> = document
-      .select("a[href]")
-      .map_err(|_| to_napi_err("Failed to select links"))?
-      .collect();
+impl std::error::Error for TimeoutError {}
 
-    let mut out: Vec<String> = Vec::new();
+/// A `timeout_ms` deadline for work on the blocking pool, checked at traversal
+/// boundaries.
+struct Deadline {
+  timeout_ms: u32,
+  at: Option<Instant>,
+}
 
-    for anchor in anchors {
-      let mut href = match anchor.attributes.borrow().get("href") {
-        Some(x) => x.to_string(),
-        None => continue,
+impl Deadline {
+  fn new(timeout_ms: Option<u32>) -> Self {
+    Self {
+      timeout_ms: timeout_ms.unwrap_or(0),
+      at: timeout_ms.map(|x| Instant::now() + Duration::from_millis(x.into())),
+    }
+  }
+
+  /// Fail with a `TimeoutError` once the deadline has passed.
+  fn check(&self) -> Result<(), TimeoutError> {
+    match self.at {
+      Some(at) if Instant::now() >= at => Err(TimeoutError(self.timeout_ms)),
+      _ => Ok(()),
+    }
+  }
+}
+
+/// Convert an error into a napi error. Timeouts are reported with the `Cancelled`
+/// status and oversized inputs with `InvalidArg`, so callers can tell them apart from
+/// failures without parsing the message.
+fn _to_napi_err(error: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> napi::Error {
+  let error = error.into();
+  let status = if error.is::<TimeoutError>() {
+    napi::Status::Cancelled
+  } else if error.is::<InputTooLargeError>() {
+    napi::Status::InvalidArg
+  } else {
+    napi::Status::GenericFailure
+  };
+  napi::Error::new(status, error.to_string())
+}
+
+/// Default limit on the size of HTML passed to the parser: 64 MiB, or the value of
+/// the FIRECRAWL_NATIVE_MAX_INPUT_BYTES environment variable.
+static MAX_INPUT_BYTES: LazyLock<usize> = LazyLock::new(|| {
+  _max_input_bytes(
+    std::env::var("FIRECRAWL_NATIVE_MAX_INPUT_BYTES")
+      .ok()
+      .as_deref(),
+  )
+});
+
+/// The input limit set by `value` of FIRECRAWL_NATIVE_MAX_INPUT_BYTES, or 64 MiB when
+/// it is unset or not a number.
+fn _max_input_bytes(value: Option<&str>) -> usize {
+  value
+    .and_then(|x| x.trim().parse().ok())
+    .unwrap_or(64 * 1024 * 1024)
+}
+
+/// Returned instead of parsing a document larger than the input limit.
+#[derive(Debug)]
+pub(crate) struct InputTooLargeError {
+  size: usize,
+  limit: usize,
+}
+
+impl std::fmt::Display for InputTooLargeError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(
+      f,
+      "Input too large: {} bytes exceeds the limit of {} bytes",
+      self.size, self.limit
+    )
+  }
+}
+
+impl std::error::Error for InputTooLargeError {}
+
+/// Fail when `size` bytes of input exceed `max_input_bytes`, or `MAX_INPUT_BYTES`
+/// when that isn't given.
+fn _check_input_size(size: usize, max_input_bytes: Option<u32>) -> Result<(), InputTooLargeError> {
+  let limit = max_input_bytes.map_or(*MAX_INPUT_BYTES, |x| x as usize);
+  if size > limit {
+    return Err(InputTooLargeError { size, limit });
+  }
+  Ok(())
+}
+
+/// Parse an HTML document, refusing inputs over `max_input_bytes` before any memory
+/// is spent on the tree.
+fn _parse_html_with_limit(
+  html: &str,
+  max_input_bytes: Option<u32>,
+) -> Result<NodeRef, InputTooLargeError> {
+  _check_input_size(html.len(), max_input_bytes)?;
+  Ok(parse_html().one(html))
+}
+
+/// Parse an HTML document, refusing inputs over `MAX_INPUT_BYTES`.
+fn _parse_html(html: &str) -> Result<NodeRef, InputTooLargeError> {
+  _parse_html_with_limit(html, None)
+}
+
+#[derive(Default)]
+#[napi(object)]
+pub struct ExtractLinksOptions {
+  /// Include `<area href>` image-map targets.
+  pub include_areas: Option<bool>,
+  /// Include `<iframe src>` targets.
+  pub include_iframes: Option<bool>,
+  /// Include `<link rel="alternate" href>` targets such as feeds and translations.
+  pub include_alternate_links: Option<bool>,
+  /// Include the `<link rel="canonical" href>` target.
+  pub include_canonical: Option<bool>,
+  /// Keep `javascript:` URLs. Defaults to false.
+  pub include_javascript: Option<bool>,
+  /// Keep `mailto:` URLs. Defaults to false.
+  pub include_mailto: Option<bool>,
+  /// Keep `tel:` URLs. Defaults to false.
+  pub include_tel: Option<bool>,
+  /// Keep `data:` URLs. Defaults to false.
+  pub include_data: Option<bool>,
+  /// Keep fragment-only links such as `#section`. Defaults to false.
+  pub include_anchor_only: Option<bool>,
+  /// Resolve links against this page URL, or the document's `<base href>` when it
+  /// has one. Without it, links are returned as written.
+  pub base_url: Option<String>,
+  /// Normalize links like `normalize_url` does, after resolving them against
+  /// `base_url`, or an absolute `<base href>` when there is no `base_url`. Links that
+  /// can't be resolved are returned as they are.
+  pub normalize: Option<NormalizeUrlOptions>,
+  /// Fail with a timeout error instead of returning links when collecting them takes
+  /// longer than this. Parsing the document is not counted.
+  pub timeout_ms: Option<u32>,
+  /// Refuse larger documents with an input-too-large error. Defaults to
+  /// `MAX_INPUT_BYTES`.
+  pub max_input_bytes: Option<u32>,
+}
+
+impl ExtractLinksOptions {
+  /// Whether a link survives the scheme and anchor filters.
+  fn keeps(&self, href: &str) -> bool {
+    if href.starts_with('#') {
+      return self.include_anchor_only.unwrap_or(false);
+    }
+
+    let scheme = href
+      .split_once(':')
+      .map(|(scheme, _)| scheme.to_ascii_lowercase());
+    match scheme.as_deref() {
+      Some("javascript") => self.include_javascript.unwrap_or(false),
+      Some("mailto") => self.include_mailto.unwrap_or(false),
+      Some("tel") => self.include_tel.unwrap_or(false),
+      Some("data") => self.include_data.unwrap_or(false),
+      _ => true,
+    }
+  }
+}
+
+#[derive(Serialize)]
+#[napi(object)]
+pub struct ExtractedLink {
+  pub url: String,
+  /// Element type the URL came from: "a", "area", "iframe", "alternate" or "canonical".
+  pub source: String,
+}
+
+static HTML_ENTITY_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+  Regex::new(r"&(#[0-9]{1,7}|#[xX][0-9a-fA-F]{1,6}|amp|lt|gt|quot|apos);")
+    .expect("HTML_ENTITY_REGEX is a valid static regex pattern")
+});
+
+/// Clean up a URL-valued attribute. The parser has already decoded entities once, so
+/// anything still encoded was double-encoded in the source; decode it one more time.
+/// Tabs and newlines are legal inside attributes but not in URLs, so drop them too.
+fn _normalize_attribute_url(value: &str) -> String {
+  let value: String = value
+    .chars()
+    .filter(|c| !matches!(c, '\n' | '\r' | '\t'))
+    .collect();
+
+  HTML_ENTITY_REGEX
+    .replace_all(value.trim(), |caps: &regex::Captures| {
+      let entity = &caps[1];
+      let decoded = match entity {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        _ if entity.starts_with("#x") || entity.starts_with("#X") => {
+          u32::from_str_radix(&entity[2..], 16)
+            .ok()
+            .and_then(char::from_u32)
+        }
+        _ => entity[1..].parse::<u32>().ok().and_then(char::from_u32),
       };
+      decoded.map_or_else(|| caps[0].to_string(), |c| c.to_string())
+    })
+    .into_owned()
+}
 
-      if href.starts_with("http:/") && !href.starts_with("http://") {
-        href = format!("http://{}", &href[6..]);
-      } else if href.starts_with("https:/") && !href.starts_with("https://") {
-        href = format!("https://{}", &href[7..]);
+fn _normalize_link_href(href: &str) -> String {
+  let href = _normalize_attribute_url(href);
+  if href.starts_with("http:/") && !href.starts_with("http://") {
+    format!("http://{}", &href[6..])
+  } else if href.starts_with("https:/") && !href.starts_with("https://") {
+    format!("https://{}", &href[7..])
+  } else {
+    href.to_string()
+  }
+}
+
+fn _extract_links_detailed_from_document(
+  document: &NodeRef,
+  options: &ExtractLinksOptions,
+) -> Result<Vec<ExtractedLink>, Box<dyn std::error::Error + Send + Sync>> {
+  let deadline = Deadline::new(options.timeout_ms);
+  let base = match &options.base_url {
+    Some(base_url) => {
+      let base_url = Url::parse(base_url)?;
+      Some(Url::parse(&_extract_base_href_from_document(
+        document, &base_url,
+      )?)?)
+    }
+    None if options.normalize.is_some() => document
+      .select_first("base[href]")
+      .ok()
+      .and_then(|x| Url::parse(x.attributes.borrow().get("href")?.trim()).ok()),
+    None => None,
+  };
+  let mut selectors = vec!["a[href]"];
+  if options.include_areas.unwrap_or(false) {
+    selectors.push("area[href]");
+  }
+  if options.include_iframes.unwrap_or(false) {
+    selectors.push("iframe[src]");
+  }
+  if options.include_alternate_links.unwrap_or(false) {
+    selectors.push("link[rel~=\"alternate\"][href]");
+  }
+  if options.include_canonical.unwrap_or(false) {
+    selectors.push("link[rel~=\"canonical\"][href]");
+  }
+
+  let elements: Vec<_> = document
+    .select(&selectors.join(", "))
+    .map_err(|_| "Failed to select links")?
+    .collect();
+
+  let mut out: Vec<ExtractedLink> = Vec::new();
+
+  for element in elements {
+    deadline.check()?;
+    let tag = element.name.local.to_string();
+    let (attribute, source) = match tag.as_str() {
+      "iframe" => ("src", tag.clone()),
+      "link" => {
+        let rel = element
+          .attributes
+          .borrow()
+          .get("rel")
+          .unwrap_or_default()
+          .to_ascii_lowercase();
+        let source = if rel.split_whitespace().any(|x| x == "canonical") {
+          "canonical"
+        } else {
+          "alternate"
+        };
+        ("href", source.to_string())
       }
+      _ => ("href", tag.clone()),
+    };
 
-      out.push(href);
+    let mut href = match element.attributes.borrow().get(attribute) {
+      Some(x) => _normalize_link_href(x),
+      None => continue,
+    };
+    if !options.keeps(&href) {
+      continue;
     }
+    if let Some(resolved) = base.as_ref().and_then(|x| x.join(&href).ok()) {
+      href = resolved.to_string();
+    }
+    if let Some(normalize) = &options.normalize {
+      if let Ok(x) = _normalize_url(&href, normalize) {
+        href = x;
+      }
+    }
+
+    out.push(ExtractedLink { url: href, source });
+  }
+
+  Ok(out)
+}
+
+fn _extract_links_from_document(
+  document: &NodeRef,
+  options: &ExtractLinksOptions,
+) -> Result<Vec<String>, Box<dyn std::error::Error + Send + Sync>> {
+  Ok(
+    _extract_links_detailed_from_document(document, options)?
+      .into_iter()
+      .map(|x| x.url)
+      .collect(),
+  )
+}
 
-    Ok(out)
+/// Extract all links from HTML document. Only anchors are considered unless
+/// `options` opts into other link-bearing elements, and `javascript:`, `mailto:`,
+/// `tel:`, `data:` and fragment-only links are dropped unless `options` keeps them.
+#[napi]
+pub async fn extract_links(
+  html: Option<String>,
+  options: Option<ExtractLinksOptions>,
+) -> napi::Result<Vec<String>> {
+  task::spawn_blocking(move || {
+    let html = match html {
+      Some(h) => h,
+      None => return Ok(Vec::new()),
+    };
+
+    let options = options.unwrap_or_default();
+    let document = _parse_html_with_limit(&html, options.max_input_bytes).map_err(_to_napi_err)?;
+    _extract_links_from_document(&document, &options).map_err(_to_napi_err)
   })
   .await
   .map_err(|e| {
@@ -102,6 +431,180 @@ pub async fn extract_links(html: Option<String>) -> napi::Result<Vec<String>> {
   })?
 }
 
+#[derive(Serialize)]
+#[napi(object)]
+pub struct LinksFromBytes {
+  pub links: Vec<String>,
+  /// Name of the encoding the body was decoded with, e.g. "windows-1251".
+  pub charset: String,
+}
+
+/// Like `extract_links`, but for a raw response body. The charset is taken from a
+/// BOM, the `charset` parameter of `content_type_header`, or a `<meta>` declaration.
+#[napi]
+pub async fn extract_links_from_bytes(
+  html: Buffer,
+  content_type_header: Option<String>,
+  options: Option<ExtractLinksOptions>,
+) -> napi::Result<LinksFromBytes> {
+  let options = options.unwrap_or_default();
+  _check_input_size(html.len(), options.max_input_bytes).map_err(_to_napi_err)?;
+  let html = html.to_vec();
+  let res = task::spawn_blocking(move || {
+    let (html, encoding) = decode_html(&html, content_type_header.as_deref());
+    let document = parse_html().one(html);
+    _extract_links_from_document(&document, &options).map(|links| LinksFromBytes {
+      links,
+      charset: encoding.name().to_string(),
+    })
+  })
+  .await
+  .map_err(|e| {
+    napi::Error::new(
+      napi::Status::GenericFailure,
+      format!("extract_links_from_bytes join error: {e}"),
+    )
+  })?;
+
+  res.map_err(_to_napi_err)
+}
+
+/// Like `extract_links`, but tags each URL with the element type that produced it.
+#[napi]
+pub async fn extract_links_detailed(
+  html: String,
+  options: Option<ExtractLinksOptions>,
+) -> napi::Result<Vec<ExtractedLink>> {
+  let res = task::spawn_blocking(move || {
+    let options = options.unwrap_or_default();
+    let document = _parse_html_with_limit(&html, options.max_input_bytes)?;
+    _extract_links_detailed_from_document(&document, &options)
+  })
+  .await
+  .map_err(|e| {
+    napi::Error::new(
+      napi::Status::GenericFailure,
+      format!("extract_links_detailed join error: {e}"),
+    )
+  })?;
+
+  res.map_err(_to_napi_err)
+}
+
+#[derive(Serialize)]
+#[napi(object)]
+pub struct ClassifiedLink {
+  /// Absolute URL for web links; the raw `href` for every other category.
+  pub url: String,
+  /// One of "internal", "subdomain", "external", "mailto", "tel", "fragment-only" or
+  /// "unparseable".
+  pub category: String,
+}
+
+#[derive(Default, Serialize)]
+#[napi(object)]
+pub struct LinkCategoryCounts {
+  pub internal: u32,
+  pub subdomain: u32,
+  pub external: u32,
+  pub mailto: u32,
+  pub tel: u32,
+  pub fragment_only: u32,
+  pub unparseable: u32,
+}
+
+#[derive(Serialize)]
+#[napi(object)]
+pub struct LinkClassification {
+  pub links: Vec<ClassifiedLink>,
+  pub counts: LinkCategoryCounts,
+}
+
+/// Category of an absolute web link relative to `page`. Hosts sharing the
+/// registrable domain of the page (per the public suffix list) are subdomains; IP
+/// addresses and hosts without a registrable domain only match themselves.
+fn _web_link_category(url: &Url, page: &Url) -> &'static str {
+  match (url.host_str(), page.host_str()) {
+    (Some(host), Some(page_host)) if host == page_host => "internal",
+    (Some(host), Some(page_host)) => match (psl::domain_str(host), psl::domain_str(page_host)) {
+      (Some(domain), Some(page_domain)) if domain == page_domain => "subdomain",
+      _ => "external",
+    },
+    _ => "unparseable",
+  }
+}
+
+fn _classify_links(
+  html: &str,
+  page_url: &str,
+) -> Result<LinkClassification, Box<dyn std::error::Error + Send + Sync>> {
+  let document = _parse_html(html)?;
+  let page = Url::parse(page_url)?;
+  let base = Url::parse(&_extract_base_href_from_document(&document, &page)?)?;
+
+  let mut links = Vec::new();
+  let mut counts = LinkCategoryCounts::default();
+  for anchor in document
+    .select("a[href]")
+    .map_err(|_| "Failed to select links")?
+  {
+    let href = _normalize_link_href(anchor.attributes.borrow().get("href").unwrap_or_default());
+
+    let scheme = href
+      .split_once(':')
+      .map(|(scheme, _)| scheme.to_ascii_lowercase());
+    let (category, url) = if href.starts_with('#') {
+      ("fragment-only", href)
+    } else {
+      match scheme.as_deref() {
+        Some("mailto") => ("mailto", href),
+        Some("tel") => ("tel", href),
+        _ => match base.join(&href) {
+          Ok(url) if matches!(url.scheme(), "http" | "https") => {
+            (_web_link_category(&url, &page), url.to_string())
+          }
+          _ => ("unparseable", href),
+        },
+      }
+    };
+
+    let count = match category {
+      "internal" => &mut counts.internal,
+      "subdomain" => &mut counts.subdomain,
+      "external" => &mut counts.external,
+      "mailto" => &mut counts.mailto,
+      "tel" => &mut counts.tel,
+      "fragment-only" => &mut counts.fragment_only,
+      _ => &mut counts.unparseable,
+    };
+    *count += 1;
+    links.push(ClassifiedLink {
+      url,
+      category: category.to_string(),
+    });
+  }
+
+  Ok(LinkClassification { links, counts })
+}
+
+/// Label every anchor of the page as internal (same host as `page_url`), subdomain
+/// (same registrable domain), external, mailto, tel, fragment-only or unparseable,
+/// with per-category counts. Links that resolve to anything other than http(s), such
+/// as `javascript:`, count as unparseable. Fragment-only links are not resolved.
+#[napi]
+pub async fn classify_links(html: String, page_url: String) -> napi::Result<LinkClassification> {
+  let res = task::spawn_blocking(move || _classify_links(&html, &page_url))
+    .await
+    .map_err(|e| {
+      napi::Error::new(
+        napi::Status::GenericFailure,
+        format!("classify_links join error: {e}"),
+      )
+    })?;
+
+  res.map_err(_to_napi_err)
+}
+
 macro_rules! insert_meta_name {
   ($out:ident, $document:ident, $metaName:expr, $outName:expr) => {
     if let Some(x) = $document
@@ -140,10 +643,34 @@ macro_rules! insert_meta_property {
   };
 }
 
+/// Metadata key for an `http-equiv` value, e.g. `Content-Language` becomes
+/// `httpEquivContentLanguage`.
+fn _http_equiv_key(value: &str) -> String {
+  let mut key = "httpEquiv".to_string();
+  for part in value
+    .split(|c: char| !c.is_ascii_alphanumeric())
+    .filter(|x| !x.is_empty())
+  {
+    let part = part.to_ascii_lowercase();
+    let mut chars = part.chars();
+    if let Some(first) = chars.next() {
+      key.push(first.to_ascii_uppercase());
+      key.push_str(chars.as_str());
+    }
+  }
+  key
+}
+
 fn _extract_metadata(
   html: &str,
 ) -> Result<HashMap<String, Value>, Box<dyn std::error::Error + Send + Sync>> {
-  let document = parse_html().one(html);
+  let document = _parse_html(html)?;
+  _extract_metadata_from_document(&document)
+}
+
+fn _extract_metadata_from_document(
+  document: &NodeRef,
+) -> Result<HashMap<String, Value>, Box<dyn std::error::Error + Send + Sync>> {
   let mut out = HashMap::<String, Value>::new();
 
   let head_node = document
@@ -151,7 +678,7 @@ fn _extract_metadata(
     .map_err(|_| "Failed to select head")?
     .next();
 
-  let search_root = head_node.as_ref().map(|h| h.as_node()).unwrap_or(&document);
+  let search_root = head_node.as_ref().map(|h| h.as_node()).unwrap_or(document);
 
   if let Some(title) = search_root
     .select("title")
@@ -188,6 +715,11 @@ fn _extract_metadata(
     out.insert("language".to_string(), Value::String(lang));
   }
 
+  let has_html_lang = out
+    .get("language")
+    .and_then(Value::as_str)
+    .is_some_and(|x| !x.trim().is_empty());
+
   insert_meta_property!(out, search_root, "og:title", "ogTitle");
   insert_meta_property!(out, search_root, "og:description", "ogDescription");
   insert_meta_property!(out, search_root, "og:url", "ogUrl");
@@ -241,11 +773,14 @@ fn _extract_metadata(
     let meta = meta.as_node().as_element().unwrap();
     let attrs = meta.attributes.borrow();
 
-    if let Some(name) = attrs
+    let key = attrs
       .get("name")
       .or_else(|| attrs.get("property"))
       .or_else(|| attrs.get("itemprop"))
-    {
+      .map(|x| x.to_string())
+      .or_else(|| attrs.get("http-equiv").map(_http_equiv_key));
+
+    if let Some(name) = key.as_deref() {
       if let Some(content) = attrs.get("content") {
         if let Some(v) = out.get(name) {
           match v {
@@ -292,6 +827,49 @@ fn _extract_metadata(
     }
   }
 
+  // Some CMSes declare the language only through meta tags. These replace whatever
+  // the loop above stored for `meta[name="language"]`.
+  if !has_html_lang {
+    let language = search_root
+      .select("meta[http-equiv][content], meta[name][content]")
+      .map_err(|_| "Failed to select language meta")?
+      .filter_map(|meta| {
+        let attrs = meta.attributes.borrow();
+        let rank = if attrs
+          .get("http-equiv")
+          .is_some_and(|x| x.trim().eq_ignore_ascii_case("content-language"))
+        {
+          0
+        } else if attrs
+          .get("name")
+          .is_some_and(|x| x.trim().eq_ignore_ascii_case("language"))
+        {
+          1
+        } else {
+          return None;
+        };
+        // Content-Language may list several languages; the first one is primary.
+        let content = attrs.get("content")?.split(',').next()?.trim().to_string();
+        (!content.is_empty()).then_some((rank, content))
+      })
+      .min_by_key(|(rank, _)| *rank);
+    if let Some((_, language)) = language {
+      out.insert("language".to_string(), Value::String(language));
+    }
+  }
+
+  // viewport and generator already come through the loop above under their own
+  // names; theme-color and application-name get camelCase keys like the rest.
+  if let Some(theme_color) = document
+    .select_first("meta[name=\"theme-color\"]:not([media])")
+    .or_else(|_| document.select_first("meta[name=\"theme-color\"]"))
+    .ok()
+    .and_then(|x| x.attributes.borrow().get("content").map(|x| x.to_string()))
+  {
+    out.insert("themeColor".to_string(), Value::String(theme_color));
+  }
+  insert_meta_name!(out, document, "application-name", "applicationName");
+
   // Backfill title from og:title, twitter:title, or meta[name="title"] if primary extraction failed
   if !out.contains_key("title") {
     let fallback_title = out
@@ -308,6 +886,76 @@ fn _extract_metadata(
     }
   }
 
+  // Resource hints. hrefs are resolved against an absolute <base href> when the
+  // document has one; otherwise they are returned as written.
+  let base = document
+    .select_first("base[href]")
+    .ok()
+    .and_then(|x| x.attributes.borrow().get("href").map(|x| x.to_string()))
+    .and_then(|x| Url::parse(&x).ok());
+
+  for (rel, key) in [
+    ("preload", "preloadUrls"),
+    ("prefetch", "prefetchUrls"),
+    ("dns-prefetch", "dnsPrefetchUrls"),
+  ] {
+    let urls: Vec<Value> = search_root
+      .select(&format!("link[rel~=\"{rel}\"][href]"))
+      .map_err(|_| "Failed to select resource hints")?
+      .filter_map(|link| link.attributes.borrow().get("href").map(|x| x.to_string()))
+      .map(
+        |href| match base.as_ref().and_then(|b| b.join(&href).ok()) {
+          Some(resolved) => resolved.to_string(),
+          None => href,
+        },
+      )
+      .map(Value::String)
+      .collect();
+
+    if !urls.is_empty() {
+      out.insert(key.to_string(), Value::Array(urls));
+    }
+  }
+
+  // AMP pages mark <html> with `amp` or `⚡`; regular pages link to their AMP version.
+  // The link is resolved against <base href>, else against the canonical URL.
+  let is_amp = document.select_first("html").is_ok_and(|x| {
+    let attrs = x.attributes.borrow();
+    attrs.contains("amp") || attrs.contains("⚡")
+  });
+  out.insert("isAmp".to_string(), Value::Bool(is_amp));
+
+  if let Some(href) = search_root
+    .select_first("link[rel~=\"amphtml\" i][href]")
+    .ok()
+    .and_then(|x| {
+      x.attributes
+        .borrow()
+        .get("href")
+        .map(|x| x.trim().to_string())
+    })
+  {
+    let canonical = search_root
+      .select_first("link[rel~=\"canonical\" i][href]")
+      .ok()
+      .and_then(|x| {
+        x.attributes
+          .borrow()
+          .get("href")
+          .map(|x| x.trim().to_string())
+      })
+      .and_then(|x| Url::parse(&x).ok());
+    let amp_url = match base
+      .as_ref()
+      .or(canonical.as_ref())
+      .and_then(|x| x.join(&href).ok())
+    {
+      Some(resolved) => resolved.to_string(),
+      None => href,
+    };
+    out.insert("ampUrl".to_string(), Value::String(amp_url));
+  }
+
   Ok(out)
 }
 
@@ -320,7 +968,7 @@ pub async fn extract_metadata(html: Option<String>) -> napi::Result<HashMap<Stri
       None => return Ok(HashMap::new()),
     };
 
-    _extract_metadata(&html).map_err(to_napi_err)
+    _extract_metadata(&html).map_err(_to_napi_err)
   })
   .await
   .map_err(|e| {
@@ -331,6 +979,85 @@ pub async fn extract_metadata(html: Option<String>) -> napi::Result<HashMap<Stri
   })?
 }
 
+#[derive(Serialize)]
+#[napi(object)]
+pub struct MetadataFromBytes {
+  pub metadata: HashMap<String, Value>,
+  /// Name of the encoding the body was decoded with, e.g. "windows-1251".
+  pub charset: String,
+}
+
+/// Like `extract_metadata`, but for a raw response body. The charset is taken from a
+/// BOM, the `charset` parameter of `content_type_header`, or a `<meta>` declaration.
+#[napi]
+pub async fn extract_metadata_from_bytes(
+  html: Buffer,
+  content_type_header: Option<String>,
+) -> napi::Result<MetadataFromBytes> {
+  _check_input_size(html.len(), None).map_err(_to_napi_err)?;
+  let html = html.to_vec();
+  let res = task::spawn_blocking(move || {
+    let (html, encoding) = decode_html(&html, content_type_header.as_deref());
+    _extract_metadata(&html).map(|metadata| MetadataFromBytes {
+      metadata,
+      charset: encoding.name().to_string(),
+    })
+  })
+  .await
+  .map_err(|e| {
+    napi::Error::new(
+      napi::Status::GenericFailure,
+      format!("extract_metadata_from_bytes join error: {e}"),
+    )
+  })?;
+
+  res.map_err(_to_napi_err)
+}
+
+/// Metadata of one document of a batch, or a map holding only an `_error` message when
+/// extraction failed or panicked.
+fn _extract_metadata_item(html: &str, max_input_bytes: Option<u32>) -> HashMap<String, Value> {
+  let extract = || _extract_metadata_from_document(&_parse_html_with_limit(html, max_input_bytes)?);
+  // A panic on one pathological page must not take the rest of the batch down with it.
+  let error = match std::panic::catch_unwind(AssertUnwindSafe(extract)) {
+    Ok(Ok(metadata)) => return metadata,
+    Ok(Err(e)) => e.to_string(),
+    Err(_) => "extract_metadata panicked".to_string(),
+  };
+
+  HashMap::from([("_error".to_string(), Value::String(error))])
+}
+
+/// Extract metadata from many HTML documents in one call, in parallel on the blocking
+/// pool. Results are in input order; a failing document yields a map with only an
+/// `_error` key instead of failing the batch. Documents over `max_input_bytes`, or the
+/// default input limit, fail without being parsed.
+#[napi]
+pub async fn batch_extract_metadata(
+  items: Vec<String>,
+  max_input_bytes: Option<u32>,
+) -> napi::Result<Vec<HashMap<String, Value>>> {
+  let mut set = JoinSet::new();
+  let count = items.len();
+
+  for (i, html) in items.into_iter().enumerate() {
+    set.spawn_blocking(move || (i, _extract_metadata_item(&html, max_input_bytes)));
+  }
+
+  let mut results: Vec<HashMap<String, Value>> = (0..count).map(|_| HashMap::new()).collect();
+  while let Some(res) = set.join_next().await {
+    let (i, result) = res.map_err(|e| {
+      napi::Error::new(
+        napi::Status::GenericFailure,
+        format!("batch_extract_metadata join error: {e}"),
+      )
+    })?;
+    results[i] = result;
+  }
+
+  Ok(results)
+}
+
 const EXCLUDE_NON_MAIN_TAGS: [&str; 42] = [
   "header",
   "footer",
@@ -403,62 +1130,594 @@ pub struct TransformHtmlOptions {
   pub exclude_tags: Vec<String>,
   pub only_main_content: bool,
   pub omce_signatures: Option<Vec<String>>,
+  /// Remove `on*` event handler attributes and `javascript:` URLs. Defaults to true.
+  pub strip_inline_scripts: Option<bool>,
+  /// Remove every `data-*` attribute after content filtering. Defaults to false.
+  pub strip_data_attributes: Option<bool>,
+  /// How only_main_content finds the main content: "selectors" (default), "density"
+  /// (readability-style text density scoring), or "hybrid" (selectors, falling back to
+  /// density when they removed too little of the page).
+  pub main_content_mode: Option<String>,
+  /// What to serialize: "document" (default) for the full document, "body" for the
+  /// children of `<body>`, or "fragment" for the body children with the wrapper `<div>`
+  /// added by include_tags or density extraction unwrapped.
+  pub output: Option<String>,
+  /// After all other filtering, remove block-level elements with fewer than this many
+  /// non-whitespace characters of text, such as `<p>.</p>` or `<li>x</li>`.
+  /// Elements in `MIN_TEXT_KEPT_TAGS`, and those inside or around one, are kept.
+  pub min_text_length: Option<u32>,
+  /// Replace each `<template>` element with its content, so filtering sees it, instead
+  /// of removing it. Defaults to false.
+  pub expand_templates: Option<bool>,
+  /// Selectors removed by only_main_content in addition to `EXCLUDE_NON_MAIN_TAGS`.
+  pub additional_exclude_selectors: Option<Vec<String>>,
+  /// Selectors kept by only_main_content in addition to `FORCE_INCLUDE_MAIN_TAGS`,
+  /// together with every element containing them.
+  pub force_include_selectors: Option<Vec<String>>,
+  /// Fail with a timeout error instead of returning partial output when the transform
+  /// takes longer than this. Checked between passes and while walking the tree.
+  pub timeout_ms: Option<u32>,
+  /// Refuse larger documents with an input-too-large error. Defaults to
+  /// `MAX_INPUT_BYTES`.
+  pub max_input_bytes: Option<u32>,
+  /// Drop trailing nodes so the output is at most this many bytes, keeping the
+  /// nodes before the cut whole. The with-stats result reports when this happened.
+  pub max_output_bytes: Option<u32>,
+  /// After filtering, remove elements left without text or media, such as `<div>`
+  /// shells whose children were excluded. Defaults to true.
+  pub prune_empty_elements: Option<bool>,
+  /// Keep empty elements with an `id` or `name` when pruning, since links may target
+  /// them. Defaults to true.
+  pub prune_keep_anchors: Option<bool>,
 }
 
-struct ImageSource {
-  url: String,
-  size: f64,
-  is_x: bool,
+/// Embedded media attributes absolutized against the base href, in addition to img[src] and a[href].
+const MEDIA_URL_ATTRIBUTES: [(&str, &str); 7] = [
+  ("iframe[src]", "src"),
+  ("video[src]", "src"),
+  ("video[poster]", "poster"),
+  ("audio[src]", "src"),
+  ("source[src]", "src"),
+  ("embed[src]", "src"),
+  ("track[src]", "src"),
+];
+
+/// Compile selectors that are part of this module, one `Selectors` per entry so they
+/// can still be applied in order.
+fn _compile_static_selectors(selectors: &[&str]) -> Vec<Selectors> {
+  selectors
+    .iter()
+    .map(|x| Selectors::compile(x).unwrap_or_else(|_| panic!("{x} is a valid static selector")))
+    .collect()
 }
 
-fn _transform_html_inner(
+static EXCLUDE_NON_MAIN_SELECTORS: LazyLock<Vec<Selectors>> =
+  LazyLock::new(|| _compile_static_selectors(&EXCLUDE_NON_MAIN_TAGS));
+
+static FORCE_INCLUDE_MAIN_SELECTORS: LazyLock<Selectors> = LazyLock::new(|| {
+  Selectors::compile(&FORCE_INCLUDE_MAIN_TAGS.join(", "))
+    .expect("FORCE_INCLUDE_MAIN_TAGS are valid static selectors")
+});
+
+static MEDIA_URL_SELECTORS: LazyLock<Vec<Selectors>> = LazyLock::new(|| {
+  let selectors: Vec<_> = MEDIA_URL_ATTRIBUTES.iter().map(|(x, _)| *x).collect();
+  _compile_static_selectors(&selectors)
+});
+
+static IMG_SRCSET_SELECTOR: LazyLock<Selectors> = LazyLock::new(|| {
+  Selectors::compile("img[srcset]").expect("IMG_SRCSET_SELECTOR is a valid static selector")
+});
+
+static IMG_SRC_SELECTOR: LazyLock<Selectors> = LazyLock::new(|| {
+  Selectors::compile("img[src]").expect("IMG_SRC_SELECTOR is a valid static selector")
+});
+
+static A_HREF_SELECTOR: LazyLock<Selectors> = LazyLock::new(|| {
+  Selectors::compile("a[href]").expect("A_HREF_SELECTOR is a valid static selector")
+});
+
+/// How many user-provided selectors (include_tags and exclude_tags) keep their
+/// compiled form between calls.
+const SELECTOR_CACHE_CAPACITY: usize = 256;
+
+/// Compiled user-provided selectors, least recently used first.
+type SelectorCache = VecDeque<(String, Arc<Selectors>)>;
+
+static SELECTOR_CACHE: LazyLock<Mutex<SelectorCache>> =
+  LazyLock::new(|| Mutex::new(VecDeque::with_capacity(SELECTOR_CACHE_CAPACITY)));
+
+/// Compile a user-provided selector, reusing the result of an earlier call when it's
+/// still cached. `None` when the selector doesn't parse.
+fn _compile_selector(selector: &str) -> Option<Arc<Selectors>> {
+  let mut cache = SELECTOR_CACHE.lock().unwrap_or_else(|e| e.into_inner());
+  if let Some(i) = cache.iter().position(|(x, _)| x == selector) {
+    let entry = cache.remove(i)?;
+    let compiled = entry.1.clone();
+    cache.push_back(entry);
+    return Some(compiled);
+  }
+
+  let compiled = Arc::new(Selectors::compile(selector).ok()?);
+  if cache.len() >= SELECTOR_CACHE_CAPACITY {
+    cache.pop_front();
+  }
+  cache.push_back((selector.to_string(), compiled.clone()));
+  Some(compiled)
+}
+
+/// Compile user-supplied selectors up front, skipping those that don't parse and
+/// recording them in `invalid`, so one malformed selector neither fails the call nor
+/// looks like a selector that matched nothing.
+fn _compile_user_selectors<'a>(
+  selectors: impl IntoIterator<Item = &'a String>,
+  invalid: &mut Vec<String>,
+) -> Vec<(&'a str, Arc<Selectors>)> {
+  let mut out = Vec::new();
+  for selector in selectors {
+    match _compile_selector(selector) {
+      Some(compiled) => out.push((selector.as_str(), compiled)),
+      None if !invalid.contains(selector) => invalid.push(selector.clone()),
+      None => {}
+    }
+  }
+  out
+}
+
+/// The elements among `node` and its descendants matching precompiled `selectors`,
+/// like `NodeRef::select` without compiling a selector string.
+fn _select<'a>(
+  node: &NodeRef,
+  selectors: &'a Selectors,
+) -> impl Iterator<Item = NodeDataRef<ElementData>> + 'a {
+  selectors.filter(node.inclusive_descendants().elements())
+}
+
+struct ImageSource {
+  url: String,
+  size: f64,
+  is_x: bool,
+}
+
+/// Attributes whose values are URLs that may carry a `javascript:` payload.
+const SCRIPTABLE_URL_ATTRIBUTES: [&str; 5] = ["href", "src", "action", "formaction", "data"];
+
+/// Remove inline event handlers and `javascript:` URLs in a single traversal.
+fn _strip_inline_scripts(document: &NodeRef) {
+  for element in document.descendants().filter_map(|x| x.into_element_ref()) {
+    let mut attrs = element.attributes.borrow_mut();
+
+    let to_remove: Vec<_> = attrs
+      .map
+      .iter()
+      .filter(|(name, attr)| {
+        let local = name.local.as_ref();
+        let is_handler =
+          local.len() > 2 && local.get(..2).is_some_and(|x| x.eq_ignore_ascii_case("on"));
+        let is_script_url = SCRIPTABLE_URL_ATTRIBUTES
+          .iter()
+          .any(|x| local.eq_ignore_ascii_case(x))
+          && attr
+            .value
+            .trim_start()
+            .get(..11)
+            .is_some_and(|x| x.eq_ignore_ascii_case("javascript:"));
+        is_handler || is_script_url
+      })
+      .map(|(name, _)| name.clone())
+      .collect();
+
+    for name in to_remove {
+      attrs.map.remove(&name);
+    }
+  }
+}
+
+#[derive(Serialize, Default)]
+#[napi(object)]
+pub struct TransformHtmlStats {
+  /// The base href actually used to resolve relative URLs.
+  pub base_href: String,
+  /// head, meta, noscript, style, and script elements removed.
+  pub removed_head_script_style: u32,
+  /// Nodes removed because they matched an OMCE signature.
+  pub removed_omce: u32,
+  /// Nodes removed by user-supplied exclude_tags.
+  pub removed_exclude_tags: u32,
+  /// Nodes removed by the only_main_content selectors.
+  pub removed_main_content: u32,
+  /// Nodes matched by the only_main_content selectors but kept because they contain
+  /// force-included content.
+  pub preserved_main_content: u32,
+  /// Images whose src was rewritten by srcset selection or URL resolution.
+  pub images_rewritten: u32,
+  /// Elements removed for having less text than min_text_length.
+  pub removed_short_text: u32,
+  /// Whether trailing nodes were dropped to fit max_output_bytes.
+  pub truncated: bool,
+  /// Elements removed by prune_empty_elements.
+  pub removed_empty_elements: u32,
+  /// User-supplied selectors that don't parse and were skipped.
+  pub invalid_selectors: Vec<String>,
+  /// Nodes removed per OMCE signature, in the order given, including signatures that
+  /// matched nothing. Empty when the OMCE pass didn't run.
+  pub omce_matches: Vec<OmceMatch>,
+}
+
+#[derive(Serialize, Default)]
+#[napi(object)]
+pub struct OmceMatch {
+  pub signature: String,
+  pub removed_count: u32,
+}
+
+#[derive(Serialize)]
+#[napi(object)]
+pub struct TransformHtmlWithStatsResult {
+  pub html: String,
+  pub stats: TransformHtmlStats,
+}
+
+/// When the selector pass removes less than this fraction of the page text, hybrid mode
+/// falls back to density scoring.
+const HYBRID_MIN_REMOVED_RATIO: f64 = 0.1;
+
+/// Selectors applied by only_main_content on top of EXCLUDE_NON_MAIN_TAGS and
+/// FORCE_INCLUDE_MAIN_TAGS.
+#[derive(Default)]
+struct MainContentSelectors {
+  exclude: Vec<Arc<Selectors>>,
+  force_include: Vec<Arc<Selectors>>,
+}
+
+impl MainContentSelectors {
+  /// Compile the selectors of `opts`, recording those that don't parse in `invalid`.
+  fn from_options(opts: &TransformHtmlOptions, invalid: &mut Vec<String>) -> Self {
+    let mut compile = |selectors: &Option<Vec<String>>| {
+      _compile_user_selectors(selectors.iter().flatten(), invalid)
+        .into_iter()
+        .map(|(_, x)| x)
+        .collect()
+    };
+
+    Self {
+      exclude: compile(&opts.additional_exclude_selectors),
+      force_include: compile(&opts.force_include_selectors),
+    }
+  }
+}
+
+/// Every node that is, or contains, a match of a force-include selector, found in one
+/// pass over the matches' ancestors instead of a subtree search per candidate. Such
+/// nodes are never detached, so the set stays valid while removing.
+fn _force_included_nodes(document: &NodeRef, extra: &MainContentSelectors) -> HashSet<*const Node> {
+  let mut nodes = HashSet::new();
+  let selectors = std::iter::once(&*FORCE_INCLUDE_MAIN_SELECTORS)
+    .chain(extra.force_include.iter().map(Arc::as_ref));
+  for selectors in selectors {
+    for m in _select(document, selectors) {
+      for node in m.as_node().inclusive_ancestors() {
+        if !nodes.insert(Rc::as_ptr(&node.0)) {
+          break;
+        }
+      }
+    }
+  }
+  nodes
+}
+
+fn _remove_non_main_content(
+  document: &NodeRef,
+  extra: &MainContentSelectors,
+  stats: &mut TransformHtmlStats,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+  let force_included = _force_included_nodes(document, extra);
+  let selectors = EXCLUDE_NON_MAIN_SELECTORS
+    .iter()
+    .chain(extra.exclude.iter().map(Arc::as_ref));
+  for x in selectors {
+    let x: Vec<_> = _select(document, x).collect();
+    for tag in x {
+      if !force_included.contains(&Rc::as_ptr(&tag.as_node().0)) {
+        tag.as_node().detach();
+        stats.removed_main_content += 1;
+      } else {
+        stats.preserved_main_content += 1;
+      }
+    }
+  }
+
+  Ok(())
+}
+
+/// Elements never removed by min_text_length, together with their ancestors and
+/// descendants: media and links carry content without text, and tables and line
+/// breaks carry structure.
+const MIN_TEXT_KEPT_TAGS: [&str; 11] = [
+  "img", "picture", "video", "audio", "iframe", "svg", "a", "table", "figure", "br", "hr",
+];
+
+/// Remove block-level elements with too little text in a single bottom-up pass. Inline
+/// elements such as `<code>` or `<sup>` are left alone, since they're short by nature.
+fn _remove_short_text_elements(
+  document: &NodeRef,
+  min_text_length: usize,
+  stats: &mut TransformHtmlStats,
+) {
+  // Non-whitespace text length of each element that's still in place, filled in as
+  // the traversal leaves it; removed elements count as empty.
+  let mut lengths: HashMap<*const Node, usize> = HashMap::new();
+  // Elements that are, or contain, one of `MIN_TEXT_KEPT_TAGS`.
+  let mut holds_kept: HashSet<*const Node> = HashSet::new();
+  // How many kept elements the traversal is inside of.
+  let mut kept_depth = 0;
+  let mut removed = Vec::new();
+
+  for edge in document.traverse() {
+    let (node, entering) = match edge {
+      NodeEdge::Start(node) => (node, true),
+      NodeEdge::End(node) => (node, false),
+    };
+    let Some(element) = node.as_element() else {
+      continue;
+    };
+    let tag = element.name.local.as_ref();
+    let kept = MIN_TEXT_KEPT_TAGS.contains(&tag);
+    if entering {
+      kept_depth += kept as usize;
+      continue;
+    }
+    kept_depth -= kept as usize;
+
+    let mut length = 0;
+    let mut contains_kept = kept;
+    for child in node.children() {
+      if let Some(text) = child.as_text() {
+        length += text.borrow().chars().filter(|c| !c.is_whitespace()).count();
+      } else {
+        let ptr = Rc::as_ptr(&child.0);
+        length += lengths.get(&ptr).copied().unwrap_or(0);
+        contains_kept |= holds_kept.contains(&ptr);
+      }
+    }
+
+    let ptr = Rc::as_ptr(&node.0);
+    if contains_kept {
+      holds_kept.insert(ptr);
+    }
+    let block =
+      tag != "body" && (TEXT_BLOCK_TAGS.contains(&tag) || TEXT_PARAGRAPH_TAGS.contains(&tag));
+    if block && !contains_kept && kept_depth == 0 && length < min_text_length {
+      removed.push(node.clone());
+    } else {
+      lengths.insert(ptr, length);
+    }
+  }
+
+  // Detaching during the traversal would cut it short, so it happens afterwards.
+  for node in removed {
+    node.detach();
+    stats.removed_short_text += 1;
+  }
+}
+
+/// Elements never removed by prune_empty_elements: the document skeleton, table cells,
+/// and void elements.
+const PRUNE_KEPT_TAGS: [&str; 19] = [
+  "html", "head", "body", "td", "th", "area", "base", "br", "col", "embed", "hr", "img", "input",
+  "link", "meta", "param", "source", "track", "wbr",
+];
+
+/// Elements that carry content without text. They are kept along with everything inside
+/// them, e.g. the paths of an `<svg>` or the params of an `<object>`.
+const PRUNE_OPAQUE_TAGS: [&str; 10] = [
+  "picture", "video", "audio", "iframe", "svg", "math", "canvas", "object", "textarea", "select",
+];
+
+/// Remove elements that have no non-whitespace text and no kept descendants, returning
+/// how many were removed. Children come before their parents, so one pass reaches the
+/// fixed point: a wrapper emptied by pruning its children is pruned too. With
+/// `keep_anchors`, elements with an `id` or `name`, which links may target, are kept.
+fn _prune_empty_elements(document: &NodeRef, keep_anchors: bool) -> u32 {
+  let mut pruned: HashSet<*const Node> = HashSet::new();
+  let mut removed = Vec::new();
+  // How many opaque elements the traversal is inside of.
+  let mut opaque_depth = 0;
+
+  for edge in document.traverse() {
+    let node = match edge {
+      NodeEdge::Start(node) => {
+        if node
+          .as_element()
+          .is_some_and(|x| PRUNE_OPAQUE_TAGS.contains(&x.name.local.as_ref()))
+        {
+          opaque_depth += 1;
+        }
+        continue;
+      }
+      NodeEdge::End(node) => node,
+    };
+    let Some(element) = node.as_element() else {
+      continue;
+    };
+    if PRUNE_OPAQUE_TAGS.contains(&element.name.local.as_ref()) {
+      opaque_depth -= 1;
+      continue;
+    }
+    if opaque_depth > 0 || PRUNE_KEPT_TAGS.contains(&element.name.local.as_ref()) {
+      continue;
+    }
+    if keep_anchors {
+      let attributes = element.attributes.borrow();
+      if attributes.contains("id") || attributes.contains("name") {
+        continue;
+      }
+    }
+
+    let empty = node.children().all(|x| match x.as_text() {
+      Some(text) => text.borrow().trim().is_empty(),
+      None => x.as_element().is_none() || pruned.contains(&Rc::as_ptr(&x.0)),
+    });
+    if empty {
+      pruned.insert(Rc::as_ptr(&node.0));
+      removed.push(node.clone());
+    }
+  }
+
+  for node in &removed {
+    node.detach();
+  }
+  removed.len() as u32
+}
+
+/// A new document whose body holds only `node`, detached from its original tree.
+fn _document_from_subtree(
+  node: &NodeRef,
+) -> Result<NodeRef, Box<dyn std::error::Error + Send + Sync>> {
+  let new_document = parse_html().one("<div></div>");
+  let root = new_document
+    .select_first("div")
+    .map_err(|_| "Failed to select root element")?;
+  root.as_node().append(node.clone());
+  Ok(new_document)
+}
+
+fn _transform_html_inner(
   opts: TransformHtmlOptions,
 ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
-  let mut document = parse_html().one(opts.html.as_ref());
+  _transform_html_with_stats(opts).map(|(html, _)| html)
+}
+
+/// Elements always removed by transform_html, in removal order.
+const REMOVED_TAGS: [&str; 5] = ["head", "meta", "noscript", "style", "script"];
+
+/// Whether `selector` is a bare tag name such as `nav` or `my-widget`.
+fn _is_tag_name(selector: &str) -> bool {
+  selector.starts_with(|c: char| c.is_ascii_alphabetic())
+    && selector
+      .chars()
+      .all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+/// Detach every element whose tag name is in `tags` in a single traversal,
+/// returning how many were removed. This matches removing each tag in turn with
+/// `select_first` loops: an element is only counted when no ancestor is removed by an
+/// earlier or the same tag, since such an ancestor takes it along.
+fn _remove_tags(document: &NodeRef, tags: &[&str]) -> u32 {
+  let mut removed = Vec::new();
+  // Position in `tags` of each open ancestor being removed.
+  let mut open: Vec<usize> = Vec::new();
+
+  for edge in document.traverse() {
+    match edge {
+      NodeEdge::Start(node) => {
+        let Some(element) = node.as_element() else {
+          continue;
+        };
+        let Some(rank) = tags
+          .iter()
+          .position(|x| x.eq_ignore_ascii_case(&element.name.local))
+        else {
+          continue;
+        };
+        if open.iter().all(|&x| x > rank) {
+          removed.push(node.clone());
+        }
+        open.push(rank);
+      }
+      NodeEdge::End(node) => {
+        let is_open = node
+          .as_element()
+          .is_some_and(|x| tags.iter().any(|t| t.eq_ignore_ascii_case(&x.name.local)));
+        if is_open {
+          open.pop();
+        }
+      }
+    }
+  }
+
+  for node in &removed {
+    node.detach();
+  }
+  removed.len() as u32
+}
+
+/// Remove every `<template>` element, or with `expand` replace it with its content.
+/// The parser keeps template content in a separate fragment that is neither traversed
+/// nor serialized, so without this the output has empty `<template>` tags. Expanded
+/// content is searched again, which expands nested templates too.
+fn _handle_templates(document: &NodeRef, expand: bool) {
+  while let Ok(template) = document.select_first("template") {
+    let node = template.as_node();
+    if expand {
+      if let Some(content) = &template.template_contents {
+        for child in content.children() {
+          node.insert_before(child);
+        }
+      }
+    }
+    node.detach();
+  }
+}
+
+fn _transform_html_with_stats(
+  opts: TransformHtmlOptions,
+) -> Result<(String, TransformHtmlStats), Box<dyn std::error::Error + Send + Sync>> {
+  let output = opts.output.as_deref().unwrap_or("document");
+  if !matches!(output, "document" | "body" | "fragment") {
+    return Err(format!("Invalid output: {output}").into());
+  }
+
+  let deadline = Deadline::new(opts.timeout_ms);
+  let mut stats = TransformHtmlStats::default();
+  let mut document = _parse_html_with_limit(&opts.html, opts.max_input_bytes)?;
+  deadline.check()?;
+  // Whether `document` is a `<div>` wrapper we built around selected subtrees.
+  let mut wrapped = false;
   let url = Url::parse(&_extract_base_href_from_document(
     &document,
     &Url::parse(&opts.url)?,
   )?)?;
+  stats.base_href = url.to_string();
+
+  _handle_templates(&document, opts.expand_templates.unwrap_or(false));
 
-  if !opts.include_tags.is_empty() {
+  let include_tags = _compile_user_selectors(&opts.include_tags, &mut stats.invalid_selectors);
+  let exclude_tags = _compile_user_selectors(&opts.exclude_tags, &mut stats.invalid_selectors);
+  let main_content_selectors =
+    MainContentSelectors::from_options(&opts, &mut stats.invalid_selectors);
+
+  // Only selectors that compiled count: a call whose include_tags are all invalid
+  // keeps the whole document rather than returning nothing.
+  if !include_tags.is_empty() {
     let new_document = parse_html().one("<div></div>");
     let root = new_document
       .select_first("div")
       .map_err(|_| "Failed to select root element")?;
 
-    for x in opts.include_tags.iter() {
-      let matching_nodes: Vec<_> = document
-        .select(x)
-        .map_err(|_| "Failed to include_tags tags")?
-        .collect();
+    for (_, selectors) in include_tags.iter() {
+      let matching_nodes: Vec<_> = _select(&document, selectors).collect();
       for tag in matching_nodes {
         root.as_node().append(tag.as_node().clone());
       }
+      deadline.check()?;
     }
 
     document = new_document;
+    wrapped = true;
   }
 
-  while let Ok(x) = document.select_first("head") {
-    x.as_node().detach();
-  }
-  while let Ok(x) = document.select_first("meta") {
-    x.as_node().detach();
-  }
-  while let Ok(x) = document.select_first("noscript") {
-    x.as_node().detach();
-  }
-  while let Ok(x) = document.select_first("style") {
-    x.as_node().detach();
-  }
-  while let Ok(x) = document.select_first("script") {
-    x.as_node().detach();
+  stats.removed_head_script_style += _remove_tags(&document, &REMOVED_TAGS);
+  deadline.check()?;
+
+  if opts.strip_inline_scripts.unwrap_or(true) {
+    _strip_inline_scripts(&document);
   }
 
   // OMCE first
   if opts.only_main_content {
     if let Some(signatures) = opts.omce_signatures.as_ref() {
       let mut nodes_to_drop: Vec<NodeRef> = Vec::new();
+      let mut removed_counts: HashMap<&str, u32> = HashMap::new();
 
       let modes = signatures
         .iter()
@@ -470,13 +1729,14 @@ fn _transform_html_inner(
         let signatures = signatures
           .iter()
           .filter(|x| x.contains(&matcher))
-          .cloned()
+          .map(String::as_str)
           .collect::<HashSet<_>>();
 
         for edge in document.traverse() {
           match edge {
             NodeEdge::Start(_) => {}
             NodeEdge::End(node) => {
+              deadline.check()?;
               if node.as_element().is_none() {
                 continue;
               }
@@ -485,7 +1745,8 @@ fn _transform_html_inner(
               }
 
               let signature = get_node_signature(&node, mode);
-              if signatures.contains(&signature) {
+              if let Some(signature) = signatures.get(signature.as_str()) {
+                *removed_counts.entry(signature).or_default() += 1;
                 nodes_to_drop.push(node);
               }
             }
@@ -493,42 +1754,101 @@ fn _transform_html_inner(
         }
       }
 
+      stats.removed_omce += nodes_to_drop.len() as u32;
+      let mut seen = HashSet::new();
+      stats.omce_matches = signatures
+        .iter()
+        .filter(|x| seen.insert(x.as_str()))
+        .map(|x| OmceMatch {
+          signature: x.clone(),
+          removed_count: removed_counts.get(x.as_str()).copied().unwrap_or(0),
+        })
+        .collect();
       for node in nodes_to_drop {
         node.detach();
       }
     }
   }
 
-  for x in opts.exclude_tags.iter() {
-    while let Ok(x) = document.select_first(x) {
-      x.as_node().detach();
+  // Runs of plain tag names are removed in one traversal each; other selectors need
+  // the selector engine.
+  let mut exclude_tags = exclude_tags.iter().peekable();
+  while let Some((selector, selectors)) = exclude_tags.next() {
+    deadline.check()?;
+    if _is_tag_name(selector) {
+      let mut tags = vec![*selector];
+      while let Some((x, _)) = exclude_tags.next_if(|(x, _)| _is_tag_name(x)) {
+        tags.push(x);
+      }
+      stats.removed_exclude_tags += _remove_tags(&document, &tags);
+    } else {
+      while let Some(x) = _select(&document, selectors).next() {
+        deadline.check()?;
+        x.as_node().detach();
+        stats.removed_exclude_tags += 1;
+      }
     }
   }
 
   if opts.only_main_content {
-    for x in EXCLUDE_NON_MAIN_TAGS.iter() {
-      let x: Vec<_> = document
-        .select(x)
-        .map_err(|_| "Failed to select tags")?
-        .collect();
-      for tag in x {
-        if !FORCE_INCLUDE_MAIN_TAGS.iter().any(|x| {
-          tag
-            .as_node()
-            .select(x)
-            .is_ok_and(|mut x| x.next().is_some())
-        }) {
-          tag.as_node().detach();
+    let mode = opts.main_content_mode.as_deref().unwrap_or("selectors");
+    match mode {
+      "selectors" => _remove_non_main_content(&document, &main_content_selectors, &mut stats)?,
+      "density" => {
+        if let Some(main) = readability::find_main_content(&document) {
+          document = _document_from_subtree(&main)?;
+          wrapped = true;
+        }
+      }
+      "hybrid" => {
+        let before = readability::text_length(&document);
+        _remove_non_main_content(&document, &main_content_selectors, &mut stats)?;
+        let after = readability::text_length(&document);
+
+        if before > 0 && (before - after) as f64 / (before as f64) < HYBRID_MIN_REMOVED_RATIO {
+          if let Some(main) = readability::find_main_content(&document) {
+            document = _document_from_subtree(&main)?;
+            wrapped = true;
+          }
         }
       }
+      _ => return Err(format!("Invalid main_content_mode: {mode}").into()),
     }
+    deadline.check()?;
   }
 
-  let srcset_images: Vec<_> = document
-    .select("img[srcset]")
-    .map_err(|_| "Failed to select srcset images")?
-    .collect();
+  if opts.strip_data_attributes.unwrap_or(false) {
+    for element in document.descendants().filter_map(|x| x.into_element_ref()) {
+      let names: Vec<_> = element
+        .attributes
+        .borrow()
+        .map
+        .keys()
+        .filter(|name| name.local.starts_with("data-"))
+        .map(|name| name.local.clone())
+        .collect();
+
+      let mut attrs = element.attributes.borrow_mut();
+      for name in names {
+        attrs.remove(name);
+      }
+    }
+  }
+
+  if let Some(min_text_length) = opts.min_text_length {
+    _remove_short_text_elements(&document, min_text_length as usize, &mut stats);
+    deadline.check()?;
+  }
+
+  if opts.prune_empty_elements.unwrap_or(true) {
+    stats.removed_empty_elements +=
+      _prune_empty_elements(&document, opts.prune_keep_anchors.unwrap_or(true));
+    deadline.check()?;
+  }
+
+  let srcset_images: Vec<_> = _select(&document, &IMG_SRCSET_SELECTOR).collect();
   for img in srcset_images {
+    deadline.check()?;
     let mut sizes: Vec<ImageSource> = img
       .attributes
       .borrow()
@@ -549,12 +1869,18 @@ fn _transform_html_inner(
 
         if let Some((last_index, _)) = last_token.char_indices().last() {
           if let Ok(parsed_size) = last_token[..last_index].parse() {
+            let url = if last_token_used {
+              tok[0..tok.len() - 1].join(" ")
+            } else {
+              tok.join(" ")
+            };
+            // Runs of whitespace before the descriptor leave empty tokens behind.
+            let url = url.trim();
+            if url.is_empty() {
+              return None;
+            }
             Some(ImageSource {
-              url: if last_token_used {
-                tok[0..tok.len() - 1].join(" ")
-              } else {
-                tok.join(" ")
-              },
+              url: url.to_string(),
               size: parsed_size,
               is_x: last_token.ends_with('x'),
             })
@@ -568,7 +1894,12 @@ fn _transform_html_inner(
       .collect();
 
     if sizes.iter().all(|x| x.is_x) {
-      if let Some(src) = img.attributes.borrow().get("src").map(|x| x.to_string()) {
+      if let Some(src) = img
+        .attributes
+        .borrow()
+        .get("src")
+        .map(|x| x.trim().to_string())
+      {
         sizes.push(ImageSource {
           url: src,
           size: 1.0,
@@ -584,18 +1915,19 @@ fn _transform_html_inner(
     });
 
     if let Some(biggest) = sizes.first() {
-      img
+      let previous = img
         .attributes
         .borrow_mut()
         .insert("src", biggest.url.clone());
+      if previous.is_none_or(|x| x.value != biggest.url) {
+        stats.images_rewritten += 1;
+      }
     }
   }
 
-  let src_images: Vec<_> = document
-    .select("img[src]")
-    .map_err(|_| "Failed to select src images")?
-    .collect();
+  let src_images: Vec<_> = _select(&document, &IMG_SRC_SELECTOR).collect();
   for img in src_images {
+    deadline.check()?;
     let old = img
       .attributes
       .borrow()
@@ -603,15 +1935,16 @@ fn _transform_html_inner(
       .map(|x| x.to_string())
       .ok_or("Failed to get src")?;
     if let Ok(new) = url.join(&old) {
+      if new.as_str() != old {
+        stats.images_rewritten += 1;
+      }
       img.attributes.borrow_mut().insert("src", new.to_string());
     }
   }
 
-  let href_anchors: Vec<_> = document
-    .select("a[href]")
-    .map_err(|_| "Failed to select href anchors")?
-    .collect();
+  let href_anchors: Vec<_> = _select(&document, &A_HREF_SELECTOR).collect();
   for anchor in href_anchors {
+    deadline.check()?;
     let old = anchor
       .attributes
       .borrow()
@@ -626,7 +1959,101 @@ fn _transform_html_inner(
     }
   }
 
-  Ok(document.to_string())
+  for (&(_, attribute), selectors) in MEDIA_URL_ATTRIBUTES.iter().zip(MEDIA_URL_SELECTORS.iter()) {
+    let elements: Vec<_> = _select(&document, selectors).collect();
+    for element in elements {
+      deadline.check()?;
+      let old = match element.attributes.borrow().get(attribute) {
+        Some(x) => x.trim().to_string(),
+        None => continue,
+      };
+      let lower = old.to_ascii_lowercase();
+      if old.is_empty()
+        || lower == "about:blank"
+        || lower.starts_with("javascript:")
+        || lower.starts_with("data:")
+      {
+        continue;
+      }
+      if let Ok(new) = url.join(&old) {
+        element
+          .attributes
+          .borrow_mut()
+          .insert(attribute, new.to_string());
+      }
+    }
+  }
+
+  if output == "document" {
+    if let Some(max_output_bytes) = opts.max_output_bytes {
+      stats.truncated = _truncate_children(&document, max_output_bytes as usize);
+    }
+    return Ok((document.to_string(), stats));
+  }
+
+  let mut container = document
+    .select_first("body")
+    .map_err(|_| "Failed to select body")?
+    .as_node()
+    .clone();
+  if output == "fragment" && wrapped {
+    if let Some(div) = _lone_child_element(&container, "div") {
+      container = div;
+    }
+  }
+
+  if let Some(max_output_bytes) = opts.max_output_bytes {
+    stats.truncated = _truncate_children(&container, max_output_bytes as usize);
+  }
+
+  // Serialize child by child so text nodes sitting directly in the container are kept.
+  let html = container.children().map(|x| x.to_string()).collect();
+  Ok((html, stats))
+}
+
+/// Detach trailing nodes so the children of `node` serialize to at most `budget`
+/// bytes, returning whether anything was dropped. The first child that doesn't fit
+/// is truncated the same way when its own tags fit, and everything after it goes.
+/// Text nodes are never split.
+fn _truncate_children(node: &NodeRef, budget: usize) -> bool {
+  let mut budget = budget;
+  let mut children = node.children();
+  while let Some(child) = children.next() {
+    let size = child.to_string().len();
+    if size <= budget {
+      budget -= size;
+      continue;
+    }
+
+    let inner: usize = child.children().map(|x| x.to_string().len()).sum();
+    let tags = size.saturating_sub(inner);
+    let fits = child.as_element().is_some() && tags <= budget;
+    if fits {
+      _truncate_children(&child, budget - tags);
+    }
+    // An element emptied by truncation carries nothing worth its tags.
+    if !fits || child.first_child().is_none() {
+      child.detach();
+    }
+    for x in children.collect::<Vec<_>>() {
+      x.detach();
+    }
+    return true;
+  }
+  false
+}
+
+/// The only child of `node` when it is a `tag` element, ignoring whitespace-only text.
+fn _lone_child_element(node: &NodeRef, tag: &str) -> Option<NodeRef> {
+  let mut children = node.children().filter(|x| match x.as_text() {
+    Some(text) => !text.borrow().trim().is_empty(),
+    None => x.as_element().is_some(),
+  });
+  let child = children.next()?;
+  if children.next().is_some() {
+    return None;
+  }
+  (child.as_element()?.name.local.as_ref() == tag).then_some(child)
 }
 
 /// Transform and clean HTML content based on provided options.
@@ -641,84 +2068,436 @@ pub async fn transform_html(opts: TransformHtmlOptions) -> napi::Result<String>
       )
     })?;
 
-  res.map_err(to_napi_err)
+  res.map_err(_to_napi_err)
 }
 
-fn _get_inner_json(html: &str) -> Result<String, ()> {
-  Ok(parse_html().one(html).select_first("body")?.text_contents())
+#[derive(Serialize)]
+#[napi(object)]
+pub struct TransformedHtmlFromBytes {
+  pub html: String,
+  /// Name of the encoding the body was decoded with, e.g. "windows-1251".
+  pub charset: String,
 }
 
-/// Extract inner text content from HTML body.
+/// Like `transform_html`, but for a raw response body, which replaces `opts.html`.
+/// The charset is taken from a BOM, the `charset` parameter of `content_type_header`,
+/// or a `<meta>` declaration.
 #[napi]
-pub async fn get_inner_json(html: String) -> napi::Result<String> {
-  let res = task::spawn_blocking(move || _get_inner_json(&html))
+pub async fn transform_html_from_bytes(
+  html: Buffer,
+  content_type_header: Option<String>,
+  mut opts: TransformHtmlOptions,
+) -> napi::Result<TransformedHtmlFromBytes> {
+  _check_input_size(html.len(), opts.max_input_bytes).map_err(_to_napi_err)?;
+  let html = html.to_vec();
+  let res = task::spawn_blocking(move || {
+    let (html, encoding) = decode_html(&html, content_type_header.as_deref());
+    opts.html = html;
+    _transform_html_inner(opts).map(|html| TransformedHtmlFromBytes {
+      html,
+      charset: encoding.name().to_string(),
+    })
+  })
+  .await
+  .map_err(|e| {
+    napi::Error::new(
+      napi::Status::GenericFailure,
+      format!("transform_html_from_bytes join error: {e}"),
+    )
+  })?;
+
+  res.map_err(_to_napi_err)
+}
+
+/// Transform HTML like `transform_html`, also returning counters describing what was removed
+/// or rewritten and why.
+#[napi]
+pub async fn transform_html_with_stats(
+  opts: TransformHtmlOptions,
+) -> napi::Result<TransformHtmlWithStatsResult> {
+  let res = task::spawn_blocking(move || _transform_html_with_stats(opts))
     .await
     .map_err(|e| {
       napi::Error::new(
         napi::Status::GenericFailure,
-        format!("get_inner_json join error: {e}"),
+        format!("transform_html_with_stats join error: {e}"),
       )
     })?;
 
-  res.map_err(|_| to_napi_err("Failed to get inner JSON"))
+  res
+    .map(|(html, stats)| TransformHtmlWithStatsResult { html, stats })
+    .map_err(_to_napi_err)
 }
 
-#[derive(Deserialize, Serialize)]
-#[napi(object)]
-pub struct AttributeSelector {
-  pub selector: String,
-  pub attribute: String,
-}
-
-#[derive(Deserialize, Serialize)]
+#[derive(Default)]
 #[napi(object)]
-pub struct ExtractAttributesOptions {
-  pub selectors: Vec<AttributeSelector>,
+pub struct BoilerplateCandidateOptions {
+  /// Elements with fewer text characters than this are not candidates. Defaults to 50.
+  pub min_text_length: Option<u32>,
 }
 
 #[derive(Serialize)]
 #[napi(object)]
-pub struct ExtractedAttributeResult {
-  pub selector: String,
-  pub attribute: String,
-  pub values: Vec<String>,
+pub struct CandidateBlock {
+  /// The OMCE signature, usable as-is in omce_signatures.
+  pub signature: String,
+  /// Characters of text in the block, ignoring whitespace around each text node.
+  pub text_length: u32,
+  pub tag_name: String,
+  /// Nesting depth, with `<html>` at 0.
+  pub depth: u32,
 }
 
-fn _extract_attributes(
-  html: &str,
+const BOILERPLATE_MIN_TEXT_LENGTH: u32 = 50;
+
+/// A candidate holding at least this fraction of its candidate parent's text makes
+/// the parent a mere wrapper, so only the inner block is reported.
+const BOILERPLATE_WRAPPER_TEXT_RATIO: f64 = 0.9;
+
+fn _compute_boilerplate_candidates(
+  html: &str,
+  mode: &str,
+  options: BoilerplateCandidateOptions,
+) -> Result<Vec<CandidateBlock>, Box<dyn std::error::Error + Send + Sync>> {
+  let signature_mode: SignatureMode = mode.to_string().into();
+  if String::from(signature_mode) != mode {
+    return Err(format!("Invalid signature mode: {mode}").into());
+  }
+  let min_text_length = options
+    .min_text_length
+    .unwrap_or(BOILERPLATE_MIN_TEXT_LENGTH) as usize;
+
+  // Signatures must be taken from the tree the OMCE pass sees in transform_html.
+  let document = _parse_html(html)?;
+  _handle_templates(&document, false);
+  _remove_tags(&document, &REMOVED_TAGS);
+  _strip_inline_scripts(&document);
+
+  struct Frame {
+    order: usize,
+    text_length: usize,
+    /// Indices into `candidates` of the outermost reported blocks below this element.
+    inner: Vec<usize>,
+  }
+
+  let mut candidates: Vec<(usize, CandidateBlock)> = Vec::new();
+  let mut frames: Vec<Frame> = Vec::new();
+  let mut order = 0;
+
+  for edge in document.traverse() {
+    match edge {
+      NodeEdge::Start(node) => {
+        if node.as_element().is_some() {
+          frames.push(Frame {
+            order,
+            text_length: 0,
+            inner: Vec::new(),
+          });
+          order += 1;
+        } else if let Some(text) = node.as_text() {
+          if let Some(frame) = frames.last_mut() {
+            frame.text_length += text.borrow().trim().chars().count();
+          }
+        }
+      }
+      NodeEdge::End(node) => {
+        let Some(element) = node.as_element() else {
+          continue;
+        };
+        let Some(frame) = frames.pop() else {
+          continue;
+        };
+
+        let mut reported = frame.inner;
+        if frame.text_length > 0 && frame.text_length >= min_text_length {
+          let is_wrapper = match reported.as_slice() {
+            [only] => {
+              candidates[*only].1.text_length as f64
+                >= frame.text_length as f64 * BOILERPLATE_WRAPPER_TEXT_RATIO
+            }
+            _ => false,
+          };
+          if !is_wrapper {
+            candidates.push((
+              frame.order,
+              CandidateBlock {
+                signature: get_node_signature(&node, signature_mode),
+                text_length: frame.text_length as u32,
+                tag_name: element.name.local.to_string(),
+                depth: frames.len() as u32,
+              },
+            ));
+            reported = vec![candidates.len() - 1];
+          }
+        }
+
+        if let Some(parent) = frames.last_mut() {
+          parent.text_length += frame.text_length;
+          parent.inner.extend(reported);
+        }
+      }
+    }
+  }
+
+  candidates.sort_by_key(|(order, _)| *order);
+  Ok(candidates.into_iter().map(|(_, x)| x).collect())
+}
+
+/// Signatures of the blocks of a page that could be cross-page boilerplate, in
+/// document order. A block that only wraps another candidate is left out in favour of
+/// the inner one, so intersecting the results of several pages of a site and passing
+/// the survivors as omce_signatures doesn't remove a whole page wrapper.
+#[napi]
+pub async fn compute_boilerplate_candidates(
+  html: String,
+  mode: String,
+  options: Option<BoilerplateCandidateOptions>,
+) -> napi::Result<Vec<CandidateBlock>> {
+  let res = task::spawn_blocking(move || {
+    _compute_boilerplate_candidates(&html, &mode, options.unwrap_or_default())
+  })
+  .await
+  .map_err(|e| {
+    napi::Error::new(
+      napi::Status::GenericFailure,
+      format!("compute_boilerplate_candidates join error: {e}"),
+    )
+  })?;
+
+  res.map_err(_to_napi_err)
+}
+
+#[derive(Serialize)]
+#[napi(object)]
+pub struct TransformHtmlBatchResult {
+  pub html: Option<String>,
+  pub error: Option<String>,
+  pub elapsed_ms: f64,
+}
+
+fn _transform_html_timed(opts: TransformHtmlOptions) -> TransformHtmlBatchResult {
+  let start = Instant::now();
+  // A panic on one pathological page must not take the rest of the batch down with it.
+  let res = std::panic::catch_unwind(AssertUnwindSafe(|| _transform_html_inner(opts)));
+  let elapsed_ms = start.elapsed().as_secs_f64() * 1000.0;
+
+  match res {
+    Ok(Ok(html)) => TransformHtmlBatchResult {
+      html: Some(html),
+      error: None,
+      elapsed_ms,
+    },
+    Ok(Err(e)) => TransformHtmlBatchResult {
+      html: None,
+      error: Some(e.to_string()),
+      elapsed_ms,
+    },
+    Err(_) => TransformHtmlBatchResult {
+      html: None,
+      error: Some("transform_html panicked".to_string()),
+      elapsed_ms,
+    },
+  }
+}
+
+/// Number of concurrent tasks for a batch: `concurrency` when set, or else the
+/// available parallelism of the machine.
+fn _batch_concurrency(concurrency: Option<u32>) -> usize {
+  match concurrency {
+    Some(x) if x > 0 => x as usize,
+    _ => thread::available_parallelism().map_or(1, |x| x.get()),
+  }
+}
+
+/// Transform many HTML documents in one call. Documents are processed in parallel on the
+/// blocking pool, at most `concurrency` at once, defaulting to the available parallelism.
+/// Results are in input order; a failing document yields an error entry instead of
+/// failing the batch.
+#[napi]
+pub async fn transform_html_batch(
+  opts: Vec<TransformHtmlOptions>,
+  concurrency: Option<u32>,
+) -> napi::Result<Vec<TransformHtmlBatchResult>> {
+  let limit = _batch_concurrency(concurrency);
+  let mut results: Vec<Option<TransformHtmlBatchResult>> = (0..opts.len()).map(|_| None).collect();
+  let mut pending = opts.into_iter().enumerate();
+  let mut set = JoinSet::new();
+
+  loop {
+    while set.len() < limit {
+      let Some((i, opts)) = pending.next() else {
+        break;
+      };
+      set.spawn_blocking(move || (i, _transform_html_timed(opts)));
+    }
+
+    let Some(res) = set.join_next().await else {
+      break;
+    };
+    let (i, result) = res.map_err(|e| {
+      napi::Error::new(
+        napi::Status::GenericFailure,
+        format!("transform_html_batch join error: {e}"),
+      )
+    })?;
+    results[i] = Some(result);
+  }
+
+  Ok(results.into_iter().flatten().collect())
+}
+
+/// Like `transform_html_batch`, but returns only the HTML, with `null` for a failing
+/// document. Use `transform_html_batch` to get the errors.
+#[napi]
+pub async fn batch_transform_html(
+  opts: Vec<TransformHtmlOptions>,
+  concurrency: Option<u32>,
+) -> napi::Result<Vec<Option<String>>> {
+  let results = transform_html_batch(opts, concurrency).await?;
+  Ok(results.into_iter().map(|x| x.html).collect())
+}
+
+fn _get_inner_json(html: &str) -> Result<String, ()> {
+  Ok(parse_html().one(html).select_first("body")?.text_contents())
+}
+
+/// Extract inner text content from HTML body.
+#[napi]
+pub async fn get_inner_json(html: String) -> napi::Result<String> {
+  _check_input_size(html.len(), None).map_err(_to_napi_err)?;
+  let res = task::spawn_blocking(move || _get_inner_json(&html))
+    .await
+    .map_err(|e| {
+      napi::Error::new(
+        napi::Status::GenericFailure,
+        format!("get_inner_json join error: {e}"),
+      )
+    })?;
+
+  res.map_err(|_| _to_napi_err("Failed to get inner JSON"))
+}
+
+#[derive(Deserialize, Serialize)]
+#[napi(object)]
+pub struct AttributeSelector {
+  pub selector: String,
+  /// Attribute name, falling back to `data-<attribute>`. `:text` extracts the trimmed
+  /// text content of the element instead, and `:html` its serialized inner HTML.
+  pub attribute: String,
+}
+
+#[derive(Deserialize, Serialize)]
+#[napi(object)]
+pub struct ExtractAttributesOptions {
+  pub selectors: Vec<AttributeSelector>,
+  /// Fail with a timeout error when extraction takes longer than this. Parsing the
+  /// document is not counted.
+  pub timeout_ms: Option<u32>,
+}
+
+#[derive(Serialize)]
+#[napi(object)]
+pub struct ExtractedAttributeResult {
+  pub selector: String,
+  pub attribute: String,
+  pub values: Vec<String>,
+  /// The selector, when it doesn't parse and so matched nothing.
+  pub invalid_selectors: Vec<String>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[napi(object)]
+pub struct GroupedAttributeSelector {
+  pub selector: String,
+  pub attributes: Vec<String>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[napi(object)]
+pub struct ExtractAttributesGroupedOptions {
+  pub selectors: Vec<GroupedAttributeSelector>,
+  /// Fail with a timeout error when extraction takes longer than this. Parsing the
+  /// document is not counted.
+  pub timeout_ms: Option<u32>,
+}
+
+#[derive(Serialize)]
+#[napi(object)]
+pub struct ExtractedAttributeGroupResult {
+  pub selector: String,
+  pub attributes: Vec<String>,
+  /// One entry per matched element, in document order. Attributes missing on
+  /// an element are present with a `null` value so rows stay aligned.
+  pub elements: Vec<HashMap<String, Option<String>>>,
+  /// The selector, when it doesn't parse and so matched nothing.
+  pub invalid_selectors: Vec<String>,
+}
+
+/// Pseudo-attribute that extracts the text content of the element.
+const TEXT_PSEUDO_ATTRIBUTE: &str = ":text";
+
+/// Pseudo-attribute that extracts the serialized children of the element.
+const HTML_PSEUDO_ATTRIBUTE: &str = ":html";
+
+fn _get_element_attribute(element: &NodeDataRef<ElementData>, attribute: &str) -> Option<String> {
+  if attribute == TEXT_PSEUDO_ATTRIBUTE {
+    return Some(element.as_node().text_contents().trim().to_string());
+  }
+  if attribute == HTML_PSEUDO_ATTRIBUTE {
+    return Some(
+      element
+        .as_node()
+        .children()
+        .map(|x| x.to_string())
+        .collect(),
+    );
+  }
+
+  let attrs = element.attributes.borrow();
+
+  if let Some(attr_value) = attrs.get(attribute) {
+    return Some(attr_value.to_string());
+  }
+
+  if !attribute.starts_with("data-") {
+    let data_attr = format!("data-{attribute}");
+    if let Some(attr_value) = attrs.get(data_attr.as_str()) {
+      return Some(attr_value.to_string());
+    }
+  }
+
+  None
+}
+
+fn _extract_attributes(
+  html: &str,
   options: &ExtractAttributesOptions,
 ) -> Result<Vec<ExtractedAttributeResult>, Box<dyn std::error::Error + Send + Sync>> {
-  let document = parse_html().one(html);
+  let document = _parse_html(html)?;
+  _extract_attributes_from_document(&document, options)
+}
+
+fn _extract_attributes_from_document(
+  document: &NodeRef,
+  options: &ExtractAttributesOptions,
+) -> Result<Vec<ExtractedAttributeResult>, Box<dyn std::error::Error + Send + Sync>> {
+  let deadline = Deadline::new(options.timeout_ms);
   let mut results = Vec::new();
 
   for selector_config in &options.selectors {
     let mut values = Vec::new();
+    let mut invalid_selectors = Vec::new();
 
-    let elements: Vec<_> = match document.select(&selector_config.selector).map_err(|_| {
-      format!(
-        "Failed to select with selector: {}",
-        selector_config.selector
-      )
-    }) {
-      Ok(x) => x.collect(),
-      Err(_) => Vec::new(), // invalid selector => empty list
-    };
+    let elements: Vec<_> =
+      _compile_user_selectors([&selector_config.selector], &mut invalid_selectors)
+        .iter()
+        .flat_map(|(_, x)| _select(document, x).collect::<Vec<_>>())
+        .collect();
 
     for element in elements {
-      if let Some(attr_value) = element
-        .attributes
-        .borrow()
-        .get(selector_config.attribute.as_str())
-      {
-        values.push(attr_value.to_string());
-        continue;
-      }
-
-      if !selector_config.attribute.starts_with("data-") {
-        let data_attr = format!("data-{}", selector_config.attribute);
-        if let Some(attr_value) = element.attributes.borrow().get(data_attr.as_str()) {
-          values.push(attr_value.to_string());
-        }
+      deadline.check()?;
+      if let Some(attr_value) = _get_element_attribute(&element, &selector_config.attribute) {
+        values.push(attr_value);
       }
     }
 
@@ -726,6 +2505,7 @@ fn _extract_attributes(
       selector: selector_config.selector.clone(),
       attribute: selector_config.attribute.clone(),
       values,
+      invalid_selectors,
     });
   }
 
@@ -747,20 +2527,94 @@ pub async fn extract_attributes(
       )
     })?;
 
-  res.map_err(to_napi_err)
+  res.map_err(_to_napi_err)
+}
+
+fn _extract_attributes_grouped(
+  html: &str,
+  options: &ExtractAttributesGroupedOptions,
+) -> Result<Vec<ExtractedAttributeGroupResult>, Box<dyn std::error::Error + Send + Sync>> {
+  let document = _parse_html(html)?;
+  let deadline = Deadline::new(options.timeout_ms);
+  let mut results = Vec::new();
+
+  for selector_config in &options.selectors {
+    deadline.check()?;
+    let mut invalid_selectors = Vec::new();
+    let elements: Vec<_> =
+      _compile_user_selectors([&selector_config.selector], &mut invalid_selectors)
+        .iter()
+        .flat_map(|(_, x)| _select(&document, x).collect::<Vec<_>>())
+        .collect();
+
+    let elements = elements
+      .iter()
+      .map(|element| {
+        deadline.check()?;
+        Ok(
+          selector_config
+            .attributes
+            .iter()
+            .map(|attribute| {
+              (
+                attribute.clone(),
+                _get_element_attribute(element, attribute),
+              )
+            })
+            .collect(),
+        )
+      })
+      .collect::<Result<_, TimeoutError>>()?;
+
+    results.push(ExtractedAttributeGroupResult {
+      selector: selector_config.selector.clone(),
+      attributes: selector_config.attributes.clone(),
+      elements,
+      invalid_selectors,
+    });
+  }
+
+  Ok(results)
+}
+
+/// Extract several attributes per matched element, keeping values of the same element together.
+#[napi]
+pub async fn extract_attributes_grouped(
+  html: String,
+  options: ExtractAttributesGroupedOptions,
+) -> napi::Result<Vec<ExtractedAttributeGroupResult>> {
+  let res = task::spawn_blocking(move || _extract_attributes_grouped(&html, &options))
+    .await
+    .map_err(|e| {
+      napi::Error::new(
+        napi::Status::GenericFailure,
+        format!("extract_attributes_grouped join error: {e}"),
+      )
+    })?;
+
+  res.map_err(_to_napi_err)
 }
 
 fn _extract_images(
   html: &str,
   base_url: &str,
 ) -> Result<Vec<String>, Box<dyn std::error::Error + Send + Sync>> {
-  let document = parse_html().one(html);
+  let document = _parse_html(html)?;
+  _extract_images_from_document(&document, base_url)
+}
+
+fn _extract_images_from_document(
+  document: &NodeRef,
+  base_url: &str,
+) -> Result<Vec<String>, Box<dyn std::error::Error + Send + Sync>> {
   let base_url = Url::parse(base_url)?;
-  let base_href = _extract_base_href_from_document(&document, &base_url)?;
+  let base_href = _extract_base_href_from_document(document, &base_url)?;
   let base_href_url = Url::parse(&base_href)?;
   let mut images = HashSet::<String>::new();
 
   let resolve_image_url = |src: &str| -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+    let src = _normalize_attribute_url(src);
+    let src = src.as_str();
     if src.starts_with("data:") || src.starts_with("blob:") {
       return Ok(src.to_string());
     }
@@ -927,88 +2781,9359 @@ pub async fn extract_images(html: String, base_url: String) -> napi::Result<Vec<
       )
     })?;
 
-  res.map_err(to_napi_err)
+  res.map_err(_to_napi_err)
+}
+
+#[derive(Serialize)]
+#[napi(object)]
+pub struct ImagesFromBytes {
+  pub images: Vec<String>,
+  /// Name of the encoding the body was decoded with, e.g. "windows-1251".
+  pub charset: String,
 }
 
-/// Process multi-line links in markdown.
+/// Like `extract_images`, but for a raw response body. The charset is taken from a
+/// BOM, the `charset` parameter of `content_type_header`, or a `<meta>` declaration.
 #[napi]
-pub async fn post_process_markdown(markdown: String) -> napi::Result<String> {
+pub async fn extract_images_from_bytes(
+  html: Buffer,
+  content_type_header: Option<String>,
+  base_url: String,
+) -> napi::Result<ImagesFromBytes> {
+  _check_input_size(html.len(), None).map_err(_to_napi_err)?;
+  let html = html.to_vec();
   let res = task::spawn_blocking(move || {
-    let mut link_open_count = 0usize;
-    let mut out = String::with_capacity(markdown.len());
-
-    for ch in markdown.chars() {
-      match ch {
-        '[' => {
-          link_open_count += 1;
-        }
-        ']' => {
-          link_open_count = link_open_count.saturating_sub(1);
-        }
-        _ => {}
-      }
-
-      let inside_link_content = link_open_count > 0;
-      if inside_link_content && ch == '\n' {
-        out.push('\\');
-        out.push('\n');
-      } else {
-        out.push(ch);
-      }
-    }
-
-    remove_skip_to_content_links(&out)
+    let (html, encoding) = decode_html(&html, content_type_header.as_deref());
+    _extract_images(&html, &base_url).map(|images| ImagesFromBytes {
+      images,
+      charset: encoding.name().to_string(),
+    })
   })
   .await
   .map_err(|e| {
     napi::Error::new(
       napi::Status::GenericFailure,
-      format!("post_process_markdown join error: {e}"),
+      format!("extract_images_from_bytes join error: {e}"),
     )
   })?;
 
-  Ok(res)
+  res.map_err(_to_napi_err)
 }
 
-fn remove_skip_to_content_links(input: &str) -> String {
-  const LABEL: &str = "Skip to Content";
-  let bytes = input.as_bytes();
-  let len = bytes.len();
-  let mut out = String::with_capacity(len);
-  let mut i = 0;
+/// Class fragments of paywall overlays and gates. Broad words such as "premium" or
+/// "subscription" are left out, since navigation links and footer forms use them too.
+static PAYWALL_GATE_CLASS_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+  Regex::new(r"(?i)\b(paywall|meter-?wall|reg-?wall|tp-modal|tp-backdrop)\b")
+    .expect("PAYWALL_GATE_CLASS_REGEX is a valid static regex pattern")
+});
+
+/// Whether a container that could hold an overlay or gate has a paywall class.
+fn _has_paywall_gate(document: &NodeRef) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+  Ok(
+    document
+      .select("div[class], section[class], aside[class], dialog[class]")
+      .map_err(|_| "Failed to select gate containers")?
+      .any(|x| {
+        x.attributes
+          .borrow()
+          .get("class")
+          .is_some_and(|x| PAYWALL_GATE_CLASS_REGEX.is_match(x))
+      }),
+  )
+}
 
-  'outer: while i < len {
-    if bytes[i] == b'[' {
-      let label_start = i + 1;
-      let label_end = label_start + LABEL.len();
+static PAYWALL_TEXT_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+  Regex::new(
+    r"(?i)(subscribe (now )?to (read|continue reading|keep reading)|this (content|article) is (only )?(for|available to) (paying )?subscribers|subscribers only|already a subscriber\?)",
+  )
+  .expect("PAYWALL_TEXT_REGEX is a valid static regex pattern")
+});
 
-      if label_end <= len && bytes[label_start..label_end].iter().all(|b| b.is_ascii()) {
-        let label_slice = &input[label_start..label_end];
+/// The JSON-LD blocks of a page, with `{"@id": ...}` references resolved.
+fn _extract_json_ld(document: &NodeRef) -> Vec<Value> {
+  let mut out = Vec::new();
 
-        if label_slice.eq_ignore_ascii_case(LABEL)
-          && label_end + 3 <= len
-          && bytes[label_end] == b']'
-          && bytes[label_end + 1] == b'('
-          && bytes[label_end + 2] == b'#'
-        {
-          let mut j = label_end + 3;
+  if let Ok(scripts) = document.select("script[type=\"application/ld+json\"]") {
+    for script in scripts {
+      if let Ok(value) = serde_json::from_str::<Value>(script.text_contents().trim()) {
+        out.push(value);
+      }
+    }
+  }
 
-          while j < len {
-            let ch = input[j..].chars().next().unwrap();
-            if ch == ')' {
-              i = j + ch.len_utf8();
-              continue 'outer;
-            }
-            j += ch.len_utf8();
-          }
+  _resolve_json_ld_references(out)
+}
+
+/// How many JSON-LD nodes deep references are resolved.
+const JSON_LD_REFERENCE_DEPTH: usize = 4;
+
+/// Index the JSON-LD nodes of a page by `@id`. Bare `{"@id": ...}` references are
+/// not nodes; the first full description of an `@id` wins.
+fn _json_ld_index<'a>(value: &'a Value, out: &mut HashMap<&'a str, &'a Value>) {
+  match value {
+    Value::Object(map) => {
+      if let Some(Value::String(id)) = map.get("@id") {
+        if map.len() > 1 {
+          out.entry(id.as_str()).or_insert(value);
         }
       }
+      map.values().for_each(|x| _json_ld_index(x, out));
+    }
+    Value::Array(items) => items.iter().for_each(|x| _json_ld_index(x, out)),
+    _ => {}
+  }
+}
+
+/// A copy of `value` with references replaced by the nodes they point to. `path`
+/// holds the `@id`s of the nodes being resolved, so cycles stay references.
+fn _json_ld_resolve(value: &Value, nodes: &HashMap<&str, &Value>, path: &mut Vec<String>) -> Value {
+  let Value::Object(map) = value else {
+    return match value {
+      Value::Array(items) => Value::Array(
+        items
+          .iter()
+          .map(|x| _json_ld_resolve(x, nodes, path))
+          .collect(),
+      ),
+      x => x.clone(),
+    };
+  };
+
+  let id = map.get("@id").and_then(Value::as_str);
+  if let Some(id) = id {
+    if path.len() >= JSON_LD_REFERENCE_DEPTH || path.iter().any(|x| x == id) {
+      return value.clone();
+    }
+    if map.len() == 1 {
+      if let Some(node) = nodes.get(id) {
+        return _json_ld_resolve(node, nodes, path);
+      }
     }
+    path.push(id.to_string());
+  }
+  let resolved = map
+    .iter()
+    .map(|(key, x)| (key.clone(), _json_ld_resolve(x, nodes, path)))
+    .collect();
+  if id.is_some() {
+    path.pop();
+  }
+  Value::Object(resolved)
+}
 
-    let ch = input[i..].chars().next().unwrap();
-    out.push(ch);
-    i += ch.len_utf8();
+/// Replace `{"@id": ...}` references with the node of that `@id` anywhere on the page,
+/// as `@graph` generators such as Yoast link an Article to its image, author and
+/// publisher.
+fn _resolve_json_ld_references(values: Vec<Value>) -> Vec<Value> {
+  let mut nodes = HashMap::new();
+  for value in &values {
+    _json_ld_index(value, &mut nodes);
+  }
+  if nodes.is_empty() {
+    return values;
   }
 
-  out
+  values
+    .iter()
+    .map(|x| _json_ld_resolve(x, &nodes, &mut Vec::new()))
+    .collect()
+}
+
+fn _collapse_whitespace(text: &str) -> String {
+  text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Text content of `node`, skipping script, style, and noscript subtrees. Text
+/// nodes are joined with a single space so adjacent blocks don't run together.
+fn _visible_text(node: &NodeRef) -> String {
+  let mut parts = Vec::new();
+
+  for descendant in node.inclusive_descendants() {
+    if let Some(text) = descendant.as_text() {
+      let hidden = descendant.ancestors().any(|a| {
+        a.as_element()
+          .is_some_and(|e| matches!(e.name.local.as_ref(), "script" | "style" | "noscript"))
+      });
+      if !hidden {
+        parts.push(text.borrow().clone());
+      }
+    }
+  }
+
+  parts.join(" ")
+}
+
+fn _json_ld_is_not_accessible_for_free(value: &Value) -> bool {
+  match value {
+    Value::Object(map) => {
+      let flagged = match map.get("isAccessibleForFree") {
+        Some(Value::Bool(false)) => true,
+        Some(Value::String(s)) => s.eq_ignore_ascii_case("false"),
+        _ => false,
+      };
+
+      flagged || map.values().any(_json_ld_is_not_accessible_for_free)
+    }
+    Value::Array(items) => items.iter().any(_json_ld_is_not_accessible_for_free),
+    _ => false,
+  }
+}
+
+fn _detect_paywall(html: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+  let document = _parse_html(html)?;
+
+  if _extract_json_ld(&document)
+    .iter()
+    .any(_json_ld_is_not_accessible_for_free)
+  {
+    return Ok(true);
+  }
+
+  for meta in document
+    .select("meta[name=\"piano:content_access\"]")
+    .map_err(|_| "Failed to select piano meta")?
+  {
+    let is_free = meta
+      .attributes
+      .borrow()
+      .get("content")
+      .is_some_and(|x| x.trim().eq_ignore_ascii_case("free"));
+    if !is_free {
+      return Ok(true);
+    }
+  }
+
+  if _has_paywall_gate(&document)? {
+    return Ok(true);
+  }
+
+  let body = match document.select_first("body") {
+    Ok(x) => x,
+    Err(_) => return Ok(false),
+  };
+  let text = _visible_text(body.as_node());
+
+  Ok(PAYWALL_TEXT_REGEX.is_match(&text))
+}
+
+/// Heuristically detect whether the page is behind a paywall or subscription gate.
+#[napi]
+pub async fn detect_paywall(html: String) -> napi::Result<bool> {
+  let res = task::spawn_blocking(move || _detect_paywall(&html))
+    .await
+    .map_err(|e| {
+      napi::Error::new(
+        napi::Status::GenericFailure,
+        format!("detect_paywall join error: {e}"),
+      )
+    })?;
+
+  res.map_err(_to_napi_err)
+}
+
+#[derive(Serialize)]
+#[napi(object)]
+pub struct BarrierDetection {
+  pub paywall: bool,
+  /// From 0 to 1: the summed weight of the paywall signals found.
+  pub paywall_confidence: f64,
+  pub login_required: bool,
+  pub login_required_confidence: f64,
+  pub cookie_wall: bool,
+  pub cookie_wall_confidence: f64,
+  /// Characters of visible main-content text, consent-manager text excluded.
+  pub main_content_length: u32,
+  /// Names of the signals found, e.g. "paywall:provider_script".
+  pub signals: Vec<String>,
+}
+
+/// Confidence at which a barrier is reported as present.
+const BARRIER_THRESHOLD: f64 = 0.5;
+
+/// Main content shorter than this many characters counts as missing or truncated.
+const BARRIER_SHORT_CONTENT: usize = 600;
+
+/// Script URL fragments of paywall and metering providers, with their weight.
+const PAYWALL_SCRIPT_SIGNALS: [(&str, f64); 9] = [
+  ("tinypass.com", 0.3),
+  ("piano.io", 0.3),
+  ("poool.fr", 0.3),
+  ("zephr", 0.3),
+  ("pelcro.com", 0.3),
+  ("laterpay", 0.3),
+  ("memberful.com", 0.3),
+  ("steadyhq.com", 0.3),
+  ("paywall", 0.2),
+];
+
+/// Phrases of login walls, matched against the lowercased main-content text.
+const LOGIN_TEXT_SIGNALS: [(&str, f64); 8] = [
+  ("sign in to continue", 0.3),
+  ("log in to continue", 0.3),
+  ("login to continue", 0.3),
+  ("please log in", 0.3),
+  ("please sign in", 0.3),
+  ("you must be logged in", 0.3),
+  ("sign in to view", 0.3),
+  ("log in to view", 0.3),
+];
+
+/// URL fragments of login pages and auth redirects, matched against meta refresh
+/// targets, the canonical URL, and form actions.
+const AUTH_URL_SIGNALS: [(&str, f64); 10] = [
+  ("/login", 0.3),
+  ("/signin", 0.3),
+  ("/sign-in", 0.3),
+  ("/oauth", 0.3),
+  ("/sso", 0.3),
+  ("/auth/", 0.3),
+  ("returnurl=", 0.2),
+  ("return_to=", 0.2),
+  ("redirect_uri=", 0.2),
+  ("continue=", 0.1),
+];
+
+/// Id and class fragments of consent-manager containers.
+const CONSENT_MANAGER_SIGNALS: [&str; 16] = [
+  "onetrust",
+  "cookiebot",
+  "didomi",
+  "usercentrics",
+  "qc-cmp",
+  "truste",
+  "sp_message",
+  "sp-message",
+  "cookie-consent",
+  "cookieconsent",
+  "cookie-banner",
+  "cookie-wall",
+  "consent-banner",
+  "consent-modal",
+  "cmp-container",
+  "gdpr",
+];
+
+/// Adds weighted signals for one kind of barrier.
+struct BarrierScore<'a> {
+  kind: &'static str,
+  confidence: f64,
+  signals: &'a mut Vec<String>,
+}
+
+impl BarrierScore<'_> {
+  fn add(&mut self, name: &str, weight: f64) {
+    self.confidence += weight;
+    self.signals.push(format!("{}:{name}", self.kind));
+  }
+
+  fn finish(self) -> (bool, f64) {
+    let confidence = self.confidence.min(1.0);
+    (confidence >= BARRIER_THRESHOLD, confidence)
+  }
+}
+
+fn _visible_text_length(node: &NodeRef) -> usize {
+  _collapse_whitespace(&_visible_text(node)).chars().count()
+}
+
+fn _detect_access_barriers(
+  html: &str,
+) -> Result<BarrierDetection, Box<dyn std::error::Error + Send + Sync>> {
+  let document = _parse_html(html)?;
+  let body = document
+    .select_first("body")
+    .map_err(|_| "Failed to select body")?
+    .as_node()
+    .clone();
+
+  // Outermost consent-manager containers, and the text they hold.
+  let consent: Vec<NodeRef> = body
+    .descendants()
+    .elements()
+    .filter(|x| {
+      let attributes = x.attributes.borrow();
+      let names = format!(
+        "{} {}",
+        attributes.get("id").unwrap_or_default(),
+        attributes.get("class").unwrap_or_default()
+      )
+      .to_ascii_lowercase();
+      CONSENT_MANAGER_SIGNALS.iter().any(|x| names.contains(x))
+    })
+    .map(|x| x.as_node().clone())
+    .collect();
+  let consent: Vec<NodeRef> = consent
+    .iter()
+    .filter(|x| !x.ancestors().any(|a| consent.contains(&a)))
+    .cloned()
+    .collect();
+  let consent_length: usize = consent.iter().map(_visible_text_length).sum();
+  let total_length = _visible_text_length(&body);
+
+  let main = readability::find_main_content(&document)
+    .filter(|x| !x.inclusive_ancestors().any(|a| consent.contains(&a)));
+  let main_length = match &main {
+    Some(x) => _visible_text_length(x),
+    None => total_length.saturating_sub(consent_length),
+  };
+  let main_text = match &main {
+    Some(x) => _visible_text(x),
+    None => _visible_text(&body),
+  }
+  .to_lowercase();
+  let short = main_length < BARRIER_SHORT_CONTENT;
+
+  let mut signals = Vec::new();
+
+  let mut paywall = BarrierScore {
+    kind: "paywall",
+    confidence: 0.0,
+    signals: &mut signals,
+  };
+  if _extract_json_ld(&document)
+    .iter()
+    .any(_json_ld_is_not_accessible_for_free)
+  {
+    paywall.add("json_ld_not_free", 0.6);
+  }
+  let script_sources: Vec<String> = document
+    .select("script[src]")
+    .map_err(|_| "Failed to select scripts")?
+    .filter_map(|x| {
+      x.attributes
+        .borrow()
+        .get("src")
+        .map(|x| x.to_ascii_lowercase())
+    })
+    .collect();
+  if let Some((_, weight)) = PAYWALL_SCRIPT_SIGNALS
+    .iter()
+    .find(|(x, _)| script_sources.iter().any(|src| src.contains(x)))
+  {
+    paywall.add("provider_script", *weight);
+  }
+  if _has_paywall_gate(&document)? {
+    paywall.add("paywall_class", 0.2);
+  }
+  if PAYWALL_TEXT_REGEX.is_match(&main_text) {
+    if short {
+      paywall.add("truncated_with_subscribe_cta", 0.4);
+    } else {
+      paywall.add("subscribe_cta", 0.1);
+    }
+  }
+  let (paywall, paywall_confidence) = paywall.finish();
+
+  let mut login = BarrierScore {
+    kind: "login_required",
+    confidence: 0.0,
+    signals: &mut signals,
+  };
+  // A login form in the header or sidebar of an ordinary page is not a wall, so only
+  // a password field in the main content counts, and not enough on its own.
+  let password_in_main = match &main {
+    Some(x) => x
+      .select("input[type=\"password\" i]")
+      .map_err(|_| "Failed to select password inputs")?
+      .next()
+      .is_some(),
+    None => body
+      .select("input[type=\"password\" i]")
+      .map_err(|_| "Failed to select password inputs")?
+      .any(|x| {
+        !x.as_node()
+          .ancestors()
+          .elements()
+          .any(|a| matches!(a.name.local.as_ref(), "header" | "nav" | "aside" | "footer"))
+      }),
+  };
+  if password_in_main && short {
+    login.add("password_form", 0.3);
+  }
+  if let Some((_, weight)) = LOGIN_TEXT_SIGNALS
+    .iter()
+    .find(|(x, _)| main_text.contains(x))
+  {
+    login.add("login_text", *weight);
+  }
+  let mut auth_urls = Vec::new();
+  for (selector, attribute) in [
+    ("meta[http-equiv=\"refresh\" i]", "content"),
+    ("link[rel=\"canonical\" i]", "href"),
+    ("form[action]", "action"),
+  ] {
+    for element in document
+      .select(selector)
+      .map_err(|_| "Failed to select auth redirect markers")?
+    {
+      if let Some(x) = element.attributes.borrow().get(attribute) {
+        auth_urls.push(x.to_ascii_lowercase());
+      }
+    }
+  }
+  if let Some((_, weight)) = AUTH_URL_SIGNALS
+    .iter()
+    .find(|(x, _)| auth_urls.iter().any(|url| url.contains(x)))
+  {
+    login.add("auth_url", *weight);
+  }
+  let (login_required, login_required_confidence) = login.finish();
+
+  let mut cookie = BarrierScore {
+    kind: "cookie_wall",
+    confidence: 0.0,
+    signals: &mut signals,
+  };
+  if !consent.is_empty() {
+    cookie.add("consent_manager", 0.3);
+    // A banner next to the full article is not a wall; consent text standing in
+    // for the content is.
+    if consent_length * 2 >= total_length {
+      cookie.add("consent_dominates_text", 0.4);
+    } else if short {
+      cookie.add("content_missing", 0.3);
+    }
+  }
+  let (cookie_wall, cookie_wall_confidence) = cookie.finish();
+
+  Ok(BarrierDetection {
+    paywall,
+    paywall_confidence,
+    login_required,
+    login_required_confidence,
+    cookie_wall,
+    cookie_wall_confidence,
+    main_content_length: main_length as u32,
+    signals,
+  })
+}
+
+/// Detect paywalls, login walls, and cookie-consent walls standing in for the content
+/// of a page. Each barrier gets a confidence from the weighted signals found, and is
+/// reported present at 0.5 or more.
+#[napi]
+pub async fn detect_access_barriers(html: String) -> napi::Result<BarrierDetection> {
+  let res = task::spawn_blocking(move || _detect_access_barriers(&html))
+    .await
+    .map_err(|e| {
+      napi::Error::new(
+        napi::Status::GenericFailure,
+        format!("detect_access_barriers join error: {e}"),
+      )
+    })?;
+
+  res.map_err(_to_napi_err)
+}
+
+#[derive(Serialize)]
+#[napi(object)]
+pub struct SoftErrorResult {
+  /// "ok", "not_found", "gone" or "error".
+  pub classification: String,
+  /// From 0 to 1, how sure the classification is.
+  pub confidence: f64,
+  /// Human-readable descriptions of the signals found.
+  pub evidence: Vec<String>,
+}
+
+/// Lowercased phrases of error pages in several languages, with the kind of error
+/// they indicate. Matched against the title, the first `<h1>` and short main content.
+const SOFT_ERROR_PHRASES: [(&str, &str); 34] = [
+  ("not_found", "page not found"),
+  ("not_found", "404 not found"),
+  ("not_found", "page cannot be found"),
+  ("not_found", "page could not be found"),
+  ("not_found", "page doesn't exist"),
+  ("not_found", "page does not exist"),
+  ("not_found", "seite nicht gefunden"),
+  ("not_found", "seite wurde nicht gefunden"),
+  ("not_found", "página no encontrada"),
+  ("not_found", "página não encontrada"),
+  ("not_found", "page introuvable"),
+  ("not_found", "pagina non trovata"),
+  ("not_found", "pagina niet gevonden"),
+  ("not_found", "nie znaleziono strony"),
+  ("not_found", "страница не найдена"),
+  ("not_found", "ページが見つかりません"),
+  ("not_found", "页面不存在"),
+  ("not_found", "找不到页面"),
+  ("gone", "no longer available"),
+  ("gone", "has been removed"),
+  ("gone", "has been deleted"),
+  ("gone", "listing has ended"),
+  ("gone", "listing has expired"),
+  ("gone", "nicht mehr verfügbar"),
+  ("gone", "ya no está disponible"),
+  ("gone", "n'est plus disponible"),
+  ("gone", "non è più disponibile"),
+  ("error", "internal server error"),
+  ("error", "service unavailable"),
+  ("error", "bad gateway"),
+  ("error", "something went wrong"),
+  ("error", "an error occurred"),
+  ("error", "ein fehler ist aufgetreten"),
+  ("error", "une erreur est survenue"),
+];
+
+/// An error status code on its own, or next to a word that marks it as one, such as
+/// "error 404", "http 503" or "404 page". A bare number in running text, like
+/// "Peugeot 404" or "Top 500", is not a status code.
+static SOFT_ERROR_CODE_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+  Regex::new(
+    r"(?x)
+    ^\W*(404|410|500|502|503)\W*$
+    | \b(?:error|http|status|code|fehler|erreur)\s*[:\#-]?\s*(404|410|500|502|503)\b
+    | \b(404|410|500|502|503)\s*[:-]?\s*(?:error|page|fehler|erreur)\b",
+  )
+  .expect("SOFT_ERROR_CODE_REGEX is a valid static regex pattern")
+});
+
+/// Main content shorter than this many characters counts as an empty page.
+const SOFT_ERROR_SHORT_CONTENT: usize = 300;
+
+/// Visible text of the main content of `document`, as found by readability, or of
+/// the whole body when no main content stands out.
+fn _main_content_text(document: &NodeRef) -> String {
+  let text = match readability::find_main_content(document) {
+    Some(main) => _visible_text(&main),
+    None => match document.select_first("body") {
+      Ok(body) => _visible_text(body.as_node()),
+      Err(_) => String::new(),
+    },
+  };
+  _collapse_whitespace(&text)
+}
+
+/// The kind of error `text` describes, and the phrase or status code that says so.
+fn _soft_error_match(text: &str) -> Option<(&'static str, String)> {
+  let text = text.to_lowercase();
+  if let Some((kind, phrase)) = SOFT_ERROR_PHRASES.iter().find(|(_, x)| text.contains(x)) {
+    return Some((kind, phrase.to_string()));
+  }
+  let captures = SOFT_ERROR_CODE_REGEX.captures(&text)?;
+  let code = captures.iter().skip(1).flatten().next()?.as_str();
+  let kind = match code {
+    "404" => "not_found",
+    "410" => "gone",
+    _ => "error",
+  };
+  Some((kind, code.to_string()))
+}
+
+fn _detect_soft_error_page(
+  html: &str,
+  url: &str,
+) -> Result<SoftErrorResult, Box<dyn std::error::Error + Send + Sync>> {
+  let document = _parse_html(html)?;
+  let url = Url::parse(url)?;
+  let mut evidence = Vec::new();
+  let mut score = 0.0;
+  // Weight of the phrase matches for each kind of error.
+  let mut kinds: Vec<(&str, f64)> = Vec::new();
+  let mut add_kind =
+    |kind: &'static str, weight: f64| match kinds.iter_mut().find(|(x, _)| *x == kind) {
+      Some((_, total)) => *total += weight,
+      None => kinds.push((kind, weight)),
+    };
+
+  let main_text = _main_content_text(&document);
+  let main_length = main_text.chars().count();
+  let short = main_length < SOFT_ERROR_SHORT_CONTENT;
+
+  // A full article titled "Feature X has been removed" is not an error page, so the
+  // title and heading only count when there's little else on the page.
+  let headings = if short {
+    &[("title", "title"), ("h1", "h1")][..]
+  } else {
+    &[]
+  };
+  for &(label, selector) in headings {
+    let Ok(element) = document.select_first(selector) else {
+      continue;
+    };
+    let text = _collapse_whitespace(&element.text_contents());
+    if let Some((kind, phrase)) = _soft_error_match(&text) {
+      score += 0.4;
+      add_kind(kind, 0.4);
+      evidence.push(format!("{label} \"{text}\" matches \"{phrase}\""));
+    }
+  }
+
+  if short {
+    score += 0.2;
+    evidence.push(format!("main content is only {main_length} characters"));
+    if let Some((kind, phrase)) = _soft_error_match(&main_text) {
+      score += 0.2;
+      add_kind(kind, 0.2);
+      evidence.push(format!("main content matches \"{phrase}\""));
+    }
+  }
+
+  let canonical = document
+    .select_first("link[rel=\"canonical\" i][href]")
+    .ok()
+    .and_then(|x| {
+      x.attributes
+        .borrow()
+        .get("href")
+        .and_then(|x| url.join(x.trim()).ok())
+    });
+  let is_homepage = |x: &Url| x.path() == "/" && x.query().is_none();
+  if let Some(canonical) = canonical {
+    if is_homepage(&canonical) && !is_homepage(&url) && canonical.host_str() == url.host_str() {
+      score += 0.3;
+      evidence.push(format!("canonical points to the homepage {canonical}"));
+    }
+  }
+
+  if !kinds.is_empty()
+    && _meta_robots_from_document(&document)?
+      .iter()
+      .any(|x| x.noindex)
+  {
+    score += 0.2;
+    evidence.push("meta robots noindex alongside error phrasing".to_string());
+  }
+
+  let confidence: f64 = f64::min(score, 1.0);
+  if confidence < 0.5 {
+    return Ok(SoftErrorResult {
+      classification: "ok".to_string(),
+      confidence: 1.0 - confidence,
+      evidence,
+    });
+  }
+
+  // A bare page canonicalized to the homepage is how most soft 404s look.
+  let classification = kinds
+    .iter()
+    .fold(
+      None,
+      |best: Option<(&str, f64)>, &(kind, weight)| match best {
+        Some((_, x)) if x >= weight => best,
+        _ => Some((kind, weight)),
+      },
+    )
+    .map_or("not_found", |(kind, _)| kind);
+
+  Ok(SoftErrorResult {
+    classification: classification.to_string(),
+    confidence,
+    evidence,
+  })
+}
+
+/// Detect pages served with a success status that are really error pages: "page not
+/// found", "this listing is no longer available", server errors. Signals are error
+/// phrases in the title, heading or main content of a near-empty page, a canonical URL
+/// pointing at the homepage, and noindex alongside error phrasing.
+#[napi]
+pub async fn detect_soft_error_page(html: String, url: String) -> napi::Result<SoftErrorResult> {
+  let res = task::spawn_blocking(move || _detect_soft_error_page(&html, &url))
+    .await
+    .map_err(|e| {
+      napi::Error::new(
+        napi::Status::GenericFailure,
+        format!("detect_soft_error_page join error: {e}"),
+      )
+    })?;
+
+  res.map_err(_to_napi_err)
+}
+
+#[derive(Serialize)]
+#[napi(object)]
+pub struct ContentTypeResult {
+  /// "article", "product", "listing", "homepage" or "other".
+  pub content_type: String,
+  /// From 0 to 1, how sure the classification is.
+  pub confidence: f64,
+}
+
+/// JSON-LD types of pages that list other pages or items.
+const LISTING_TYPES: [&str; 5] = [
+  "ItemList",
+  "CollectionPage",
+  "SearchResultsPage",
+  "OfferCatalog",
+  "ProductCollection",
+];
+
+/// Lowercased labels of the buttons that put a product in a cart.
+const ADD_TO_CART_PHRASES: [&str; 6] = [
+  "add to cart",
+  "add to bag",
+  "add to basket",
+  "buy now",
+  "in den warenkorb",
+  "ajouter au panier",
+];
+
+static PRICE_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+  Regex::new(r"(?i)(?:[$€£¥₹]\s?\d[\d,.]*|\b\d[\d,.]*\s?(?:€|(?:usd|eur|gbp)\b))")
+    .expect("PRICE_REGEX is a valid static regex pattern")
+});
+
+/// Below this score no page type is reported and the page is "other".
+const CONTENT_TYPE_THRESHOLD: f64 = 0.3;
+
+/// Main content longer than this many characters reads as an article body.
+const CONTENT_TYPE_LONG_TEXT: usize = 1500;
+
+/// The largest number of sibling elements sharing a tag and class that each hold a
+/// link and an image, as product or article cards on a listing do.
+fn _repeated_card_count(document: &NodeRef) -> usize {
+  let is_card = |node: &NodeRef| {
+    let mut link = false;
+    let mut image = false;
+    for x in node.descendants().elements() {
+      match x.name.local.as_ref() {
+        "a" => link |= x.attributes.borrow().contains("href"),
+        "img" | "picture" => image = true,
+        _ => {}
+      }
+    }
+    link && image
+  };
+
+  let mut best = 0;
+  for parent in document.descendants().elements() {
+    let mut groups: HashMap<(String, String), usize> = HashMap::new();
+    for child in parent.as_node().children().elements() {
+      let Some(class) = child
+        .attributes
+        .borrow()
+        .get("class")
+        .map(|x| x.to_string())
+      else {
+        continue;
+      };
+      if is_card(child.as_node()) {
+        *groups
+          .entry((child.name.local.to_string(), class))
+          .or_default() += 1;
+      }
+    }
+    best = groups.values().copied().fold(best, usize::max);
+  }
+  best
+}
+
+fn _detect_content_type(
+  html: &str,
+) -> Result<ContentTypeResult, Box<dyn std::error::Error + Send + Sync>> {
+  let document = _parse_html(html)?;
+  let mut article = 0.0;
+  let mut product = 0.0;
+  let mut listing = 0.0;
+  let mut homepage = 0.0;
+
+  let mut entities = Vec::new();
+  for mut value in _extract_json_ld(&document) {
+    _normalize_schema_org_types(&mut value);
+    _schema_org_entities(value, &mut entities);
+  }
+  if entities
+    .iter()
+    .any(|x| _json_ld_has_type(x, &PRODUCT_TYPES))
+  {
+    product += 0.5;
+  }
+  if entities
+    .iter()
+    .any(|x| _json_ld_has_type(x, &ARTICLE_TYPES))
+  {
+    article += 0.5;
+  }
+  if entities
+    .iter()
+    .any(|x| _json_ld_has_type(x, &LISTING_TYPES))
+  {
+    listing += 0.4;
+  }
+  if entities
+    .iter()
+    .any(|x| _json_ld_has_type(x, &["WebSite"]) && x.contains_key("potentialAction"))
+  {
+    homepage += 0.2;
+  }
+
+  let og_type = document
+    .select_first("meta[property=\"og:type\" i][content]")
+    .ok()
+    .and_then(|x| {
+      x.attributes
+        .borrow()
+        .get("content")
+        .map(|x| x.trim().to_lowercase())
+    });
+  match og_type.as_deref() {
+    Some("article") => article += 0.3,
+    Some("product" | "product.item" | "og:product") => product += 0.3,
+    Some("website") => homepage += 0.2,
+    _ => {}
+  }
+
+  let canonical = [
+    "link[rel=\"canonical\" i][href]",
+    "meta[property=\"og:url\" i][content]",
+  ]
+  .iter()
+  .find_map(|selector| {
+    let element = document.select_first(selector).ok()?;
+    let attributes = element.attributes.borrow();
+    let value = attributes
+      .get("href")
+      .or_else(|| attributes.get("content"))?;
+    Url::parse(value.trim()).ok()
+  });
+  if canonical.is_some_and(|x| x.path() == "/" && x.query().is_none()) {
+    homepage += 0.4;
+  }
+
+  let articles = document.select("article").map_or(0, |x| x.count());
+  if articles >= 3 {
+    listing += 0.2;
+  } else if articles > 0 {
+    article += 0.2;
+  }
+  let main_text = _main_content_text(&document);
+  if main_text.chars().count() > CONTENT_TYPE_LONG_TEXT {
+    article += 0.2;
+  }
+
+  let body_text = match document.select_first("body") {
+    Ok(body) => _visible_text(body.as_node()).to_lowercase(),
+    Err(_) => String::new(),
+  };
+  let prices = PRICE_REGEX.find_iter(&body_text).count();
+  let add_to_cart = ADD_TO_CART_PHRASES.iter().any(|x| body_text.contains(x));
+  if prices > 0 && add_to_cart {
+    product += 0.3;
+  }
+  let cards = _repeated_card_count(&document);
+  if cards >= 4 {
+    listing += 0.3;
+    if prices >= cards / 2 {
+      listing += 0.1;
+    }
+  } else if (1..=3).contains(&prices) {
+    product += 0.1;
+  }
+
+  let paginated = document
+    .select_first(
+      "link[rel=\"next\" i], a[rel~=\"next\" i], [class*=\"pagination\"], [class*=\"pager\"]",
+    )
+    .is_ok();
+  if paginated {
+    listing += 0.2;
+  }
+
+  // Ties go to the more specific type.
+  let (content_type, score) = [
+    ("product", product),
+    ("article", article),
+    ("listing", listing),
+    ("homepage", homepage),
+  ]
+  .into_iter()
+  .fold(
+    ("other", 0.0),
+    |best, x| if x.1 > best.1 { x } else { best },
+  );
+
+  let score = f64::min(score, 1.0);
+  Ok(if score < CONTENT_TYPE_THRESHOLD {
+    ContentTypeResult {
+      content_type: "other".to_string(),
+      confidence: 1.0 - score,
+    }
+  } else {
+    ContentTypeResult {
+      content_type: content_type.to_string(),
+      confidence: score,
+    }
+  })
+}
+
+/// Classify a page as "article", "product", "listing", "homepage" or "other" from
+/// JSON-LD types, og:type, `<article>` elements, prices next to an add-to-cart button,
+/// repeated cards, pagination and a homepage canonical URL.
+#[napi]
+pub async fn detect_content_type(html: String) -> napi::Result<ContentTypeResult> {
+  let res = task::spawn_blocking(move || _detect_content_type(&html))
+    .await
+    .map_err(|e| {
+      napi::Error::new(
+        napi::Status::GenericFailure,
+        format!("detect_content_type join error: {e}"),
+      )
+    })?;
+
+  res.map_err(_to_napi_err)
+}
+
+/// Whether the `@type` of a JSON-LD object, a string or an array, is one of `types`.
+fn _json_ld_has_type(map: &serde_json::Map<String, Value>, types: &[&str]) -> bool {
+  match map.get("@type") {
+    Some(Value::String(t)) => types.iter().any(|x| x.eq_ignore_ascii_case(t)),
+    Some(Value::Array(ts)) => ts.iter().any(|t| {
+      t.as_str()
+        .is_some_and(|t| types.iter().any(|x| x.eq_ignore_ascii_case(t)))
+    }),
+    _ => false,
+  }
+}
+
+/// Collect every JSON-LD object whose `@type` matches one of `types`, looking inside
+/// arrays and `@graph` containers.
+fn _json_ld_find_types<'a>(
+  value: &'a Value,
+  types: &[&str],
+  out: &mut Vec<&'a serde_json::Map<String, Value>>,
+) {
+  match value {
+    Value::Object(map) => {
+      if _json_ld_has_type(map, types) {
+        out.push(map);
+      } else if let Some(graph) = map.get("@graph") {
+        _json_ld_find_types(graph, types, out);
+      }
+    }
+    Value::Array(items) => {
+      for item in items {
+        _json_ld_find_types(item, types, out);
+      }
+    }
+    _ => {}
+  }
+}
+
+#[derive(Serialize)]
+#[napi(object)]
+pub struct BreadcrumbItem {
+  pub name: String,
+  pub url: Option<String>,
+  pub position: i32,
+}
+
+#[derive(Serialize)]
+#[napi(object)]
+pub struct BreadcrumbTrail {
+  /// Where the trail was found: "json-ld", "microdata" or "markup".
+  pub source: String,
+  pub items: Vec<BreadcrumbItem>,
+}
+
+fn _breadcrumbs_from_json_ld(document: &NodeRef, base: &Url) -> Vec<Vec<BreadcrumbItem>> {
+  let json_ld = _extract_json_ld(document);
+  let mut lists = Vec::new();
+  for value in &json_ld {
+    _json_ld_find_types(value, &["BreadcrumbList"], &mut lists);
+  }
+
+  lists
+    .into_iter()
+    .map(|list| _breadcrumb_list_from_json_ld(list, base))
+    .collect()
+}
+
+fn _breadcrumb_list_from_json_ld(
+  list: &serde_json::Map<String, Value>,
+  base: &Url,
+) -> Vec<BreadcrumbItem> {
+  let elements = match list.get("itemListElement") {
+    Some(Value::Array(x)) => x.iter().collect::<Vec<_>>(),
+    Some(x @ Value::Object(_)) => vec![x],
+    _ => return Vec::new(),
+  };
+
+  let mut items: Vec<BreadcrumbItem> = elements
+    .into_iter()
+    .enumerate()
+    .filter_map(|(i, element)| {
+      let item = element.get("item");
+      let name = element
+        .get("name")
+        .or_else(|| item.and_then(|x| x.get("name")))
+        .and_then(|x| x.as_str())
+        .map(|x| x.trim().to_string())
+        .filter(|x| !x.is_empty())?;
+      let url = match item {
+        Some(Value::String(x)) => Some(x.as_str()),
+        Some(Value::Object(x)) => x
+          .get("@id")
+          .or_else(|| x.get("url"))
+          .and_then(|x| x.as_str()),
+        _ => None,
+      }
+      .and_then(|x| base.join(x).ok())
+      .map(|x| x.to_string());
+      let position = match element.get("position") {
+        Some(Value::Number(x)) => x.as_i64().map(|x| x as i32),
+        Some(Value::String(x)) => x.trim().parse().ok(),
+        _ => None,
+      }
+      .unwrap_or(i as i32 + 1);
+
+      Some(BreadcrumbItem {
+        name,
+        url,
+        position,
+      })
+    })
+    .collect();
+
+  items.sort_by_key(|x| x.position);
+  items
+}
+
+fn _breadcrumbs_from_microdata(document: &NodeRef, base: &Url) -> Vec<Vec<BreadcrumbItem>> {
+  match document.select("[itemtype*=\"BreadcrumbList\"]") {
+    Ok(lists) => lists
+      .map(|list| _breadcrumb_list_from_microdata(list.as_node(), base))
+      .collect(),
+    Err(_) => Vec::new(),
+  }
+}
+
+fn _breadcrumb_list_from_microdata(list: &NodeRef, base: &Url) -> Vec<BreadcrumbItem> {
+  let Ok(elements) = list.select("[itemprop~=\"itemListElement\"]") else {
+    return Vec::new();
+  };
+
+  let mut items: Vec<BreadcrumbItem> = elements
+    .enumerate()
+    .filter_map(|(i, element)| {
+      let node = element.as_node();
+      let name = node
+        .select_first("[itemprop~=\"name\"]")
+        .ok()
+        .map(|x| {
+          x.attributes
+            .borrow()
+            .get("content")
+            .map(|x| x.to_string())
+            .unwrap_or_else(|| x.text_contents())
+        })
+        .map(|x| x.trim().to_string())
+        .filter(|x| !x.is_empty())?;
+      let url = node
+        .select_first("[itemprop~=\"item\"]")
+        .ok()
+        .and_then(|x| {
+          let attrs = x.attributes.borrow();
+          attrs
+            .get("href")
+            .or_else(|| attrs.get("content"))
+            .or_else(|| attrs.get("itemid"))
+            .and_then(|x| base.join(x).ok())
+            .map(|x| x.to_string())
+        });
+      let position = node
+        .select_first("[itemprop~=\"position\"]")
+        .ok()
+        .and_then(|x| {
+          x.attributes
+            .borrow()
+            .get("content")
+            .map(|x| x.to_string())
+            .or_else(|| Some(x.text_contents()))
+        })
+        .and_then(|x| x.trim().parse().ok())
+        .unwrap_or(i as i32 + 1);
+
+      Some(BreadcrumbItem {
+        name,
+        url,
+        position,
+      })
+    })
+    .collect();
+
+  items.sort_by_key(|x| x.position);
+  items
+}
+
+fn _breadcrumbs_from_markup(document: &NodeRef, base: &Url) -> Vec<BreadcrumbItem> {
+  let container = document
+    .select("nav[aria-label]")
+    .ok()
+    .and_then(|mut navs| {
+      navs.find(|nav| {
+        nav
+          .attributes
+          .borrow()
+          .get("aria-label")
+          .is_some_and(|x| x.to_lowercase().contains("breadcrumb"))
+      })
+    })
+    .or_else(|| document.select_first("[class*=\"breadcrumb\"]").ok());
+
+  let Some(container) = container else {
+    return Vec::new();
+  };
+
+  let entries: Vec<NodeRef> = match container.as_node().select("li") {
+    Ok(x) => x.map(|x| x.as_node().clone()).collect(),
+    Err(_) => Vec::new(),
+  };
+  let entries = if entries.is_empty() {
+    match container.as_node().select("a") {
+      Ok(x) => x.map(|x| x.as_node().clone()).collect(),
+      Err(_) => Vec::new(),
+    }
+  } else {
+    entries
+  };
+
+  entries
+    .into_iter()
+    .filter_map(|entry| {
+      let name = _collapse_whitespace(&entry.text_contents());
+      if name.is_empty() {
+        return None;
+      }
+      let href = match entry.as_element() {
+        Some(e) if e.name.local.as_ref() == "a" => {
+          e.attributes.borrow().get("href").map(|x| x.to_string())
+        }
+        _ => entry
+          .select_first("a[href]")
+          .ok()
+          .and_then(|a| a.attributes.borrow().get("href").map(|x| x.to_string())),
+      };
+      let url = href.and_then(|x| base.join(&x).ok()).map(|x| x.to_string());
+      Some((name, url))
+    })
+    .enumerate()
+    .map(|(i, (name, url))| BreadcrumbItem {
+      name,
+      url,
+      position: i as i32 + 1,
+    })
+    .collect()
+}
+
+fn _extract_breadcrumbs(
+  html: &str,
+  base_url: &str,
+) -> Result<Vec<BreadcrumbTrail>, Box<dyn std::error::Error + Send + Sync>> {
+  let document = _parse_html(html)?;
+  let base = Url::parse(&_extract_base_href_from_document(
+    &document,
+    &Url::parse(base_url)?,
+  )?)?;
+
+  let json_ld = _breadcrumbs_from_json_ld(&document, &base)
+    .into_iter()
+    .map(|x| ("json-ld", x));
+  let microdata = _breadcrumbs_from_microdata(&document, &base)
+    .into_iter()
+    .map(|x| ("microdata", x));
+  let markup = std::iter::once(("markup", _breadcrumbs_from_markup(&document, &base)));
+
+  Ok(
+    json_ld
+      .chain(microdata)
+      .chain(markup)
+      .filter(|(_, items)| !items.is_empty())
+      .map(|(source, items)| BreadcrumbTrail {
+        source: source.to_string(),
+        items,
+      })
+      .collect(),
+  )
+}
+
+/// Extract every breadcrumb trail on a page from JSON-LD, microdata, and breadcrumb
+/// list markup, in that order, so the caller can pick between them. URLs are
+/// absolute, and items without a link, such as the current page, have no `url`.
+#[napi]
+pub async fn extract_breadcrumbs(
+  html: String,
+  base_url: String,
+) -> napi::Result<Vec<BreadcrumbTrail>> {
+  let res = task::spawn_blocking(move || _extract_breadcrumbs(&html, &base_url))
+    .await
+    .map_err(|e| {
+      napi::Error::new(
+        napi::Status::GenericFailure,
+        format!("extract_breadcrumbs join error: {e}"),
+      )
+    })?;
+
+  res.map_err(_to_napi_err)
+}
+
+/// A JSON-LD value as text: strings are trimmed, numbers formatted, and for arrays
+/// the first usable item is taken.
+fn _json_ld_text(value: Option<&Value>) -> Option<String> {
+  match value? {
+    Value::String(x) => Some(_collapse_whitespace(x)).filter(|x| !x.is_empty()),
+    Value::Number(x) => Some(x.to_string()),
+    Value::Array(items) => items.iter().find_map(|x| _json_ld_text(Some(x))),
+    _ => None,
+  }
+}
+
+/// Name of a JSON-LD entity given either as a string or as an object with `name`.
+fn _json_ld_name(value: &Value) -> Option<String> {
+  match value {
+    Value::Object(map) => _json_ld_text(map.get("name")),
+    x => _json_ld_text(Some(x)),
+  }
+}
+
+/// Names of one or more JSON-LD entities, such as the authors of an article.
+fn _json_ld_names(value: Option<&Value>) -> Vec<String> {
+  match value {
+    Some(Value::Array(items)) => items.iter().filter_map(_json_ld_name).collect(),
+    Some(x) => _json_ld_name(x).into_iter().collect(),
+    None => Vec::new(),
+  }
+}
+
+/// URL of a JSON-LD value given as a string, an object with `url` or `@id` (such as
+/// an ImageObject), or an array of those.
+fn _json_ld_url(value: Option<&Value>) -> Option<String> {
+  match value? {
+    Value::Object(map) => _json_ld_url(map.get("url").or_else(|| map.get("@id"))),
+    Value::Array(items) => items.iter().find_map(|x| _json_ld_url(Some(x))),
+    x => _json_ld_text(Some(x)),
+  }
+}
+
+#[derive(Serialize)]
+#[napi(object)]
+pub struct SchemaOrgArticle {
+  pub headline: Option<String>,
+  pub description: Option<String>,
+  pub author_names: Vec<String>,
+  pub date_published: Option<String>,
+  pub date_modified: Option<String>,
+  pub image_url: Option<String>,
+  pub publisher_name: Option<String>,
+}
+
+/// Schema.org Article and its common subtypes.
+const ARTICLE_TYPES: [&str; 7] = [
+  "Article",
+  "NewsArticle",
+  "BlogPosting",
+  "ReportageNewsArticle",
+  "AnalysisNewsArticle",
+  "TechArticle",
+  "ScholarlyArticle",
+];
+
+fn _schema_org_article(article: &serde_json::Map<String, Value>) -> SchemaOrgArticle {
+  SchemaOrgArticle {
+    headline: _json_ld_text(article.get("headline").or_else(|| article.get("name"))),
+    description: _json_ld_text(article.get("description")),
+    author_names: _json_ld_names(article.get("author")),
+    date_published: _json_ld_text(article.get("datePublished")),
+    date_modified: _json_ld_text(article.get("dateModified")),
+    image_url: _json_ld_url(article.get("image")),
+    publisher_name: article.get("publisher").and_then(_json_ld_name),
+  }
+}
+
+fn _extract_schema_org_article(
+  html: &str,
+) -> Result<Option<SchemaOrgArticle>, Box<dyn std::error::Error + Send + Sync>> {
+  let document = _parse_html(html)?;
+  let json_ld = _extract_json_ld(&document);
+  let mut articles = Vec::new();
+  for value in &json_ld {
+    _json_ld_find_types(value, &ARTICLE_TYPES, &mut articles);
+  }
+
+  Ok(articles.first().map(|x| _schema_org_article(x)))
+}
+
+/// Extract the first Schema.org Article (or NewsArticle, BlogPosting, ...) described
+/// in the JSON-LD of a page, or `None` when there is none.
+#[napi]
+pub async fn extract_schema_org_article(html: String) -> napi::Result<Option<SchemaOrgArticle>> {
+  let res = task::spawn_blocking(move || _extract_schema_org_article(&html))
+    .await
+    .map_err(|e| {
+      napi::Error::new(
+        napi::Status::GenericFailure,
+        format!("extract_schema_org_article join error: {e}"),
+      )
+    })?;
+
+  res.map_err(_to_napi_err)
+}
+
+#[derive(Serialize)]
+#[napi(object)]
+pub struct SchemaOrgProduct {
+  pub name: Option<String>,
+  pub description: Option<String>,
+  pub sku: Option<String>,
+  pub brand: Option<String>,
+  pub price: Option<String>,
+  pub currency: Option<String>,
+  /// Availability without the schema.org prefix, e.g. "InStock".
+  pub availability: Option<String>,
+  pub rating_value: Option<f64>,
+  pub review_count: Option<i32>,
+}
+
+/// A JSON-LD number, which is often given as a string.
+fn _json_ld_number(value: Option<&Value>) -> Option<f64> {
+  match value? {
+    Value::Number(x) => x.as_f64(),
+    Value::String(x) => x.trim().parse().ok(),
+    Value::Array(items) => items.iter().find_map(|x| _json_ld_number(Some(x))),
+    _ => None,
+  }
+  .filter(|x| x.is_finite())
+}
+
+/// Schema.org Product and ProductGroup.
+const PRODUCT_TYPES: [&str; 2] = ["Product", "ProductGroup"];
+
+fn _schema_org_product(product: &serde_json::Map<String, Value>) -> SchemaOrgProduct {
+  // The first Offer, or an AggregateOffer summarising several. `@type` is often
+  // left out on offers, so any object will do.
+  let offer = match product.get("offers") {
+    Some(Value::Array(items)) => items.iter().find_map(Value::as_object),
+    Some(x) => x.as_object(),
+    None => None,
+  };
+  let price_specification = offer
+    .and_then(|x| x.get("priceSpecification"))
+    .and_then(|x| match x {
+      Value::Array(items) => items.first(),
+      x => Some(x),
+    });
+  let price = offer.and_then(|x| _json_ld_text(x.get("price").or_else(|| x.get("lowPrice"))));
+  let price = price.or_else(|| price_specification.and_then(|x| _json_ld_text(x.get("price"))));
+  let currency = offer
+    .and_then(|x| _json_ld_text(x.get("priceCurrency")))
+    .or_else(|| price_specification.and_then(|x| _json_ld_text(x.get("priceCurrency"))));
+  let availability = offer
+    .and_then(|x| _json_ld_text(x.get("availability")))
+    .map(|x| match x.rsplit_once('/') {
+      Some((_, name)) => name.to_string(),
+      None => x,
+    });
+
+  let rating = product.get("aggregateRating");
+  let rating_value = rating.and_then(|x| _json_ld_number(x.get("ratingValue")));
+  let review_count = rating
+    .and_then(|x| _json_ld_number(x.get("reviewCount").or_else(|| x.get("ratingCount"))))
+    .filter(|x| *x >= 0.0 && *x <= i32::MAX as f64)
+    .map(|x| x as i32);
+
+  SchemaOrgProduct {
+    name: _json_ld_text(product.get("name")),
+    description: _json_ld_text(product.get("description")),
+    sku: _json_ld_text(product.get("sku")),
+    brand: product.get("brand").and_then(_json_ld_name),
+    price,
+    currency,
+    availability,
+    rating_value,
+    review_count,
+  }
+}
+
+fn _extract_schema_org_product(
+  html: &str,
+) -> Result<Option<SchemaOrgProduct>, Box<dyn std::error::Error + Send + Sync>> {
+  let document = _parse_html(html)?;
+  let json_ld = _extract_json_ld(&document);
+  let mut products = Vec::new();
+  for value in &json_ld {
+    _json_ld_find_types(value, &PRODUCT_TYPES, &mut products);
+  }
+
+  Ok(products.first().map(|x| _schema_org_product(x)))
+}
+
+/// Extract the first Schema.org Product described in the JSON-LD of a page, with
+/// its price and availability taken from the first Offer and its rating from
+/// AggregateRating, or `None` when there is none.
+#[napi]
+pub async fn extract_schema_org_product(html: String) -> napi::Result<Option<SchemaOrgProduct>> {
+  let res = task::spawn_blocking(move || _extract_schema_org_product(&html))
+    .await
+    .map_err(|e| {
+      napi::Error::new(
+        napi::Status::GenericFailure,
+        format!("extract_schema_org_product join error: {e}"),
+      )
+    })?;
+
+  res.map_err(_to_napi_err)
+}
+
+#[derive(Serialize)]
+#[napi(object)]
+pub struct SchemaOrgEvent {
+  pub name: Option<String>,
+  /// ISO 8601: a date, a local date-time, or an RFC 3339 timestamp. Values that
+  /// can't be parsed are passed through as written.
+  pub start_date: Option<String>,
+  pub end_date: Option<String>,
+  pub location_name: Option<String>,
+  pub location_address: Option<String>,
+  pub organizer_name: Option<String>,
+  pub url: Option<String>,
+  pub image_url: Option<String>,
+}
+
+/// Schema.org Event and its common subtypes.
+const EVENT_TYPES: [&str; 16] = [
+  "Event",
+  "BusinessEvent",
+  "ChildrensEvent",
+  "ComedyEvent",
+  "DanceEvent",
+  "EducationEvent",
+  "ExhibitionEvent",
+  "Festival",
+  "FoodEvent",
+  "LiteraryEvent",
+  "MusicEvent",
+  "SaleEvent",
+  "ScreeningEvent",
+  "SocialEvent",
+  "SportsEvent",
+  "TheaterEvent",
+];
+
+/// A JSON-LD date or date-time in a normalized ISO 8601 form, or as written when it
+/// isn't one.
+fn _json_ld_date(value: Option<&Value>) -> Option<String> {
+  let raw = _json_ld_text(value)?;
+  if let Ok(x) = chrono::DateTime::parse_from_rfc3339(&raw) {
+    return Some(x.to_rfc3339_opts(chrono::SecondsFormat::Secs, true));
+  }
+  for format in ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M:%S"] {
+    if let Ok(x) = chrono::NaiveDateTime::parse_from_str(&raw, format) {
+      return Some(x.format("%Y-%m-%dT%H:%M:%S").to_string());
+    }
+  }
+  if let Ok(x) = chrono::NaiveDate::parse_from_str(&raw, "%Y-%m-%d") {
+    return Some(x.format("%Y-%m-%d").to_string());
+  }
+  Some(raw)
+}
+
+/// A JSON-LD PostalAddress as one line, or the address as written when it's a string.
+fn _json_ld_address(value: Option<&Value>) -> Option<String> {
+  match value? {
+    Value::Object(map) => {
+      let parts: Vec<String> = [
+        "streetAddress",
+        "addressLocality",
+        "addressRegion",
+        "postalCode",
+        "addressCountry",
+      ]
+      .iter()
+      .filter_map(|key| map.get(*key).and_then(_json_ld_name))
+      .collect();
+      Some(parts.join(", ")).filter(|x| !x.is_empty())
+    }
+    Value::Array(items) => items.iter().find_map(|x| _json_ld_address(Some(x))),
+    x => _json_ld_text(Some(x)),
+  }
+}
+
+fn _schema_org_event(event: &serde_json::Map<String, Value>) -> SchemaOrgEvent {
+  let location = match event.get("location") {
+    Some(Value::Array(items)) => items.first(),
+    x => x,
+  };
+  let (location_name, location_address) = match location {
+    Some(Value::Object(place)) => (
+      _json_ld_text(place.get("name")),
+      _json_ld_address(place.get("address")),
+    ),
+    x => (_json_ld_text(x), None),
+  };
+
+  SchemaOrgEvent {
+    name: _json_ld_text(event.get("name")),
+    start_date: _json_ld_date(event.get("startDate")),
+    end_date: _json_ld_date(event.get("endDate")),
+    location_name,
+    location_address,
+    organizer_name: event.get("organizer").and_then(_json_ld_name),
+    url: _json_ld_url(event.get("url")),
+    image_url: _json_ld_url(event.get("image")),
+  }
+}
+
+fn _extract_schema_org_events(
+  html: &str,
+) -> Result<Vec<SchemaOrgEvent>, Box<dyn std::error::Error + Send + Sync>> {
+  let document = _parse_html(html)?;
+  let json_ld = _extract_json_ld(&document);
+  let mut events = Vec::new();
+  for value in &json_ld {
+    _json_ld_find_types(value, &EVENT_TYPES, &mut events);
+  }
+
+  Ok(events.into_iter().map(_schema_org_event).collect())
+}
+
+/// Extract every Schema.org Event (or MusicEvent, SportsEvent, ...) described in the
+/// JSON-LD of a page, in document order.
+#[napi]
+pub async fn extract_schema_org_event(html: String) -> napi::Result<Vec<SchemaOrgEvent>> {
+  let res = task::spawn_blocking(move || _extract_schema_org_events(&html))
+    .await
+    .map_err(|e| {
+      napi::Error::new(
+        napi::Status::GenericFailure,
+        format!("extract_schema_org_event join error: {e}"),
+      )
+    })?;
+
+  res.map_err(_to_napi_err)
+}
+
+#[derive(Serialize)]
+#[napi(object)]
+pub struct SchemaOrgRecipe {
+  pub name: Option<String>,
+  pub description: Option<String>,
+  pub author_names: Vec<String>,
+  pub date_published: Option<String>,
+  pub image_url: Option<String>,
+  pub ingredients: Vec<String>,
+  /// Steps in order, with HowToSection steps flattened.
+  pub instructions: Vec<String>,
+  /// ISO 8601 durations as written, e.g. "PT20M".
+  pub prep_time: Option<String>,
+  pub cook_time: Option<String>,
+  pub total_time: Option<String>,
+  pub recipe_yield: Option<String>,
+}
+
+#[derive(Serialize)]
+#[napi(object)]
+pub struct SchemaOrgOrganization {
+  pub name: Option<String>,
+  pub url: Option<String>,
+  pub logo_url: Option<String>,
+  pub same_as: Vec<String>,
+}
+
+#[derive(Serialize)]
+#[napi(object)]
+pub struct SchemaOrgResult {
+  pub products: Vec<SchemaOrgProduct>,
+  pub articles: Vec<SchemaOrgArticle>,
+  pub recipes: Vec<SchemaOrgRecipe>,
+  pub events: Vec<SchemaOrgEvent>,
+  pub organizations: Vec<SchemaOrgOrganization>,
+  /// Items of any other type, as JSON-LD-shaped objects.
+  pub other: Vec<Value>,
+  /// Syntaxes structured data was found in: "json-ld", "microdata" and/or "rdfa".
+  pub sources: Vec<String>,
+  /// Kinds ("Product", "Article", "Recipe", "Event" or "Organization") of items
+  /// described in both JSON-LD and microdata or RDFa. Only the JSON-LD copy of such an
+  /// item is returned; other markup items of the same kind are kept.
+  pub conflicting_types: Vec<String>,
+}
+
+/// Schema.org Organization and its common subtypes.
+const ORGANIZATION_TYPES: [&str; 7] = [
+  "Organization",
+  "Corporation",
+  "EducationalOrganization",
+  "GovernmentOrganization",
+  "LocalBusiness",
+  "NewsMediaOrganization",
+  "NGO",
+];
+
+/// Attribute names of an HTML syntax for embedding items.
+struct ItemSyntax {
+  scope: &'static str,
+  item_type: &'static str,
+  id: &'static str,
+  property: &'static str,
+}
+
+const MICRODATA: ItemSyntax = ItemSyntax {
+  scope: "itemscope",
+  item_type: "itemtype",
+  id: "itemid",
+  property: "itemprop",
+};
+
+const RDFA_LITE: ItemSyntax = ItemSyntax {
+  scope: "typeof",
+  item_type: "typeof",
+  id: "resource",
+  property: "property",
+};
+
+/// A schema.org type or property name without its vocabulary: `https://schema.org/Product`
+/// and `schema:Product` both become `Product`.
+fn _schema_org_name(value: &str) -> &str {
+  let value = value.trim().trim_end_matches('/');
+  [
+    "http://schema.org/",
+    "https://schema.org/",
+    "http://www.schema.org/",
+    "https://www.schema.org/",
+    "schema:",
+  ]
+  .iter()
+  .find_map(|x| value.strip_prefix(x))
+  .unwrap_or(value)
+}
+
+/// Strip the vocabulary from every `@type` in a JSON-LD value.
+fn _normalize_schema_org_types(value: &mut Value) {
+  match value {
+    Value::Object(map) => {
+      for (key, value) in map.iter_mut() {
+        match value {
+          Value::String(t) if key == "@type" => *t = _schema_org_name(t).to_string(),
+          Value::Array(ts) if key == "@type" => {
+            for t in ts.iter_mut() {
+              if let Value::String(t) = t {
+                *t = _schema_org_name(t).to_string();
+              }
+            }
+          }
+          x => _normalize_schema_org_types(x),
+        }
+      }
+    }
+    Value::Array(items) => items.iter_mut().for_each(_normalize_schema_org_types),
+    _ => {}
+  }
+}
+
+/// Top-level JSON-LD entities, looking inside arrays and `@graph` containers.
+fn _schema_org_entities(value: Value, out: &mut Vec<serde_json::Map<String, Value>>) {
+  match value {
+    Value::Array(items) => {
+      for item in items {
+        _schema_org_entities(item, out);
+      }
+    }
+    Value::Object(mut map) => {
+      if let Some(graph) = map.remove("@graph") {
+        _schema_org_entities(graph, out);
+      }
+      if map.contains_key("@type") {
+        out.push(map);
+      }
+    }
+    _ => {}
+  }
+}
+
+/// Value of a microdata or RDFa property element: its `content` attribute, the URL or
+/// value attribute for its tag, or else its text.
+fn _item_property_value(element: &NodeDataRef<ElementData>) -> String {
+  let attributes = element.attributes.borrow();
+  let attribute = match element.name.local.as_ref() {
+    "a" | "area" | "link" => "href",
+    "audio" | "embed" | "iframe" | "img" | "source" | "track" | "video" => "src",
+    "object" => "data",
+    "data" | "meter" => "value",
+    "time" => "datetime",
+    _ => "content",
+  };
+
+  match attributes
+    .get("content")
+    .or_else(|| attributes.get(attribute))
+  {
+    Some(x) => x.trim().to_string(),
+    None => _collapse_whitespace(&element.text_contents()),
+  }
+}
+
+/// Add the properties found under `node` to `item`, stopping at nested items, whose
+/// own properties belong to them.
+fn _collect_item_properties(
+  node: &NodeRef,
+  syntax: &ItemSyntax,
+  item: &mut serde_json::Map<String, Value>,
+) {
+  for child in node.children() {
+    let Some(element) = child.clone().into_element_ref() else {
+      continue;
+    };
+    let attributes = element.attributes.borrow();
+    let is_item = attributes.contains(syntax.scope);
+
+    if let Some(names) = attributes.get(syntax.property) {
+      let value = if is_item {
+        Value::Object(_item(&element, syntax))
+      } else {
+        Value::String(_item_property_value(&element))
+      };
+      for name in names.split_whitespace() {
+        match item.entry(_schema_org_name(name).to_string()) {
+          serde_json::map::Entry::Vacant(x) => {
+            x.insert(value.clone());
+          }
+          serde_json::map::Entry::Occupied(mut x) => match x.get_mut() {
+            Value::Array(values) => values.push(value.clone()),
+            existing => *existing = Value::Array(vec![existing.take(), value.clone()]),
+          },
+        }
+      }
+    }
+
+    if !is_item {
+      _collect_item_properties(&child, syntax, item);
+    }
+  }
+}
+
+/// A microdata or RDFa item as a JSON-LD-shaped object.
+fn _item(
+  element: &NodeDataRef<ElementData>,
+  syntax: &ItemSyntax,
+) -> serde_json::Map<String, Value> {
+  let mut item = serde_json::Map::new();
+  let attributes = element.attributes.borrow();
+
+  let types: Vec<Value> = attributes
+    .get(syntax.item_type)
+    .unwrap_or_default()
+    .split_whitespace()
+    .map(|x| Value::String(_schema_org_name(x).to_string()))
+    .collect();
+  match types.len() {
+    0 => {}
+    1 => {
+      item.insert("@type".to_string(), types[0].clone());
+    }
+    _ => {
+      item.insert("@type".to_string(), Value::Array(types));
+    }
+  }
+  if let Some(id) = attributes.get(syntax.id) {
+    item.insert("@id".to_string(), Value::String(id.trim().to_string()));
+  }
+
+  _collect_item_properties(element.as_node(), syntax, &mut item);
+  item
+}
+
+/// Items that aren't the property of another item, in document order.
+fn _extract_items(document: &NodeRef, syntax: &ItemSyntax) -> Vec<serde_json::Map<String, Value>> {
+  document
+    .descendants()
+    .elements()
+    .filter(|x| {
+      let attributes = x.attributes.borrow();
+      attributes.contains(syntax.scope) && !attributes.contains(syntax.property)
+    })
+    .map(|x| _item(&x, syntax))
+    .collect()
+}
+
+/// Texts of a JSON-LD value that may be a single value or an array.
+fn _json_ld_texts(value: Option<&Value>) -> Vec<String> {
+  match value {
+    Some(Value::Array(items)) => items
+      .iter()
+      .filter_map(|x| _json_ld_text(Some(x)))
+      .collect(),
+    x => _json_ld_text(x).into_iter().collect(),
+  }
+}
+
+/// Steps of `recipeInstructions`, given as text, HowToStep objects, or HowToSection
+/// objects listing steps.
+fn _json_ld_instructions(value: &Value, out: &mut Vec<String>) {
+  match value {
+    Value::String(x) => out.extend(
+      x.lines()
+        .map(_collapse_whitespace)
+        .filter(|x| !x.is_empty()),
+    ),
+    Value::Array(items) => {
+      for item in items {
+        _json_ld_instructions(item, out);
+      }
+    }
+    Value::Object(map) => match map.get("itemListElement") {
+      Some(steps) => _json_ld_instructions(steps, out),
+      None => out.extend(_json_ld_text(map.get("text").or_else(|| map.get("name")))),
+    },
+    _ => {}
+  }
+}
+
+fn _schema_org_recipe(recipe: &serde_json::Map<String, Value>) -> SchemaOrgRecipe {
+  let mut instructions = Vec::new();
+  if let Some(value) = recipe.get("recipeInstructions") {
+    _json_ld_instructions(value, &mut instructions);
+  }
+
+  SchemaOrgRecipe {
+    name: _json_ld_text(recipe.get("name")),
+    description: _json_ld_text(recipe.get("description")),
+    author_names: _json_ld_names(recipe.get("author")),
+    date_published: _json_ld_text(recipe.get("datePublished")),
+    image_url: _json_ld_url(recipe.get("image")),
+    ingredients: _json_ld_texts(
+      recipe
+        .get("recipeIngredient")
+        .or_else(|| recipe.get("ingredients")),
+    ),
+    instructions,
+    prep_time: _json_ld_text(recipe.get("prepTime")),
+    cook_time: _json_ld_text(recipe.get("cookTime")),
+    total_time: _json_ld_text(recipe.get("totalTime")),
+    recipe_yield: _json_ld_text(recipe.get("recipeYield")),
+  }
+}
+
+fn _schema_org_organization(
+  organization: &serde_json::Map<String, Value>,
+) -> SchemaOrgOrganization {
+  SchemaOrgOrganization {
+    name: _json_ld_text(organization.get("name")),
+    url: _json_ld_url(organization.get("url")),
+    logo_url: _json_ld_url(organization.get("logo")),
+    same_as: _json_ld_texts(organization.get("sameAs")),
+  }
+}
+
+/// The kind of typed output an entity is returned as, if any.
+fn _schema_org_kind(entity: &serde_json::Map<String, Value>) -> Option<&'static str> {
+  if _json_ld_has_type(entity, &PRODUCT_TYPES) {
+    Some("Product")
+  } else if _json_ld_has_type(entity, &ARTICLE_TYPES) {
+    Some("Article")
+  } else if _json_ld_has_type(entity, &["Recipe"]) {
+    Some("Recipe")
+  } else if _json_ld_has_type(entity, &EVENT_TYPES) {
+    Some("Event")
+  } else if _json_ld_has_type(entity, &ORGANIZATION_TYPES) {
+    Some("Organization")
+  } else {
+    None
+  }
+}
+
+/// Whether two schema.org items describe the same thing: they share an `@id`, a
+/// `url` or a `name`.
+fn _schema_org_same_item(
+  a: &serde_json::Map<String, Value>,
+  b: &serde_json::Map<String, Value>,
+) -> bool {
+  let same = |x: Option<String>, y: Option<String>| match (x, y) {
+    (Some(x), Some(y)) => x.eq_ignore_ascii_case(&y),
+    _ => false,
+  };
+  same(_json_ld_text(a.get("@id")), _json_ld_text(b.get("@id")))
+    || same(_json_ld_url(a.get("url")), _json_ld_url(b.get("url")))
+    || same(_json_ld_text(a.get("name")), _json_ld_text(b.get("name")))
+}
+
+fn _extract_schema_org(
+  html: &str,
+) -> Result<SchemaOrgResult, Box<dyn std::error::Error + Send + Sync>> {
+  let document = _parse_html(html)?;
+  let mut sources = Vec::new();
+
+  let mut json_ld = Vec::new();
+  for mut value in _extract_json_ld(&document) {
+    _normalize_schema_org_types(&mut value);
+    _schema_org_entities(value, &mut json_ld);
+  }
+  if !json_ld.is_empty() {
+    sources.push("json-ld".to_string());
+  }
+
+  let mut markup = Vec::new();
+  for (source, syntax) in [("microdata", &MICRODATA), ("rdfa", &RDFA_LITE)] {
+    let items = _extract_items(&document, syntax);
+    if !items.is_empty() {
+      sources.push(source.to_string());
+    }
+    markup.extend(items);
+  }
+
+  // A markup item describing the same thing as a JSON-LD item of its kind is
+  // dropped in favour of the JSON-LD.
+  let mut conflicting_types: Vec<&str> = Vec::new();
+  markup.retain(|item| {
+    let Some(kind) = _schema_org_kind(item) else {
+      return true;
+    };
+    let duplicate = json_ld
+      .iter()
+      .any(|x| _schema_org_kind(x) == Some(kind) && _schema_org_same_item(x, item));
+    if duplicate && !conflicting_types.contains(&kind) {
+      conflicting_types.push(kind);
+    }
+    !duplicate
+  });
+
+  let mut result = SchemaOrgResult {
+    products: Vec::new(),
+    articles: Vec::new(),
+    recipes: Vec::new(),
+    events: Vec::new(),
+    organizations: Vec::new(),
+    other: Vec::new(),
+    sources,
+    conflicting_types: conflicting_types.iter().map(|x| x.to_string()).collect(),
+  };
+
+  for entity in json_ld.into_iter().chain(markup) {
+    match _schema_org_kind(&entity) {
+      Some("Product") => result.products.push(_schema_org_product(&entity)),
+      Some("Article") => result.articles.push(_schema_org_article(&entity)),
+      Some("Recipe") => result.recipes.push(_schema_org_recipe(&entity)),
+      Some("Event") => result.events.push(_schema_org_event(&entity)),
+      Some(_) => result.organizations.push(_schema_org_organization(&entity)),
+      None => result.other.push(Value::Object(entity)),
+    }
+  }
+
+  Ok(result)
+}
+
+/// Extract schema.org items from the JSON-LD, microdata and RDFa Lite of a page as
+/// typed Products, Articles, Recipes, Events and Organizations, keeping items of
+/// other types as raw objects. When an item is described both in JSON-LD and in
+/// markup, matched by `@id`, url or name, the JSON-LD wins and its kind is listed in
+/// `conflicting_types`.
+#[napi]
+pub async fn extract_schema_org(html: String) -> napi::Result<SchemaOrgResult> {
+  let res = task::spawn_blocking(move || _extract_schema_org(&html))
+    .await
+    .map_err(|e| {
+      napi::Error::new(
+        napi::Status::GenericFailure,
+        format!("extract_schema_org join error: {e}"),
+      )
+    })?;
+
+  res.map_err(_to_napi_err)
+}
+
+#[derive(Serialize)]
+#[napi(object)]
+pub struct MicrodataItem {
+  /// The `itemtype` URL, e.g. "https://schema.org/Product".
+  pub item_type: Option<String>,
+  /// JSON object mapping each `itemprop` name to its values, in document order.
+  /// A value that is itself an item is an object with its `@type` and properties.
+  pub properties: String,
+}
+
+fn _extract_microdata(
+  html: &str,
+) -> Result<Vec<MicrodataItem>, Box<dyn std::error::Error + Send + Sync>> {
+  let document = _parse_html(html)?;
+  let mut out = Vec::new();
+
+  for element in document
+    .select("[itemscope]")
+    .map_err(|_| "Failed to select microdata items")?
+  {
+    let mut item = _item(&element, &MICRODATA);
+    item.remove("@type");
+    item.remove("@id");
+    let properties: serde_json::Map<String, Value> = item
+      .into_iter()
+      .map(|(name, value)| match value {
+        Value::Array(_) => (name, value),
+        x => (name, Value::Array(vec![x])),
+      })
+      .collect();
+
+    out.push(MicrodataItem {
+      item_type: _non_empty_attribute(&element, "itemtype"),
+      properties: serde_json::to_string(&properties)?,
+    });
+  }
+
+  Ok(out)
+}
+
+/// Extract every microdata item (`itemscope` element) of a page, nested ones
+/// included, with the `itemprop` values that belong to it. A property that is itself
+/// an item is given as an object of its own properties, and also appears as its own
+/// item.
+#[napi]
+pub async fn extract_microdata(html: String) -> napi::Result<Vec<MicrodataItem>> {
+  let res = task::spawn_blocking(move || _extract_microdata(&html))
+    .await
+    .map_err(|e| {
+      napi::Error::new(
+        napi::Status::GenericFailure,
+        format!("extract_microdata join error: {e}"),
+      )
+    })?;
+
+  res.map_err(_to_napi_err)
+}
+
+#[derive(Serialize)]
+#[napi(object)]
+pub struct PaginationInfo {
+  pub next: Option<String>,
+  pub prev: Option<String>,
+  pub pages: Vec<String>,
+  pub last: Option<String>,
+  /// Every next-page candidate found, best first.
+  pub next_candidates: Vec<PaginationCandidate>,
+  /// Every previous-page candidate found, best first.
+  pub prev_candidates: Vec<PaginationCandidate>,
+}
+
+#[derive(Serialize, Clone)]
+#[napi(object)]
+pub struct PaginationCandidate {
+  pub url: String,
+  /// Evidence for the candidate, in the order found: "link_rel", "anchor_rel",
+  /// "anchor_text", "anchor_class" or "numeric_sequence".
+  pub sources: Vec<String>,
+  /// From 0 to 1: the strongest source, plus a little for each agreeing source.
+  pub score: f64,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum PageDirection {
+  Next,
+  Prev,
+}
+
+/// Link texts meaning "next page", compared after trimming arrows and lowercasing.
+const NEXT_PAGE_TEXTS: [&str; 22] = [
+  "next",
+  "next page",
+  "older",
+  "older posts",
+  "older entries",
+  "weiter",
+  "nächste",
+  "nächste seite",
+  "suivant",
+  "page suivante",
+  "siguiente",
+  "próxima",
+  "próximo",
+  "successivo",
+  "successiva",
+  "volgende",
+  "次へ",
+  "次のページ",
+  "下一页",
+  "下一頁",
+  "다음",
+  "следующая",
+];
+
+/// Link texts meaning "previous page", compared after trimming arrows and lowercasing.
+const PREV_PAGE_TEXTS: [&str; 22] = [
+  "prev",
+  "previous",
+  "previous page",
+  "newer",
+  "newer posts",
+  "newer entries",
+  "zurück",
+  "vorherige",
+  "vorherige seite",
+  "précédent",
+  "page précédente",
+  "anterior",
+  "precedente",
+  "vorige",
+  "前へ",
+  "前のページ",
+  "上一页",
+  "上一頁",
+  "이전",
+  "предыдущая",
+  "назад",
+  "back",
+];
+
+const NEXT_PAGE_ARROWS: [&str; 6] = ["›", "»", ">", "→", ">>", "»»"];
+const PREV_PAGE_ARROWS: [&str; 6] = ["‹", "«", "<", "←", "<<", "««"];
+
+fn _page_direction_from_text(text: &str) -> Option<PageDirection> {
+  let text = _collapse_whitespace(text).to_lowercase();
+  if NEXT_PAGE_ARROWS.contains(&text.as_str()) {
+    return Some(PageDirection::Next);
+  }
+  if PREV_PAGE_ARROWS.contains(&text.as_str()) {
+    return Some(PageDirection::Prev);
+  }
+
+  let word = text
+    .trim_matches(|c: char| c.is_whitespace() || "›»>→‹«<←".contains(c))
+    .to_string();
+  if NEXT_PAGE_TEXTS.contains(&word.as_str()) {
+    Some(PageDirection::Next)
+  } else if PREV_PAGE_TEXTS.contains(&word.as_str()) {
+    Some(PageDirection::Prev)
+  } else {
+    None
+  }
+}
+
+fn _add_pagination_candidate(
+  candidates: &mut Vec<PaginationCandidate>,
+  url: String,
+  source: &str,
+  score: f64,
+) {
+  match candidates.iter_mut().find(|x| x.url == url) {
+    Some(existing) => {
+      if !existing.sources.iter().any(|x| x == source) {
+        existing.sources.push(source.to_string());
+        existing.score = (existing.score.max(score) + 0.05).min(1.0);
+      }
+    }
+    None => candidates.push(PaginationCandidate {
+      url,
+      sources: vec![source.to_string()],
+      score,
+    }),
+  }
+}
+
+/// Next/previous links of a run of sibling page-number links, like
+/// `1 2 <span>3</span> 4 5`, relative to the page marked as current.
+fn _numeric_pagination(
+  parent: &NodeRef,
+  base: &Url,
+  page_url: &str,
+) -> Option<(Option<String>, Option<String>)> {
+  let mut items: Vec<(u32, Option<String>, bool)> = Vec::new();
+
+  for child in parent.children() {
+    let Some(element) = child.as_element() else {
+      continue;
+    };
+    let Ok(number) = _collapse_whitespace(&child.text_contents()).parse::<u32>() else {
+      continue;
+    };
+
+    let anchor = if element.name.local.as_ref() == "a" {
+      child.clone().into_element_ref()
+    } else {
+      child.select_first("a").ok()
+    };
+    let href = anchor
+      .as_ref()
+      .and_then(|x| x.attributes.borrow().get("href").map(|x| x.to_string()))
+      .and_then(|x| base.join(x.trim()).ok())
+      .map(|mut x| {
+        x.set_fragment(None);
+        x.to_string()
+      });
+
+    let marked_current = |attrs: &kuchikiki::Attributes| {
+      attrs.contains("aria-current")
+        || attrs.get("class").is_some_and(|class| {
+          class
+            .split_whitespace()
+            .any(|x| matches!(x.to_lowercase().as_str(), "active" | "current" | "selected"))
+        })
+    };
+    let current = href.is_none()
+      || href.as_deref() == Some(page_url)
+      || marked_current(&element.attributes.borrow())
+      || anchor
+        .as_ref()
+        .is_some_and(|x| marked_current(&x.attributes.borrow()));
+
+    items.push((number, href, current));
+  }
+
+  if items.len() < 3 || items.windows(2).any(|x| x[0].0 >= x[1].0) {
+    return None;
+  }
+  let current = items.iter().find(|x| x.2)?.0;
+  let link_to = |number: u32| {
+    items
+      .iter()
+      .find(|x| x.0 == number && !x.2)
+      .and_then(|x| x.1.clone())
+  };
+
+  Some((
+    link_to(current + 1),
+    current.checked_sub(1).and_then(link_to),
+  ))
+}
+
+/// Ranked next/previous page candidates from rel links, link texts and numeric page
+/// runs.
+fn _pagination_candidates(
+  document: &NodeRef,
+  base: &Url,
+  page_url: &Url,
+) -> Result<
+  (Vec<PaginationCandidate>, Vec<PaginationCandidate>),
+  Box<dyn std::error::Error + Send + Sync>,
+> {
+  let mut page_url = page_url.clone();
+  page_url.set_fragment(None);
+  let page_url = page_url.to_string();
+
+  let mut next: Vec<PaginationCandidate> = Vec::new();
+  let mut prev: Vec<PaginationCandidate> = Vec::new();
+  let mut add = |direction: PageDirection, href: &str, source: &str, score: f64| {
+    let Ok(mut url) = base.join(&_normalize_attribute_url(href)) else {
+      return;
+    };
+    if !matches!(url.scheme(), "http" | "https") {
+      return;
+    }
+    url.set_fragment(None);
+    let url = url.to_string();
+    if url == page_url {
+      return;
+    }
+    let candidates = match direction {
+      PageDirection::Next => &mut next,
+      PageDirection::Prev => &mut prev,
+    };
+    _add_pagination_candidate(candidates, url, source, score);
+  };
+
+  for element in document
+    .select("link[rel][href], a[href]")
+    .map_err(|_| "Failed to select pagination links")?
+  {
+    let attrs = element.attributes.borrow();
+    let href = attrs.get("href").unwrap_or_default();
+    let is_link = element.name.local.as_ref() == "link";
+
+    for rel in attrs.get("rel").unwrap_or_default().split_whitespace() {
+      let direction = match rel.to_lowercase().as_str() {
+        "next" => PageDirection::Next,
+        "prev" | "previous" => PageDirection::Prev,
+        _ => continue,
+      };
+      if is_link {
+        add(direction, href, "link_rel", 1.0);
+      } else {
+        add(direction, href, "anchor_rel", 0.9);
+      }
+    }
+    if is_link {
+      continue;
+    }
+
+    let text_direction = _page_direction_from_text(&element.text_contents())
+      .or_else(|| attrs.get("aria-label").and_then(_page_direction_from_text));
+    if let Some(direction) = text_direction {
+      add(direction, href, "anchor_text", 0.7);
+    }
+
+    for class in attrs.get("class").unwrap_or_default().split_whitespace() {
+      let class = class.to_lowercase();
+      let direction = if class.contains("next") {
+        PageDirection::Next
+      } else if class.contains("prev") {
+        PageDirection::Prev
+      } else {
+        continue;
+      };
+      add(direction, href, "anchor_class", 0.5);
+      break;
+    }
+  }
+
+  // Page-number runs are either sibling anchors or anchors wrapped in list items, so
+  // only the parents and grandparents of numeric links need checking.
+  let mut containers: Vec<NodeRef> = Vec::new();
+  for anchor in document
+    .select("a[href]")
+    .map_err(|_| "Failed to select pagination links")?
+  {
+    if _collapse_whitespace(&anchor.text_contents())
+      .parse::<u32>()
+      .is_err()
+    {
+      continue;
+    }
+    for ancestor in anchor.as_node().ancestors().take(2) {
+      if !containers.contains(&ancestor) {
+        containers.push(ancestor);
+      }
+    }
+  }
+
+  for parent in containers {
+    if let Some((next_url, prev_url)) = _numeric_pagination(&parent, base, &page_url) {
+      if let Some(url) = next_url {
+        add(PageDirection::Next, &url, "numeric_sequence", 0.8);
+      }
+      if let Some(url) = prev_url {
+        add(PageDirection::Prev, &url, "numeric_sequence", 0.8);
+      }
+    }
+  }
+
+  for candidates in [&mut next, &mut prev] {
+    candidates.sort_by(|a, b| {
+      b.score
+        .partial_cmp(&a.score)
+        .unwrap_or(std::cmp::Ordering::Equal)
+    });
+  }
+  Ok((next, prev))
+}
+
+fn _first_rel_href(document: &NodeRef, selector: &str, base: &Url) -> Option<String> {
+  document
+    .select(selector)
+    .ok()?
+    .filter_map(|x| x.attributes.borrow().get("href").map(|x| x.to_string()))
+    .find_map(|href| base.join(href.trim()).ok())
+    .map(|x| x.to_string())
+}
+
+fn _extract_pagination(
+  html: &str,
+  base_url: &str,
+) -> Result<PaginationInfo, Box<dyn std::error::Error + Send + Sync>> {
+  let document = _parse_html(html)?;
+  let base = Url::parse(&_extract_base_href_from_document(
+    &document,
+    &Url::parse(base_url)?,
+  )?)?;
+
+  let mut next = _first_rel_href(&document, "link[rel~=\"next\"][href]", &base)
+    .or_else(|| _first_rel_href(&document, "a[rel~=\"next\"][href]", &base));
+  let mut prev = _first_rel_href(&document, "link[rel~=\"prev\"][href]", &base)
+    .or_else(|| _first_rel_href(&document, "link[rel~=\"previous\"][href]", &base))
+    .or_else(|| _first_rel_href(&document, "a[rel~=\"prev\"][href]", &base))
+    .or_else(|| _first_rel_href(&document, "a[rel~=\"previous\"][href]", &base));
+  let mut last = _first_rel_href(&document, "a[rel~=\"last\"][href]", &base);
+
+  let mut pages: Vec<(u32, String)> = Vec::new();
+  let containers = document
+    .select("[class*=\"pagination\"], [class*=\"pager\"]")
+    .map_err(|_| "Failed to select pagination containers")?;
+
+  for container in containers {
+    let anchors = match container.as_node().select("a[href]") {
+      Ok(x) => x,
+      Err(_) => continue,
+    };
+
+    for anchor in anchors {
+      let href = match anchor.attributes.borrow().get("href") {
+        Some(x) => x.trim().to_string(),
+        None => continue,
+      };
+      if href.is_empty() || href.starts_with('#') || href.starts_with("javascript:") {
+        continue;
+      }
+      let resolved = match base.join(&href) {
+        Ok(x) => x.to_string(),
+        Err(_) => continue,
+      };
+
+      let text = anchor.text_contents().trim().to_lowercase();
+      let class = anchor
+        .attributes
+        .borrow()
+        .get("class")
+        .unwrap_or_default()
+        .to_lowercase();
+
+      if let Ok(number) = text.parse::<u32>() {
+        if !pages.iter().any(|(_, x)| *x == resolved) {
+          pages.push((number, resolved));
+        }
+      } else if next.is_none()
+        && (class.contains("next") || matches!(text.as_str(), "next" | "›" | "»" | ">"))
+      {
+        next = Some(resolved);
+      } else if prev.is_none()
+        && (class.contains("prev")
+          || matches!(text.as_str(), "prev" | "previous" | "‹" | "«" | "<"))
+      {
+        prev = Some(resolved);
+      } else if last.is_none() && (class.contains("last") || text == "last") {
+        last = Some(resolved);
+      }
+    }
+  }
+
+  if last.is_none() {
+    last = pages
+      .iter()
+      .max_by_key(|(number, _)| *number)
+      .map(|(_, x)| x.clone());
+  }
+
+  let (next_candidates, prev_candidates) =
+    _pagination_candidates(&document, &base, &Url::parse(base_url)?)?;
+
+  Ok(PaginationInfo {
+    next,
+    prev,
+    pages: pages.into_iter().map(|(_, x)| x).collect(),
+    last,
+    next_candidates,
+    prev_candidates,
+  })
+}
+
+fn _detect_pagination(
+  html: &str,
+  url: &str,
+) -> Result<PaginationInfo, Box<dyn std::error::Error + Send + Sync>> {
+  let mut info = _extract_pagination(html, url)?;
+  if let Some(best) = info.next_candidates.first() {
+    info.next = Some(best.url.clone());
+  }
+  if let Some(best) = info.prev_candidates.first() {
+    info.prev = Some(best.url.clone());
+  }
+  Ok(info)
+}
+
+/// Like `extract_pagination`, but `next` and `prev` are the best-ranked of all
+/// candidates found across rel links, localized link texts and numeric page runs.
+#[napi]
+pub async fn detect_pagination(html: String, url: String) -> napi::Result<PaginationInfo> {
+  let res = task::spawn_blocking(move || _detect_pagination(&html, &url))
+    .await
+    .map_err(|e| {
+      napi::Error::new(
+        napi::Status::GenericFailure,
+        format!("detect_pagination join error: {e}"),
+      )
+    })?;
+
+  res.map_err(_to_napi_err)
+}
+
+/// Identify next/previous/page-number links from common pagination patterns.
+#[napi]
+pub async fn extract_pagination(html: String, base_url: String) -> napi::Result<PaginationInfo> {
+  let res = task::spawn_blocking(move || _extract_pagination(&html, &base_url))
+    .await
+    .map_err(|e| {
+      napi::Error::new(
+        napi::Status::GenericFailure,
+        format!("extract_pagination join error: {e}"),
+      )
+    })?;
+
+  res.map_err(_to_napi_err)
+}
+
+#[derive(Serialize)]
+#[napi(object)]
+pub struct CodeBlock {
+  pub code: String,
+  pub language: Option<String>,
+  pub is_block: bool,
+}
+
+/// Language hint from highlight.js/Prism-style `language-*` or `lang-*` classes.
+fn _code_language(element: &NodeDataRef<ElementData>) -> Option<String> {
+  element
+    .attributes
+    .borrow()
+    .get("class")?
+    .split_whitespace()
+    .find_map(|class| {
+      class
+        .strip_prefix("language-")
+        .or_else(|| class.strip_prefix("lang-"))
+        .filter(|x| !x.is_empty())
+        .map(|x| x.to_string())
+    })
+}
+
+fn _extract_code_blocks(
+  html: &str,
+) -> Result<Vec<CodeBlock>, Box<dyn std::error::Error + Send + Sync>> {
+  let document = _parse_html(html)?;
+  let mut out = Vec::new();
+
+  for code in document
+    .select("code")
+    .map_err(|_| "Failed to select code elements")?
+  {
+    let text = code.text_contents();
+    if text.trim().is_empty() {
+      continue;
+    }
+
+    let pre = code.as_node().ancestors().find_map(|x| {
+      x.into_element_ref()
+        .filter(|e| e.name.local.as_ref() == "pre")
+    });
+    let language = _code_language(&code).or_else(|| pre.as_ref().and_then(_code_language));
+
+    out.push(CodeBlock {
+      code: text,
+      language,
+      is_block: pre.is_some(),
+    });
+  }
+
+  Ok(out)
+}
+
+/// Extract `<pre><code>` blocks and inline `<code>` elements with language hints.
+#[napi]
+pub async fn extract_code_blocks(html: String) -> napi::Result<Vec<CodeBlock>> {
+  let res = task::spawn_blocking(move || _extract_code_blocks(&html))
+    .await
+    .map_err(|e| {
+      napi::Error::new(
+        napi::Status::GenericFailure,
+        format!("extract_code_blocks join error: {e}"),
+      )
+    })?;
+
+  res.map_err(_to_napi_err)
+}
+
+#[derive(Serialize)]
+#[napi(object)]
+pub struct DefinitionItem {
+  pub term: String,
+  pub definitions: Vec<String>,
+}
+
+fn _extract_definition_lists(
+  html: &str,
+) -> Result<Vec<DefinitionItem>, Box<dyn std::error::Error + Send + Sync>> {
+  let document = _parse_html(html)?;
+  let mut out: Vec<DefinitionItem> = Vec::new();
+
+  for dl in document
+    .select("dl")
+    .map_err(|_| "Failed to select definition lists")?
+  {
+    // dt/dd may be wrapped in a <div> directly inside the <dl>.
+    let entries = dl.as_node().children().flat_map(|child| {
+      match child.as_element().map(|e| e.name.local.as_ref() == "div") {
+        Some(true) => child.children().collect::<Vec<_>>(),
+        _ => vec![child],
+      }
+    });
+
+    // Indices into `out` of the terms that the next <dd> belongs to.
+    let mut group: Vec<usize> = Vec::new();
+    let mut seen_definition = false;
+
+    for entry in entries {
+      let Some(tag) = entry.as_element().map(|e| e.name.local.to_string()) else {
+        continue;
+      };
+
+      match tag.as_str() {
+        "dt" => {
+          if seen_definition {
+            group.clear();
+            seen_definition = false;
+          }
+          out.push(DefinitionItem {
+            term: _collapse_whitespace(&entry.text_contents()),
+            definitions: Vec::new(),
+          });
+          group.push(out.len() - 1);
+        }
+        "dd" => {
+          seen_definition = true;
+          let definition = _collapse_whitespace(&entry.text_contents());
+          for &i in &group {
+            out[i].definitions.push(definition.clone());
+          }
+        }
+        _ => {}
+      }
+    }
+  }
+
+  Ok(out)
+}
+
+/// Extract `<dl>` term/definition pairs, one entry per `<dt>`.
+#[napi]
+pub async fn extract_definition_lists(html: String) -> napi::Result<Vec<DefinitionItem>> {
+  let res = task::spawn_blocking(move || _extract_definition_lists(&html))
+    .await
+    .map_err(|e| {
+      napi::Error::new(
+        napi::Status::GenericFailure,
+        format!("extract_definition_lists join error: {e}"),
+      )
+    })?;
+
+  res.map_err(_to_napi_err)
+}
+
+#[derive(Serialize, Default)]
+#[napi(object)]
+pub struct MetaRobots {
+  /// Lowercased `name` of the meta tag, `robots` or `googlebot`.
+  pub name: String,
+  pub noindex: bool,
+  pub nofollow: bool,
+  pub noarchive: bool,
+  pub nosnippet: bool,
+  pub nocache: bool,
+  pub noimageindex: bool,
+  pub raw: String,
+}
+
+fn _extract_meta_robots(
+  html: &str,
+) -> Result<Vec<MetaRobots>, Box<dyn std::error::Error + Send + Sync>> {
+  _meta_robots_from_document(&_parse_html(html)?)
+}
+
+fn _meta_robots_from_document(
+  document: &NodeRef,
+) -> Result<Vec<MetaRobots>, Box<dyn std::error::Error + Send + Sync>> {
+  let mut out = Vec::new();
+
+  for meta in document
+    .select("meta[name][content]")
+    .map_err(|_| "Failed to select meta tags")?
+  {
+    let attrs = meta.attributes.borrow();
+    let name = attrs.get("name").unwrap_or_default().trim().to_lowercase();
+    if name != "robots" && name != "googlebot" {
+      continue;
+    }
+
+    let raw = attrs.get("content").unwrap_or_default().trim().to_string();
+    let mut robots = MetaRobots {
+      name,
+      ..Default::default()
+    };
+
+    for directive in raw.split(',').map(|x| x.trim().to_lowercase()) {
+      match directive.as_str() {
+        "noindex" => robots.noindex = true,
+        "nofollow" => robots.nofollow = true,
+        "none" => {
+          robots.noindex = true;
+          robots.nofollow = true;
+        }
+        "noarchive" => robots.noarchive = true,
+        "nosnippet" => robots.nosnippet = true,
+        "nocache" => robots.nocache = true,
+        "noimageindex" => robots.noimageindex = true,
+        _ => {}
+      }
+    }
+
+    robots.raw = raw;
+    out.push(robots);
+  }
+
+  Ok(out)
+}
+
+/// Parse `<meta name="robots">` and `<meta name="googlebot">` directives, one entry
+/// per tag in document order.
+#[napi]
+pub async fn extract_meta_robots(html: String) -> napi::Result<Vec<MetaRobots>> {
+  let res = task::spawn_blocking(move || _extract_meta_robots(&html))
+    .await
+    .map_err(|e| {
+      napi::Error::new(
+        napi::Status::GenericFailure,
+        format!("extract_meta_robots join error: {e}"),
+      )
+    })?;
+
+  res.map_err(_to_napi_err)
+}
+
+#[derive(Serialize)]
+#[napi(object)]
+pub struct SocialLink {
+  pub platform: String,
+  pub url: String,
+}
+
+/// Hostnames of known social platforms. Subdomains (`www.`, `m.`, ...) also match.
+const SOCIAL_PLATFORMS: [(&str, &str); 27] = [
+  ("twitter.com", "twitter"),
+  ("x.com", "twitter"),
+  ("facebook.com", "facebook"),
+  ("fb.com", "facebook"),
+  ("instagram.com", "instagram"),
+  ("linkedin.com", "linkedin"),
+  ("youtube.com", "youtube"),
+  ("youtu.be", "youtube"),
+  ("tiktok.com", "tiktok"),
+  ("github.com", "github"),
+  ("gitlab.com", "gitlab"),
+  ("pinterest.com", "pinterest"),
+  ("reddit.com", "reddit"),
+  ("threads.net", "threads"),
+  ("bsky.app", "bluesky"),
+  ("discord.gg", "discord"),
+  ("discord.com", "discord"),
+  ("t.me", "telegram"),
+  ("wa.me", "whatsapp"),
+  ("snapchat.com", "snapchat"),
+  ("tumblr.com", "tumblr"),
+  ("medium.com", "medium"),
+  ("twitch.tv", "twitch"),
+  ("vimeo.com", "vimeo"),
+  ("dribbble.com", "dribbble"),
+  ("behance.net", "behance"),
+  ("mastodon.social", "mastodon"),
+];
+
+/// Share-button endpoints, which point at the platform but not at a profile.
+const SOCIAL_SHARE_PATHS: [&str; 5] = ["/sharer", "/share", "/intent/", "/sharing/", "/pin/create"];
+
+/// Whether `host` is `domain` or one of its subdomains.
+fn _host_matches(host: &str, domain: &str) -> bool {
+  host == domain || host.strip_suffix(domain).is_some_and(|x| x.ends_with('.'))
+}
+
+fn _social_platform(url: &Url) -> Option<&'static str> {
+  let host = url.host_str()?.to_lowercase();
+  let host = host.trim_end_matches('.');
+  let platform = SOCIAL_PLATFORMS
+    .iter()
+    .find_map(|(domain, platform)| _host_matches(host, domain).then_some(*platform))?;
+
+  let path = url.path().to_lowercase();
+  if SOCIAL_SHARE_PATHS.iter().any(|x| path.starts_with(x)) {
+    return None;
+  }
+
+  Some(platform)
+}
+
+fn _extract_social_links(
+  html: &str,
+) -> Result<Vec<SocialLink>, Box<dyn std::error::Error + Send + Sync>> {
+  let document = _parse_html(html)?;
+  let mut seen = HashSet::new();
+  let mut out = Vec::new();
+
+  for anchor in document
+    .select("a[href]")
+    .map_err(|_| "Failed to select links")?
+  {
+    let href = _normalize_attribute_url(anchor.attributes.borrow().get("href").unwrap_or_default());
+    let Ok(url) = Url::parse(&href) else {
+      continue;
+    };
+    if !matches!(url.scheme(), "http" | "https") {
+      continue;
+    }
+    let Some(platform) = _social_platform(&url) else {
+      continue;
+    };
+
+    let url = url.to_string();
+    if seen.insert(url.clone()) {
+      out.push(SocialLink {
+        platform: platform.to_string(),
+        url,
+      });
+    }
+  }
+
+  Ok(out)
+}
+
+/// Extract links to known social platforms (Twitter/X, Facebook, LinkedIn, GitHub,
+/// ...), once per URL in document order. Share buttons are skipped.
+#[napi]
+pub async fn extract_social_links(html: String) -> napi::Result<Vec<SocialLink>> {
+  let res = task::spawn_blocking(move || _extract_social_links(&html))
+    .await
+    .map_err(|e| {
+      napi::Error::new(
+        napi::Status::GenericFailure,
+        format!("extract_social_links join error: {e}"),
+      )
+    })?;
+
+  res.map_err(_to_napi_err)
+}
+
+#[derive(Serialize)]
+#[napi(object)]
+pub struct FormField {
+  pub name: Option<String>,
+  /// Lowercased `type` of an `<input>` (default `text`), or `select`/`textarea`.
+  pub field_type: String,
+  pub label: Option<String>,
+  pub required: bool,
+  pub placeholder: Option<String>,
+  /// The current value, as `element.value` would read it in a browser. Always
+  /// `<redacted>` for password fields that have one.
+  pub value: Option<String>,
+  /// Values of the options of a `<select>`.
+  pub options: Vec<String>,
+  /// Values of the selected options of a `<select>`, all of them when it allows
+  /// several.
+  pub selected_values: Vec<String>,
+  /// Whether a `<select>` allows several options to be chosen.
+  pub multiple: bool,
+  /// Whether a checkbox or radio button is checked. False for other fields.
+  pub checked: bool,
+}
+
+#[derive(Serialize)]
+#[napi(object)]
+pub struct Form {
+  /// Absolute submission URL, or `None` when the form has no `action`.
+  pub action: Option<String>,
+  /// Uppercased submission method, `GET` when missing or invalid.
+  pub method: String,
+  pub fields: Vec<FormField>,
+  /// Whether this is the synthetic entry holding the fields that belong to no form.
+  pub unassociated: bool,
+}
+
+/// Stands in for the value of password fields.
+const REDACTED_VALUE: &str = "<redacted>";
+
+fn _non_empty_attribute(element: &NodeDataRef<ElementData>, name: &str) -> Option<String> {
+  element
+    .attributes
+    .borrow()
+    .get(name)
+    .map(_collapse_whitespace)
+    .filter(|x| !x.is_empty())
+}
+
+/// Text of a `<label>`, leaving out the options of any control nested inside it.
+fn _label_text(label: &NodeRef) -> Option<String> {
+  let text: String = label
+    .descendants()
+    .filter(|x| {
+      !x.ancestors().any(|a| {
+        a.as_element()
+          .is_some_and(|e| matches!(e.name.local.as_ref(), "select" | "textarea"))
+      })
+    })
+    .filter_map(|x| x.as_text().map(|t| t.borrow().clone()))
+    .collect();
+  Some(_collapse_whitespace(&text)).filter(|x| !x.is_empty())
+}
+
+fn _extract_forms(
+  html: &str,
+  base_url: &str,
+) -> Result<Vec<Form>, Box<dyn std::error::Error + Send + Sync>> {
+  let document = _parse_html(html)?;
+  let base = Url::parse(&_extract_base_href_from_document(
+    &document,
+    &Url::parse(base_url)?,
+  )?)?;
+
+  let mut labels: HashMap<String, String> = HashMap::new();
+  for label in document
+    .select("label[for]")
+    .map_err(|_| "Failed to select labels")?
+  {
+    let Some(id) = label.attributes.borrow().get("for").map(|x| x.to_string()) else {
+      continue;
+    };
+    if let Some(text) = _label_text(label.as_node()) {
+      labels.entry(id).or_insert(text);
+    }
+  }
+
+  let mut out = Vec::new();
+  let mut form_ids: HashMap<String, usize> = HashMap::new();
+  let mut form_nodes: HashMap<*const Node, usize> = HashMap::new();
+  for form in document
+    .select("form")
+    .map_err(|_| "Failed to select forms")?
+  {
+    let (action, method) = {
+      let attrs = form.attributes.borrow();
+      let action = attrs
+        .get("action")
+        .and_then(|x| base.join(&_normalize_attribute_url(x)).ok())
+        .map(|x| x.to_string());
+      let method = match attrs.get("method").map(|x| x.trim().to_ascii_uppercase()) {
+        Some(x) if x == "POST" || x == "DIALOG" => x,
+        _ => "GET".to_string(),
+      };
+      (action, method)
+    };
+
+    if let Some(id) = _non_empty_attribute(&form, "id") {
+      form_ids.entry(id).or_insert(out.len());
+    }
+    form_nodes.insert(Rc::as_ptr(&form.as_node().0), out.len());
+    out.push(Form {
+      action,
+      method,
+      fields: Vec::new(),
+      unassociated: false,
+    });
+  }
+
+  let mut unassociated = Vec::new();
+  for field in document
+    .select("input, select, textarea")
+    .map_err(|_| "Failed to select form fields")?
+  {
+    let tag = field.name.local.to_string();
+    let field_type = match tag.as_str() {
+      "input" => _non_empty_attribute(&field, "type")
+        .map(|x| x.to_ascii_lowercase())
+        .unwrap_or_else(|| "text".to_string()),
+      _ => tag,
+    };
+
+    let label = _non_empty_attribute(&field, "id")
+      .and_then(|id| labels.get(&id).cloned())
+      .or_else(|| {
+        field
+          .as_node()
+          .ancestors()
+          .find(|x| {
+            x.as_element()
+              .is_some_and(|e| e.name.local.as_ref() == "label")
+          })
+          .and_then(|x| _label_text(&x))
+      })
+      .or_else(|| _non_empty_attribute(&field, "aria-label"));
+
+    let multiple = field_type == "select" && field.attributes.borrow().contains("multiple");
+    let mut options = Vec::new();
+    let mut selected_values = Vec::new();
+    if field_type == "select" {
+      for option in field
+        .as_node()
+        .select("option")
+        .map_err(|_| "Failed to select options")?
+      {
+        let attrs = option.attributes.borrow();
+        let value = attrs
+          .get("value")
+          .map(|x| x.to_string())
+          .unwrap_or_else(|| _collapse_whitespace(&option.text_contents()));
+        if attrs.contains("selected") && (multiple || selected_values.is_empty()) {
+          selected_values.push(value.clone());
+        }
+        options.push(value);
+      }
+      // A single-choice select shows its first option when none is selected.
+      if !multiple && selected_values.is_empty() {
+        selected_values.extend(options.first().cloned());
+      }
+    }
+
+    let value = match field_type.as_str() {
+      "select" => selected_values.first().cloned(),
+      "textarea" => Some(field.text_contents()).filter(|x| !x.is_empty()),
+      "password" => field
+        .attributes
+        .borrow()
+        .get("value")
+        .filter(|x| !x.is_empty())
+        .map(|_| REDACTED_VALUE.to_string()),
+      "checkbox" | "radio" => Some(
+        field
+          .attributes
+          .borrow()
+          .get("value")
+          .unwrap_or("on")
+          .to_string(),
+      ),
+      _ => field
+        .attributes
+        .borrow()
+        .get("value")
+        .map(|x| x.to_string()),
+    };
+
+    let checked = matches!(field_type.as_str(), "checkbox" | "radio")
+      && field.attributes.borrow().contains("checked");
+    let field_data = FormField {
+      name: _non_empty_attribute(&field, "name"),
+      field_type,
+      label,
+      required: field.attributes.borrow().contains("required"),
+      placeholder: _non_empty_attribute(&field, "placeholder"),
+      value,
+      options,
+      selected_values,
+      multiple,
+      checked,
+    };
+
+    // A `form` attribute overrides the enclosing form, and ties the field to no form
+    // at all when no form has that id.
+    let owner = match field.attributes.borrow().get("form") {
+      Some(id) => form_ids.get(id.trim()).copied(),
+      None => field
+        .as_node()
+        .ancestors()
+        .find_map(|x| form_nodes.get(&Rc::as_ptr(&x.0)).copied()),
+    };
+    match owner {
+      Some(i) => out[i].fields.push(field_data),
+      None => unassociated.push(field_data),
+    }
+  }
+
+  if !unassociated.is_empty() {
+    out.push(Form {
+      action: None,
+      method: "GET".to_string(),
+      fields: unassociated,
+      unassociated: true,
+    });
+  }
+
+  Ok(out)
+}
+
+/// Extract `<form>` elements with their resolved action, method and input fields.
+/// Fields that belong to no form are gathered in a last, `unassociated` entry.
+#[napi]
+pub async fn extract_forms(html: String, base_url: String) -> napi::Result<Vec<Form>> {
+  let res = task::spawn_blocking(move || _extract_forms(&html, &base_url))
+    .await
+    .map_err(|e| {
+      napi::Error::new(
+        napi::Status::GenericFailure,
+        format!("extract_forms join error: {e}"),
+      )
+    })?;
+
+  res.map_err(_to_napi_err)
+}
+
+#[derive(Serialize)]
+#[napi(object)]
+pub struct TechnologyMatch {
+  pub technology: String,
+  /// The signal that matched, e.g. `script src: https://cdn.shopify.com/...`.
+  pub evidence: String,
+  /// From 0 to 1; the strongest matching signal wins.
+  pub confidence: f64,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum SignalKind {
+  /// Case-insensitive substring of `<meta name="generator">` content.
+  Generator,
+  /// Case-insensitive substring of a `script[src]` or `link[href]` URL.
+  ResourceUrl,
+  /// Substring of an inline `<script>` body.
+  InlineScript,
+  /// Exact element id.
+  ElementId,
+  /// Prefix of a class token.
+  ClassPrefix,
+}
+
+impl SignalKind {
+  fn label(self) -> &'static str {
+    match self {
+      SignalKind::Generator => "meta generator",
+      SignalKind::ResourceUrl => "resource url",
+      SignalKind::InlineScript => "inline script",
+      SignalKind::ElementId => "element id",
+      SignalKind::ClassPrefix => "class",
+    }
+  }
+}
+
+struct TechnologySignal {
+  technology: &'static str,
+  kind: SignalKind,
+  /// Lowercase for the case-insensitive kinds.
+  pattern: &'static str,
+  confidence: f64,
+}
+
+const fn signal(
+  technology: &'static str,
+  kind: SignalKind,
+  pattern: &'static str,
+  confidence: f64,
+) -> TechnologySignal {
+  TechnologySignal {
+    technology,
+    kind,
+    pattern,
+    confidence,
+  }
+}
+
+const TECHNOLOGY_SIGNALS: [TechnologySignal; 29] = [
+  signal("WordPress", SignalKind::Generator, "wordpress", 0.95),
+  signal("WordPress", SignalKind::ResourceUrl, "/wp-content/", 0.9),
+  signal("WordPress", SignalKind::ResourceUrl, "/wp-includes/", 0.9),
+  signal("WordPress", SignalKind::ClassPrefix, "wp-block-", 0.7),
+  signal("Shopify", SignalKind::ResourceUrl, "cdn.shopify.com", 0.9),
+  signal("Shopify", SignalKind::InlineScript, "Shopify.theme", 0.9),
+  signal("Shopify", SignalKind::ClassPrefix, "shopify-", 0.6),
+  signal("Wix", SignalKind::Generator, "wix.com", 0.95),
+  signal(
+    "Wix",
+    SignalKind::ResourceUrl,
+    "static.parastorage.com",
+    0.9,
+  ),
+  signal("Wix", SignalKind::ResourceUrl, "static.wixstatic.com", 0.8),
+  signal(
+    "Squarespace",
+    SignalKind::ResourceUrl,
+    "static1.squarespace.com",
+    0.9,
+  ),
+  signal(
+    "Squarespace",
+    SignalKind::InlineScript,
+    "SQUARESPACE_CONTEXT",
+    0.9,
+  ),
+  signal("Squarespace", SignalKind::ClassPrefix, "sqs-", 0.7),
+  signal("Next.js", SignalKind::Generator, "next.js", 0.95),
+  signal("Next.js", SignalKind::ElementId, "__NEXT_DATA__", 0.95),
+  signal("Next.js", SignalKind::ResourceUrl, "/_next/static/", 0.9),
+  signal("Next.js", SignalKind::ElementId, "__next", 0.8),
+  signal("Nuxt", SignalKind::InlineScript, "__NUXT__", 0.9),
+  signal("Nuxt", SignalKind::ResourceUrl, "/_nuxt/", 0.9),
+  signal("Nuxt", SignalKind::ElementId, "__nuxt", 0.8),
+  signal("Gatsby", SignalKind::Generator, "gatsby", 0.95),
+  signal("Gatsby", SignalKind::ElementId, "___gatsby", 0.9),
+  signal("Drupal", SignalKind::Generator, "drupal", 0.95),
+  signal("Drupal", SignalKind::InlineScript, "drupalSettings", 0.9),
+  signal(
+    "Drupal",
+    SignalKind::ResourceUrl,
+    "/sites/default/files/",
+    0.8,
+  ),
+  signal("Joomla", SignalKind::Generator, "joomla", 0.95),
+  signal("Webflow", SignalKind::Generator, "webflow", 0.95),
+  signal("Ghost", SignalKind::Generator, "ghost", 0.95),
+  signal("Hugo", SignalKind::Generator, "hugo", 0.95),
+];
+
+/// Elements looked at before giving up on the rest of an enormous document.
+const MAX_TECHNOLOGY_SCAN_ELEMENTS: usize = 20_000;
+
+/// Bytes of each inline script searched for signals.
+const MAX_TECHNOLOGY_SCRIPT_BYTES: usize = 64 * 1024;
+
+/// Longest value quoted in `TechnologyMatch::evidence`.
+const MAX_TECHNOLOGY_EVIDENCE_CHARS: usize = 200;
+
+fn _match_technology_signals(
+  kind: SignalKind,
+  value: &str,
+  found: &mut HashMap<&'static str, TechnologyMatch>,
+) {
+  let lowered = match kind {
+    SignalKind::Generator | SignalKind::ResourceUrl => value.to_lowercase(),
+    _ => String::new(),
+  };
+
+  for signal in TECHNOLOGY_SIGNALS.iter().filter(|x| x.kind == kind) {
+    let matched = match kind {
+      SignalKind::Generator | SignalKind::ResourceUrl => lowered.contains(signal.pattern),
+      SignalKind::InlineScript => value.contains(signal.pattern),
+      SignalKind::ElementId => value == signal.pattern,
+      SignalKind::ClassPrefix => value.starts_with(signal.pattern),
+    };
+    if !matched
+      || found
+        .get(signal.technology)
+        .is_some_and(|x| x.confidence >= signal.confidence)
+    {
+      continue;
+    }
+
+    let quoted = match kind {
+      SignalKind::InlineScript => signal.pattern,
+      _ => value,
+    };
+    found.insert(
+      signal.technology,
+      TechnologyMatch {
+        technology: signal.technology.to_string(),
+        evidence: format!(
+          "{}: {}",
+          kind.label(),
+          quoted
+            .chars()
+            .take(MAX_TECHNOLOGY_EVIDENCE_CHARS)
+            .collect::<String>()
+        ),
+        confidence: signal.confidence,
+      },
+    );
+  }
+}
+
+fn _detect_site_technology(
+  html: &str,
+) -> Result<Vec<TechnologyMatch>, Box<dyn std::error::Error + Send + Sync>> {
+  let document = _parse_html(html)?;
+  let mut found: HashMap<&'static str, TechnologyMatch> = HashMap::new();
+
+  for node in document
+    .descendants()
+    .filter(|x| x.as_element().is_some())
+    .take(MAX_TECHNOLOGY_SCAN_ELEMENTS)
+  {
+    let element = node.as_element().unwrap();
+    let attrs = element.attributes.borrow();
+
+    if let Some(id) = attrs.get("id") {
+      _match_technology_signals(SignalKind::ElementId, id.trim(), &mut found);
+    }
+    if let Some(class) = attrs.get("class") {
+      for token in class.split_whitespace() {
+        _match_technology_signals(SignalKind::ClassPrefix, token, &mut found);
+      }
+    }
+
+    match element.name.local.as_ref() {
+      "meta" => {
+        let is_generator = attrs
+          .get("name")
+          .is_some_and(|x| x.trim().eq_ignore_ascii_case("generator"));
+        if let (true, Some(content)) = (is_generator, attrs.get("content")) {
+          _match_technology_signals(SignalKind::Generator, content.trim(), &mut found);
+        }
+      }
+      "script" => {
+        if let Some(src) = attrs.get("src") {
+          _match_technology_signals(SignalKind::ResourceUrl, src.trim(), &mut found);
+        } else {
+          let text = node.text_contents();
+          let mut end = text.len().min(MAX_TECHNOLOGY_SCRIPT_BYTES);
+          while !text.is_char_boundary(end) {
+            end -= 1;
+          }
+          _match_technology_signals(SignalKind::InlineScript, &text[..end], &mut found);
+        }
+      }
+      "link" => {
+        if let Some(href) = attrs.get("href") {
+          _match_technology_signals(SignalKind::ResourceUrl, href.trim(), &mut found);
+        }
+      }
+      _ => {}
+    }
+  }
+
+  let mut out: Vec<TechnologyMatch> = found.into_values().collect();
+  out.sort_by(|a, b| {
+    b.confidence
+      .partial_cmp(&a.confidence)
+      .unwrap_or(std::cmp::Ordering::Equal)
+      .then_with(|| a.technology.cmp(&b.technology))
+  });
+  Ok(out)
+}
+
+/// Fingerprint the CMS or framework a page is built with from a table of known
+/// signals (generator tag, asset paths, root element ids, class prefixes). Matches
+/// are ordered by confidence.
+#[napi]
+pub async fn detect_site_technology(html: String) -> napi::Result<Vec<TechnologyMatch>> {
+  let res = task::spawn_blocking(move || _detect_site_technology(&html))
+    .await
+    .map_err(|e| {
+      napi::Error::new(
+        napi::Status::GenericFailure,
+        format!("detect_site_technology join error: {e}"),
+      )
+    })?;
+
+  res.map_err(_to_napi_err)
+}
+
+#[derive(Serialize)]
+#[napi(object)]
+pub struct ReadingTimeEstimate {
+  pub word_count: i32,
+  pub minutes: f64,
+}
+
+/// Average silent reading speed for adults reading non-fiction.
+const READING_WORDS_PER_MINUTE: f64 = 238.0;
+
+/// Scripts without spaces between words, where each character is counted as a word.
+fn _is_cjk(c: char) -> bool {
+  matches!(
+    c,
+    '\u{3040}'..='\u{30FF}'
+      | '\u{3400}'..='\u{4DBF}'
+      | '\u{4E00}'..='\u{9FFF}'
+      | '\u{F900}'..='\u{FAFF}'
+  )
+}
+
+fn _count_words(text: &str) -> usize {
+  text
+    .split_whitespace()
+    .map(|token| {
+      let cjk = token.chars().filter(|&c| _is_cjk(c)).count();
+      let rest = token.chars().any(|c| c.is_alphanumeric() && !_is_cjk(c));
+      cjk + rest as usize
+    })
+    .sum()
+}
+
+fn _estimate_reading_time(
+  html: &str,
+) -> Result<ReadingTimeEstimate, Box<dyn std::error::Error + Send + Sync>> {
+  let document = _parse_html(html)?;
+
+  let skipped: Vec<_> = document
+    .select("script, style, noscript, template, pre")
+    .map_err(|_| "Failed to select non-prose elements")?
+    .collect();
+  for node in skipped {
+    node.as_node().detach();
+  }
+
+  let text = match document.select_first("body") {
+    Ok(body) => body.text_contents(),
+    Err(_) => document.text_contents(),
+  };
+  let word_count = _count_words(&text);
+
+  Ok(ReadingTimeEstimate {
+    word_count: word_count as i32,
+    minutes: word_count as f64 / READING_WORDS_PER_MINUTE,
+  })
+}
+
+/// Count the words of a page, leaving out scripts, styles and `<pre>` code blocks,
+/// and estimate the minutes needed to read them.
+#[napi]
+pub async fn estimate_reading_time(html: String) -> napi::Result<ReadingTimeEstimate> {
+  let res = task::spawn_blocking(move || _estimate_reading_time(&html))
+    .await
+    .map_err(|e| {
+      napi::Error::new(
+        napi::Status::GenericFailure,
+        format!("estimate_reading_time join error: {e}"),
+      )
+    })?;
+
+  res.map_err(_to_napi_err)
+}
+
+#[derive(Default)]
+#[napi(object)]
+pub struct EmbeddedJsonOptions {
+  /// Blobs larger than this many bytes are skipped. Defaults to 5 MB.
+  pub max_blob_bytes: Option<u32>,
+  /// Fail with a timeout error when extraction takes longer than this. Parsing the
+  /// document is not counted.
+  pub timeout_ms: Option<u32>,
+}
+
+#[derive(Serialize)]
+#[napi(object)]
+pub struct EmbeddedJson {
+  /// The script's `id`, or the global name for `window.__X__ = {...}` assignments.
+  pub id: Option<String>,
+  /// The script's `type` attribute, `text/javascript` when missing.
+  pub script_type: String,
+  pub json: Value,
+}
+
+#[derive(Serialize)]
+#[napi(object)]
+pub struct EmbeddedJsonResult {
+  pub entries: Vec<EmbeddedJson>,
+  /// Candidate scripts whose content was not valid JSON.
+  pub parse_failures: i32,
+  /// Candidate scripts skipped for exceeding `max_blob_bytes`.
+  pub oversized: i32,
+}
+
+const DEFAULT_MAX_EMBEDDED_JSON_BYTES: u32 = 5 * 1024 * 1024;
+
+/// Script ids and window globals that frameworks use to ship their initial state.
+const STATE_CONTAINER_NAMES: [&str; 7] = [
+  "__NEXT_DATA__",
+  "__NUXT_DATA__",
+  "__NUXT__",
+  "__APOLLO_STATE__",
+  "__INITIAL_STATE__",
+  "__PRELOADED_STATE__",
+  "__remixContext",
+];
+
+static STATE_ASSIGNMENT_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+  Regex::new(r"^\s*(?:window\.|self\.|(?:var|let|const)\s+)([A-Za-z_$][\w$]*)\s*=\s*")
+    .expect("STATE_ASSIGNMENT_REGEX is a valid static regex pattern")
+});
+
+fn _extract_embedded_json(
+  html: &str,
+  options: &EmbeddedJsonOptions,
+) -> Result<EmbeddedJsonResult, Box<dyn std::error::Error + Send + Sync>> {
+  let document = _parse_html(html)?;
+  let deadline = Deadline::new(options.timeout_ms);
+  let max_bytes = options
+    .max_blob_bytes
+    .unwrap_or(DEFAULT_MAX_EMBEDDED_JSON_BYTES) as usize;
+  let mut result = EmbeddedJsonResult {
+    entries: Vec::new(),
+    parse_failures: 0,
+    oversized: 0,
+  };
+
+  for script in document
+    .select("script")
+    .map_err(|_| "Failed to select scripts")?
+  {
+    deadline.check()?;
+    let (id, script_type) = {
+      let attrs = script.attributes.borrow();
+      (
+        attrs.get("id").map(|x| x.trim().to_string()),
+        attrs
+          .get("type")
+          .map(|x| x.trim().to_ascii_lowercase())
+          .filter(|x| !x.is_empty()),
+      )
+    };
+    let text = script.text_contents();
+
+    let is_json_type = matches!(
+      script_type.as_deref(),
+      Some("application/json" | "application/ld+json")
+    );
+    let is_state_id = id
+      .as_deref()
+      .is_some_and(|x| STATE_CONTAINER_NAMES.contains(&x));
+
+    let (id, body) = if is_json_type || is_state_id {
+      (id, text.as_str())
+    } else if script_type.is_none() || script_type.as_deref() == Some("text/javascript") {
+      // `window.__INITIAL_STATE__ = {...};` style assignments in inline scripts.
+      let Some(captures) = STATE_ASSIGNMENT_REGEX.captures(&text) else {
+        continue;
+      };
+      let name = &captures[1];
+      if !STATE_CONTAINER_NAMES.contains(&name) {
+        continue;
+      }
+      let body = text[captures.get(0).unwrap().end()..]
+        .trim_end()
+        .trim_end_matches(';');
+      (Some(name.to_string()), body)
+    } else {
+      continue;
+    };
+
+    if body.len() > max_bytes {
+      result.oversized += 1;
+      continue;
+    }
+
+    match serde_json::from_str::<Value>(body.trim()) {
+      Ok(json) => result.entries.push(EmbeddedJson {
+        id,
+        script_type: script_type.unwrap_or_else(|| "text/javascript".to_string()),
+        json,
+      }),
+      Err(_) => result.parse_failures += 1,
+    }
+  }
+
+  Ok(result)
+}
+
+/// Parse JSON embedded in `<script>` tags: `application/json` and
+/// `application/ld+json` scripts, plus framework state containers such as
+/// `__NEXT_DATA__` or `window.__INITIAL_STATE__ = {...}`. Runs against the raw HTML,
+/// since `transform_html` strips scripts.
+#[napi]
+pub async fn extract_embedded_json(
+  html: String,
+  options: Option<EmbeddedJsonOptions>,
+) -> napi::Result<EmbeddedJsonResult> {
+  let res =
+    task::spawn_blocking(move || _extract_embedded_json(&html, &options.unwrap_or_default()))
+      .await
+      .map_err(|e| {
+        napi::Error::new(
+          napi::Status::GenericFailure,
+          format!("extract_embedded_json join error: {e}"),
+        )
+      })?;
+
+  res.map_err(_to_napi_err)
+}
+
+#[derive(Default)]
+#[napi(object)]
+pub struct HtmlToTextOptions {
+  /// Drop headers, footers, navigation and other chrome using the same selectors as
+  /// `transform_html`. Defaults to false.
+  pub only_main_content: Option<bool>,
+  /// Fail with a timeout error when rendering takes longer than this. Parsing the
+  /// document is not counted.
+  pub timeout_ms: Option<u32>,
+}
+
+/// Elements whose contents never show up as text.
+const TEXT_SKIPPED_TAGS: [&str; 11] = [
+  "head", "script", "style", "noscript", "template", "iframe", "object", "svg", "canvas", "select",
+  "button",
+];
+
+/// Elements rendered on their own line.
+const TEXT_BLOCK_TAGS: [&str; 30] = [
+  "address",
+  "article",
+  "aside",
+  "blockquote",
+  "body",
+  "dd",
+  "details",
+  "div",
+  "dl",
+  "dt",
+  "fieldset",
+  "figcaption",
+  "figure",
+  "footer",
+  "form",
+  "header",
+  "hr",
+  "li",
+  "main",
+  "nav",
+  "ol",
+  "section",
+  "summary",
+  "table",
+  "tbody",
+  "tfoot",
+  "thead",
+  "tr",
+  "ul",
+  "caption",
+];
+
+/// Elements separated from their neighbours by a blank line.
+const TEXT_PARAGRAPH_TAGS: [&str; 8] = ["p", "pre", "h1", "h2", "h3", "h4", "h5", "h6"];
+
+fn _is_hidden_element(element: &ElementData) -> bool {
+  let attrs = element.attributes.borrow();
+  if attrs.contains("hidden")
+    || attrs
+      .get("aria-hidden")
+      .is_some_and(|x| x.trim().eq_ignore_ascii_case("true"))
+  {
+    return true;
+  }
+  attrs.get("style").is_some_and(|style| {
+    let style: String = style
+      .chars()
+      .filter(|c| !c.is_whitespace())
+      .collect::<String>()
+      .to_ascii_lowercase();
+    style.contains("display:none") || style.contains("visibility:hidden")
+  })
+}
+
+/// Accumulates rendered text, deferring whitespace so that runs collapse and no
+/// line starts or ends with a space.
+#[derive(Default)]
+struct TextWriter {
+  out: String,
+  pending_newlines: usize,
+  pending_space: bool,
+  pending_prefix: Option<String>,
+}
+
+impl TextWriter {
+  fn newlines(&mut self, count: usize) {
+    self.pending_newlines = self.pending_newlines.max(count);
+    self.pending_space = false;
+  }
+
+  fn space(&mut self) {
+    if self.pending_newlines == 0 {
+      self.pending_space = true;
+    }
+  }
+
+  fn flush(&mut self) {
+    if !self.out.is_empty() {
+      if self.pending_newlines > 0 {
+        let existing = self.out.len() - self.out.trim_end_matches('\n').len();
+        for _ in existing..self.pending_newlines {
+          self.out.push('\n');
+        }
+      } else if self.pending_space && !self.out.ends_with([' ', '\n']) {
+        self.out.push(' ');
+      }
+    }
+    if let Some(prefix) = self.pending_prefix.take() {
+      self.out.push_str(&prefix);
+    }
+    self.pending_newlines = 0;
+    self.pending_space = false;
+  }
+
+  fn text(&mut self, text: &str) {
+    if text.starts_with(char::is_whitespace) {
+      self.space();
+    }
+    let mut words = text.split_whitespace().peekable();
+    while let Some(word) = words.next() {
+      self.flush();
+      self.out.push_str(word);
+      if words.peek().is_some() || text.ends_with(char::is_whitespace) {
+        self.space();
+      }
+    }
+  }
+
+  fn preformatted(&mut self, text: &str) {
+    let mut lines = text.split('\n').peekable();
+    while let Some(line) = lines.next() {
+      if !line.is_empty() {
+        self.flush();
+        self.out.push_str(line.trim_end());
+      }
+      if lines.peek().is_some() {
+        self.flush();
+        self.out.push('\n');
+      }
+    }
+  }
+}
+
+fn _html_to_text(
+  html: &str,
+  options: &HtmlToTextOptions,
+) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+  let document = _parse_html(html)?;
+  let deadline = Deadline::new(options.timeout_ms);
+  if options.only_main_content.unwrap_or(false) {
+    _remove_non_main_content(
+      &document,
+      &MainContentSelectors::default(),
+      &mut TransformHtmlStats::default(),
+    )?;
+  }
+
+  let mut writer = TextWriter::default();
+  let mut skipping: Option<NodeRef> = None;
+  let mut pre_depth = 0usize;
+  let mut list_depth = 0usize;
+
+  for edge in document.traverse() {
+    match edge {
+      NodeEdge::Start(node) => {
+        if skipping.is_some() {
+          continue;
+        }
+        if let Some(text) = node.as_text() {
+          if pre_depth > 0 {
+            writer.preformatted(&text.borrow());
+          } else {
+            writer.text(&text.borrow());
+          }
+          continue;
+        }
+        let Some(element) = node.as_element() else {
+          continue;
+        };
+        deadline.check()?;
+        let tag = element.name.local.as_ref();
+        if TEXT_SKIPPED_TAGS.contains(&tag) || _is_hidden_element(element) {
+          skipping = Some(node.clone());
+          continue;
+        }
+
+        match tag {
+          "br" => {
+            writer.flush();
+            writer.out.push('\n');
+          }
+          "li" => {
+            writer.newlines(1);
+            writer.pending_prefix =
+              Some(format!("{}- ", "  ".repeat(list_depth.saturating_sub(1))));
+          }
+          "ul" | "ol" => {
+            list_depth += 1;
+            writer.newlines(1);
+          }
+          "pre" => {
+            pre_depth += 1;
+            writer.newlines(2);
+          }
+          "td" | "th" | "img" | "input" => writer.space(),
+          _ if TEXT_PARAGRAPH_TAGS.contains(&tag) => writer.newlines(2),
+          _ if TEXT_BLOCK_TAGS.contains(&tag) => writer.newlines(1),
+          _ => {}
+        }
+      }
+      NodeEdge::End(node) => {
+        if let Some(skipped) = &skipping {
+          if *skipped == node {
+            skipping = None;
+          }
+          continue;
+        }
+        let Some(element) = node.as_element() else {
+          continue;
+        };
+        let tag = element.name.local.as_ref();
+        match tag {
+          "ul" | "ol" => {
+            list_depth = list_depth.saturating_sub(1);
+            writer.newlines(1);
+          }
+          "pre" => {
+            pre_depth = pre_depth.saturating_sub(1);
+            writer.newlines(2);
+          }
+          "td" | "th" => writer.space(),
+          _ if TEXT_PARAGRAPH_TAGS.contains(&tag) => writer.newlines(2),
+          _ if TEXT_BLOCK_TAGS.contains(&tag) => writer.newlines(1),
+          _ => {}
+        }
+      }
+    }
+  }
+
+  Ok(writer.out.trim_end().to_string())
+}
+
+/// Render a page as plain text: block elements go on their own lines, list items get
+/// a leading "- ", inline whitespace is collapsed, and scripts, styles and hidden
+/// elements are skipped. The output is deterministic, so it can be hashed for change
+/// detection.
+#[napi]
+pub async fn html_to_text(
+  html: String,
+  options: Option<HtmlToTextOptions>,
+) -> napi::Result<String> {
+  let res = task::spawn_blocking(move || _html_to_text(&html, &options.unwrap_or_default()))
+    .await
+    .map_err(|e| {
+      napi::Error::new(
+        napi::Status::GenericFailure,
+        format!("html_to_text join error: {e}"),
+      )
+    })?;
+
+  res.map_err(_to_napi_err)
+}
+
+#[derive(Serialize)]
+#[napi(object)]
+pub struct ArticleContent {
+  /// og:title, else the `<title>`, else the first `<h1>`.
+  pub title: Option<String>,
+  /// Outer HTML of the highest-scoring subtree, or of `<body>` when nothing scores.
+  pub content_html: String,
+  /// content_html rendered like `html_to_text`.
+  pub content_text: String,
+  /// The meta or og:description, else the first paragraph of the content.
+  pub excerpt: Option<String>,
+}
+
+/// Paragraphs shorter than this many characters aren't used as the excerpt.
+const EXCERPT_MIN_LENGTH: usize = 25;
+
+fn _extract_article_content(
+  html: &str,
+) -> Result<ArticleContent, Box<dyn std::error::Error + Send + Sync>> {
+  let document = _parse_html(html)?;
+  let first_text = |selectors: &[&str]| {
+    selectors.iter().find_map(|selector| {
+      let element = document.select_first(selector).ok()?;
+      let text = match element.attributes.borrow().get("content") {
+        Some(x) => _collapse_whitespace(x),
+        None => _collapse_whitespace(&element.text_contents()),
+      };
+      (!text.is_empty()).then_some(text)
+    })
+  };
+
+  let title = first_text(&["meta[property=\"og:title\" i][content]", "title", "h1"]);
+  let description = first_text(&[
+    "meta[name=\"description\" i][content]",
+    "meta[property=\"og:description\" i][content]",
+  ]);
+
+  _remove_tags(&document, &REMOVED_TAGS);
+  let content = match readability::find_main_content(&document) {
+    Some(x) => x,
+    None => document
+      .select_first("body")
+      .map_err(|_| "Failed to select body")?
+      .as_node()
+      .clone(),
+  };
+
+  let excerpt = description.or_else(|| {
+    content.select("p").ok()?.find_map(|x| {
+      let text = _collapse_whitespace(&x.text_contents());
+      (text.chars().count() >= EXCERPT_MIN_LENGTH).then_some(text)
+    })
+  });
+
+  let content_html = content.to_string();
+  let content_text = _html_to_text(&content_html, &HtmlToTextOptions::default())?;
+  Ok(ArticleContent {
+    title,
+    content_html,
+    content_text,
+    excerpt,
+  })
+}
+
+/// Find the main body of an article by content density, like Mozilla's Readability:
+/// paragraphs score by length and commas, their containers inherit the scores, and
+/// link-heavy containers are penalized. Unlike `transform_html` with
+/// only_main_content, this doesn't depend on class names of page chrome.
+#[napi]
+pub async fn extract_article_content(html: String) -> napi::Result<ArticleContent> {
+  let res = task::spawn_blocking(move || _extract_article_content(&html))
+    .await
+    .map_err(|e| {
+      napi::Error::new(
+        napi::Status::GenericFailure,
+        format!("extract_article_content join error: {e}"),
+      )
+    })?;
+
+  res.map_err(_to_napi_err)
+}
+
+#[derive(Default)]
+#[napi(object)]
+pub struct ContentHashOptions {
+  /// Hash only the main content, see `html_to_text`. Defaults to true.
+  pub only_main_content: Option<bool>,
+  /// Replace digit runs with `#` so counters, dates and timestamps don't change the
+  /// hash. Defaults to false.
+  pub strip_digits: Option<bool>,
+  /// Words per simhash shingle. Defaults to 3.
+  pub shingle_size: Option<u32>,
+}
+
+#[derive(Serialize)]
+#[napi(object)]
+pub struct ContentHash {
+  /// Hex SHA-256 of the normalized text, for exact deduplication.
+  pub sha256: String,
+  /// 64-bit simhash of the word shingles as 16 hex digits, for near-duplicate
+  /// detection. See `content_hash_distance`.
+  pub simhash: String,
+  /// Number of words in the normalized text.
+  pub token_count: u32,
+}
+
+const DEFAULT_SHINGLE_SIZE: usize = 3;
+
+/// 64-bit FNV-1a followed by the splitmix64 finalizer, so that every output bit
+/// depends on the whole shingle. Stable across platforms and Rust versions.
+fn _shingle_hash(words: &[&str]) -> u64 {
+  let mut hash: u64 = 0xcbf29ce484222325;
+  for (i, word) in words.iter().enumerate() {
+    if i > 0 {
+      hash ^= b' ' as u64;
+      hash = hash.wrapping_mul(0x100000001b3);
+    }
+    for byte in word.bytes() {
+      hash ^= byte as u64;
+      hash = hash.wrapping_mul(0x100000001b3);
+    }
+  }
+  hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+  hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d049bb133111eb);
+  hash ^ (hash >> 31)
+}
+
+fn _simhash(words: &[&str], shingle_size: usize) -> u64 {
+  if words.is_empty() {
+    return 0;
+  }
+
+  let mut weights = [0i64; 64];
+  let shingle_size = shingle_size.clamp(1, words.len());
+  for shingle in words.windows(shingle_size) {
+    let hash = _shingle_hash(shingle);
+    for (bit, weight) in weights.iter_mut().enumerate() {
+      if hash & (1 << bit) != 0 {
+        *weight += 1;
+      } else {
+        *weight -= 1;
+      }
+    }
+  }
+
+  weights
+    .iter()
+    .enumerate()
+    .filter(|(_, weight)| **weight > 0)
+    .fold(0, |hash, (bit, _)| hash | (1 << bit))
+}
+
+fn _compute_content_hash(
+  html: &str,
+  options: &ContentHashOptions,
+) -> Result<ContentHash, Box<dyn std::error::Error + Send + Sync>> {
+  // Rendering to text drops attributes, so srcset, query strings and CSRF tokens
+  // don't affect the hash.
+  let text = _html_to_text(
+    html,
+    &HtmlToTextOptions {
+      only_main_content: Some(options.only_main_content.unwrap_or(true)),
+      timeout_ms: None,
+    },
+  )?
+  .to_lowercase();
+
+  let strip_digits = options.strip_digits.unwrap_or(false);
+  let words: Vec<String> = text
+    .split(|c: char| !c.is_alphanumeric())
+    .filter(|x| !x.is_empty())
+    .map(|word| {
+      if !strip_digits {
+        return word.to_string();
+      }
+      let mut out = String::with_capacity(word.len());
+      for c in word.chars() {
+        if !c.is_ascii_digit() {
+          out.push(c);
+        } else if !out.ends_with('#') {
+          out.push('#');
+        }
+      }
+      out
+    })
+    .collect();
+  let words: Vec<&str> = words.iter().map(|x| x.as_str()).collect();
+
+  let sha256 = Sha256::digest(words.join(" ").as_bytes());
+  let shingle_size = options
+    .shingle_size
+    .map(|x| x as usize)
+    .unwrap_or(DEFAULT_SHINGLE_SIZE);
+
+  Ok(ContentHash {
+    sha256: format!("{sha256:x}"),
+    simhash: format!("{:016x}", _simhash(&words, shingle_size)),
+    token_count: words.len() as u32,
+  })
+}
+
+/// Hash the normalized main-content text of a page: an exact SHA-256 plus a 64-bit
+/// simhash over word shingles for near-duplicate detection.
+#[napi]
+pub async fn compute_content_hash(
+  html: String,
+  options: Option<ContentHashOptions>,
+) -> napi::Result<ContentHash> {
+  let res =
+    task::spawn_blocking(move || _compute_content_hash(&html, &options.unwrap_or_default()))
+      .await
+      .map_err(|e| {
+        napi::Error::new(
+          napi::Status::GenericFailure,
+          format!("compute_content_hash join error: {e}"),
+        )
+      })?;
+
+  res.map_err(_to_napi_err)
+}
+
+/// Hamming distance between two simhashes from `compute_content_hash`. With the
+/// default options, re-scrapes of the same article differing only in timestamps, ads
+/// or a sentence stay within 10 bits, while different articles are 20 or more bits
+/// apart, so treat a distance of 10 or less as a near-duplicate.
+#[napi]
+pub fn content_hash_distance(a: String, b: String) -> napi::Result<u32> {
+  let parse = |x: &str| {
+    u64::from_str_radix(x.trim(), 16)
+      .map_err(|_| napi::Error::new(napi::Status::InvalidArg, format!("Invalid simhash: {x}")))
+  };
+  Ok((parse(&a)? ^ parse(&b)?).count_ones())
+}
+
+#[derive(Serialize)]
+#[napi(object)]
+pub struct TimeElement {
+  /// The `datetime` attribute as written, when present and non-empty.
+  pub datetime: Option<String>,
+  /// Text content with whitespace collapsed.
+  pub text: String,
+}
+
+fn _extract_time_elements(
+  html: &str,
+) -> Result<Vec<TimeElement>, Box<dyn std::error::Error + Send + Sync>> {
+  let document = _parse_html(html)?;
+
+  Ok(
+    document
+      .select("time")
+      .map_err(|_| "Failed to select time elements")?
+      .map(|time| TimeElement {
+        datetime: _non_empty_attribute(&time, "datetime"),
+        text: _collapse_whitespace(&time.text_contents()),
+      })
+      .collect(),
+  )
+}
+
+/// Extract every `<time>` element with its `datetime` attribute and text.
+#[napi]
+pub async fn extract_time_elements(html: String) -> napi::Result<Vec<TimeElement>> {
+  let res = task::spawn_blocking(move || _extract_time_elements(&html))
+    .await
+    .map_err(|e| {
+      napi::Error::new(
+        napi::Status::GenericFailure,
+        format!("extract_time_elements join error: {e}"),
+      )
+    })?;
+
+  res.map_err(_to_napi_err)
+}
+
+#[derive(Serialize)]
+#[napi(object)]
+pub struct CssVar {
+  pub name: String,
+  pub value: String,
+}
+
+static CSS_VAR_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+  Regex::new(r"(--[\w-]+)\s*:\s*([^;}]+)").expect("CSS_VAR_REGEX is a valid static regex pattern")
+});
+
+fn _extract_inline_styles_css_vars(
+  html: &str,
+) -> Result<Vec<CssVar>, Box<dyn std::error::Error + Send + Sync>> {
+  let document = _parse_html(html)?;
+
+  let mut sources: Vec<String> = Vec::new();
+  for element in document
+    .select("style, [style]")
+    .map_err(|_| "Failed to select styles")?
+  {
+    if element.name.local.as_ref() == "style" {
+      sources.push(element.text_contents());
+    }
+    if let Some(style) = element.attributes.borrow().get("style") {
+      sources.push(style.to_string());
+    }
+  }
+
+  let mut out: Vec<CssVar> = Vec::new();
+  let mut index: HashMap<String, usize> = HashMap::new();
+  for source in &sources {
+    for captures in CSS_VAR_REGEX.captures_iter(source) {
+      let name = captures[1].to_string();
+      let value = captures[2].trim().to_string();
+      match index.get(&name) {
+        Some(&i) => out[i].value = value,
+        None => {
+          index.insert(name.clone(), out.len());
+          out.push(CssVar { name, value });
+        }
+      }
+    }
+  }
+
+  Ok(out)
+}
+
+/// Extract the CSS custom properties (`--brand-color: #ff0000`) declared in `<style>`
+/// elements and `style` attributes, in order of first declaration. A property declared
+/// more than once keeps its last value.
+#[napi]
+pub async fn extract_inline_styles_css_vars(html: String) -> napi::Result<Vec<CssVar>> {
+  let res = task::spawn_blocking(move || _extract_inline_styles_css_vars(&html))
+    .await
+    .map_err(|e| {
+      napi::Error::new(
+        napi::Status::GenericFailure,
+        format!("extract_inline_styles_css_vars join error: {e}"),
+      )
+    })?;
+
+  res.map_err(_to_napi_err)
+}
+
+#[derive(Default)]
+#[napi(object)]
+pub struct DiscoverFeedsOptions {
+  /// Also list the common feed locations in `FALLBACK_FEED_PATHS` on the page's
+  /// origin. These are guesses that still need to be fetched. Defaults to false.
+  pub include_fallback_paths: Option<bool>,
+}
+
+#[derive(Serialize)]
+#[napi(object)]
+pub struct DiscoveredFeed {
+  pub href: String,
+  /// "application/rss+xml", "application/atom+xml" or "application/feed+json".
+  pub feed_type: String,
+  pub title: Option<String>,
+}
+
+const FEED_TYPES: [&str; 3] = [
+  "application/rss+xml",
+  "application/atom+xml",
+  "application/feed+json",
+];
+
+const FALLBACK_FEED_PATHS: [(&str, &str); 6] = [
+  ("/feed", "application/rss+xml"),
+  ("/rss.xml", "application/rss+xml"),
+  ("/feed.xml", "application/rss+xml"),
+  ("/index.xml", "application/rss+xml"),
+  ("/atom.xml", "application/atom+xml"),
+  ("/feed.json", "application/feed+json"),
+];
+
+fn _discover_feeds(
+  html: &str,
+  url: &str,
+  options: &DiscoverFeedsOptions,
+) -> Result<Vec<DiscoveredFeed>, Box<dyn std::error::Error + Send + Sync>> {
+  let document = _parse_html(html)?;
+  let page = Url::parse(url)?;
+  let base = Url::parse(&_extract_base_href_from_document(&document, &page)?)?;
+  let head = document.select_first("head").ok();
+  let search_root = head.as_ref().map(|h| h.as_node()).unwrap_or(&document);
+
+  let mut out: Vec<DiscoveredFeed> = Vec::new();
+  for link in search_root
+    .select("link[rel~=\"alternate\"][type][href]")
+    .map_err(|_| "Failed to select feed links")?
+  {
+    let attrs = link.attributes.borrow();
+    let feed_type = attrs
+      .get("type")
+      .and_then(|x| x.split(';').next())
+      .unwrap_or_default()
+      .trim()
+      .to_ascii_lowercase();
+    if !FEED_TYPES.contains(&feed_type.as_str()) {
+      continue;
+    }
+    let Ok(href) = base.join(&_normalize_attribute_url(
+      attrs.get("href").unwrap_or_default(),
+    )) else {
+      continue;
+    };
+    let href = href.to_string();
+    let title = _non_empty_attribute(&link, "title");
+
+    match out.iter_mut().find(|x| x.href == href) {
+      Some(existing) => existing.title = existing.title.take().or(title),
+      None => out.push(DiscoveredFeed {
+        href,
+        feed_type,
+        title,
+      }),
+    }
+  }
+
+  if options.include_fallback_paths.unwrap_or(false) {
+    for (path, feed_type) in FALLBACK_FEED_PATHS {
+      let href = page.join(path)?.to_string();
+      if !out.iter().any(|x| x.href == href) {
+        out.push(DiscoveredFeed {
+          href,
+          feed_type: feed_type.to_string(),
+          title: None,
+        });
+      }
+    }
+  }
+
+  Ok(out)
+}
+
+/// Discover the RSS, Atom and JSON feeds a page advertises through
+/// `<link rel="alternate">` in its head, with absolute URLs. A feed linked more than
+/// once is listed once, with the first title given for it.
+#[napi]
+pub async fn discover_feeds(
+  html: String,
+  url: String,
+  options: Option<DiscoverFeedsOptions>,
+) -> napi::Result<Vec<DiscoveredFeed>> {
+  let res =
+    task::spawn_blocking(move || _discover_feeds(&html, &url, &options.unwrap_or_default()))
+      .await
+      .map_err(|e| {
+        napi::Error::new(
+          napi::Status::GenericFailure,
+          format!("discover_feeds join error: {e}"),
+        )
+      })?;
+
+  res.map_err(_to_napi_err)
+}
+
+#[derive(Serialize)]
+#[napi(object)]
+pub struct ExtractedVideo {
+  pub url: String,
+  /// Where the URL came from: "video", "source", "og:video", "twitter:player" or
+  /// "iframe".
+  pub source: String,
+  pub mime_type: Option<String>,
+  /// Hosting provider from `VIDEO_PROVIDERS`, such as "youtube".
+  pub provider: Option<String>,
+  pub video_id: Option<String>,
+  /// Absolute URL of the poster image of the `<video>`.
+  pub poster: Option<String>,
+}
+
+/// Where the video ID sits in a provider URL.
+enum VideoIdLocation {
+  /// The path segment following this path prefix.
+  PathSegment(&'static str),
+  /// Like `PathSegment`, for providers whose IDs are all digits.
+  NumericPathSegment(&'static str),
+  /// The value of this query parameter.
+  QueryParam(&'static str),
+}
+
+struct VideoProvider {
+  domain: &'static str,
+  provider: &'static str,
+  id: VideoIdLocation,
+}
+
+const fn video_provider(
+  domain: &'static str,
+  provider: &'static str,
+  id: VideoIdLocation,
+) -> VideoProvider {
+  VideoProvider {
+    domain,
+    provider,
+    id,
+  }
+}
+
+/// Known video hosts, matched on the domain and its subdomains. The video ID comes
+/// from the first entry of the domain whose location yields a valid ID.
+const VIDEO_PROVIDERS: [VideoProvider; 12] = [
+  video_provider(
+    "youtube.com",
+    "youtube",
+    VideoIdLocation::PathSegment("/embed/"),
+  ),
+  video_provider(
+    "youtube.com",
+    "youtube",
+    VideoIdLocation::PathSegment("/shorts/"),
+  ),
+  video_provider("youtube.com", "youtube", VideoIdLocation::QueryParam("v")),
+  video_provider(
+    "youtube-nocookie.com",
+    "youtube",
+    VideoIdLocation::PathSegment("/embed/"),
+  ),
+  video_provider("youtu.be", "youtube", VideoIdLocation::PathSegment("/")),
+  video_provider(
+    "player.vimeo.com",
+    "vimeo",
+    VideoIdLocation::NumericPathSegment("/video/"),
+  ),
+  video_provider(
+    "vimeo.com",
+    "vimeo",
+    VideoIdLocation::NumericPathSegment("/"),
+  ),
+  video_provider(
+    "wistia.net",
+    "wistia",
+    VideoIdLocation::PathSegment("/embed/iframe/"),
+  ),
+  video_provider(
+    "wistia.net",
+    "wistia",
+    VideoIdLocation::PathSegment("/embed/medias/"),
+  ),
+  video_provider(
+    "wistia.com",
+    "wistia",
+    VideoIdLocation::PathSegment("/medias/"),
+  ),
+  video_provider("loom.com", "loom", VideoIdLocation::PathSegment("/embed/")),
+  video_provider("loom.com", "loom", VideoIdLocation::PathSegment("/share/")),
+];
+
+/// Provider and, when the URL shape allows it, video ID of a video URL.
+fn _video_provider(url: &Url) -> Option<(&'static str, Option<String>)> {
+  let host = url.host_str()?.to_lowercase();
+  let host = host.trim_end_matches('.');
+  let entries: Vec<&VideoProvider> = VIDEO_PROVIDERS
+    .iter()
+    .filter(|x| _host_matches(host, x.domain))
+    .collect();
+  let provider = entries.first()?.provider;
+
+  let id = entries.iter().find_map(|entry| {
+    let id = match entry.id {
+      VideoIdLocation::PathSegment(prefix) => url
+        .path()
+        .strip_prefix(prefix)
+        .and_then(|x| x.split(['/', '.']).next())
+        .map(|x| x.to_string()),
+      VideoIdLocation::NumericPathSegment(prefix) => url
+        .path()
+        .strip_prefix(prefix)
+        .and_then(|x| x.split('/').next())
+        .filter(|x| x.chars().all(|c| c.is_ascii_digit()))
+        .map(|x| x.to_string()),
+      VideoIdLocation::QueryParam(name) => url
+        .query_pairs()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.into_owned()),
+    }?;
+    let valid = !id.is_empty()
+      && id
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'));
+    valid.then_some(id)
+  });
+
+  Some((provider, id))
+}
+
+fn _extract_videos(
+  html: &str,
+  base_url: &str,
+) -> Result<Vec<ExtractedVideo>, Box<dyn std::error::Error + Send + Sync>> {
+  let document = _parse_html(html)?;
+  let base = Url::parse(&_extract_base_href_from_document(
+    &document,
+    &Url::parse(base_url)?,
+  )?)?;
+  let resolve = |value: &str| {
+    base
+      .join(&_normalize_attribute_url(value))
+      .ok()
+      .filter(|x| matches!(x.scheme(), "http" | "https"))
+  };
+
+  let mut out: Vec<ExtractedVideo> = Vec::new();
+  let mut seen: HashMap<String, usize> = HashMap::new();
+  let mut add = |url: Url, source: &str, mime_type: Option<String>, poster: Option<String>| {
+    if let Some(&i) = seen.get(url.as_str()) {
+      // Fill in details the first occurrence didn't have.
+      let existing = &mut out[i];
+      existing.mime_type = existing.mime_type.take().or(mime_type);
+      existing.poster = existing.poster.take().or(poster);
+      return;
+    }
+
+    let (provider, video_id) = match _video_provider(&url) {
+      Some((provider, id)) => (Some(provider.to_string()), id),
+      None => (None, None),
+    };
+    seen.insert(url.to_string(), out.len());
+    out.push(ExtractedVideo {
+      url: url.to_string(),
+      source: source.to_string(),
+      mime_type,
+      provider,
+      video_id,
+      poster,
+    });
+  };
+
+  let og_type = document
+    .select_first("meta[property=\"og:video:type\"]")
+    .ok()
+    .and_then(|x| _non_empty_attribute(&x, "content"));
+  for meta in document
+    .select("meta[property][content], meta[name][content]")
+    .map_err(|_| "Failed to select video meta tags")?
+  {
+    let (name, content) = {
+      let attrs = meta.attributes.borrow();
+      let name = attrs
+        .get("property")
+        .or_else(|| attrs.get("name"))
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+      (name, attrs.get("content").unwrap_or_default().to_string())
+    };
+    let (source, mime_type) = match name.as_str() {
+      "og:video" | "og:video:url" | "og:video:secure_url" => ("og:video", og_type.clone()),
+      "twitter:player" => ("twitter:player", None),
+      _ => continue,
+    };
+    if let Some(url) = resolve(&content) {
+      add(url, source, mime_type, None);
+    }
+  }
+
+  for element in document
+    .select("video, iframe")
+    .map_err(|_| "Failed to select videos")?
+  {
+    if element.name.local.as_ref() == "iframe" {
+      let src = _non_empty_attribute(&element, "src")
+        .or_else(|| _non_empty_attribute(&element, "data-src"));
+      if let Some(url) = src.and_then(|x| resolve(&x)) {
+        if _video_provider(&url).is_some() {
+          add(url, "iframe", None, None);
+        }
+      }
+      continue;
+    }
+
+    let poster = _non_empty_attribute(&element, "poster")
+      .and_then(|x| resolve(&x))
+      .map(|x| x.to_string());
+    if let Some(url) = _non_empty_attribute(&element, "src").and_then(|x| resolve(&x)) {
+      add(url, "video", None, poster.clone());
+    }
+    for source in element
+      .as_node()
+      .select("source[src]")
+      .map_err(|_| "Failed to select video sources")?
+    {
+      if let Some(url) = _non_empty_attribute(&source, "src").and_then(|x| resolve(&x)) {
+        let mime_type = _non_empty_attribute(&source, "type");
+        add(url, "source", mime_type, poster.clone());
+      }
+    }
+  }
+
+  Ok(out)
+}
+
+/// Extract the videos of a page: `<video>` and `<source>` URLs, `og:video` and
+/// `twitter:player` meta tags, and iframe embeds from the providers in
+/// `VIDEO_PROVIDERS` with their video IDs. URLs are absolute and listed once.
+#[napi]
+pub async fn extract_videos(html: String, base_url: String) -> napi::Result<Vec<ExtractedVideo>> {
+  let res = task::spawn_blocking(move || _extract_videos(&html, &base_url))
+    .await
+    .map_err(|e| {
+      napi::Error::new(
+        napi::Status::GenericFailure,
+        format!("extract_videos join error: {e}"),
+      )
+    })?;
+
+  res.map_err(_to_napi_err)
+}
+
+#[derive(Serialize)]
+#[napi(object)]
+pub struct IframeEmbed {
+  pub url: String,
+  /// Embedded service from `VIDEO_PROVIDERS` or `EMBED_PROVIDERS`, such as "youtube"
+  /// or "google_maps".
+  pub provider: Option<String>,
+  pub width: Option<String>,
+  pub height: Option<String>,
+}
+
+/// Known embed hosts other than the video hosts of `VIDEO_PROVIDERS`, as domain, path
+/// prefix and provider, matched on the domain and its subdomains. The first match
+/// wins.
+const EMBED_PROVIDERS: [(&str, &str, &str); 17] = [
+  ("google.com", "/maps", "google_maps"),
+  ("maps.google.com", "/", "google_maps"),
+  ("twitter.com", "/", "twitter"),
+  ("x.com", "/", "twitter"),
+  ("instagram.com", "/", "instagram"),
+  ("facebook.com", "/", "facebook"),
+  ("tiktok.com", "/", "tiktok"),
+  ("spotify.com", "/", "spotify"),
+  ("soundcloud.com", "/", "soundcloud"),
+  ("twitch.tv", "/", "twitch"),
+  ("dailymotion.com", "/", "dailymotion"),
+  ("codepen.io", "/", "codepen"),
+  ("codesandbox.io", "/", "codesandbox"),
+  ("figma.com", "/", "figma"),
+  ("calendly.com", "/", "calendly"),
+  ("typeform.com", "/", "typeform"),
+  ("slideshare.net", "/", "slideshare"),
+];
+
+fn _embed_provider(url: &Url) -> Option<&'static str> {
+  let host = url.host_str()?.to_lowercase();
+  let host = host.trim_end_matches('.');
+  if let Some((provider, _)) = _video_provider(url) {
+    return Some(provider);
+  }
+  EMBED_PROVIDERS.iter().find_map(|(domain, path, provider)| {
+    let matches = _host_matches(host, domain) && url.path().starts_with(path);
+    matches.then_some(*provider)
+  })
+}
+
+fn _extract_iframe_embeds(
+  html: &str,
+  base_url: &str,
+) -> Result<Vec<IframeEmbed>, Box<dyn std::error::Error + Send + Sync>> {
+  let document = _parse_html(html)?;
+  let base = Url::parse(&_extract_base_href_from_document(
+    &document,
+    &Url::parse(base_url)?,
+  )?)?;
+  let resolve = |value: String| {
+    base
+      .join(&_normalize_attribute_url(&value))
+      .ok()
+      .filter(|x| matches!(x.scheme(), "http" | "https"))
+  };
+
+  let mut out = Vec::new();
+  for iframe in document
+    .select("iframe[src], iframe[data-src]")
+    .map_err(|_| "Failed to select iframes")?
+  {
+    // Lazy-loaded iframes keep a placeholder such as about:blank in src.
+    let Some(url) = _non_empty_attribute(&iframe, "src")
+      .and_then(resolve)
+      .or_else(|| _non_empty_attribute(&iframe, "data-src").and_then(resolve))
+    else {
+      continue;
+    };
+
+    out.push(IframeEmbed {
+      provider: _embed_provider(&url).map(|x| x.to_string()),
+      url: url.to_string(),
+      width: _non_empty_attribute(&iframe, "width"),
+      height: _non_empty_attribute(&iframe, "height"),
+    });
+  }
+
+  Ok(out)
+}
+
+/// Extract the `<iframe>` embeds of a page with their absolute URL, taken from `src`
+/// or the lazy-loading `data-src`, and the provider from `EMBED_PROVIDERS`.
+#[napi]
+pub async fn extract_iframe_embeds(
+  html: String,
+  base_url: String,
+) -> napi::Result<Vec<IframeEmbed>> {
+  let res = task::spawn_blocking(move || _extract_iframe_embeds(&html, &base_url))
+    .await
+    .map_err(|e| {
+      napi::Error::new(
+        napi::Status::GenericFailure,
+        format!("extract_iframe_embeds join error: {e}"),
+      )
+    })?;
+
+  res.map_err(_to_napi_err)
+}
+
+#[derive(Serialize)]
+#[napi(object)]
+pub struct ResourceHint {
+  pub url: String,
+  /// "preload", "modulepreload", "prefetch", "preconnect" or "dns-prefetch".
+  pub rel: String,
+  /// Lowercased `as` attribute, such as "font", "script" or "style".
+  pub as_: Option<String>,
+  /// Whether the hint carries a `crossorigin` attribute.
+  pub crossorigin: bool,
+}
+
+const RESOURCE_HINT_RELS: [&str; 5] = [
+  "preload",
+  "modulepreload",
+  "prefetch",
+  "preconnect",
+  "dns-prefetch",
+];
+
+fn _extract_preload_resources(
+  html: &str,
+  base_url: &str,
+) -> Result<Vec<ResourceHint>, Box<dyn std::error::Error + Send + Sync>> {
+  let document = _parse_html(html)?;
+  let base = Url::parse(&_extract_base_href_from_document(
+    &document,
+    &Url::parse(base_url)?,
+  )?)?;
+
+  let mut out = Vec::new();
+  for link in document
+    .select("link[rel][href]")
+    .map_err(|_| "Failed to select resource hints")?
+  {
+    let attrs = link.attributes.borrow();
+    let href = _normalize_attribute_url(attrs.get("href").unwrap_or_default());
+    let Ok(url) = base.join(&href) else {
+      continue;
+    };
+    let as_ = attrs
+      .get("as")
+      .map(|x| x.trim().to_ascii_lowercase())
+      .filter(|x| !x.is_empty());
+    let crossorigin = attrs.contains("crossorigin");
+
+    // `rel` is a token list, so one element can carry several hints.
+    let rels = attrs.get("rel").unwrap_or_default().to_ascii_lowercase();
+    for rel in rels
+      .split_whitespace()
+      .filter(|x| RESOURCE_HINT_RELS.contains(x))
+    {
+      out.push(ResourceHint {
+        url: url.to_string(),
+        rel: rel.to_string(),
+        as_: as_.clone(),
+        crossorigin,
+      });
+    }
+  }
+
+  Ok(out)
+}
+
+/// Extract the resource hints of a page (`preload`, `modulepreload`, `prefetch`,
+/// `preconnect` and `dns-prefetch` links) with their absolute URLs.
+#[napi]
+pub async fn extract_preload_resources(
+  html: String,
+  base_url: String,
+) -> napi::Result<Vec<ResourceHint>> {
+  let res = task::spawn_blocking(move || _extract_preload_resources(&html, &base_url))
+    .await
+    .map_err(|e| {
+      napi::Error::new(
+        napi::Status::GenericFailure,
+        format!("extract_preload_resources join error: {e}"),
+      )
+    })?;
+
+  res.map_err(_to_napi_err)
+}
+
+#[derive(Serialize)]
+#[napi(object)]
+pub struct FigureInfo {
+  pub image_url: Option<String>,
+  pub caption: Option<String>,
+  pub alt: Option<String>,
+}
+
+fn _extract_figures(
+  html: &str,
+  base_url: &str,
+) -> Result<Vec<FigureInfo>, Box<dyn std::error::Error + Send + Sync>> {
+  let document = _parse_html(html)?;
+  let base = Url::parse(&_extract_base_href_from_document(
+    &document,
+    &Url::parse(base_url)?,
+  )?)?;
+
+  let mut out = Vec::new();
+  for figure in document
+    .select("figure")
+    .map_err(|_| "Failed to select figures")?
+  {
+    let image = figure
+      .as_node()
+      .select("img[src]")
+      .map_err(|_| "Failed to select figure images")?
+      .find(|x| {
+        // Images of a nested figure belong to that figure.
+        x.as_node()
+          .ancestors()
+          .find(|a| {
+            a.as_element()
+              .is_some_and(|e| e.name.local.as_ref() == "figure")
+          })
+          .is_some_and(|a| &a == figure.as_node())
+      });
+    let image_url = image.as_ref().and_then(|img| {
+      let src = _normalize_attribute_url(img.attributes.borrow().get("src").unwrap_or_default());
+      base.join(&src).ok().map(|x| x.to_string())
+    });
+    let alt = image
+      .as_ref()
+      .and_then(|img| _non_empty_attribute(img, "alt"));
+
+    let caption = figure
+      .as_node()
+      .children()
+      .find(|x| {
+        x.as_element()
+          .is_some_and(|e| e.name.local.as_ref() == "figcaption")
+      })
+      .map(|x| _collapse_whitespace(&x.text_contents()))
+      .filter(|x| !x.is_empty());
+
+    out.push(FigureInfo {
+      image_url,
+      caption,
+      alt,
+    });
+  }
+
+  Ok(out)
+}
+
+/// Extract every `<figure>` with the absolute URL and alt text of its first image and
+/// the text of its `<figcaption>`.
+#[napi]
+pub async fn extract_figures(html: String, base_url: String) -> napi::Result<Vec<FigureInfo>> {
+  let res = task::spawn_blocking(move || _extract_figures(&html, &base_url))
+    .await
+    .map_err(|e| {
+      napi::Error::new(
+        napi::Status::GenericFailure,
+        format!("extract_figures join error: {e}"),
+      )
+    })?;
+
+  res.map_err(_to_napi_err)
+}
+
+#[derive(Serialize)]
+#[napi(object)]
+pub struct AriaLandmark {
+  pub role: String,
+  /// Accessible name from `aria-labelledby` or `aria-label`.
+  pub label: Option<String>,
+  pub element_tag: String,
+}
+
+const LANDMARK_ROLES: [&str; 8] = [
+  "main",
+  "navigation",
+  "banner",
+  "contentinfo",
+  "complementary",
+  "search",
+  "form",
+  "region",
+];
+
+/// Sectioning elements that keep `<header>` and `<footer>` from being page-level
+/// banner and contentinfo landmarks.
+const SECTIONING_TAGS: [&str; 5] = ["article", "aside", "main", "nav", "section"];
+
+fn _extract_aria_landmarks(
+  html: &str,
+) -> Result<Vec<AriaLandmark>, Box<dyn std::error::Error + Send + Sync>> {
+  let document = _parse_html(html)?;
+
+  let mut ids: HashMap<String, NodeRef> = HashMap::new();
+  for element in document.descendants().elements() {
+    if let Some(id) = element.attributes.borrow().get("id") {
+      ids
+        .entry(id.to_string())
+        .or_insert_with(|| element.as_node().clone());
+    }
+  }
+
+  let mut out = Vec::new();
+  for element in document.descendants().elements() {
+    let tag = element.name.local.as_ref().to_ascii_lowercase();
+
+    let label = element
+      .attributes
+      .borrow()
+      .get("aria-labelledby")
+      .map(|x| {
+        let text: Vec<String> = x
+          .split_whitespace()
+          .filter_map(|id| ids.get(id))
+          .map(|x| _collapse_whitespace(&x.text_contents()))
+          .filter(|x| !x.is_empty())
+          .collect();
+        text.join(" ")
+      })
+      .filter(|x| !x.is_empty())
+      .or_else(|| _non_empty_attribute(&element, "aria-label"));
+
+    // An explicit role replaces the implicit one, even when it is not a landmark.
+    let explicit_role = element
+      .attributes
+      .borrow()
+      .get("role")
+      .map(|x| x.to_ascii_lowercase())
+      .filter(|x| !x.trim().is_empty());
+    let role = match explicit_role {
+      Some(roles) => roles
+        .split_whitespace()
+        .find(|x| LANDMARK_ROLES.contains(x))
+        .map(|x| x.to_string()),
+      None => {
+        let in_sectioning = || {
+          element.as_node().ancestors().any(|x| {
+            x.as_element()
+              .is_some_and(|e| SECTIONING_TAGS.contains(&e.name.local.as_ref()))
+          })
+        };
+        match tag.as_str() {
+          "main" => Some("main"),
+          "nav" => Some("navigation"),
+          "aside" => Some("complementary"),
+          "header" if !in_sectioning() => Some("banner"),
+          "footer" if !in_sectioning() => Some("contentinfo"),
+          // A section is only a region landmark once it has a name.
+          "section" if label.is_some() => Some("region"),
+          _ => None,
+        }
+        .map(|x| x.to_string())
+      }
+    };
+
+    if let Some(role) = role {
+      out.push(AriaLandmark {
+        role,
+        label,
+        element_tag: tag,
+      });
+    }
+  }
+
+  Ok(out)
+}
+
+/// Extract the ARIA landmark regions of a page in document order, from explicit
+/// `role` attributes and from the roles implied by `<main>`, `<nav>`, `<header>`,
+/// `<footer>`, `<aside>` and labelled `<section>` elements.
+#[napi]
+pub async fn extract_aria_landmarks(html: String) -> napi::Result<Vec<AriaLandmark>> {
+  let res = task::spawn_blocking(move || _extract_aria_landmarks(&html))
+    .await
+    .map_err(|e| {
+      napi::Error::new(
+        napi::Status::GenericFailure,
+        format!("extract_aria_landmarks join error: {e}"),
+      )
+    })?;
+
+  res.map_err(_to_napi_err)
+}
+
+#[derive(Serialize)]
+#[napi(object)]
+pub struct LanguageSignal {
+  /// BCP 47 language tag such as "en" or "pt-BR", or "x-default".
+  pub lang: String,
+  /// Comma-separated signals that declared this language, in the order checked:
+  /// "html_lang", "content_language", "og_locale", "og_locale_alternate" and
+  /// "hreflang".
+  pub source: String,
+  /// Absolute URL of the page in this language, from hreflang.
+  pub url: Option<String>,
+}
+
+/// A language tag in BCP 47 form: `en_us` and `EN-US` both become `en-US`.
+fn _normalize_language_tag(tag: &str) -> Option<String> {
+  let tag = tag.trim().replace('_', "-");
+  if tag.is_empty() {
+    return None;
+  }
+  if tag.eq_ignore_ascii_case("x-default") {
+    return Some("x-default".to_string());
+  }
+
+  let parts: Vec<String> = tag
+    .split('-')
+    .enumerate()
+    .map(|(i, part)| {
+      // Only well-formed subtags are recased; anything else passes through unchanged.
+      if !part.bytes().all(|b| b.is_ascii_alphanumeric()) {
+        return part.to_string();
+      }
+      match (i, part.len()) {
+        (0, _) => part.to_ascii_lowercase(),
+        (_, 2) => part.to_ascii_uppercase(),
+        (_, 4) if part.bytes().all(|b| b.is_ascii_alphabetic()) => {
+          let mut script = part.to_ascii_lowercase();
+          script[..1].make_ascii_uppercase();
+          script
+        }
+        _ => part.to_ascii_lowercase(),
+      }
+    })
+    .collect();
+  Some(parts.join("-"))
+}
+
+fn _extract_language_alternates(
+  html: &str,
+  base_url: &str,
+) -> Result<Vec<LanguageSignal>, Box<dyn std::error::Error + Send + Sync>> {
+  let document = _parse_html(html)?;
+  let base = Url::parse(&_extract_base_href_from_document(
+    &document,
+    &Url::parse(base_url)?,
+  )?)?;
+
+  let mut signals: Vec<(&str, String, Option<String>)> = Vec::new();
+  let attribute_values = |selector: &str, attribute: &str| -> Vec<String> {
+    document.select(selector).map_or(Vec::new(), |x| {
+      x.filter_map(|x| x.attributes.borrow().get(attribute).map(|x| x.to_string()))
+        .collect()
+    })
+  };
+
+  for lang in attribute_values("html[lang]", "lang") {
+    signals.push(("html_lang", lang, None));
+  }
+  for content in attribute_values(
+    "meta[http-equiv=\"content-language\" i][content]",
+    "content",
+  ) {
+    for lang in content.split(',') {
+      signals.push(("content_language", lang.to_string(), None));
+    }
+  }
+  for lang in attribute_values("meta[property=\"og:locale\" i][content]", "content") {
+    signals.push(("og_locale", lang, None));
+  }
+  for lang in attribute_values(
+    "meta[property=\"og:locale:alternate\" i][content]",
+    "content",
+  ) {
+    signals.push(("og_locale_alternate", lang, None));
+  }
+  if let Ok(links) = document.select("link[rel~=\"alternate\" i][hreflang]") {
+    for link in links {
+      let attributes = link.attributes.borrow();
+      let url = attributes
+        .get("href")
+        .and_then(|x| base.join(&_normalize_attribute_url(x)).ok())
+        .map(|x| x.to_string());
+      if let Some(lang) = attributes.get("hreflang") {
+        signals.push(("hreflang", lang.to_string(), url));
+      }
+    }
+  }
+
+  let mut out: Vec<LanguageSignal> = Vec::new();
+  for (source, lang, url) in signals {
+    let Some(lang) = _normalize_language_tag(&lang) else {
+      continue;
+    };
+    match out.iter_mut().find(|x| x.lang == lang) {
+      Some(existing) => {
+        if !existing.source.split(',').any(|x| x == source) {
+          existing.source = format!("{},{source}", existing.source);
+        }
+        if existing.url.is_none() {
+          existing.url = url;
+        }
+      }
+      None => out.push(LanguageSignal {
+        lang,
+        source: source.to_string(),
+        url,
+      }),
+    }
+  }
+
+  Ok(out)
+}
+
+/// Collect the languages a page declares, one entry per language tag, from
+/// `<html lang>`, Content-Language, og:locale and its alternates, and hreflang
+/// alternate links, which also give the URL of each translation.
+#[napi]
+pub async fn extract_language_alternates(
+  html: String,
+  base_url: String,
+) -> napi::Result<Vec<LanguageSignal>> {
+  let res = task::spawn_blocking(move || _extract_language_alternates(&html, &base_url))
+    .await
+    .map_err(|e| {
+      napi::Error::new(
+        napi::Status::GenericFailure,
+        format!("extract_language_alternates join error: {e}"),
+      )
+    })?;
+
+  res.map_err(_to_napi_err)
+}
+
+#[derive(Serialize)]
+#[napi(object)]
+pub struct NavNode {
+  pub text: String,
+  /// Absolute URL of the item's link; `None` for labels and `#` or `javascript:`
+  /// toggles.
+  pub url: Option<String>,
+  pub children: Vec<NavNode>,
+}
+
+fn _is_list(node: &NodeRef) -> bool {
+  node
+    .as_element()
+    .is_some_and(|e| matches!(e.name.local.as_ref(), "ul" | "ol"))
+}
+
+/// Descendants of `node` that aren't inside a nested `<ul>` or `<ol>`, which are
+/// returned themselves but not entered.
+fn _outside_lists(node: &NodeRef, out: &mut Vec<NodeRef>) {
+  for child in node.children() {
+    out.push(child.clone());
+    if !_is_list(&child) {
+      _outside_lists(&child, out);
+    }
+  }
+}
+
+/// A link target worth following: not empty, a fragment-only toggle or `javascript:`.
+fn _nav_url(element: &NodeDataRef<ElementData>, base: &Url) -> Option<String> {
+  let href = _normalize_attribute_url(element.attributes.borrow().get("href")?);
+  if href.is_empty()
+    || href.starts_with('#')
+    || href.to_ascii_lowercase().starts_with("javascript:")
+  {
+    return None;
+  }
+  base.join(&href).ok().map(|x| x.to_string())
+}
+
+fn _nav_nodes_from_list(list: &NodeRef, base: &Url) -> Vec<NavNode> {
+  let mut out = Vec::new();
+  for item in list.children().elements() {
+    if item.name.local.as_ref() != "li" {
+      continue;
+    }
+
+    let mut own = Vec::new();
+    _outside_lists(item.as_node(), &mut own);
+
+    // Text nodes are adjacent in the source, so `Pro<b>duct</b>` is one word.
+    let text: String = own
+      .iter()
+      .filter_map(|x| x.as_text().map(|x| x.borrow().clone()))
+      .collect();
+    let url = own
+      .iter()
+      .filter_map(|x| x.clone().into_element_ref())
+      .find(|x| x.name.local.as_ref() == "a")
+      .and_then(|x| _nav_url(&x, base));
+    let children: Vec<NavNode> = own
+      .iter()
+      .filter(|x| _is_list(x))
+      .flat_map(|x| _nav_nodes_from_list(x, base))
+      .collect();
+
+    let text = _collapse_whitespace(&text);
+    if text.is_empty() && url.is_none() && children.is_empty() {
+      continue;
+    }
+    out.push(NavNode {
+      text,
+      url,
+      children,
+    });
+  }
+  out
+}
+
+fn _extract_navigation_tree(
+  html: &str,
+  base_url: &str,
+) -> Result<Vec<NavNode>, Box<dyn std::error::Error + Send + Sync>> {
+  let document = _parse_html(html)?;
+  let base = Url::parse(&_extract_base_href_from_document(
+    &document,
+    &Url::parse(base_url)?,
+  )?)?;
+
+  let containers: Vec<NodeRef> = document
+    .select("nav, [role=\"navigation\" i], [class*=\"menu\"]")
+    .map_err(|_| "Failed to select navigation")?
+    .map(|x| x.as_node().clone())
+    .collect();
+  let is_container = |node: &NodeRef| containers.iter().any(|x| x == node);
+
+  let mut out = Vec::new();
+  for container in &containers {
+    // Menus nested in another container are part of its tree.
+    if container.ancestors().any(|x| is_container(&x)) {
+      continue;
+    }
+
+    let mut own = Vec::new();
+    _outside_lists(container, &mut own);
+    let lists: Vec<_> = own.iter().filter(|x| _is_list(x)).collect();
+    if !lists.is_empty() {
+      for list in lists {
+        out.extend(_nav_nodes_from_list(list, &base));
+      }
+      continue;
+    }
+
+    // A flat menu of bare links.
+    for link in own.iter().filter_map(|x| x.clone().into_element_ref()) {
+      if link.name.local.as_ref() != "a" {
+        continue;
+      }
+      let text = _collapse_whitespace(&link.text_contents());
+      let url = _nav_url(&link, &base);
+      if !text.is_empty() || url.is_some() {
+        out.push(NavNode {
+          text,
+          url,
+          children: Vec::new(),
+        });
+      }
+    }
+  }
+
+  Ok(out)
+}
+
+/// Extract site navigation as a tree: `<nav>`, `role="navigation"` and menu-classed
+/// elements are read as nested `<ul>`/`<ol>` lists, where a list inside an `<li>`
+/// holds the children of that item. Menus of bare links become flat lists.
+#[napi]
+pub async fn extract_navigation_tree(html: String, base_url: String) -> napi::Result<Vec<NavNode>> {
+  let res = task::spawn_blocking(move || _extract_navigation_tree(&html, &base_url))
+    .await
+    .map_err(|e| {
+      napi::Error::new(
+        napi::Status::GenericFailure,
+        format!("extract_navigation_tree join error: {e}"),
+      )
+    })?;
+
+  res.map_err(_to_napi_err)
+}
+
+#[napi(object)]
+pub struct SanitizeOptions {
+  /// Tag names to keep. Other elements are removed together with their contents.
+  pub allowed_tags: Vec<String>,
+  /// Attributes to keep per tag name. Attributes listed under `*` are kept on every
+  /// allowed tag.
+  pub allowed_attributes: HashMap<String, Vec<String>>,
+  pub strip_comments: bool,
+}
+
+fn _sanitize_html(
+  html: &str,
+  options: &SanitizeOptions,
+) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+  let document = _parse_html(html)?;
+  let body = document
+    .select_first("body")
+    .map_err(|_| "Failed to select body")?;
+
+  let allowed_tags: HashSet<String> = options
+    .allowed_tags
+    .iter()
+    .map(|x| x.trim().to_ascii_lowercase())
+    .collect();
+  let allowed_attributes: HashMap<String, HashSet<String>> = options
+    .allowed_attributes
+    .iter()
+    .map(|(tag, attributes)| {
+      (
+        tag.trim().to_ascii_lowercase(),
+        attributes
+          .iter()
+          .map(|x| x.trim().to_ascii_lowercase())
+          .collect(),
+      )
+    })
+    .collect();
+  let global_attributes = allowed_attributes.get("*");
+
+  let mut removed = Vec::new();
+  for node in body.as_node().descendants() {
+    if let Some(element) = node.as_element() {
+      let tag = element.name.local.as_ref().to_ascii_lowercase();
+      if !allowed_tags.contains(&tag) {
+        removed.push(node.clone());
+        continue;
+      }
+
+      let tag_attributes = allowed_attributes.get(&tag);
+      element.attributes.borrow_mut().map.retain(|name, _| {
+        let name = name.local.as_ref().to_ascii_lowercase();
+        tag_attributes.is_some_and(|x| x.contains(&name))
+          || global_attributes.is_some_and(|x| x.contains(&name))
+      });
+    } else if options.strip_comments && node.as_comment().is_some() {
+      removed.push(node.clone());
+    }
+  }
+  for node in removed {
+    node.detach();
+  }
+
+  Ok(body.as_node().children().map(|x| x.to_string()).collect())
+}
+
+/// Sanitize an HTML fragment against an allowlist of tags and attributes, returning
+/// the sanitized contents of `<body>`. URLs are left as they are.
+#[napi]
+pub async fn sanitize_html(html: String, options: SanitizeOptions) -> napi::Result<String> {
+  let res = task::spawn_blocking(move || _sanitize_html(&html, &options))
+    .await
+    .map_err(|e| {
+      napi::Error::new(
+        napi::Status::GenericFailure,
+        format!("sanitize_html join error: {e}"),
+      )
+    })?;
+
+  res.map_err(_to_napi_err)
+}
+
+type DocumentJob = Box<dyn FnOnce(&NodeRef) + Send>;
+
+/// Limit on the number of parsed documents alive at once: 64, or the value of the
+/// FIRECRAWL_NATIVE_MAX_PARSED_DOCUMENTS environment variable.
+static MAX_PARSED_DOCUMENTS: LazyLock<usize> = LazyLock::new(|| {
+  std::env::var("FIRECRAWL_NATIVE_MAX_PARSED_DOCUMENTS")
+    .ok()
+    .and_then(|x| x.trim().parse().ok())
+    .unwrap_or(64)
+});
+
+/// Number of parsed document threads still running.
+static PARSED_DOCUMENTS: AtomicUsize = AtomicUsize::new(0);
+
+/// Counts a parsed document thread towards `MAX_PARSED_DOCUMENTS` until it exits.
+struct ParsedDocumentSlot;
+
+impl Drop for ParsedDocumentSlot {
+  fn drop(&mut self) {
+    PARSED_DOCUMENTS.fetch_sub(1, Ordering::SeqCst);
+  }
+}
+
+/// An HTML document parsed once and shared across extraction calls.
+///
+/// kuchikiki trees are not `Send`, so the tree lives on a dedicated thread and each
+/// extraction is shipped to it as a job, while a libuv worker waits for the reply.
+/// Every document therefore costs a thread and its tree until it is released with
+/// `release_document` or its handle is garbage collected, so at most
+/// `MAX_PARSED_DOCUMENTS` can be alive at once.
+pub struct ParsedDocument {
+  /// `None` once the document has been released.
+  jobs: Mutex<Option<mpsc::Sender<DocumentJob>>>,
+}
+
+impl ParsedDocument {
+  fn new(html: String) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+    _check_input_size(html.len(), None)?;
+    let limit = *MAX_PARSED_DOCUMENTS;
+    PARSED_DOCUMENTS
+      .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |x| {
+        (x < limit).then_some(x + 1)
+      })
+      .map_err(|_| {
+        format!("Too many parsed documents: at most {limit} can be alive, release some first")
+      })?;
+    // Dropped with the thread, or with the closure if the thread can't be spawned.
+    let slot = ParsedDocumentSlot;
+    let (jobs, queue) = mpsc::channel::<DocumentJob>();
+
+    thread::Builder::new()
+      .name("parsed-document".to_string())
+      .spawn(move || {
+        let _slot = slot;
+        let document = parse_html().one(html);
+        for job in queue {
+          job(&document);
+        }
+      })?;
+
+    Ok(Self {
+      jobs: Mutex::new(Some(jobs)),
+    })
+  }
+
+  /// Close the job queue. Jobs already queued still run, then the thread exits and
+  /// frees the tree.
+  fn release(&self) {
+    self.jobs.lock().unwrap().take();
+  }
+
+  /// Queue `f` on the document thread. The returned task waits for the reply on the
+  /// libuv thread pool, so the JS thread isn't blocked while the job runs.
+  fn send<T, F>(&self, f: F) -> DocumentTask<T>
+  where
+    T: Send + 'static,
+    F: FnOnce(&NodeRef) -> Result<T, Box<dyn std::error::Error + Send + Sync>> + Send + 'static,
+  {
+    let (tx, reply) = mpsc::sync_channel(1);
+    match self.jobs.lock().unwrap().as_ref() {
+      // When the thread is gone the job and `tx` are dropped, which `compute` reports.
+      Some(jobs) => {
+        let _ = jobs.send(Box::new(move |document| {
+          let _ = tx.send(f(document).map_err(|e| e.to_string()));
+        }));
+      }
+      None => {
+        let _ = tx.send(Err("Parsed document was released".to_string()));
+      }
+    }
+
+    DocumentTask { reply }
+  }
+}
+
+/// The pending result of a job sent to a `ParsedDocument`.
+pub struct DocumentTask<T> {
+  reply: mpsc::Receiver<Result<T, String>>,
+}
+
+impl<T: ToNapiValue + TypeName + Send + 'static> Task for DocumentTask<T> {
+  type Output = T;
+  type JsValue = T;
+
+  fn compute(&mut self) -> napi::Result<T> {
+    self
+      .reply
+      .recv()
+      .map_err(|_| _to_napi_err("Parsed document worker stopped"))?
+      .map_err(_to_napi_err)
+  }
+
+  fn resolve(&mut self, _: Env, output: T) -> napi::Result<T> {
+    Ok(output)
+  }
+}
+
+/// Parse an HTML document once for use with the `*_from_doc` extraction functions.
+#[napi]
+pub fn parse_document(html: String) -> napi::Result<External<ParsedDocument>> {
+  ParsedDocument::new(html)
+    .map(External::new)
+    .map_err(_to_napi_err)
+}
+
+/// Release a parsed document without waiting for garbage collection. Extractions
+/// already started still finish; later `*_from_doc` calls on it fail.
+#[napi]
+pub fn release_document(doc: &External<ParsedDocument>) {
+  doc.release();
+}
+
+/// Extract the base href from a parsed document.
+#[napi(ts_return_type = "Promise<string>")]
+pub fn extract_base_href_from_doc(
+  doc: &External<ParsedDocument>,
+  url: String,
+) -> AsyncTask<DocumentTask<String>> {
+  AsyncTask::new(doc.send(move |document| {
+    let url = Url::parse(&url)?;
+    _extract_base_href_from_document(document, &url)
+  }))
+}
+
+/// Extract all links from a parsed document.
+#[napi(ts_return_type = "Promise<Array<string>>")]
+pub fn extract_links_from_doc(
+  doc: &External<ParsedDocument>,
+  options: Option<ExtractLinksOptions>,
+) -> AsyncTask<DocumentTask<Vec<String>>> {
+  let options = options.unwrap_or_default();
+  AsyncTask::new(doc.send(move |document| _extract_links_from_document(document, &options)))
+}
+
+/// Extract metadata from a parsed document.
+#[napi(ts_return_type = "Promise<Record<string, any>>")]
+pub fn extract_metadata_from_doc(
+  doc: &External<ParsedDocument>,
+) -> AsyncTask<DocumentTask<HashMap<String, Value>>> {
+  AsyncTask::new(doc.send(_extract_metadata_from_document))
+}
+
+/// Extract all image URLs from a parsed document.
+#[napi(ts_return_type = "Promise<Array<string>>")]
+pub fn extract_images_from_doc(
+  doc: &External<ParsedDocument>,
+  base_url: String,
+) -> AsyncTask<DocumentTask<Vec<String>>> {
+  AsyncTask::new(doc.send(move |document| _extract_images_from_document(document, &base_url)))
+}
+
+/// Extract specified attributes from elements of a parsed document.
+#[napi(ts_return_type = "Promise<Array<ExtractedAttributeResult>>")]
+pub fn extract_attributes_from_doc(
+  doc: &External<ParsedDocument>,
+  options: ExtractAttributesOptions,
+) -> AsyncTask<DocumentTask<Vec<ExtractedAttributeResult>>> {
+  AsyncTask::new(doc.send(move |document| _extract_attributes_from_document(document, &options)))
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum OpenBracket {
+  Link,
+  Image,
+  /// A `[` nested inside image alt text, written out escaped.
+  EscapedInImage,
+}
+
+/// Length of the fence opening `line` (three or more backticks or tildes after at most
+/// three spaces of indentation), and the fence character.
+fn _code_fence(line: &str) -> Option<(char, usize)> {
+  let trimmed = line.trim_start_matches(' ');
+  if line.len() - trimmed.len() > 3 {
+    return None;
+  }
+  let marker = trimmed.chars().next().filter(|c| matches!(c, '`' | '~'))?;
+  let length = trimmed.chars().take_while(|&c| c == marker).count();
+  (length >= 3).then_some((marker, length))
+}
+
+/// Whether `line` closes the code block opened by `fence`.
+fn _closes_code_fence(line: &str, (marker, length): (char, usize)) -> bool {
+  _code_fence(line).is_some_and(|(m, l)| m == marker && l >= length)
+    && line.trim().chars().all(|c| c == marker)
+}
+
+/// Escape newlines inside link labels so the label stays on one logical line, fold
+/// newlines inside image alt text into spaces, and escape brackets nested inside alt
+/// text, which most parsers can't handle (`![a [b]](c)` becomes `![a \[b\]](c)`).
+///
+/// Brackets inside code spans and fenced code blocks are ignored, and unclosed
+/// brackets are forgotten at the end of a paragraph, so a stray `[` only affects the
+/// paragraph it appears in.
+fn escape_multiline_link_labels(markdown: &str) -> String {
+  let mut open: Vec<OpenBracket> = Vec::new();
+  let mut out = String::with_capacity(markdown.len());
+  let mut fence: Option<(char, usize)> = None;
+  // Length of the backtick run that opened the current code span.
+  let mut code_span: Option<usize> = None;
+
+  let mut lines = markdown.split_inclusive('\n').peekable();
+  while let Some(line) = lines.next() {
+    if let Some(opening) = fence {
+      out.push_str(line);
+      if _closes_code_fence(line, opening) {
+        fence = None;
+      }
+      continue;
+    }
+    if line.trim().is_empty() {
+      open.clear();
+      code_span = None;
+      out.push_str(line);
+      continue;
+    }
+    if let Some(opening) = _code_fence(line) {
+      open.clear();
+      code_span = None;
+      fence = Some(opening);
+      out.push_str(line);
+      continue;
+    }
+
+    // A label can't continue past the end of its paragraph.
+    let paragraph_continues = lines
+      .peek()
+      .is_some_and(|next| !next.trim().is_empty() && _code_fence(next).is_none());
+
+    let chars: Vec<char> = line.chars().collect();
+    let mut i = 0;
+    let mut previous: Option<char> = None;
+    while i < chars.len() {
+      let ch = chars[i];
+      let escaped = previous == Some('\\');
+
+      if ch == '`' && !escaped {
+        let run = chars[i..].iter().take_while(|&&c| c == '`').count();
+        code_span = match code_span {
+          None => Some(run),
+          Some(length) if length == run => None,
+          other => other,
+        };
+        out.extend(&chars[i..i + run]);
+        i += run;
+        previous = Some('`');
+        continue;
+      }
+
+      if code_span.is_some() {
+        out.push(ch);
+      } else {
+        match ch {
+          '[' if !escaped => match open.last() {
+            Some(OpenBracket::Image | OpenBracket::EscapedInImage) => {
+              open.push(OpenBracket::EscapedInImage);
+              out.push_str("\\[");
+            }
+            _ => {
+              open.push(if previous == Some('!') {
+                OpenBracket::Image
+              } else {
+                OpenBracket::Link
+              });
+              out.push(ch);
+            }
+          },
+          ']' if !escaped => {
+            if open.pop() == Some(OpenBracket::EscapedInImage) {
+              out.push_str("\\]");
+            } else {
+              out.push(ch);
+            }
+          }
+          '\n' if paragraph_continues => match open.last() {
+            Some(OpenBracket::Image | OpenBracket::EscapedInImage) => out.push(' '),
+            Some(OpenBracket::Link) => {
+              out.push('\\');
+              out.push('\n');
+            }
+            None => out.push(ch),
+          },
+          _ => out.push(ch),
+        }
+      }
+
+      // An escaped backslash doesn't escape the character after it.
+      previous = if escaped && ch == '\\' {
+        None
+      } else {
+        Some(ch)
+      };
+      i += 1;
+    }
+  }
+
+  out
+}
+
+#[derive(Default)]
+#[napi(object)]
+pub struct NormalizeMarkdownOptions {
+  /// Collapse runs of three or more blank lines to two. Defaults to true.
+  pub collapse_blank_lines: Option<bool>,
+  /// Replace links with empty text by their bare URL, or drop them when the URL is a
+  /// fragment. Defaults to true.
+  pub remove_empty_links: Option<bool>,
+  /// Drop a link that repeats the link right before it. Defaults to true.
+  pub dedupe_adjacent_links: Option<bool>,
+  /// Strip trailing whitespace from every line. Defaults to true.
+  pub strip_trailing_whitespace: Option<bool>,
+  /// Drop thematic breaks (`---`, `* * *`, ...). Defaults to false.
+  pub remove_thematic_breaks: Option<bool>,
+}
+
+static MARKDOWN_LINK_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+  Regex::new(r#"!?\[([^\[\]\n]*)\]\(([^()\s]*)(?:\s+"[^"\n]*")?\)"#)
+    .expect("MARKDOWN_LINK_REGEX is a valid static regex pattern")
+});
+
+static THEMATIC_BREAK_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+  Regex::new(r"^ {0,3}(?:(?:\*[ \t]*){3,}|(?:-[ \t]*){3,}|(?:_[ \t]*){3,})$")
+    .expect("THEMATIC_BREAK_REGEX is a valid static regex pattern")
+});
+
+/// Apply the link cleanups of `normalize_markdown` to a single line.
+fn _normalize_markdown_links(line: &str, remove_empty: bool, dedupe: bool) -> String {
+  let mut out = String::with_capacity(line.len());
+  let mut copied = 0;
+  // End offset in `line` and source text of the last link written out.
+  let mut last_link: Option<(usize, &str)> = None;
+
+  for captures in MARKDOWN_LINK_REGEX.captures_iter(line) {
+    let link = captures.get(0).unwrap();
+    if link.as_str().starts_with('!') {
+      continue;
+    }
+    let (text, url) = (&captures[1], &captures[2]);
+
+    let repeated = dedupe
+      && last_link.is_some_and(|(end, previous)| {
+        previous == link.as_str() && line[end..link.start()].trim().is_empty()
+      });
+    if repeated {
+      copied = link.end();
+      continue;
+    }
+
+    out.push_str(&line[copied..link.start()]);
+    if remove_empty && text.trim().is_empty() {
+      if !url.starts_with('#') {
+        out.push_str(url);
+      }
+    } else {
+      out.push_str(link.as_str());
+    }
+    copied = link.end();
+    last_link = Some((link.end(), link.as_str()));
+  }
+  out.push_str(&line[copied..]);
+
+  out
+}
+
+fn _normalize_markdown(markdown: &str, options: &NormalizeMarkdownOptions) -> String {
+  let collapse_blank_lines = options.collapse_blank_lines.unwrap_or(true);
+  let remove_empty_links = options.remove_empty_links.unwrap_or(true);
+  let dedupe_adjacent_links = options.dedupe_adjacent_links.unwrap_or(true);
+  let strip_trailing_whitespace = options.strip_trailing_whitespace.unwrap_or(true);
+  let remove_thematic_breaks = options.remove_thematic_breaks.unwrap_or(false);
+
+  let mut out = String::with_capacity(markdown.len());
+  let mut fence: Option<(char, usize)> = None;
+  let mut blank_run = 0;
+
+  for line in markdown.split_inclusive('\n') {
+    let (content, newline) = match line.strip_suffix('\n') {
+      Some(x) => (x.strip_suffix('\r').unwrap_or(x), &line[x.len()..]),
+      None => (line, ""),
+    };
+
+    // Code blocks are copied as they are.
+    if let Some(opening) = fence {
+      out.push_str(line);
+      if _closes_code_fence(content, opening) {
+        fence = None;
+      }
+      blank_run = 0;
+      continue;
+    }
+    if let Some(opening) = _code_fence(content) {
+      fence = Some(opening);
+      out.push_str(line);
+      blank_run = 0;
+      continue;
+    }
+
+    if remove_thematic_breaks && THEMATIC_BREAK_REGEX.is_match(content) {
+      continue;
+    }
+
+    let mut content = if remove_empty_links || dedupe_adjacent_links {
+      _normalize_markdown_links(content, remove_empty_links, dedupe_adjacent_links)
+    } else {
+      content.to_string()
+    };
+    if strip_trailing_whitespace {
+      content.truncate(content.trim_end().len());
+    }
+
+    if content.trim().is_empty() {
+      blank_run += 1;
+      if collapse_blank_lines && blank_run > 2 {
+        continue;
+      }
+    } else {
+      blank_run = 0;
+    }
+
+    out.push_str(&content);
+    out.push_str(newline);
+  }
+
+  out
+}
+
+/// Clean up converted markdown: collapse runs of blank lines, drop empty and repeated
+/// links, strip trailing whitespace and optionally drop thematic breaks. Each cleanup
+/// can be turned off for byte-faithful output; fenced code blocks are never changed.
+#[napi]
+pub async fn normalize_markdown(
+  markdown: String,
+  options: Option<NormalizeMarkdownOptions>,
+) -> napi::Result<String> {
+  task::spawn_blocking(move || _normalize_markdown(&markdown, &options.unwrap_or_default()))
+    .await
+    .map_err(|e| {
+      napi::Error::new(
+        napi::Status::GenericFailure,
+        format!("normalize_markdown join error: {e}"),
+      )
+    })
+}
+
+fn _post_process_markdown(markdown: &str, skip_link_labels: Option<&[String]>) -> String {
+  let out = escape_multiline_link_labels(markdown);
+
+  match skip_link_labels {
+    Some(labels) => remove_skip_to_content_links(&out, labels),
+    None => {
+      let out = SKIP_LINK_REGEX.replace_all(&out, "");
+      remove_skip_to_content_links(&out, &DEFAULT_SKIP_LINK_LABELS)
+    }
+  }
+}
+
+#[derive(Default)]
+#[napi(object)]
+pub struct ChunkMarkdownOptions {
+  /// Target chunk size in characters, not counting the overlap. Code blocks and
+  /// tables longer than this are kept whole. Defaults to 2000.
+  pub max_chars: Option<u32>,
+  /// Characters of the previous chunk in the same section repeated at the start of
+  /// the next one. Defaults to 0.
+  pub overlap_chars: Option<u32>,
+}
+
+#[derive(Serialize)]
+#[napi(object)]
+pub struct MarkdownChunk {
+  pub text: String,
+  /// Titles of the enclosing headings, outermost first.
+  pub heading_path: Vec<String>,
+  /// UTF-8 byte offset of `text` in the original markdown.
+  pub start: u32,
+  /// UTF-8 byte offset just past the end of `text`.
+  pub end: u32,
+}
+
+static ATX_HEADING_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+  Regex::new(r"^ {0,3}(#{1,6})(?:[ \t]+(.*?))?(?:[ \t]+#+)?[ \t]*$")
+    .expect("ATX_HEADING_REGEX is a valid static regex pattern")
+});
+
+enum MarkdownBlockKind {
+  Heading(usize, String),
+  /// Code block or table, never split.
+  Atomic,
+  Text,
+}
+
+struct MarkdownBlock {
+  start: usize,
+  end: usize,
+  kind: MarkdownBlockKind,
+}
+
+/// Split markdown into headings, code blocks, tables and paragraphs. Block ranges
+/// leave out the trailing line break.
+fn _markdown_blocks(markdown: &str) -> Vec<MarkdownBlock> {
+  let mut lines: Vec<(usize, &str)> = Vec::new();
+  let mut offset = 0;
+  for line in markdown.split_inclusive('\n') {
+    let content = line.strip_suffix('\n').unwrap_or(line);
+    lines.push((offset, content.strip_suffix('\r').unwrap_or(content)));
+    offset += line.len();
+  }
+  let line_end = |i: usize| lines[i].0 + lines[i].1.len();
+  let is_table_row = |line: &str| line.trim_start().starts_with('|');
+
+  let mut blocks = Vec::new();
+  let mut i = 0;
+  while i < lines.len() {
+    let (start, line) = lines[i];
+    if line.trim().is_empty() {
+      i += 1;
+      continue;
+    }
+
+    if let Some(opening) = _code_fence(line) {
+      let mut last = i + 1;
+      while last < lines.len() && !_closes_code_fence(lines[last].1, opening) {
+        last += 1;
+      }
+      let last = last.min(lines.len() - 1);
+      blocks.push(MarkdownBlock {
+        start,
+        end: line_end(last),
+        kind: MarkdownBlockKind::Atomic,
+      });
+      i = last + 1;
+    } else if let Some(captures) = ATX_HEADING_REGEX.captures(line) {
+      let title = captures.get(2).map_or("", |x| x.as_str());
+      blocks.push(MarkdownBlock {
+        start,
+        end: line_end(i),
+        kind: MarkdownBlockKind::Heading(captures[1].len(), title.trim().to_string()),
+      });
+      i += 1;
+    } else {
+      let table = is_table_row(line);
+      let mut last = i;
+      while last + 1 < lines.len() {
+        let next = lines[last + 1].1;
+        let continues = if table {
+          is_table_row(next)
+        } else {
+          !next.trim().is_empty()
+            && _code_fence(next).is_none()
+            && !ATX_HEADING_REGEX.is_match(next)
+        };
+        if !continues {
+          break;
+        }
+        last += 1;
+      }
+      blocks.push(MarkdownBlock {
+        start,
+        end: line_end(last),
+        kind: if table {
+          MarkdownBlockKind::Atomic
+        } else {
+          MarkdownBlockKind::Text
+        },
+      });
+      i = last + 1;
+    }
+  }
+
+  blocks
+}
+
+/// Cut `markdown[start..end]` into pieces of at most `max_chars` characters, or
+/// `first_max_chars` for the first piece, preferring to cut at whitespace.
+fn _split_text_block(
+  markdown: &str,
+  start: usize,
+  end: usize,
+  first_max_chars: usize,
+  max_chars: usize,
+) -> Vec<(usize, usize)> {
+  let mut pieces = Vec::new();
+  let mut piece_start = start;
+  let mut chars = 0;
+  let mut last_space = None;
+
+  for (i, c) in markdown[start..end].char_indices() {
+    let i = start + i;
+    let limit = if pieces.is_empty() {
+      first_max_chars
+    } else {
+      max_chars
+    };
+    if chars == limit {
+      let cut = last_space.filter(|&x| x > piece_start).unwrap_or(i);
+      pieces.push((piece_start, cut));
+      piece_start = markdown[cut..end]
+        .find(|c: char| !c.is_whitespace())
+        .map_or(end, |x| cut + x);
+      let (counted, spaces) = match markdown.get(piece_start..i) {
+        Some(x) => (x.chars().count(), x.rfind(char::is_whitespace)),
+        None => (0, None),
+      };
+      chars = counted;
+      last_space = spaces.map(|x| piece_start + x);
+    }
+    if c.is_whitespace() && i > piece_start {
+      last_space = Some(i);
+    }
+    if i >= piece_start {
+      chars += 1;
+    }
+  }
+  if piece_start < end {
+    pieces.push((piece_start, end));
+  }
+
+  pieces
+}
+
+/// Offset where a chunk repeating up to `overlap_chars` characters before
+/// `previous_end` should start, or `None` when nothing can be repeated. The overlap
+/// starts at a word boundary, never before `previous_start` and never inside a block
+/// in `atomic`.
+fn _chunk_overlap_start(
+  markdown: &str,
+  previous_start: usize,
+  previous_end: usize,
+  overlap_chars: usize,
+  atomic: &[(usize, usize)],
+) -> Option<usize> {
+  if overlap_chars == 0 {
+    return None;
+  }
+  let mut start = markdown[previous_start..previous_end]
+    .char_indices()
+    .rev()
+    .nth(overlap_chars - 1)
+    .map_or(previous_start, |(i, _)| previous_start + i);
+
+  if let Some(&(_, end)) = atomic.iter().find(|(s, e)| *s < start && start < *e) {
+    start = end;
+  }
+  if start > previous_start && !markdown[..start].ends_with(char::is_whitespace) {
+    start += markdown[start..previous_end].find(char::is_whitespace)?;
+  }
+  start += markdown[start..previous_end].find(|c: char| !c.is_whitespace())?;
+
+  (start < previous_end).then_some(start)
+}
+
+fn _chunk_markdown(markdown: &str, options: &ChunkMarkdownOptions) -> Vec<MarkdownChunk> {
+  let max_chars = options.max_chars.unwrap_or(2000).max(1) as usize;
+  let overlap_chars = options.overlap_chars.unwrap_or(0) as usize;
+
+  // Sections start at each heading, with the heading path in effect there.
+  let mut sections: Vec<(Vec<String>, Vec<MarkdownBlock>)> = vec![(Vec::new(), Vec::new())];
+  let mut headings: Vec<(usize, String)> = Vec::new();
+  for block in _markdown_blocks(markdown) {
+    if let MarkdownBlockKind::Heading(level, title) = &block.kind {
+      while headings.last().is_some_and(|(l, _)| l >= level) {
+        headings.pop();
+      }
+      headings.push((*level, title.clone()));
+      sections.push((
+        headings.iter().map(|(_, x)| x.clone()).collect(),
+        Vec::new(),
+      ));
+    }
+    sections.last_mut().unwrap().1.push(block);
+  }
+
+  let mut out = Vec::new();
+  // Headings are attached to the content that follows them, so no chunk is a bare
+  // heading. This is the range of the headings still waiting for content.
+  let mut pending: Option<(usize, usize)> = None;
+  let mut last_path = Vec::new();
+  for (heading_path, blocks) in sections {
+    let mut pieces: Vec<(usize, usize)> = Vec::new();
+    let mut atomic: Vec<(usize, usize)> = Vec::new();
+    for block in &blocks {
+      match block.kind {
+        MarkdownBlockKind::Text => {
+          // The headings count against the first piece, so they fit in its chunk.
+          let first_max_chars = pending.map_or(max_chars, |(start, _)| {
+            max_chars
+              .saturating_sub(markdown[start..block.start].chars().count())
+              .max(1)
+          });
+          let mut split =
+            _split_text_block(markdown, block.start, block.end, first_max_chars, max_chars);
+          if let (Some(first), Some((start, _))) = (split.first_mut(), pending.take()) {
+            first.0 = start;
+          }
+          pieces.extend(split);
+        }
+        MarkdownBlockKind::Atomic => {
+          atomic.push((block.start, block.end));
+          pieces.push((pending.take().map_or(block.start, |(x, _)| x), block.end));
+        }
+        MarkdownBlockKind::Heading(..) => {
+          pending = Some((pending.map_or(block.start, |(x, _)| x), block.end));
+        }
+      }
+    }
+    if !heading_path.is_empty() || !blocks.is_empty() {
+      last_path = heading_path.clone();
+    }
+
+    // Pack whole pieces into chunks of at most `max_chars` characters.
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for (start, end) in pieces {
+      match ranges.last_mut() {
+        Some(last) if markdown[last.0..end].chars().count() <= max_chars => last.1 = end,
+        _ => ranges.push((start, end)),
+      }
+    }
+
+    for (i, &(start, end)) in ranges.iter().enumerate() {
+      let start = i
+        .checked_sub(1)
+        .and_then(|p| {
+          let (previous_start, previous_end) = ranges[p];
+          _chunk_overlap_start(
+            markdown,
+            previous_start,
+            previous_end,
+            overlap_chars,
+            &atomic,
+          )
+        })
+        .unwrap_or(start);
+      out.push(MarkdownChunk {
+        text: markdown[start..end].to_string(),
+        heading_path: heading_path.clone(),
+        start: start as u32,
+        end: end as u32,
+      });
+    }
+  }
+
+  // Headings at the very end have nothing to attach to.
+  if let Some((start, end)) = pending {
+    out.push(MarkdownChunk {
+      text: markdown[start..end].to_string(),
+      heading_path: last_path,
+      start: start as u32,
+      end: end as u32,
+    });
+  }
+
+  out
+}
+
+/// Split markdown into chunks for retrieval: first at headings, then between
+/// paragraphs, keeping fenced code blocks and tables whole. Paragraphs longer than
+/// `max_chars` are cut at whitespace, and headings stay in the chunk of the content
+/// that follows them. Each chunk reports its heading path and its byte range in
+/// `markdown`.
+#[napi]
+pub async fn chunk_markdown(
+  markdown: String,
+  options: Option<ChunkMarkdownOptions>,
+) -> napi::Result<Vec<MarkdownChunk>> {
+  task::spawn_blocking(move || _chunk_markdown(&markdown, &options.unwrap_or_default()))
+    .await
+    .map_err(|e| {
+      napi::Error::new(
+        napi::Status::GenericFailure,
+        format!("chunk_markdown join error: {e}"),
+      )
+    })
+}
+
+/// Process multi-line links in markdown and remove "skip to content" links.
+/// `skip_link_labels` replaces the built-in skip link labels and patterns.
+#[napi]
+pub async fn post_process_markdown(
+  markdown: String,
+  skip_link_labels: Option<Vec<String>>,
+) -> napi::Result<String> {
+  let res =
+    task::spawn_blocking(move || _post_process_markdown(&markdown, skip_link_labels.as_deref()))
+      .await
+      .map_err(|e| {
+        napi::Error::new(
+          napi::Status::GenericFailure,
+          format!("post_process_markdown join error: {e}"),
+        )
+      })?;
+
+  Ok(res)
+}
+
+/// Labels of "skip to content" accessibility links, compared case-insensitively.
+const DEFAULT_SKIP_LINK_LABELS: [&str; 24] = [
+  "skip to content",
+  "skip to main content",
+  "skip to main",
+  "skip to navigation",
+  "skip to main navigation",
+  "skip navigation",
+  "skip to footer",
+  "skip to search",
+  "jump to content",
+  "jump to main content",
+  "jump to navigation",
+  "zum inhalt springen",
+  "zum hauptinhalt springen",
+  "direkt zum inhalt",
+  "aller au contenu",
+  "aller au contenu principal",
+  "ir al contenido",
+  "saltar al contenido",
+  "saltar al contenido principal",
+  "passa al contenuto",
+  "pular para o conteúdo",
+  "naar inhoud springen",
+  "перейти к содержанию",
+  "跳到主要内容",
+];
+
+/// Common English skip links not spelled out in `DEFAULT_SKIP_LINK_LABELS`, such as
+/// "Skip main content" or "Skip to nav".
+static SKIP_LINK_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+  Regex::new(r"(?i)\[skip\s+(to\s+)?(main\s+)?(content|navigation|nav|main)\]\(#[^)]*\)")
+    .expect("SKIP_LINK_REGEX is a valid static regex pattern")
+});
+
+/// Longest link label considered when looking for skip links.
+const MAX_SKIP_LINK_LABEL_LEN: usize = 128;
+
+fn _eq_ignore_case(a: &str, b: &str) -> bool {
+  a.chars()
+    .flat_map(char::to_lowercase)
+    .eq(b.chars().flat_map(char::to_lowercase))
+}
+
+/// Offset of `target` in `bytes[from..]`, not crossing a line break.
+fn _find_on_line(bytes: &[u8], from: usize, target: u8) -> Option<usize> {
+  bytes
+    .get(from..)?
+    .iter()
+    .position(|&b| b == target || b == b'\n')
+    .map(|x| from + x)
+    .filter(|&x| bytes[x] == target)
+}
+
+/// Remove skip links whose label matches one of `labels`, both inline
+/// (`[Skip to content](#main)`) and reference-style (`[Skip to content][1]`). The
+/// definitions of removed references are dropped too once nothing else uses them.
+fn remove_skip_to_content_links<S: AsRef<str>>(input: &str, labels: &[S]) -> String {
+  let bytes = input.as_bytes();
+  let len = bytes.len();
+  let mut out = String::with_capacity(len);
+  let mut references: Vec<&str> = Vec::new();
+  // Bracketed labels left in the output, which include reference ids and the ids of
+  // definitions, to tell which definitions are still used.
+  let mut kept_labels: Vec<&str> = Vec::new();
+  let mut copied = 0;
+  let mut i = 0;
+
+  while i < len {
+    if bytes[i] != b'[' {
+      i += 1;
+      continue;
+    }
+
+    let label_start = i + 1;
+    let label_end = bytes[label_start..]
+      .iter()
+      .take(MAX_SKIP_LINK_LABEL_LEN)
+      .position(|&b| matches!(b, b']' | b'[' | b'\n'))
+      .map(|x| label_start + x)
+      .filter(|&x| bytes[x] == b']');
+    let Some(label_end) = label_end else {
+      i += 1;
+      continue;
+    };
+
+    let label = input[label_start..label_end].trim();
+    if !labels.iter().any(|x| _eq_ignore_case(x.as_ref(), label)) {
+      kept_labels.push(label);
+      i = label_end;
+      continue;
+    }
+
+    let after = label_end + 1;
+    let link_end = match bytes.get(after..after + 2) {
+      Some([b'(', b'#']) => _find_on_line(bytes, after + 2, b')'),
+      Some([b'[', _]) => _find_on_line(bytes, after + 1, b']').inspect(|&end| {
+        let id = &input[after + 1..end];
+        references.push(if id.is_empty() { label } else { id });
+      }),
+      _ => None,
+    };
+
+    match link_end {
+      Some(end) => {
+        out.push_str(&input[copied..i]);
+        copied = end + 1;
+        i = end + 1;
+      }
+      None => {
+        kept_labels.push(label);
+        i = label_end;
+      }
+    }
+  }
+  out.push_str(&input[copied..]);
+
+  // A definition is dangling when its own `[id]:` is the only remaining use of a
+  // reference that was removed.
+  let dangling: Vec<&str> = references
+    .into_iter()
+    .filter(|id| {
+      kept_labels
+        .iter()
+        .filter(|x| _eq_ignore_case(x, id.trim()))
+        .count()
+        == 1
+    })
+    .collect();
+  if dangling.is_empty() {
+    return out;
+  }
+
+  let is_dangling_definition = |line: &str| {
+    let Some(rest) = line.trim_start().strip_prefix('[') else {
+      return false;
+    };
+    let Some((id, _)) = rest.split_once("]:") else {
+      return false;
+    };
+    dangling.iter().any(|x| _eq_ignore_case(x, id.trim()))
+  };
+  out
+    .split_inclusive('\n')
+    .filter(|line| !is_dangling_definition(line))
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_detect_paywall() {
+    let paywalled = r#"<html><head>
+<script type="application/ld+json">
+{"@context":"https://schema.org","@type":"NewsArticle","headline":"Markets","isAccessibleForFree":false,
+ "hasPart":{"@type":"WebPageElement","isAccessibleForFree":"False","cssSelector":".locked"}}
+</script></head>
+<body><article><p>The first paragraph of the story.</p>
+<div class="locked"><p>Subscribe to continue reading.</p></div></article></body></html>"#;
+    assert!(_detect_paywall(paywalled).unwrap());
+
+    let gated_by_class =
+      r#"<html><body><p>Teaser</p><div class="tp-modal tp-active"></div></body></html>"#;
+    assert!(_detect_paywall(gated_by_class).unwrap());
+
+    // Plan links and signup forms mention subscriptions without gating anything.
+    let upsell = r#"<html><body><nav><a class="premium-plans" href="/plans">Plans</a></nav>
+<article><p>Everything is readable here.</p></article>
+<footer><form class="newsletter-subscription"><input type="email"></form>
+<div class="subscription-box">Get the newsletter</div></footer></body></html>"#;
+    assert!(!_detect_paywall(upsell).unwrap());
+
+    let by_text =
+      r#"<html><body><p>Teaser</p><p>This content is for subscribers only.</p></body></html>"#;
+    assert!(_detect_paywall(by_text).unwrap());
+
+    let free = r#"<html><head><meta name="piano:content_access" content="free"></head>
+<body><article><p>Everything is readable here.</p></article></body></html>"#;
+    assert!(!_detect_paywall(free).unwrap());
+  }
+
+  #[test]
+  fn test_parsed_document_reused_across_extractions() {
+    let doc = ParsedDocument::new(
+      r#"<html><head><title>Doc</title><base href="https://example.com/docs/"></head>
+<body><a href="intro">Intro</a><img src="logo.png"></body></html>"#
+        .to_string(),
+    )
+    .unwrap();
+
+    let metadata = doc.send(_extract_metadata_from_document).compute().unwrap();
+    assert_eq!(
+      metadata.get("title"),
+      Some(&Value::String("Doc".to_string()))
+    );
+
+    let links = doc
+      .send(|document| _extract_links_from_document(document, &ExtractLinksOptions::default()))
+      .compute()
+      .unwrap();
+    assert_eq!(links, vec!["intro".to_string()]);
+
+    let images = doc
+      .send(|document| _extract_images_from_document(document, "https://example.com/"))
+      .compute()
+      .unwrap();
+    assert_eq!(
+      images,
+      vec!["https://example.com/docs/logo.png".to_string()]
+    );
+
+    // Jobs queued before the release still run; later ones fail.
+    let mut queued = doc.send(_extract_metadata_from_document);
+    doc.release();
+    assert!(queued.compute().is_ok());
+    let error = doc
+      .send(_extract_metadata_from_document)
+      .compute()
+      .unwrap_err();
+    assert_eq!(error.reason, "Parsed document was released");
+  }
+
+  #[test]
+  fn test_extract_breadcrumbs() {
+    let json_ld = r#"<html><head><script type="application/ld+json">
+{"@context":"https://schema.org","@graph":[{"@type":"BreadcrumbList","itemListElement":[
+  {"@type":"ListItem","position":2,"name":"Shoes","item":"/shoes"},
+  {"@type":"ListItem","position":1,"name":"Home","item":{"@id":"https://example.com/"}},
+  {"@type":"ListItem","position":3,"name":"Boots"}]}]}
+</script></head><body></body></html>"#;
+    let trails = _extract_breadcrumbs(json_ld, "https://example.com/shoes/boots").unwrap();
+    assert_eq!(trails.len(), 1);
+    assert_eq!(trails[0].source, "json-ld");
+    let items = &trails[0].items;
+    let names: Vec<_> = items.iter().map(|x| x.name.as_str()).collect();
+    assert_eq!(names, vec!["Home", "Shoes", "Boots"]);
+    assert_eq!(items[1].url.as_deref(), Some("https://example.com/shoes"));
+    assert_eq!(items[2].url, None);
+
+    let markup = r#"<html><body><nav aria-label="Breadcrumb"><ol>
+<li><a href="/">Home</a></li><li><a href="/docs">Docs</a></li><li>Install</li></ol></nav></body></html>"#;
+    let trails = _extract_breadcrumbs(markup, "https://example.com/docs/install").unwrap();
+    assert_eq!(trails.len(), 1);
+    assert_eq!(trails[0].source, "markup");
+    let items = &trails[0].items;
+    assert_eq!(items.len(), 3);
+    assert_eq!(items[1].url.as_deref(), Some("https://example.com/docs"));
+    assert_eq!(items[2].name, "Install");
+    assert_eq!(items[2].position, 3);
+
+    let both = r#"<html><head><script type="application/ld+json">
+{"@type":"BreadcrumbList","itemListElement":[{"position":1,"name":"Home","item":"/"}]}
+</script></head><body>
+<ol itemscope itemtype="https://schema.org/BreadcrumbList">
+  <li itemprop="itemListElement" itemscope itemtype="https://schema.org/ListItem">
+    <a itemprop="item" href="/guides"><span itemprop="name">Guides</span></a><meta itemprop="position" content="1"></li>
+  <li itemprop="itemListElement" itemscope itemtype="https://schema.org/ListItem">
+    <span itemprop="name">Setup</span><meta itemprop="position" content="2"></li>
+</ol></body></html>"#;
+    let trails = _extract_breadcrumbs(both, "https://example.com/guides/setup").unwrap();
+    let sources: Vec<_> = trails.iter().map(|x| x.source.as_str()).collect();
+    assert_eq!(sources, vec!["json-ld", "microdata"]);
+    assert_eq!(
+      trails[1].items[0].url.as_deref(),
+      Some("https://example.com/guides")
+    );
+    assert_eq!(trails[1].items[1].name, "Setup");
+    assert_eq!(trails[1].items[1].url, None);
+
+    let none = _extract_breadcrumbs("<p>nothing</p>", "https://example.com/").unwrap();
+    assert!(none.is_empty());
+  }
+
+  #[test]
+  fn test_extract_links_options() {
+    let html = r#"<html><head>
+<link rel="canonical" href="https://example.com/page">
+<link rel="alternate" type="application/rss+xml" href="/feed.xml">
+</head><body>
+<a href="http:/example.com/a">A</a>
+<map><area href="/area" alt="Area"></map>
+<iframe src="https://embed.example.com/x"></iframe>
+</body></html>"#;
+    let document = parse_html().one(html);
+
+    let default = _extract_links_from_document(&document, &ExtractLinksOptions::default()).unwrap();
+    assert_eq!(default, vec!["http://example.com/a".to_string()]);
+
+    let options = ExtractLinksOptions {
+      include_areas: Some(true),
+      include_iframes: Some(true),
+      include_alternate_links: Some(true),
+      include_canonical: Some(true),
+      ..Default::default()
+    };
+    let detailed: Vec<(String, String)> =
+      _extract_links_detailed_from_document(&document, &options)
+        .unwrap()
+        .into_iter()
+        .map(|x| (x.url, x.source))
+        .collect();
+    assert_eq!(
+      detailed,
+      vec![
+        (
+          "https://example.com/page".to_string(),
+          "canonical".to_string()
+        ),
+        ("/feed.xml".to_string(), "alternate".to_string()),
+        ("http://example.com/a".to_string(), "a".to_string()),
+        ("/area".to_string(), "area".to_string()),
+        (
+          "https://embed.example.com/x".to_string(),
+          "iframe".to_string()
+        ),
+      ]
+    );
+  }
+
+  #[test]
+  fn test_extract_links_resolves_before_normalizing() {
+    let html = r#"<html><head><base href="/docs/"></head><body>
+<a href="guide/?utm_source=x&b=2&a=1">Guide</a>
+<a href="HTTPS://Example.com:443/Other?utm_medium=y">Other</a>
+</body></html>"#;
+    let document = parse_html().one(html);
+
+    let options = ExtractLinksOptions {
+      base_url: Some("https://example.com/page".to_string()),
+      ..Default::default()
+    };
+    assert_eq!(
+      _extract_links_from_document(&document, &options).unwrap(),
+      vec![
+        "https://example.com/docs/guide/?utm_source=x&b=2&a=1".to_string(),
+        "https://example.com/Other?utm_medium=y".to_string(),
+      ]
+    );
+
+    let options = ExtractLinksOptions {
+      base_url: Some("https://example.com/page".to_string()),
+      normalize: Some(NormalizeUrlOptions::default()),
+      ..Default::default()
+    };
+    assert_eq!(
+      _extract_links_from_document(&document, &options).unwrap(),
+      vec![
+        "https://example.com/docs/guide/?a=1&b=2".to_string(),
+        "https://example.com/Other".to_string(),
+      ]
+    );
+
+    // Without a base, relative links can't be normalized and are kept as written.
+    let options = ExtractLinksOptions {
+      normalize: Some(NormalizeUrlOptions::default()),
+      ..Default::default()
+    };
+    assert_eq!(
+      _extract_links_from_document(&document, &options).unwrap()[0],
+      "guide/?utm_source=x&b=2&a=1"
+    );
+  }
+
+  #[test]
+  fn test_extract_links_filters_schemes() {
+    let html = r##"<html><body>
+<a href="/page">Page</a>
+<a href="javascript:void(0)">JS</a>
+<a href="JavaScript:alert(1)">JS upper</a>
+<a href="mailto:user@example.com">Mail</a>
+<a href="tel:+15555550100">Call</a>
+<a href="data:text/html,hi">Data</a>
+<a href="#top">Top</a>
+</body></html>"##;
+    let document = parse_html().one(html);
+
+    let default = _extract_links_from_document(&document, &ExtractLinksOptions::default()).unwrap();
+    assert_eq!(default, vec!["/page".to_string()]);
+
+    let options = ExtractLinksOptions {
+      include_mailto: Some(true),
+      include_anchor_only: Some(true),
+      ..Default::default()
+    };
+    let kept = _extract_links_from_document(&document, &options).unwrap();
+    assert_eq!(
+      kept,
+      vec![
+        "/page".to_string(),
+        "mailto:user@example.com".to_string(),
+        "#top".to_string(),
+      ]
+    );
+  }
+
+  #[test]
+  fn test_extract_urls_decode_double_encoded_entities() {
+    // Shapes seen in the wild: CMS templates that escape an already-escaped query
+    // string, and attribute values wrapped across lines by HTML minifiers.
+    let html = r#"<html><body>
+<a href="/search?q=shoes&amp;amp;page=2">Next</a>
+<a href="
+  https://example.com/article?id=7&amp;amp;ref=home
+">Article</a>
+<img src="/img/thumb.jpg?w=300&amp;amp;h=200">
+<img srcset="/img/a.jpg?w=1&amp;amp;h=1 1x,
+  /img/b.jpg?w=2&amp;amp;h=2 2x">
+</body></html>"#;
+    let document = parse_html().one(html);
+
+    let links = _extract_links_from_document(&document, &ExtractLinksOptions::default()).unwrap();
+    assert_eq!(
+      links,
+      vec![
+        "/search?q=shoes&page=2".to_string(),
+        "https://example.com/article?id=7&ref=home".to_string(),
+      ]
+    );
+
+    let mut images = _extract_images_from_document(&document, "https://example.com/").unwrap();
+    images.sort();
+    assert_eq!(
+      images,
+      vec![
+        "https://example.com/img/a.jpg?w=1&h=1".to_string(),
+        "https://example.com/img/b.jpg?w=2&h=2".to_string(),
+        "https://example.com/img/thumb.jpg?w=300&h=200".to_string(),
+      ]
+    );
+
+    assert_eq!(_normalize_attribute_url("/a?b=1&amp;c=&#50;"), "/a?b=1&c=2");
+    assert_eq!(_normalize_attribute_url("/a?b=1&c=2"), "/a?b=1&c=2");
+  }
+
+  fn transform_opts(html: &str, url: &str) -> TransformHtmlOptions {
+    TransformHtmlOptions {
+      html: html.to_string(),
+      url: url.to_string(),
+      include_tags: vec![],
+      exclude_tags: vec![],
+      only_main_content: false,
+      omce_signatures: None,
+      strip_inline_scripts: None,
+      strip_data_attributes: None,
+      main_content_mode: None,
+      output: None,
+      min_text_length: None,
+      expand_templates: None,
+      additional_exclude_selectors: None,
+      force_include_selectors: None,
+      timeout_ms: None,
+      max_input_bytes: None,
+      max_output_bytes: None,
+      prune_empty_elements: None,
+      prune_keep_anchors: None,
+    }
+  }
+
+  #[test]
+  fn test_transform_html_absolutizes_media() {
+    let html = r#"<html><body>
+<iframe src="//cdn.example.net/player?id=1"></iframe>
+<iframe src="about:blank"></iframe>
+<video src="clip.mp4" poster="/poster.jpg"><source src="clip.webm"><track src="subs.vtt"></video>
+<audio src="../sound.mp3"></audio>
+<embed src="javascript:void(0)">
+</body></html>"#;
+    let out = _transform_html_inner(transform_opts(html, "http://example.com/media/page")).unwrap();
+    assert!(out.contains(r#"src="http://cdn.example.net/player?id=1""#));
+    assert!(out.contains(r#"src="about:blank""#));
+    assert!(out.contains(r#"src="http://example.com/media/clip.mp4""#));
+    assert!(out.contains(r#"poster="http://example.com/poster.jpg""#));
+    assert!(out.contains(r#"src="http://example.com/media/clip.webm""#));
+    assert!(out.contains(r#"src="http://example.com/media/subs.vtt""#));
+    assert!(out.contains(r#"src="http://example.com/sound.mp3""#));
+    assert!(!out.contains("example.com/media/javascript"));
+  }
+
+  #[test]
+  fn test_transform_html_trims_srcset_candidates() {
+    let html = "<html><body>\
+<img srcset=\"  /a.jpg   2x,\n\t /b.jpg    3x  \">\
+<img src=\" /fallback.jpg \" srcset=\" /small.jpg  0.5x\">\
+</body></html>";
+
+    let out = _transform_html_inner(transform_opts(html, "https://example.com/")).unwrap();
+    assert!(out.contains(r#" src="https://example.com/b.jpg""#));
+    assert!(out.contains(r#" src="https://example.com/fallback.jpg""#));
+  }
+
+  #[test]
+  fn test_transform_html_strips_inline_scripts() {
+    let html = r#"<html><body onload="init()">
+<a href="javascript:void(0)" onclick="go()">Menu</a>
+<a href=" JavaScript:alert(1)">Alert</a>
+<a href="/about" onMouseOver="hover()">About</a>
+<img src="/a.png" onerror="fallback()">
+</body></html>"#;
+    let out = _transform_html_inner(transform_opts(html, "https://example.com/")).unwrap();
+    assert!(!out.to_lowercase().contains("javascript:"));
+    assert!(!out.contains("onclick"));
+    assert!(!out.contains("onload"));
+    assert!(!out.to_lowercase().contains("onmouseover"));
+    assert!(!out.contains("onerror"));
+    assert!(out.contains(r#"href="https://example.com/about""#));
+
+    let mut opts = transform_opts(html, "https://example.com/");
+    opts.strip_inline_scripts = Some(false);
+    let out = _transform_html_inner(opts).unwrap();
+    assert!(out.contains("onclick"));
+
+    // Attribute names may start with a multi-byte character.
+    let html = r#"<html><body><div 中文="1" ön="2" onclick="go()">Text</div></body></html>"#;
+    let out = _transform_html_inner(transform_opts(html, "https://example.com/")).unwrap();
+    assert!(out.contains(r#"中文="1""#));
+    assert!(out.contains(r#"ön="2""#));
+    assert!(!out.contains("onclick"));
+  }
+
+  #[test]
+  fn test_transform_html_strips_data_attributes() {
+    let html = r#"<html><body>
+<div data-role="content" data-tracking-id="abc" class="post"><p data-v-3f2a>Text</p></div>
+<div data-role="ad">Ad</div>
+</body></html>"#;
+    let out = _transform_html_inner(transform_opts(html, "https://example.com/")).unwrap();
+    assert!(out.contains(r#"data-tracking-id="abc""#));
+
+    // Selectors still see the attributes, which are removed after filtering.
+    let mut opts = transform_opts(html, "https://example.com/");
+    opts.strip_data_attributes = Some(true);
+    opts.include_tags = vec![r#"[data-role="content"]"#.to_string()];
+    let out = _transform_html_inner(opts).unwrap();
+    assert!(!out.contains("data-"));
+    assert!(out.contains(r#"<div class="post"><p>Text</p></div>"#));
+    assert!(!out.contains("Ad"));
+  }
+
+  #[test]
+  fn test_transform_html_with_stats() {
+    let html = r#"<html><head><title>t</title><base href="https://cdn.example.com/site/"></head>
+<body><script>x()</script><style>p{}</style>
+<nav>Menu</nav><footer><div id="main">Keep me</div></footer>
+<div class="promo">Ad</div><img src="a.png"><img src="https://other.example.com/b.png"></body></html>"#;
+    let mut opts = transform_opts(html, "https://example.com/page");
+    opts.only_main_content = true;
+    opts.exclude_tags = vec![".promo".to_string()];
+
+    let (out, stats) = _transform_html_with_stats(opts).unwrap();
+    assert_eq!(stats.base_href, "https://cdn.example.com/site/");
+    assert_eq!(stats.removed_head_script_style, 3);
+    assert_eq!(stats.removed_exclude_tags, 1);
+    assert_eq!(stats.removed_main_content, 1);
+    assert_eq!(stats.preserved_main_content, 1);
+    assert_eq!(stats.images_rewritten, 1);
+    assert!(out.contains("Keep me"));
+    assert!(!out.contains("Menu"));
+  }
+
+  #[test]
+  fn test_transform_html_density_mode() {
+    let html = r#"<html><body>
+<div class="x1"><a href="/">Home</a> <a href="/a">Products</a> <a href="/b">Pricing</a> <a href="/c">Company</a></div>
+<div class="x7f3">
+  <div class="q2"><p>Rust is a multi-paradigm, general-purpose programming language that emphasizes performance, type safety, and concurrency.</p>
+  <p>It enforces memory safety, meaning that all references point to valid memory, without a garbage collector.</p>
+  <p>To simultaneously enforce memory safety and prevent data races, its borrow checker tracks the object lifetime of all references in a program during compilation.</p></div>
+</div>
+<div class="z9"><a href="/terms">Terms of service and the rest of the legal fine print</a> <a href="/privacy">Privacy</a></div>
+</body></html>"#;
+    let mut opts = transform_opts(html, "https://example.com/");
+    opts.only_main_content = true;
+    opts.main_content_mode = Some("density".to_string());
+    let out = _transform_html_inner(opts).unwrap();
+    assert!(out.contains("borrow checker"));
+    assert!(!out.contains("Pricing"));
+    assert!(!out.contains("Terms of service"));
+
+    // Nothing in this page carries a semantic class, so the selector pass keeps the navigation
+    // and hybrid mode falls back to density scoring.
+    let mut opts = transform_opts(html, "https://example.com/");
+    opts.only_main_content = true;
+    opts.main_content_mode = Some("hybrid".to_string());
+    let out = _transform_html_inner(opts).unwrap();
+    assert!(out.contains("borrow checker"));
+    assert!(!out.contains("Pricing"));
+
+    let mut opts = transform_opts(html, "https://example.com/");
+    opts.only_main_content = true;
+    opts.main_content_mode = Some("nope".to_string());
+    assert!(_transform_html_inner(opts).is_err());
+  }
+
+  #[test]
+  fn test_transform_html_output_modes() {
+    let html =
+      r#"<html><head><title>T</title></head><body>Intro <b>bold</b><p>Para</p></body></html>"#;
+
+    let mut opts = transform_opts(html, "https://example.com/");
+    let document = _transform_html_inner(opts).unwrap();
+    assert!(document.starts_with("<html>"));
+
+    opts = transform_opts(html, "https://example.com/");
+    opts.output = Some("body".to_string());
+    assert_eq!(
+      _transform_html_inner(opts).unwrap(),
+      "Intro <b>bold</b><p>Para</p>"
+    );
+
+    opts = transform_opts(html, "https://example.com/");
+    opts.include_tags = vec!["p".to_string()];
+    opts.output = Some("body".to_string());
+    assert_eq!(
+      _transform_html_inner(opts).unwrap(),
+      "<div><p>Para</p></div>"
+    );
+
+    opts = transform_opts(html, "https://example.com/");
+    opts.include_tags = vec!["p".to_string()];
+    opts.output = Some("fragment".to_string());
+    assert_eq!(_transform_html_inner(opts).unwrap(), "<p>Para</p>");
+
+    opts = transform_opts(html, "https://example.com/");
+    opts.output = Some("nope".to_string());
+    assert!(_transform_html_inner(opts).is_err());
+  }
+
+  #[test]
+  fn test_extract_base_href_edge_cases() {
+    let page = "https://site.com/blog/post#comments";
+
+    let empty = r#"<html><head><base href=""></head><body></body></html>"#;
+    assert_eq!(
+      _extract_base_href(empty, page).unwrap(),
+      "https://site.com/blog/post"
+    );
+
+    let fragment = r##"<html><head><base href="#top"></head><body></body></html>"##;
+    assert_eq!(
+      _extract_base_href(fragment, page).unwrap(),
+      "https://site.com/blog/post"
+    );
+
+    let cross_origin = r#"<html><head><base href="https://cdn.other.com/assets/"></head></html>"#;
+    assert_eq!(
+      _extract_base_href(cross_origin, page).unwrap(),
+      "https://cdn.other.com/assets/"
+    );
+
+    let javascript = r#"<html><head><base href="javascript:alert(1)"></head></html>"#;
+    assert_eq!(
+      _extract_base_href(javascript, page).unwrap(),
+      "https://site.com/blog/post"
+    );
+
+    let html =
+      r##"<html><head><base href="#top"></head><body><a href="page2">Next</a></body></html>"##;
+    let out = _transform_html_inner(transform_opts(html, "https://site.com/")).unwrap();
+    assert!(out.contains(r#"href="https://site.com/page2""#));
+  }
+
+  #[test]
+  fn test_extract_meta_robots() {
+    let html = r#"<html><head>
+      <meta name="Robots" content="NoIndex, nofollow">
+      <meta name="googlebot" content="nosnippet,noimageindex">
+      <meta name="bingbot" content="noindex">
+      <meta name="description" content="noindex">
+    </head><body></body></html>"#;
+
+    let robots = _extract_meta_robots(html).unwrap();
+    assert_eq!(robots.len(), 2);
+
+    assert_eq!(robots[0].name, "robots");
+    assert_eq!(robots[0].raw, "NoIndex, nofollow");
+    assert!(robots[0].noindex && robots[0].nofollow);
+    assert!(!robots[0].nosnippet && !robots[0].noarchive);
+
+    assert_eq!(robots[1].name, "googlebot");
+    assert!(robots[1].nosnippet && robots[1].noimageindex);
+    assert!(!robots[1].noindex);
+
+    let none = _extract_meta_robots(r#"<meta name="robots" content="none">"#).unwrap();
+    assert!(none[0].noindex && none[0].nofollow);
+  }
+
+  #[test]
+  fn test_extract_social_links() {
+    let html = r#"<html><body>
+      <a href="https://WWW.Twitter.com/acme">Twitter</a>
+      <a href="https://x.com/acme">X</a>
+      <a href="https://m.facebook.com/acme">Facebook</a>
+      <a href="https://www.facebook.com/sharer/sharer.php?u=https://acme.com">Share</a>
+      <a href="https://github.com/acme">GitHub</a>
+      <a href="https://github.com/acme">GitHub again</a>
+      <a href="https://notgithub.com/acme">Lookalike</a>
+      <a href="/about">About</a>
+      <a href="mailto:hi@acme.com">Mail</a>
+    </body></html>"#;
+
+    let links: Vec<(String, String)> = _extract_social_links(html)
+      .unwrap()
+      .into_iter()
+      .map(|x| (x.platform, x.url))
+      .collect();
+    assert_eq!(
+      links,
+      vec![
+        (
+          "twitter".to_string(),
+          "https://www.twitter.com/acme".to_string()
+        ),
+        ("twitter".to_string(), "https://x.com/acme".to_string()),
+        (
+          "facebook".to_string(),
+          "https://m.facebook.com/acme".to_string()
+        ),
+        ("github".to_string(), "https://github.com/acme".to_string()),
+      ]
+    );
+  }
+
+  #[test]
+  fn test_extract_metadata_http_equiv_and_app_tags() {
+    let html = r##"<html><head>
+      <meta http-equiv="Content-Language" content="de-AT">
+      <meta http-equiv="refresh" content="30">
+      <meta http-equiv="X-UA-Compatible" content="IE=edge">
+      <meta http-equiv="x-ua-compatible" content="chrome=1">
+      <meta name="viewport" content="width=device-width, initial-scale=1">
+      <meta name="theme-color" content="#000000" media="(prefers-color-scheme: dark)">
+      <meta name="theme-color" content="#ffffff">
+      <meta name="generator" content="WordPress 6.5">
+      <meta name="application-name" content="Acme">
+    </head><body></body></html>"##;
+
+    let metadata = _extract_metadata(html).unwrap();
+    assert_eq!(metadata["language"], "de-AT");
+    assert_eq!(metadata["httpEquivContentLanguage"], "de-AT");
+    assert_eq!(metadata["httpEquivRefresh"], "30");
+    assert_eq!(
+      metadata["httpEquivXUaCompatible"],
+      serde_json::json!(["IE=edge", "chrome=1"])
+    );
+    assert_eq!(metadata["viewport"], "width=device-width, initial-scale=1");
+    assert_eq!(metadata["themeColor"], "#ffffff");
+    assert_eq!(metadata["generator"], "WordPress 6.5");
+    assert_eq!(metadata["applicationName"], "Acme");
+  }
+
+  #[test]
+  fn test_extract_forms() {
+    let html = r#"<html><body>
+      <form action="/login" method="post">
+        <label for="email">Email address</label>
+        <input id="email" name="email" type="Email" required placeholder="you@example.com">
+        <label>Password <input name="password" type="password" value="hunter2" required></label>
+        <label>Country
+          <select name="country"><option>Austria</option><option>Germany</option></select>
+        </label>
+        <textarea name="note" aria-label="Note"></textarea>
+        <input type="submit">
+      </form>
+      <form method="PATCH"><input name="q"></form>
+    </body></html>"#;
+
+    let forms = _extract_forms(html, "https://example.com/account/").unwrap();
+    assert_eq!(forms.len(), 2);
+
+    let login = &forms[0];
+    assert_eq!(login.action.as_deref(), Some("https://example.com/login"));
+    assert_eq!(login.method, "POST");
+    let fields: Vec<(Option<&str>, &str, Option<&str>, bool)> = login
+      .fields
+      .iter()
+      .map(|x| {
+        (
+          x.name.as_deref(),
+          x.field_type.as_str(),
+          x.label.as_deref(),
+          x.required,
+        )
+      })
+      .collect();
+    assert_eq!(
+      fields,
+      vec![
+        (Some("email"), "email", Some("Email address"), true),
+        (Some("password"), "password", Some("Password"), true),
+        (Some("country"), "select", Some("Country"), false),
+        (Some("note"), "textarea", Some("Note"), false),
+        (None, "submit", None, false),
+      ]
+    );
+    assert_eq!(
+      login.fields[0].placeholder.as_deref(),
+      Some("you@example.com")
+    );
+    assert_eq!(login.fields[1].value.as_deref(), Some("<redacted>"));
+    assert_eq!(login.fields[2].options, vec!["Austria", "Germany"]);
+    assert_eq!(login.fields[2].value.as_deref(), Some("Austria"));
+    assert_eq!(login.fields[2].selected_values, vec!["Austria"]);
+
+    let search = &forms[1];
+    assert_eq!(search.action, None);
+    assert_eq!(search.method, "GET");
+    assert_eq!(search.fields[0].field_type, "text");
+  }
+
+  #[test]
+  fn test_detect_site_technology() {
+    let html = r#"<html><head>
+      <meta name="Generator" content="WordPress 6.5.2">
+      <link rel="stylesheet" href="https://example.com/wp-content/themes/acme/style.css">
+      <script src="https://example.com/_next/static/chunks/main.js"></script>
+    </head><body>
+      <div id="__next"><div class="wp-block-group">Hello</div></div>
+    </body></html>"#;
+
+    let matches = _detect_site_technology(html).unwrap();
+    let summary: Vec<(&str, f64)> = matches
+      .iter()
+      .map(|x| (x.technology.as_str(), x.confidence))
+      .collect();
+    assert_eq!(summary, vec![("WordPress", 0.95), ("Next.js", 0.9)]);
+    assert_eq!(matches[0].evidence, "meta generator: WordPress 6.5.2");
+    assert_eq!(
+      matches[1].evidence,
+      "resource url: https://example.com/_next/static/chunks/main.js"
+    );
+
+    let nuxt = r#"<div id="__nuxt"></div><script>window.__NUXT__={state:{}}</script>"#;
+    let matches = _detect_site_technology(nuxt).unwrap();
+    assert_eq!(matches.len(), 1);
+    assert_eq!(matches[0].technology, "Nuxt");
+    assert_eq!(matches[0].evidence, "inline script: __NUXT__");
+
+    assert!(_detect_site_technology("<p>plain page</p>")
+      .unwrap()
+      .is_empty());
+  }
+
+  #[test]
+  fn test_estimate_reading_time() {
+    let words = "word ".repeat(476);
+    let html = format!(
+      r#"<html><head><title>Not counted</title><style>p {{ color: red }}</style></head><body>
+        <p>{words}</p>
+        <pre><code>let ignored = true;</code></pre>
+        <script>var ignored = 1;</script>
+        <p>— — 日本語</p>
+      </body></html>"#
+    );
+
+    let estimate = _estimate_reading_time(&html).unwrap();
+    assert_eq!(estimate.word_count, 479);
+    assert!((estimate.minutes - 479.0 / 238.0).abs() < 1e-9);
+
+    let empty = _estimate_reading_time("<html><body></body></html>").unwrap();
+    assert_eq!(empty.word_count, 0);
+    assert_eq!(empty.minutes, 0.0);
+  }
+
+  #[test]
+  fn test_extract_embedded_json() {
+    let html = r#"<html><head>
+      <script type="application/ld+json">{"@type": "Article", "headline": "Hi"}</script>
+      <script type="application/json">{broken</script>
+      <script>window.__INITIAL_STATE__ = {"user": null};</script>
+      <script>window.__NUXT__=(function(a){return {a:a}}(1));</script>
+      <script>var tracking = {"id": 1};</script>
+    </head><body>
+      <script id="__NEXT_DATA__" type="application/json">{"props": {"pageProps": {"id": 7}}}</script>
+    </body></html>"#;
+
+    let result = _extract_embedded_json(html, &EmbeddedJsonOptions::default()).unwrap();
+    let entries: Vec<(Option<&str>, &str)> = result
+      .entries
+      .iter()
+      .map(|x| (x.id.as_deref(), x.script_type.as_str()))
+      .collect();
+    assert_eq!(
+      entries,
+      vec![
+        (None, "application/ld+json"),
+        (Some("__INITIAL_STATE__"), "text/javascript"),
+        (Some("__NEXT_DATA__"), "application/json"),
+      ]
+    );
+    assert_eq!(result.entries[0].json["headline"], "Hi");
+    assert_eq!(result.entries[2].json["props"]["pageProps"]["id"], 7);
+    // The malformed JSON script and the Nuxt IIFE.
+    assert_eq!(result.parse_failures, 2);
+    assert_eq!(result.oversized, 0);
+
+    let capped = _extract_embedded_json(
+      html,
+      &EmbeddedJsonOptions {
+        max_blob_bytes: Some(20),
+        timeout_ms: None,
+      },
+    )
+    .unwrap();
+    assert_eq!(capped.entries.len(), 1);
+    assert_eq!(capped.oversized, 3);
+  }
+
+  #[test]
+  fn test_html_to_text() {
+    let html = r#"<html><head><title>Title</title><style>p { }</style></head><body>
+      <nav><a href="/">Home</a><a href="/about">About</a><a href="/contact">Contact</a></nav>
+      <h1>Heading</h1>
+      <p>Some   <b>bold</b>
+        text,<br>next line.</p>
+      <ul><li>One</li><li>Two<ul><li>Nested</li></ul></li></ul>
+      <div hidden>Hidden</div><span style="display: none">Also hidden</span>
+      <pre>fn main() {
+    println!("hi");
+}</pre>
+      <table><tr><td>a</td><td>b</td></tr><tr><td>c</td><td>d</td></tr></table>
+      <script>var x = 1;</script>
+      <footer>Footer</footer>
+    </body></html>"#;
+
+    let text = _html_to_text(html, &HtmlToTextOptions::default()).unwrap();
+    assert_eq!(
+      text,
+      "HomeAboutContact\n\nHeading\n\nSome bold text,\nnext line.\n\n- One\n- Two\n  - Nested\n\nfn main() {\n    println!(\"hi\");\n}\n\na b\nc d\nFooter"
+    );
+
+    let main = _html_to_text(
+      html,
+      &HtmlToTextOptions {
+        only_main_content: Some(true),
+        timeout_ms: None,
+      },
+    )
+    .unwrap();
+    assert!(main.starts_with("Heading\n\n"));
+    assert!(!main.contains("Footer"));
+  }
+
+  #[test]
+  fn test_extract_attributes_pseudo_attributes() {
+    let html = r#"<h1 class="article-title">
+      Hello <em>world</em>
+    </h1><a href="/a" data-id="1"> Link </a>"#;
+    let options = ExtractAttributesOptions {
+      selectors: vec![
+        AttributeSelector {
+          selector: "h1.article-title".to_string(),
+          attribute: ":text".to_string(),
+        },
+        AttributeSelector {
+          selector: "a".to_string(),
+          attribute: ":text".to_string(),
+        },
+        AttributeSelector {
+          selector: "a".to_string(),
+          attribute: "id".to_string(),
+        },
+        AttributeSelector {
+          selector: "h1".to_string(),
+          attribute: ":html".to_string(),
+        },
+      ],
+      timeout_ms: None,
+    };
+
+    let values: Vec<Vec<String>> = _extract_attributes(html, &options)
+      .unwrap()
+      .into_iter()
+      .map(|x| x.values)
+      .collect();
+    assert_eq!(
+      values,
+      vec![
+        vec!["Hello world"],
+        vec!["Link"],
+        vec!["1"],
+        vec!["\n      Hello <em>world</em>\n    "],
+      ]
+    );
+  }
+
+  #[test]
+  fn test_extract_attributes_grouped() {
+    let html = r#"<ul>
+      <li class="product"><a href="/a" title="Kettle">Kettle</a><span data-price="30">30 €</span></li>
+      <li class="product"><a href="/b">Toaster</a></li>
+    </ul>"#;
+    let options = ExtractAttributesGroupedOptions {
+      selectors: vec![
+        GroupedAttributeSelector {
+          selector: ".product a".to_string(),
+          attributes: vec!["href".to_string(), "title".to_string(), ":text".to_string()],
+        },
+        GroupedAttributeSelector {
+          selector: ".product span".to_string(),
+          attributes: vec!["price".to_string()],
+        },
+        GroupedAttributeSelector {
+          selector: "li[".to_string(),
+          attributes: vec!["id".to_string()],
+        },
+      ],
+      timeout_ms: None,
+    };
+
+    let results = _extract_attributes_grouped(html, &options).unwrap();
+    assert_eq!(results.len(), 3);
+
+    // Rows stay aligned: a missing attribute is present as `None`.
+    let row = |result: &ExtractedAttributeGroupResult, i: usize, name: &str| {
+      result.elements[i].get(name).cloned().flatten()
+    };
+    let links = &results[0];
+    assert_eq!(links.elements.len(), 2);
+    assert!(links.elements.iter().all(|x| x.len() == 3));
+    assert_eq!(row(links, 0, "href").as_deref(), Some("/a"));
+    assert_eq!(row(links, 0, "title").as_deref(), Some("Kettle"));
+    assert_eq!(row(links, 1, ":text").as_deref(), Some("Toaster"));
+    assert_eq!(row(links, 1, "title"), None);
+    assert!(links.elements[1].contains_key("title"));
+
+    // `price` falls back to `data-price`.
+    assert_eq!(row(&results[1], 0, "price").as_deref(), Some("30"));
+
+    assert!(results[2].elements.is_empty());
+    assert_eq!(results[2].invalid_selectors, ["li["]);
+  }
+
+  const ARTICLE_A: &str =
+    "The city council voted on Tuesday to expand the downtown bike lane network, \
+    adding twelve miles of protected lanes over the next three years. Supporters said the plan \
+    would make commuting safer and reduce traffic congestion, while several business owners \
+    raised concerns about the loss of street parking near their shops. The transportation \
+    department will hold public meetings in each affected neighborhood before construction \
+    begins, and officials expect the first segment along Main Street to open next spring. \
+    Funding for the project comes from a combination of state grants and the municipal \
+    infrastructure budget approved earlier this year.";
+
+  const ARTICLE_B: &str =
+    "Researchers at the university have developed a new battery chemistry that \
+    could double the range of electric vehicles while cutting charging times in half. The team \
+    replaced the graphite anode with a silicon composite and added a protective coating that \
+    prevents the material from cracking during repeated charge cycles. Early prototypes retained \
+    ninety percent of their capacity after one thousand cycles, a result the authors describe \
+    as promising but preliminary. Commercial production is still several years away, and the \
+    group is now working with manufacturers to test the cells at larger scale.";
+
+  fn content_page(article: &str, timestamp: &str, sidebar: &str) -> String {
+    format!(
+      r#"<html><body>
+        <nav>Home News Sports</nav>
+        <main><h1>Local news</h1><p class="time">Updated {timestamp}</p><p>{article}</p>
+        <img src="/a.jpg?v={timestamp}" srcset="/a.jpg?w=400&amp;t={timestamp} 400w"></main>
+        <aside class="sidebar">{sidebar}</aside>
+        <form><input type="hidden" name="csrf" value="{timestamp}"></form>
+      </body></html>"#
+    )
+  }
+
+  fn simhash_distance(a: &ContentHash, b: &ContentHash) -> u32 {
+    content_hash_distance(a.simhash.clone(), b.simhash.clone()).unwrap()
+  }
+
+  #[test]
+  fn test_compute_content_hash() {
+    let options = ContentHashOptions::default();
+    let hash = |html: &str| _compute_content_hash(html, &options).unwrap();
+
+    // Timestamps, image URLs, CSRF tokens and sidebar chrome don't matter once digits
+    // are stripped.
+    let stripped = ContentHashOptions {
+      strip_digits: Some(true),
+      ..Default::default()
+    };
+    let first = _compute_content_hash(
+      &content_page(ARTICLE_A, "2024-05-01 10:00", "Trending: cats"),
+      &stripped,
+    )
+    .unwrap();
+    let second = _compute_content_hash(
+      &content_page(ARTICLE_A, "2024-05-02 18:45", "Trending: dogs"),
+      &stripped,
+    )
+    .unwrap();
+    assert_eq!(first.sha256, second.sha256);
+    assert_eq!(first.simhash, second.simhash);
+    assert_eq!(first.simhash.len(), 16);
+
+    // Without stripping digits the exact hash changes but the simhash stays close.
+    let a1 = hash(&content_page(ARTICLE_A, "2024-05-01 10:00", ""));
+    let a2 = hash(&content_page(ARTICLE_A, "2024-05-02 18:45", ""));
+    assert_ne!(a1.sha256, a2.sha256);
+
+    let edited = ARTICLE_A.replace(
+      "while several business owners",
+      "although a number of local business owners",
+    );
+    let a3 = hash(&content_page(&edited, "2024-05-01 10:00", ""));
+    let b = hash(&content_page(ARTICLE_B, "2024-05-01 10:00", ""));
+
+    // The threshold documented on content_hash_distance.
+    assert!(simhash_distance(&a1, &a2) <= 10);
+    assert!(simhash_distance(&a1, &a3) <= 10);
+    assert!(simhash_distance(&a1, &b) >= 20);
+  }
+
+  #[test]
+  fn test_extract_time_elements() {
+    let html = r#"<p>Published <time datetime="2024-01-15T09:00:00Z">January
+      15, 2024</time>, updated <time>yesterday</time> <time datetime=" ">now</time></p>"#;
+
+    let times: Vec<(Option<String>, String)> = _extract_time_elements(html)
+      .unwrap()
+      .into_iter()
+      .map(|x| (x.datetime, x.text))
+      .collect();
+    assert_eq!(
+      times,
+      vec![
+        (
+          Some("2024-01-15T09:00:00Z".to_string()),
+          "January 15, 2024".to_string()
+        ),
+        (None, "yesterday".to_string()),
+        (None, "now".to_string()),
+      ]
+    );
+  }
+
+  #[test]
+  fn test_detect_pagination() {
+    let html = r#"<html><head>
+      <link rel="next" href="/blog?page=4">
+    </head><body>
+      <ul class="pages">
+        <li><a href="/blog?page=1">1</a></li>
+        <li><a href="/blog?page=2">2</a></li>
+        <li class="active"><span>3</span></li>
+        <li><a href="/blog?page=4">4</a></li>
+        <li><a href="/blog?page=5">5</a></li>
+      </ul>
+      <a href="/blog/archive">Older posts ›</a>
+      <a href="/blog?page=2#top">«</a>
+      <a href="javascript:void(0)">Next</a>
+    </body></html>"#;
+
+    let info = _detect_pagination(html, "https://example.com/blog?page=3").unwrap();
+    assert_eq!(
+      info.next.as_deref(),
+      Some("https://example.com/blog?page=4")
+    );
+    assert_eq!(
+      info.prev.as_deref(),
+      Some("https://example.com/blog?page=2")
+    );
+
+    let next: Vec<(&str, Vec<&str>)> = info
+      .next_candidates
+      .iter()
+      .map(|x| {
+        (
+          x.url.as_str(),
+          x.sources.iter().map(|x| x.as_str()).collect(),
+        )
+      })
+      .collect();
+    assert_eq!(
+      next,
+      vec![
+        (
+          "https://example.com/blog?page=4",
+          vec!["link_rel", "numeric_sequence"]
+        ),
+        ("https://example.com/blog/archive", vec!["anchor_text"]),
+      ]
+    );
+    assert_eq!(info.next_candidates[0].score, 1.0);
+
+    let prev: Vec<(&str, Vec<&str>)> = info
+      .prev_candidates
+      .iter()
+      .map(|x| {
+        (
+          x.url.as_str(),
+          x.sources.iter().map(|x| x.as_str()).collect(),
+        )
+      })
+      .collect();
+    assert_eq!(
+      prev,
+      vec![(
+        "https://example.com/blog?page=2",
+        vec!["anchor_text", "numeric_sequence"]
+      )]
+    );
+
+    let none = _detect_pagination("<p>No pages</p>", "https://example.com/").unwrap();
+    assert!(none.next.is_none() && none.next_candidates.is_empty());
+  }
+
+  #[test]
+  fn test_remove_skip_to_content_links() {
+    let labels = &DEFAULT_SKIP_LINK_LABELS;
+    assert_eq!(
+      remove_skip_to_content_links("[Skip to Content](#page)\n# Title", labels),
+      "\n# Title"
+    );
+    assert_eq!(
+      remove_skip_to_content_links("[ skip to MAIN content ](#main)[Home](/)", labels),
+      "[Home](/)"
+    );
+    assert_eq!(
+      remove_skip_to_content_links("[Zum Inhalt springen](#inhalt) Text", labels),
+      " Text"
+    );
+    // Only fragment links are skip links.
+    assert_eq!(
+      remove_skip_to_content_links("[Skip navigation](/nav)", labels),
+      "[Skip navigation](/nav)"
+    );
+
+    let reference =
+      "[Skip to main content][1]\n[Docs][2]\n\n[1]: #main-content\n[2]: https://example.com/docs\n";
+    assert_eq!(
+      remove_skip_to_content_links(reference, labels),
+      "\n[Docs][2]\n\n[2]: https://example.com/docs\n"
+    );
+
+    // A definition still used by another link is kept.
+    let shared = "[Skip to content][top] [Back to top][top]\n\n[top]: #top\n";
+    assert_eq!(
+      remove_skip_to_content_links(shared, labels),
+      " [Back to top][top]\n\n[top]: #top\n"
+    );
+
+    // Reference ids match case-insensitively, like markdown does.
+    let mixed_case = "[Skip to content][Main]\n[Up][TOP]\n\n[main]: #content\n[top]: #top\n";
+    assert_eq!(
+      remove_skip_to_content_links(mixed_case, labels),
+      "\n[Up][TOP]\n\n[top]: #top\n"
+    );
+    let shared_mixed_case = "[Skip to content][top] [Back to top][Top]\n\n[TOP]: #top\n";
+    assert_eq!(
+      remove_skip_to_content_links(shared_mixed_case, labels),
+      " [Back to top][Top]\n\n[TOP]: #top\n"
+    );
+
+    assert_eq!(
+      remove_skip_to_content_links("[Saltar](#c) [Skip to content](#c)", &["saltar"]),
+      " [Skip to content](#c)"
+    );
+  }
+
+  #[test]
+  fn test_escape_multiline_link_labels() {
+    assert_eq!(
+      escape_multiline_link_labels("[multi\nline](/a) after\nnext"),
+      "[multi\\\nline](/a) after\nnext"
+    );
+    assert_eq!(
+      escape_multiline_link_labels("![alt with [brackets]](/img.png) [ok](/b)"),
+      "![alt with \\[brackets\\]](/img.png) [ok](/b)"
+    );
+    assert_eq!(
+      escape_multiline_link_labels("![two\nlines [x]](/i.png)"),
+      "![two lines \\[x\\]](/i.png)"
+    );
+    // Links nested in links are left alone, and images inside links are still images.
+    assert_eq!(
+      escape_multiline_link_labels("[![logo [v2]](/l.png)](/home)"),
+      "[![logo \\[v2\\]](/l.png)](/home)"
+    );
+    // Already escaped brackets are not counted.
+    assert_eq!(
+      escape_multiline_link_labels("\\[not a link\nstill text"),
+      "\\[not a link\nstill text"
+    );
+  }
+
+  #[test]
+  fn test_sanitize_html() {
+    let html = r#"<p class="intro" onclick="evil()">Hello <b style="color:red">world</b><!-- note --></p>
+<script>alert(1)</script><a href="/relative" title="Docs" target="_blank">docs</a><img src="x.png" onerror="evil()">"#;
+    let options = SanitizeOptions {
+      allowed_tags: vec!["p".to_string(), "B".to_string(), "a".to_string()],
+      allowed_attributes: HashMap::from([
+        ("a".to_string(), vec!["href".to_string()]),
+        (
+          "*".to_string(),
+          vec!["title".to_string(), "class".to_string()],
+        ),
+      ]),
+      strip_comments: true,
+    };
+
+    assert_eq!(
+      _sanitize_html(html, &options).unwrap(),
+      "<p class=\"intro\">Hello <b>world</b></p>\n<a href=\"/relative\" title=\"Docs\">docs</a>"
+    );
+
+    let keep_comments = SanitizeOptions {
+      strip_comments: false,
+      ..options
+    };
+    assert!(_sanitize_html(html, &keep_comments)
+      .unwrap()
+      .contains("<!-- note -->"));
+  }
+
+  #[test]
+  fn test_escape_multiline_link_labels_is_contained() {
+    // A multi-line image inside a link: the alt text is folded, the link is untouched.
+    assert_eq!(
+      escape_multiline_link_labels("[![Product\nphoto](/p.jpg)](/product)\n"),
+      "[![Product photo](/p.jpg)](/product)\n"
+    );
+
+    // From a converted pricing page: a stray `[` in one paragraph used to escape every
+    // newline in the rest of the document.
+    let pricing = "Plans start at $9 [billed annually\nfor teams.\n\n## Features\n\n- Unlimited projects\n- Priority support\n";
+    assert_eq!(
+      escape_multiline_link_labels(pricing),
+      "Plans start at $9 [billed annually\\\nfor teams.\n\n## Features\n\n- Unlimited projects\n- Priority support\n"
+    );
+
+    // From a converted API reference: brackets in code never open a label.
+    let reference = "Use `items[0` to read the head.\nSee [the\nguide](/guide).\n\n```python\nrows = data[\n  1:\n]\n```\nThen\ncontinue.\n";
+    assert_eq!(
+      escape_multiline_link_labels(reference),
+      "Use `items[0` to read the head.\nSee [the\\\nguide](/guide).\n\n```python\nrows = data[\n  1:\n]\n```\nThen\ncontinue.\n"
+    );
+
+    // Double-backtick spans may contain single backticks.
+    assert_eq!(
+      escape_multiline_link_labels("``a ` [b`` and\nmore\n"),
+      "``a ` [b`` and\nmore\n"
+    );
+  }
+
+  #[test]
+  fn test_normalize_markdown() {
+    let markdown = "# Title   \n\n\n\n\nIntro [](https://example.com/a) and [ ](#top).\n\n* * *\n\n[Docs](/docs) [Docs](/docs) [Blog](/blog)[Docs](/docs)\n![](/img.png)\n```\ncode   \n\n\n\n[](x)\n```\n";
+
+    assert_eq!(
+      _normalize_markdown(markdown, &NormalizeMarkdownOptions::default()),
+      "# Title\n\n\nIntro https://example.com/a and .\n\n* * *\n\n[Docs](/docs) [Blog](/blog)[Docs](/docs)\n![](/img.png)\n```\ncode   \n\n\n\n[](x)\n```\n"
+    );
+
+    let breaks = NormalizeMarkdownOptions {
+      remove_thematic_breaks: Some(true),
+      ..Default::default()
+    };
+    assert!(!_normalize_markdown(markdown, &breaks).contains("* * *"));
+
+    let faithful = NormalizeMarkdownOptions {
+      collapse_blank_lines: Some(false),
+      remove_empty_links: Some(false),
+      dedupe_adjacent_links: Some(false),
+      strip_trailing_whitespace: Some(false),
+      remove_thematic_breaks: Some(false),
+    };
+    assert_eq!(_normalize_markdown(markdown, &faithful), markdown);
+  }
+
+  #[test]
+  fn test_post_process_markdown_skip_link_patterns() {
+    assert_eq!(
+      _post_process_markdown(
+        "[Skip to nav](#nav)[SKIP MAIN CONTENT](#c) [skip   content](#) [Home](/)\n",
+        None
+      ),
+      "  [Home](/)\n"
+    );
+    assert_eq!(
+      _post_process_markdown("[Skip to nav](#nav) [Jump to content](#c)", None),
+      " "
+    );
+
+    // Custom labels replace the built-in patterns.
+    let labels = vec!["skip to nav".to_string()];
+    assert_eq!(
+      _post_process_markdown("[Skip to nav](#nav)[Skip to main](#m)", Some(&labels)),
+      "[Skip to main](#m)"
+    );
+  }
+
+  #[test]
+  fn test_extract_aria_landmarks() {
+    let html = r#"<html><body>
+<header><h1>Site</h1></header>
+<nav aria-label="Primary"><a href="/">Home</a></nav>
+<div role="search"><input type="search"></div>
+<main><article><header>Post header</header>
+<section aria-labelledby="c-title c-sub"><h2 id="c-title">Comments</h2><p id="c-sub">3 new</p></section>
+<section><p>Unnamed</p></section></article></main>
+<nav role="presentation"></nav>
+<aside role="note"></aside>
+<div role="Complementary banner" aria-label=" Related "></div>
+<footer>(c) Site</footer>
+</body></html>"#;
+
+    let landmarks: Vec<(String, Option<String>, String)> = _extract_aria_landmarks(html)
+      .unwrap()
+      .into_iter()
+      .map(|x| (x.role, x.label, x.element_tag))
+      .collect();
+    let expected = [
+      ("banner", None, "header"),
+      ("navigation", Some("Primary"), "nav"),
+      ("search", None, "div"),
+      ("main", None, "main"),
+      ("region", Some("Comments 3 new"), "section"),
+      ("complementary", Some("Related"), "div"),
+      ("contentinfo", None, "footer"),
+    ];
+    assert_eq!(
+      landmarks,
+      expected
+        .iter()
+        .map(|(r, l, t)| (r.to_string(), l.map(String::from), t.to_string()))
+        .collect::<Vec<_>>()
+    );
+  }
+
+  #[test]
+  fn test_chunk_markdown() {
+    let markdown = "Intro line.\n\n# Installation\n\nDownload the archive first.\n\n## Linux\n\nRun the installer from a terminal window, then log out and back in again.\n\n```sh\n./install.sh\n\nexport PATH=$PATH:/opt/tool/bin\n```\n\n| OS | Arch |\n| --- | --- |\n| Debian | amd64 |\n\n## macOS ##\n\nUse Homebrew.\n";
+    let options = ChunkMarkdownOptions {
+      max_chars: Some(60),
+      overlap_chars: Some(15),
+    };
+    let chunks = _chunk_markdown(markdown, &options);
+
+    for chunk in &chunks {
+      assert_eq!(
+        &markdown[chunk.start as usize..chunk.end as usize],
+        chunk.text
+      );
+    }
+    let summary: Vec<(Vec<&str>, &str)> = chunks
+      .iter()
+      .map(|x| {
+        (
+          x.heading_path.iter().map(String::as_str).collect(),
+          x.text.as_str(),
+        )
+      })
+      .collect();
+    assert_eq!(
+      summary,
+      vec![
+        (vec![], "Intro line."),
+        (
+          vec!["Installation"],
+          "# Installation\n\nDownload the archive first."
+        ),
+        // The heading stays with the paragraph that follows it.
+        (
+          vec!["Installation", "Linux"],
+          "## Linux\n\nRun the installer from a terminal window, then"
+        ),
+        (
+          vec!["Installation", "Linux"],
+          "window, then log out and back in again."
+        ),
+        (
+          vec!["Installation", "Linux"],
+          "back in again.\n\n```sh\n./install.sh\n\nexport PATH=$PATH:/opt/tool/bin\n```"
+        ),
+        // No overlap is taken from inside a code block.
+        (
+          vec!["Installation", "Linux"],
+          "| OS | Arch |\n| --- | --- |\n| Debian | amd64 |"
+        ),
+        (
+          vec!["Installation", "macOS"],
+          "## macOS ##\n\nUse Homebrew."
+        ),
+      ]
+    );
+
+    // Consecutive headings go with the first content after them, and trailing ones
+    // make up the last chunk.
+    let chunks = _chunk_markdown("# A\n\n## B\n\nText.\n\n## C\n", &options);
+    let summary: Vec<(Vec<&str>, &str)> = chunks
+      .iter()
+      .map(|x| {
+        (
+          x.heading_path.iter().map(String::as_str).collect(),
+          x.text.as_str(),
+        )
+      })
+      .collect();
+    assert_eq!(
+      summary,
+      vec![
+        (vec!["A", "B"], "# A\n\n## B\n\nText."),
+        (vec!["A", "C"], "## C"),
+      ]
+    );
+
+    assert_eq!(
+      _split_text_block("aaaa bbbbbb", 0, 11, 4, 4),
+      vec![(0, 4), (5, 9), (9, 11)]
+    );
+    assert_eq!(
+      _split_text_block("aaaa bbbbbb", 0, 11, 2, 6),
+      vec![(0, 2), (2, 4), (5, 11)]
+    );
+  }
+
+  #[test]
+  fn test_extract_figures() {
+    let html = r#"<html><body>
+<figure><a href="/big.jpg"><img src="photos/cat.jpg" alt=" A  cat "></a>
+<figcaption>The office <b>cat</b>.</figcaption></figure>
+<figure><figure><img src="/inner.png"></figure><img src="/outer.png" alt="">
+<figcaption>Outer</figcaption></figure>
+<figure><blockquote>Quote</blockquote></figure>
+</body></html>"#;
+
+    let figures: Vec<(Option<String>, Option<String>, Option<String>)> =
+      _extract_figures(html, "https://example.com/blog/post")
+        .unwrap()
+        .into_iter()
+        .map(|x| (x.image_url, x.caption, x.alt))
+        .collect();
+    assert_eq!(
+      figures,
+      vec![
+        (
+          Some("https://example.com/blog/photos/cat.jpg".to_string()),
+          Some("The office cat.".to_string()),
+          Some("A cat".to_string())
+        ),
+        (
+          Some("https://example.com/outer.png".to_string()),
+          Some("Outer".to_string()),
+          None
+        ),
+        (
+          Some("https://example.com/inner.png".to_string()),
+          None,
+          None
+        ),
+        (None, None, None),
+      ]
+    );
+  }
+
+  #[test]
+  fn test_classify_links() {
+    let html = r##"<html><body>
+<a href="/about">About</a>
+<a href="https://blog.example.co.uk/post">Blog</a>
+<a href="https://example.com/">Other site</a>
+<a href="#top">Top</a>
+<a href="MAILTO:hi@example.co.uk">Mail</a>
+<a href="tel:+441234">Call</a>
+<a href="javascript:void(0)">Menu</a>
+<a href="http://[::1">Broken</a>
+</body></html>"##;
+
+    let result = _classify_links(html, "https://www.example.co.uk/index.html").unwrap();
+    let links: Vec<(&str, &str)> = result
+      .links
+      .iter()
+      .map(|x| (x.category.as_str(), x.url.as_str()))
+      .collect();
+    assert_eq!(
+      links,
+      vec![
+        ("internal", "https://www.example.co.uk/about"),
+        ("subdomain", "https://blog.example.co.uk/post"),
+        ("external", "https://example.com/"),
+        ("fragment-only", "#top"),
+        ("mailto", "MAILTO:hi@example.co.uk"),
+        ("tel", "tel:+441234"),
+        ("unparseable", "javascript:void(0)"),
+        ("unparseable", "http://[::1"),
+      ]
+    );
+
+    let counts = &result.counts;
+    assert_eq!(
+      (
+        counts.internal,
+        counts.subdomain,
+        counts.external,
+        counts.mailto,
+        counts.tel,
+        counts.fragment_only,
+        counts.unparseable
+      ),
+      (1, 1, 1, 1, 1, 1, 2)
+    );
+  }
+
+  #[test]
+  fn test_extract_metadata_language_fallbacks() {
+    let language = |html: &str| _extract_metadata(html).unwrap().get("language").cloned();
+
+    assert_eq!(
+      language(
+        r#"<html lang="en"><head><meta http-equiv="Content-Language" content="fr"></head></html>"#
+      ),
+      Some(Value::String("en".to_string()))
+    );
+    assert_eq!(
+      language(
+        r#"<html lang=""><head><meta name="Language" content="German">
+<meta http-equiv="content-language" content=" en-US, fr "></head></html>"#
+      ),
+      Some(Value::String("en-US".to_string()))
+    );
+    assert_eq!(
+      language(r#"<html><head><meta name="language" content="German"></head></html>"#),
+      Some(Value::String("German".to_string()))
+    );
+    assert_eq!(language("<html><head></head></html>"), None);
+  }
+
+  #[test]
+  fn test_extract_preload_resources() {
+    let html = r#"<html><head>
+<link rel="preload" href="/fonts/inter.woff2" as="Font" type="font/woff2" crossorigin>
+<link rel="modulepreload" href="app.js">
+<link rel="preconnect dns-prefetch" href="https://cdn.example.net">
+<link rel="prefetch" href="/next-page" as="document">
+<link rel="stylesheet" href="/site.css">
+<link rel="preload" as="image">
+</head><body></body></html>"#;
+
+    let hints: Vec<(String, String, Option<String>, bool)> =
+      _extract_preload_resources(html, "https://example.com/blog/")
+        .unwrap()
+        .into_iter()
+        .map(|x| (x.url, x.rel, x.as_, x.crossorigin))
+        .collect();
+    let expected = [
+      (
+        "https://example.com/fonts/inter.woff2",
+        "preload",
+        Some("font"),
+        true,
+      ),
+      (
+        "https://example.com/blog/app.js",
+        "modulepreload",
+        None,
+        false,
+      ),
+      ("https://cdn.example.net/", "preconnect", None, false),
+      ("https://cdn.example.net/", "dns-prefetch", None, false),
+      (
+        "https://example.com/next-page",
+        "prefetch",
+        Some("document"),
+        false,
+      ),
+    ];
+    assert_eq!(
+      hints,
+      expected
+        .iter()
+        .map(|(u, r, a, c)| (u.to_string(), r.to_string(), a.map(String::from), *c))
+        .collect::<Vec<_>>()
+    );
+  }
+
+  #[test]
+  fn test_extract_videos() {
+    let html = r#"<html><head>
+<meta property="og:video" content="https://www.youtube.com/watch?v=dQw4w9WgXcQ&t=10">
+<meta property="og:video:type" content="text/html">
+<meta name="twitter:player" content="https://player.vimeo.com/video/76979871">
+</head><body>
+<video src="/media/intro.mp4" poster="/media/intro.jpg"></video>
+<video poster="poster.png"><source src="clip.webm" type="video/webm"><source src="/media/intro.mp4" type="video/mp4"></video>
+<iframe src="https://www.youtube-nocookie.com/embed/aqz-KE-bpKQ?rel=0"></iframe>
+<iframe data-src="https://fast.wistia.net/embed/iframe/e4a27b971d"></iframe>
+<iframe src="https://www.loom.com/share/0281766fa2d04bb788eaf19e65135184"></iframe>
+<iframe src="https://maps.example.com/embed"></iframe>
+<iframe src="https://vimeo.com/channels/staffpicks"></iframe>
+</body></html>"#;
+
+    type Video = (
+      String,
+      String,
+      Option<String>,
+      Option<String>,
+      Option<String>,
+      Option<String>,
+    );
+    let videos: Vec<Video> = _extract_videos(html, "https://example.com/blog/post")
+      .unwrap()
+      .into_iter()
+      .map(|x| {
+        (
+          x.url,
+          x.source,
+          x.mime_type,
+          x.provider,
+          x.video_id,
+          x.poster,
+        )
+      })
+      .collect();
+    let expected = [
+      (
+        "https://www.youtube.com/watch?v=dQw4w9WgXcQ&t=10",
+        "og:video",
+        Some("text/html"),
+        Some("youtube"),
+        Some("dQw4w9WgXcQ"),
+        None,
+      ),
+      (
+        "https://player.vimeo.com/video/76979871",
+        "twitter:player",
+        None,
+        Some("vimeo"),
+        Some("76979871"),
+        None,
+      ),
+      (
+        "https://example.com/media/intro.mp4",
+        "video",
+        Some("video/mp4"),
+        None,
+        None,
+        Some("https://example.com/media/intro.jpg"),
+      ),
+      (
+        "https://example.com/blog/clip.webm",
+        "source",
+        Some("video/webm"),
+        None,
+        None,
+        Some("https://example.com/blog/poster.png"),
+      ),
+      (
+        "https://www.youtube-nocookie.com/embed/aqz-KE-bpKQ?rel=0",
+        "iframe",
+        None,
+        Some("youtube"),
+        Some("aqz-KE-bpKQ"),
+        None,
+      ),
+      (
+        "https://fast.wistia.net/embed/iframe/e4a27b971d",
+        "iframe",
+        None,
+        Some("wistia"),
+        Some("e4a27b971d"),
+        None,
+      ),
+      (
+        "https://www.loom.com/share/0281766fa2d04bb788eaf19e65135184",
+        "iframe",
+        None,
+        Some("loom"),
+        Some("0281766fa2d04bb788eaf19e65135184"),
+        None,
+      ),
+      (
+        "https://vimeo.com/channels/staffpicks",
+        "iframe",
+        None,
+        Some("vimeo"),
+        None,
+        None,
+      ),
+    ];
+    assert_eq!(
+      videos,
+      expected
+        .iter()
+        .map(|(u, s, m, p, i, o)| (
+          u.to_string(),
+          s.to_string(),
+          m.map(String::from),
+          p.map(String::from),
+          i.map(String::from),
+          o.map(String::from)
+        ))
+        .collect::<Vec<_>>()
+    );
+  }
+
+  #[test]
+  fn test_discover_feeds() {
+    let html = r#"<html><head>
+<base href="https://example.com/blog/">
+<link rel="alternate" type="application/rss+xml" title="Posts" href="feed.xml">
+<link rel="alternate" type="APPLICATION/ATOM+XML; charset=utf-8" href="/atom.xml">
+<link rel="alternate" type="application/rss+xml" title="Posts (again)" href="https://example.com/blog/feed.xml">
+<link rel="alternate" type="application/feed+json" title="JSON" href="feed.json">
+<link rel="alternate" hreflang="de" href="/de/">
+<link rel="stylesheet" type="application/rss+xml" href="/not-a-feed.xml">
+</head><body></body></html>"#;
+
+    let feeds = |options: &DiscoverFeedsOptions| -> Vec<(String, String, Option<String>)> {
+      _discover_feeds(html, "https://example.com/blog/post", options)
+        .unwrap()
+        .into_iter()
+        .map(|x| (x.href, x.feed_type, x.title))
+        .collect()
+    };
+    let discovered = vec![
+      (
+        "https://example.com/blog/feed.xml".to_string(),
+        "application/rss+xml".to_string(),
+        Some("Posts".to_string()),
+      ),
+      (
+        "https://example.com/atom.xml".to_string(),
+        "application/atom+xml".to_string(),
+        None,
+      ),
+      (
+        "https://example.com/blog/feed.json".to_string(),
+        "application/feed+json".to_string(),
+        Some("JSON".to_string()),
+      ),
+    ];
+    assert_eq!(feeds(&DiscoverFeedsOptions::default()), discovered);
+
+    let with_fallbacks = feeds(&DiscoverFeedsOptions {
+      include_fallback_paths: Some(true),
+    });
+    assert_eq!(with_fallbacks[..3], discovered[..]);
+    let fallback_hrefs: Vec<&str> = with_fallbacks[3..].iter().map(|x| x.0.as_str()).collect();
+    assert_eq!(
+      fallback_hrefs,
+      vec![
+        "https://example.com/feed",
+        "https://example.com/rss.xml",
+        "https://example.com/feed.xml",
+        "https://example.com/index.xml",
+        "https://example.com/feed.json",
+      ]
+    );
+  }
+
+  #[test]
+  fn test_transform_html_min_text_length() {
+    let html = r#"<html><body><div id="wrapper">
+<p>.</p><span>&nbsp;</span>
+<ul><li>x</li><li>A proper list item</li></ul>
+<div><span>a</span><span>b</span></div>
+<p><img src="/chart.png"></p>
+<p>Line<br>two</p>
+<table><tr><td></td><td>Cell</td></tr></table>
+<p>Kept paragraph with text.</p>
+<p>E=mc<sup>2</sup> where <code>x</code> is <b>a</b> variable.</p>
+</div></body></html>"#;
+
+    let mut opts = transform_opts(html, "https://example.com/");
+    opts.min_text_length = Some(3);
+    opts.output = Some("body".to_string());
+    let (out, stats) = _transform_html_with_stats(opts).unwrap();
+
+    assert!(!out.contains("<p>.</p>"));
+    assert!(!out.contains("<span>a</span>"));
+    assert!(!out.contains("<li>x</li>"));
+    assert!(out.contains("<li>A proper list item</li>"));
+    assert!(!out.contains("<div></div>"));
+    assert!(out.contains(r#"<img src="https://example.com/chart.png">"#));
+    assert!(out.contains("<p>Line<br>two</p>"));
+    assert!(out.contains("<td></td>"));
+    assert!(out.contains("Kept paragraph with text."));
+    // Inline elements are short by nature and stay.
+    assert!(out.contains("E=mc<sup>2</sup>"));
+    assert!(out.contains("<code>x</code>"));
+    assert_eq!(stats.removed_short_text, 3);
+
+    let unchanged = _transform_html_inner(transform_opts(html, "https://example.com/")).unwrap();
+    assert!(unchanged.contains("<p>.</p>"));
+  }
+
+  #[test]
+  fn test_extract_inline_styles_css_vars() {
+    let html = r#"<html><head><style>
+:root { --brand-color: #ff0000; --font-stack: "Inter", sans-serif; --spacing-2:8px }
+.dark { color: var(--brand-color); --brand-color : #990000 ; }
+</style></head>
+<body style="--body_bg: rgb(255, 255, 255); margin: 0">
+<div style="--spacing-2: 12px"></div>
+</body></html>"#;
+
+    let vars: Vec<(String, String)> = _extract_inline_styles_css_vars(html)
+      .unwrap()
+      .into_iter()
+      .map(|x| (x.name, x.value))
+      .collect();
+    assert_eq!(
+      vars,
+      vec![
+        ("--brand-color".to_string(), "#990000".to_string()),
+        (
+          "--font-stack".to_string(),
+          "\"Inter\", sans-serif".to_string()
+        ),
+        ("--spacing-2".to_string(), "12px".to_string()),
+        ("--body_bg".to_string(), "rgb(255, 255, 255)".to_string()),
+      ]
+    );
+  }
+
+  #[test]
+  fn test_extract_schema_org_article() {
+    let html = r#"<html><head>
+<script type="application/ld+json">{"@type":"Organization","name":"Acme"}</script>
+<script type="application/ld+json">
+{"@context":"https://schema.org","@graph":[
+  {"@type":"WebPage","name":"Page"},
+  {"@type":["NewsArticle"],"headline":" Rates  rise ","description":"Central bank moves.",
+   "author":[{"@type":"Person","name":"Ada Lovelace"},"Grace Hopper",{"@type":"Person"}],
+   "datePublished":"2024-05-01T08:00:00Z","dateModified":"2024-05-02",
+   "image":[{"@type":"ImageObject","url":"https://example.com/rates.jpg"},"https://example.com/other.jpg"],
+   "publisher":{"@type":"Organization","name":"Daily News"}}
+]}
+</script></head><body></body></html>"#;
+
+    let article = _extract_schema_org_article(html).unwrap().unwrap();
+    assert_eq!(article.headline.as_deref(), Some("Rates rise"));
+    assert_eq!(article.description.as_deref(), Some("Central bank moves."));
+    assert_eq!(article.author_names, vec!["Ada Lovelace", "Grace Hopper"]);
+    assert_eq!(
+      article.date_published.as_deref(),
+      Some("2024-05-01T08:00:00Z")
+    );
+    assert_eq!(article.date_modified.as_deref(), Some("2024-05-02"));
+    assert_eq!(
+      article.image_url.as_deref(),
+      Some("https://example.com/rates.jpg")
+    );
+    assert_eq!(article.publisher_name.as_deref(), Some("Daily News"));
+
+    let blog = r#"<script type="application/ld+json">{"@type":"BlogPosting","name":"Notes","author":"Sam","image":"/a.png"}</script>"#;
+    let article = _extract_schema_org_article(blog).unwrap().unwrap();
+    assert_eq!(article.headline.as_deref(), Some("Notes"));
+    assert_eq!(article.author_names, vec!["Sam"]);
+    assert_eq!(article.image_url.as_deref(), Some("/a.png"));
+    assert!(article.publisher_name.is_none());
+
+    let none = r#"<script type="application/ld+json">{"@type":"Product","name":"Shoe"}</script>"#;
+    assert!(_extract_schema_org_article(none).unwrap().is_none());
+
+    // Yoast links the nodes of its graph by `@id`, with a cycle between the
+    // organization and its founder.
+    let yoast = r##"<script type="application/ld+json">{"@context":"https://schema.org","@graph":[
+  {"@type":"Article","@id":"https://example.com/post/#article","headline":"Post",
+   "author":{"@id":"https://example.com/#/schema/person/1"},
+   "publisher":{"@id":"https://example.com/#organization"},
+   "image":{"@id":"https://example.com/post/#primaryimage"}},
+  {"@type":"ImageObject","@id":"https://example.com/post/#primaryimage","url":"https://example.com/cover.jpg"},
+  {"@type":"Organization","@id":"https://example.com/#organization","name":"Example Inc",
+   "founder":{"@id":"https://example.com/#/schema/person/1"}},
+  {"@type":"Person","@id":"https://example.com/#/schema/person/1","name":"Jane Doe",
+   "worksFor":{"@id":"https://example.com/#organization"}}
+]}</script>"##;
+    let article = _extract_schema_org_article(yoast).unwrap().unwrap();
+    assert_eq!(
+      article.image_url.as_deref(),
+      Some("https://example.com/cover.jpg")
+    );
+    assert_eq!(article.author_names, vec!["Jane Doe"]);
+    assert_eq!(article.publisher_name.as_deref(), Some("Example Inc"));
+
+    let document = parse_html().one(yoast);
+    let graph = &_extract_json_ld(&document)[0]["@graph"];
+    assert_eq!(
+      graph[2]["founder"]["worksFor"],
+      serde_json::json!({"@id": "https://example.com/#organization"})
+    );
+  }
+
+  #[test]
+  fn test_extract_schema_org_product() {
+    let html = r#"<html><head>
+<script type="application/ld+json">{"@type":"BreadcrumbList","itemListElement":[]}</script>
+<script type="application/ld+json">
+{"@context":"https://schema.org/","@type":"Product","name":"Trail Shoe","description":"Light  and grippy.",
+ "sku":"TS-42","brand":{"@type":"Brand","name":"Acme"},
+ "offers":[{"@type":"Offer","price":"89.99","priceCurrency":"USD","availability":"https://schema.org/InStock"},
+           {"@type":"Offer","price":"99.99","priceCurrency":"EUR"}],
+ "aggregateRating":{"@type":"AggregateRating","ratingValue":"4.6","reviewCount":128}}
+</script></head><body></body></html>"#;
+
+    let product = _extract_schema_org_product(html).unwrap().unwrap();
+    assert_eq!(product.name.as_deref(), Some("Trail Shoe"));
+    assert_eq!(product.description.as_deref(), Some("Light and grippy."));
+    assert_eq!(product.sku.as_deref(), Some("TS-42"));
+    assert_eq!(product.brand.as_deref(), Some("Acme"));
+    assert_eq!(product.price.as_deref(), Some("89.99"));
+    assert_eq!(product.currency.as_deref(), Some("USD"));
+    assert_eq!(product.availability.as_deref(), Some("InStock"));
+    assert_eq!(product.rating_value, Some(4.6));
+    assert_eq!(product.review_count, Some(128));
+
+    let aggregate = r#"<script type="application/ld+json">{"@graph":[{"@type":"Product","name":"Mug","brand":"Potter",
+"offers":{"lowPrice":12,"priceCurrency":"GBP"},
+"aggregateRating":{"ratingValue":5,"ratingCount":"7"}}]}</script>"#;
+    let product = _extract_schema_org_product(aggregate).unwrap().unwrap();
+    assert_eq!(product.brand.as_deref(), Some("Potter"));
+    assert_eq!(product.price.as_deref(), Some("12"));
+    assert_eq!(product.currency.as_deref(), Some("GBP"));
+    assert!(product.availability.is_none());
+    assert_eq!(product.rating_value, Some(5.0));
+    assert_eq!(product.review_count, Some(7));
+
+    let none =
+      r#"<script type="application/ld+json">{"@type":"Article","headline":"News"}</script>"#;
+    assert!(_extract_schema_org_product(none).unwrap().is_none());
+  }
+
+  #[test]
+  fn test_extract_schema_org_events() {
+    let html = r#"<html><head>
+<script type="application/ld+json">[
+  {"@type":"MusicEvent","name":"Night Set","startDate":"2025-07-04T20:00:00+02:00","endDate":"2025-07-04T23:30",
+   "location":{"@type":"Place","name":"The Hall",
+     "address":{"@type":"PostalAddress","streetAddress":"1 Main St","addressLocality":"Berlin","addressCountry":{"@type":"Country","name":"DE"}}},
+   "organizer":{"@type":"Organization","name":"Loud Co"},"url":"https://example.com/night","image":["https://example.com/night.jpg"]},
+  {"@type":"Event","name":"Fair","startDate":"2025-09-01","endDate":"early autumn","location":"Town Square"}
+]</script></head><body></body></html>"#;
+
+    let events = _extract_schema_org_events(html).unwrap();
+    assert_eq!(events.len(), 2);
+
+    let set = &events[0];
+    assert_eq!(set.name.as_deref(), Some("Night Set"));
+    assert_eq!(set.start_date.as_deref(), Some("2025-07-04T20:00:00+02:00"));
+    assert_eq!(set.end_date.as_deref(), Some("2025-07-04T23:30:00"));
+    assert_eq!(set.location_name.as_deref(), Some("The Hall"));
+    assert_eq!(
+      set.location_address.as_deref(),
+      Some("1 Main St, Berlin, DE")
+    );
+    assert_eq!(set.organizer_name.as_deref(), Some("Loud Co"));
+    assert_eq!(set.url.as_deref(), Some("https://example.com/night"));
+    assert_eq!(
+      set.image_url.as_deref(),
+      Some("https://example.com/night.jpg")
+    );
+
+    let fair = &events[1];
+    assert_eq!(fair.start_date.as_deref(), Some("2025-09-01"));
+    assert_eq!(fair.end_date.as_deref(), Some("early autumn"));
+    assert_eq!(fair.location_name.as_deref(), Some("Town Square"));
+    assert!(fair.location_address.is_none());
+    assert!(fair.organizer_name.is_none());
+
+    assert!(_extract_schema_org_events("<p>No events</p>")
+      .unwrap()
+      .is_empty());
+  }
+
+  #[test]
+  fn test_extract_schema_org() {
+    let html = r#"<html><head>
+<script type="application/ld+json">{"@context":"https://schema.org","@graph":[
+  {"@type":"schema:Product","name":"Kettle","offers":{"price":"30","priceCurrency":"EUR"}},
+  {"@type":"WebSite","name":"Shop","url":"https://shop.example"},
+  {"@type":"https://schema.org/NewsArticle","headline":"Kettles are back"}
+]}</script></head><body>
+<div itemscope itemtype="https://schema.org/Product"><span itemprop="name">Kettle</span></div>
+<div itemscope itemtype="https://schema.org/Product"><span itemprop="name">Teapot</span></div>
+<article itemscope itemtype="http://schema.org/Recipe">
+  <h1 itemprop="name">Tea</h1>
+  <span itemprop="author" itemscope itemtype="https://schema.org/Person"><span itemprop="name">Ada</span></span>
+  <meta itemprop="totalTime" content="PT5M">
+  <ul><li itemprop="recipeIngredient">Water</li><li itemprop="recipeIngredient ingredients">Tea  leaves</li></ul>
+  <ol><li itemprop="recipeInstructions">Boil water.</li>
+    <li itemprop="recipeInstructions">Steep the
+      leaves.</li></ol>
+  <img itemprop="image" src="/tea.jpg">
+</article>
+<div vocab="https://schema.org/" typeof="Organization">
+  <a property="url" href="https://acme.example"><span property="name">Acme</span></a>
+  <img property="logo" src="https://acme.example/logo.png">
+  <link property="sameAs" href="https://social.example/acme">
+</div>
+</body></html>"#;
+
+    let result = _extract_schema_org(html).unwrap();
+    assert_eq!(result.sources, vec!["json-ld", "microdata", "rdfa"]);
+    assert_eq!(result.conflicting_types, vec!["Product"]);
+
+    // The markup Kettle is the JSON-LD one; the Teapot is only in markup.
+    assert_eq!(result.products.len(), 2);
+    assert_eq!(result.products[0].name.as_deref(), Some("Kettle"));
+    assert_eq!(result.products[0].price.as_deref(), Some("30"));
+    assert_eq!(result.products[1].name.as_deref(), Some("Teapot"));
+    assert_eq!(result.articles.len(), 1);
+    assert_eq!(
+      result.articles[0].headline.as_deref(),
+      Some("Kettles are back")
+    );
+
+    let recipe = &result.recipes[0];
+    assert_eq!(recipe.name.as_deref(), Some("Tea"));
+    assert_eq!(recipe.author_names, vec!["Ada"]);
+    assert_eq!(recipe.total_time.as_deref(), Some("PT5M"));
+    assert_eq!(recipe.ingredients, vec!["Water", "Tea leaves"]);
+    assert_eq!(
+      recipe.instructions,
+      vec!["Boil water.", "Steep the leaves."]
+    );
+    assert_eq!(recipe.image_url.as_deref(), Some("/tea.jpg"));
+
+    let organization = &result.organizations[0];
+    assert_eq!(organization.name.as_deref(), Some("Acme"));
+    assert_eq!(organization.url.as_deref(), Some("https://acme.example"));
+    assert_eq!(
+      organization.logo_url.as_deref(),
+      Some("https://acme.example/logo.png")
+    );
+    assert_eq!(organization.same_as, vec!["https://social.example/acme"]);
+
+    assert_eq!(result.other.len(), 1);
+    assert_eq!(result.other[0]["@type"], "WebSite");
+    assert!(result.events.is_empty());
+  }
+
+  #[test]
+  fn test_extract_microdata() {
+    let html = r#"<div itemscope itemtype="https://schema.org/Product">
+  <h1 itemprop="name">Kettle</h1>
+  <img itemprop="image" src="https://shop.example/kettle.jpg">
+  <a itemprop="url sameAs" href="https://shop.example/kettle">Details</a>
+  <div itemprop="offers" itemscope itemtype="https://schema.org/Offer">
+    <meta itemprop="price" content="30.00">
+    <span itemprop="priceCurrency">EUR</span>
+  </div>
+  <span itemprop="color">Red</span> <span itemprop="color">Blue</span>
+</div>
+<section itemscope><p itemprop="note">Untyped</p></section>"#;
+
+    let items = _extract_microdata(html).unwrap();
+    assert_eq!(items.len(), 3);
+    let properties = |i: usize| -> HashMap<String, Vec<Value>> {
+      serde_json::from_str(&items[i].properties).unwrap()
+    };
+
+    assert_eq!(
+      items[0].item_type.as_deref(),
+      Some("https://schema.org/Product")
+    );
+    let product = properties(0);
+    assert_eq!(product["name"], vec!["Kettle"]);
+    assert_eq!(product["image"], vec!["https://shop.example/kettle.jpg"]);
+    assert_eq!(product["url"], vec!["https://shop.example/kettle"]);
+    assert_eq!(product["sameAs"], vec!["https://shop.example/kettle"]);
+    assert_eq!(product["color"], vec!["Red", "Blue"]);
+    assert_eq!(
+      product["offers"],
+      vec![serde_json::json!({"@type": "Offer", "price": "30.00", "priceCurrency": "EUR"})]
+    );
+    assert!(!product.contains_key("price"));
+
+    assert_eq!(
+      items[1].item_type.as_deref(),
+      Some("https://schema.org/Offer")
+    );
+    let offer = properties(1);
+    assert_eq!(offer["price"], vec!["30.00"]);
+    assert_eq!(offer["priceCurrency"], vec!["EUR"]);
+
+    assert!(items[2].item_type.is_none());
+    assert_eq!(properties(2)["note"], vec!["Untyped"]);
+  }
+
+  #[test]
+  fn test_detect_access_barriers() {
+    let story =
+      "The council voted on Tuesday to extend the tram line, after years of debate. ".repeat(20);
+
+    let news_with_banner = format!(
+      r#"<html><body><article><h1>Trams</h1><p>{story}</p></article>
+<div id="onetrust-banner-sdk"><p>We use cookies to improve your experience.</p><button>Accept all</button></div>
+</body></html>"#
+    );
+    let result = _detect_access_barriers(&news_with_banner).unwrap();
+    assert!(!result.cookie_wall);
+    assert!(!result.paywall);
+    assert!(!result.login_required);
+    assert!(result.main_content_length > 1000);
+    assert_eq!(result.signals, vec!["cookie_wall:consent_manager"]);
+
+    let consent_wall = r#"<html><body><div class="cmp-container"><h2>Your privacy</h2>
+<p>We and our 300 partners use cookies and similar technologies to store and access information on your device. Choose accept all to continue.</p>
+<button>Accept all</button><button>Manage options</button></div><p>Loading</p></body></html>"#;
+    let result = _detect_access_barriers(consent_wall).unwrap();
+    assert!(result.cookie_wall);
+    assert!(result.cookie_wall_confidence >= 0.7);
+
+    let paywalled = r#"<html><head>
+<script src="https://cdn.tinypass.com/api/tinypass.min.js"></script>
+<script type="application/ld+json">{"@type":"NewsArticle","isAccessibleForFree":false}</script>
+</head><body><article><p>The opening paragraph of the story.</p>
+<p>Subscribe to continue reading.</p></article></body></html>"#;
+    let result = _detect_access_barriers(paywalled).unwrap();
+    assert!(result.paywall);
+    assert_eq!(result.paywall_confidence, 1.0);
+    assert!(result
+      .signals
+      .contains(&"paywall:truncated_with_subscribe_cta".to_string()));
+
+    let login = r#"<html><head><link rel="canonical" href="https://example.com/login?returnUrl=%2Fdocs"></head>
+<body><main><h1>Please sign in</h1><form action="/session"><input name="user">
+<input type="password" name="pass"><button>Sign in</button></form></main></body></html>"#;
+    let result = _detect_access_barriers(login).unwrap();
+    assert!(result.login_required);
+    assert!(!result.paywall);
+    assert!(!result.cookie_wall);
+
+    // A header login form next to short content, or a bare password field, isn't a wall.
+    let header_login = r#"<html><body><header><form><input name="user">
+<input type="password" name="pass"><button>Go</button></form></header>
+<main><h1>Opening hours</h1><p>Monday to Friday, 9 to 5.</p></main></body></html>"#;
+    let result = _detect_access_barriers(header_login).unwrap();
+    assert!(!result.login_required);
+    assert!(result.signals.is_empty());
+
+    let bare_form = r#"<html><body><main><h1>Change password</h1><form>
+<input type="password" name="new"><button>Save</button></form></main></body></html>"#;
+    let result = _detect_access_barriers(bare_form).unwrap();
+    assert!(!result.login_required);
+    assert_eq!(result.login_required_confidence, 0.3);
+  }
+
+  #[test]
+  fn test_extract_metadata_item() {
+    let metadata = _extract_metadata_item("<html><head><title>Batch</title></head></html>", None);
+    assert_eq!(metadata["title"], "Batch");
+    assert!(!metadata.contains_key("_error"));
+  }
+
+  #[test]
+  fn test_batch_extract_metadata() {
+    // Results come back in input order, however the blocking pool schedules them, and
+    // a failing document becomes an `_error` entry without failing the batch.
+    let mut items: Vec<String> = (0..16)
+      .map(|i| {
+        format!(
+          "<html><head><title>Page {i}</title></head><body>{}</body></html>",
+          "<p>x</p>".repeat(i * 50)
+        )
+      })
+      .collect();
+    items.insert(5, "a".repeat(16 * 1024 + 1));
+
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let results = runtime
+      .block_on(batch_extract_metadata(items, Some(16 * 1024)))
+      .unwrap();
+    assert_eq!(results.len(), 17);
+
+    let failed = &results[5];
+    assert_eq!(failed.len(), 1);
+    assert!(failed["_error"]
+      .as_str()
+      .unwrap()
+      .starts_with("Input too large: "));
+
+    let titles: Vec<&str> = results
+      .iter()
+      .enumerate()
+      .filter(|(i, _)| *i != 5)
+      .map(|(_, x)| x["title"].as_str().unwrap())
+      .collect();
+    let expected: Vec<String> = (0..16).map(|i| format!("Page {i}")).collect();
+    assert_eq!(titles, expected);
+
+    assert!(runtime
+      .block_on(batch_extract_metadata(Vec::new(), None))
+      .unwrap()
+      .is_empty());
+  }
+
+  #[test]
+  fn test_detect_soft_error_page() {
+    let not_found = r#"<html><head><title>Seite nicht gefunden | Shop</title>
+<link rel="canonical" href="/"><meta name="robots" content="noindex"></head>
+<body><h1>Oops!</h1><p>Die Seite existiert leider nicht.</p></body></html>"#;
+    let result = _detect_soft_error_page(not_found, "https://shop.example/p/123").unwrap();
+    assert_eq!(result.classification, "not_found");
+    assert_eq!(result.confidence, 1.0);
+    assert_eq!(result.evidence.len(), 4);
+    assert!(result.evidence[0].contains("seite nicht gefunden"));
+
+    let gone = r#"<html><head><title>Blue sofa - Listings</title></head>
+<body><h1>This listing is no longer available</h1><p>Browse similar items.</p></body></html>"#;
+    let result = _detect_soft_error_page(gone, "https://market.example/item/9").unwrap();
+    assert_eq!(result.classification, "gone");
+    assert!(result.confidence >= 0.8);
+
+    let error =
+      r#"<html><head><title>500 - Internal Server Error</title></head><body></body></html>"#;
+    let result = _detect_soft_error_page(error, "https://example.com/x").unwrap();
+    assert_eq!(result.classification, "error");
+
+    let story =
+      "Our guide to fixing a 404 page not found error covers redirects and sitemaps. ".repeat(10);
+    let article = format!(
+      r#"<html><head><title>How to fix broken links</title><link rel="canonical" href="https://example.com/guides/links"></head>
+<body><article><h1>How to fix broken links</h1><p>{story}</p></article></body></html>"#
+    );
+    let result = _detect_soft_error_page(&article, "https://example.com/guides/links").unwrap();
+    assert_eq!(result.classification, "ok");
+    assert_eq!(result.confidence, 1.0);
+    assert!(result.evidence.is_empty());
+
+    let story = "The old editor was retired after years of service and feedback. ".repeat(10);
+    let removed = format!(
+      r#"<html><head><title>Feature X has been removed</title></head>
+<body><article><h1>Something went wrong with Feature X</h1><p>{story}</p></article></body></html>"#
+    );
+    let result = _detect_soft_error_page(&removed, "https://example.com/blog/x").unwrap();
+    assert_eq!(result.classification, "ok");
+
+    let car = r#"<html><head><title>Peugeot 404 for sale</title></head>
+<body><h1>Peugeot 404</h1><p>Top 500 classic cars.</p></body></html>"#;
+    let result = _detect_soft_error_page(car, "https://cars.example/404").unwrap();
+    assert_eq!(result.classification, "ok");
+
+    assert_eq!(_soft_error_match("Peugeot 404"), None);
+    assert_eq!(_soft_error_match("Top 500 companies"), None);
+    assert_eq!(
+      _soft_error_match("Error 404 | Shop"),
+      Some(("not_found", "404".to_string()))
+    );
+    assert_eq!(_soft_error_match("503"), Some(("error", "503".to_string())));
+    assert_eq!(
+      _soft_error_match("410 page"),
+      Some(("gone", "410".to_string()))
+    );
+  }
+
+  #[test]
+  fn test_batch_transform_html() {
+    let mut oversized = transform_opts("<p>Too long</p>", "https://example.com/");
+    oversized.max_input_bytes = Some(4);
+    let opts = vec![
+      transform_opts("<p>Kept</p>", "https://example.com/"),
+      oversized,
+    ];
+
+    let results = tokio::runtime::Runtime::new()
+      .unwrap()
+      .block_on(batch_transform_html(opts, Some(2)))
+      .unwrap();
+    assert!(results[0].as_deref().unwrap().contains("Kept"));
+    assert_eq!(results[1], None);
+  }
+
+  #[test]
+  fn test_batch_concurrency() {
+    assert_eq!(_batch_concurrency(Some(3)), 3);
+    assert!(_batch_concurrency(Some(0)) >= 1);
+    assert_eq!(
+      _batch_concurrency(None),
+      thread::available_parallelism().map_or(1, |x| x.get())
+    );
+  }
+
+  #[test]
+  fn test_remove_tags_matches_select_first_loops() {
+    let html = r#"<html><head><title>T</title><style>a{}</style></head><body>
+<noscript><style>b{}</style></noscript>
+<style>c{}</style><script>d()</script>
+<div><script>e()</script><section><meta itemprop="x" content="y"><p>Keep</p></section></div>
+<aside><nav><aside>Nested</aside></nav></aside><nav>Menu</nav><p>Tail</p></body></html>"#;
+
+    let loops = |tags: &[&str]| {
+      let document = parse_html().one(html);
+      let mut count = 0;
+      for tag in tags {
+        while let Ok(x) = document.select_first(tag) {
+          x.as_node().detach();
+          count += 1;
+        }
+      }
+      (document.to_string(), count)
+    };
+    let single = |tags: &[&str]| {
+      let document = parse_html().one(html);
+      let count = _remove_tags(&document, tags);
+      (document.to_string(), count)
+    };
+
+    assert_eq!(single(&REMOVED_TAGS), loops(&REMOVED_TAGS));
+    assert_eq!(
+      single(&["nav", "aside", "p"]),
+      loops(&["nav", "aside", "p"])
+    );
+    assert_eq!(single(&["aside", "nav"]), loops(&["aside", "nav"]));
+    assert_eq!(single(&["NAV"]), loops(&["NAV"]));
+
+    assert!(_is_tag_name("my-widget"));
+    assert!(!_is_tag_name(".ad"));
+    assert!(!_is_tag_name("div > p"));
+  }
+
+  #[test]
+  fn test_remove_non_main_content_keeps_force_included() {
+    let html = r#"<html><body>
+      <header><nav>Menu</nav></header>
+      <footer><div><div class="swoogo-logo">Logo</div></div><nav>Links</nav></footer>
+      <aside id="main"><p>Body</p></aside>
+      <p>Text</p></body></html>"#;
+    let document = parse_html().one(html);
+    let mut stats = TransformHtmlStats::default();
+    _remove_non_main_content(&document, &MainContentSelectors::default(), &mut stats).unwrap();
+
+    let out = document.to_string();
+    assert!(!out.contains("<header>"));
+    assert!(out.contains("<footer>") && out.contains("swoogo-logo"));
+    assert!(!out.contains("Links"));
+    assert!(out.contains(r#"<aside id="main">"#));
+    assert!(out.contains("<p>Text</p>"));
+    assert_eq!(stats.removed_main_content, 2);
+    assert_eq!(stats.preserved_main_content, 2);
+  }
+
+  #[test]
+  fn test_transform_html_templates() {
+    let html = r#"<html><body><p>Before</p><template id="t"><div class="card"><p>Inside</p><template><span>Nested</span></template></div></template><p>After</p></body></html>"#;
+
+    let out = _transform_html_inner(transform_opts(html, "https://example.com/")).unwrap();
+    assert!(!out.contains("<template"));
+    assert!(!out.contains("Inside"));
+    assert!(out.contains("<p>Before</p><p>After</p>"));
+
+    let mut opts = transform_opts(html, "https://example.com/");
+    opts.expand_templates = Some(true);
+    opts.exclude_tags = vec![".card p".to_string()];
+    let out = _transform_html_inner(opts).unwrap();
+    assert!(!out.contains("<template"));
+    assert!(out.contains(r#"<p>Before</p><div class="card"><span>Nested</span></div><p>After</p>"#));
+  }
+
+  #[test]
+  fn test_compile_selector_reuses_compiled() {
+    let first = _compile_selector("div.compile-cache > p").unwrap();
+    let second = _compile_selector("div.compile-cache > p").unwrap();
+    assert!(Arc::ptr_eq(&first, &second));
+    assert!(_compile_selector("div[").is_none());
+
+    let document =
+      parse_html().one(r#"<div class="compile-cache"><p>a</p><span><p>b</p></span></div>"#);
+    let matched: Vec<_> = _select(&document, &first)
+      .map(|x| x.text_contents())
+      .collect();
+    assert_eq!(matched, ["a"]);
+  }
+
+  #[test]
+  fn test_transform_html_custom_main_content_selectors() {
+    let html = r#"<html><body>
+      <div class="promo"><p>Buy now</p></div>
+      <footer><div class="sponsor-wall">Sponsors</div><p>Copyright</p></footer>
+      <article><p>Body</p></article></body></html>"#;
+
+    let mut opts = transform_opts(html, "https://example.com/");
+    opts.only_main_content = true;
+    let out = _transform_html_inner(opts).unwrap();
+    assert!(out.contains("Buy now"));
+    assert!(!out.contains("Sponsors"));
+
+    let mut opts = transform_opts(html, "https://example.com/");
+    opts.only_main_content = true;
+    opts.additional_exclude_selectors = Some(vec![".promo".to_string()]);
+    opts.force_include_selectors = Some(vec![".sponsor-wall".to_string()]);
+    let (out, stats) = _transform_html_with_stats(opts).unwrap();
+    assert!(!out.contains("Buy now"));
+    assert!(out.contains("Sponsors") && out.contains("Copyright"));
+    assert!(out.contains("<p>Body</p>"));
+    assert_eq!(stats.preserved_main_content, 1);
+
+    let mut opts = transform_opts(html, "https://example.com/");
+    opts.only_main_content = true;
+    opts.force_include_selectors = Some(vec!["div[".to_string()]);
+    let (out, stats) = _transform_html_with_stats(opts).unwrap();
+    assert!(!out.contains("Sponsors"));
+    assert_eq!(stats.invalid_selectors, ["div["]);
+  }
+
+  #[test]
+  fn test_detect_content_type() {
+    let classify = |html: &str| {
+      let result = _detect_content_type(html).unwrap();
+      (result.content_type, result.confidence)
+    };
+
+    let article = format!(
+      r#"<html><head><meta property="og:type" content="article"></head><body><main><article><h1>Story</h1><p>{}</p></article></main></body></html>"#,
+      "Long paragraph text. ".repeat(100)
+    );
+    let (kind, confidence) = classify(&article);
+    assert_eq!(kind, "article");
+    assert!(confidence >= 0.7);
+
+    let product = r#"<html><head><script type="application/ld+json">{"@type": "https://schema.org/Product", "name": "Kettle"}</script></head>
+      <body><h1>Kettle</h1><p>$49.99</p><button>Add to cart</button></body></html>"#;
+    assert_eq!(classify(product).0, "product");
+
+    // Prices written the European way, with the symbol after the amount.
+    let euro_product = r#"<html><body><h1>Wasserkocher</h1><p>49,99 €</p><button>Add to cart</button></body></html>"#;
+    assert_eq!(PRICE_REGEX.find_iter("49,99 €").count(), 1);
+    assert_eq!(PRICE_REGEX.find_iter("12.00 eur, 3 europeans").count(), 1);
+    assert_eq!(classify(euro_product).0, "product");
+
+    let cards: String = (0..8)
+      .map(|i| format!(r#"<li class="card"><a href="/p/{i}"><img src="/{i}.jpg">Item {i}</a><span>$1{i}.00</span></li>"#))
+      .collect();
+    let listing = format!(
+      r#"<html><body><ul>{cards}</ul><nav class="pagination"><a rel="next" href="?page=2">Next</a></nav></body></html>"#
+    );
+    assert_eq!(classify(&listing).0, "listing");
+
+    let homepage = r#"<html><head><link rel="canonical" href="https://example.com/"><meta property="og:type" content="website"></head>
+      <body><h1>Welcome</h1></body></html>"#;
+    assert_eq!(classify(homepage).0, "homepage");
+
+    let (kind, confidence) = classify("<html><body><p>Contact us</p></body></html>");
+    assert_eq!(kind, "other");
+    assert_eq!(confidence, 1.0);
+  }
+
+  #[test]
+  fn test_transform_html_timeout() {
+    // A deadline of 0 ms has already passed by the first check.
+    let html = "<html><body><main><p>Text</p></main></body></html>";
+    let mut opts = transform_opts(html, "https://example.com/");
+    opts.timeout_ms = Some(0);
+    let err = _transform_html_inner(opts).unwrap_err();
+    assert!(err.downcast_ref::<TimeoutError>().is_some());
+    assert_eq!(err.to_string(), "Timed out after 0 ms");
+    let err = _to_napi_err(err);
+    assert!(matches!(err.status, napi::Status::Cancelled));
+    assert_eq!(err.reason, "Timed out after 0 ms");
+
+    let err = _html_to_text(
+      html,
+      &HtmlToTextOptions {
+        only_main_content: None,
+        timeout_ms: Some(0),
+      },
+    )
+    .unwrap_err();
+    assert!(err.downcast_ref::<TimeoutError>().is_some());
+
+    let options = ExtractAttributesOptions {
+      selectors: vec![AttributeSelector {
+        selector: "p".to_string(),
+        attribute: ":text".to_string(),
+      }],
+      timeout_ms: Some(0),
+    };
+    let Err(err) = _extract_attributes(html, &options) else {
+      panic!("extract_attributes finished past its deadline");
+    };
+    assert!(err.downcast_ref::<TimeoutError>().is_some());
+
+    let mut opts = transform_opts("<p>Fast</p>", "https://example.com/");
+    opts.timeout_ms = Some(60_000);
+    assert!(_transform_html_inner(opts).unwrap().contains("<p>Fast</p>"));
+    assert!(matches!(
+      _to_napi_err("Failed").status,
+      napi::Status::GenericFailure
+    ));
+  }
+
+  #[test]
+  fn test_extract_article_content() {
+    let paragraph = "Readers stay when the story is clear, concise, and well paced, with details. ";
+    let html = format!(
+      r#"<html><head><title>Story | Site</title><script>var x = 1;</script></head><body>
+      <nav><a href="/">Home</a> <a href="/news">News</a> <a href="/sports">Sports</a></nav>
+      <div class="content"><h1>Story</h1><p>{}</p><p>{}</p></div>
+      <div class="links"><a href="/a">Another story with a long enough headline</a></div>
+      </body></html>"#,
+      paragraph.repeat(3),
+      paragraph.repeat(2)
+    );
+
+    let article = _extract_article_content(&html).unwrap();
+    assert_eq!(article.title.as_deref(), Some("Story | Site"));
+    assert!(article.content_html.starts_with(r#"<div class="content">"#));
+    assert!(!article.content_html.contains("Home"));
+    assert!(article.content_text.starts_with("Story\n"));
+    assert_eq!(article.excerpt.as_deref(), Some(paragraph.repeat(3).trim()));
+
+    let article =
+      _extract_article_content(r#"<meta name="description" content="About us"><p>Hi</p>"#).unwrap();
+    assert_eq!(article.title, None);
+    assert_eq!(article.content_html, "<body><p>Hi</p></body>");
+    assert_eq!(article.excerpt.as_deref(), Some("About us"));
+  }
+
+  #[test]
+  fn test_input_and_output_limits() {
+    let html = format!("<html><body>{}</body></html>", "<p>x</p>".repeat(1000));
+
+    let mut opts = transform_opts(&html, "https://example.com/");
+    opts.max_input_bytes = Some(1024);
+    let err = _transform_html_inner(opts).unwrap_err();
+    assert!(err.downcast_ref::<InputTooLargeError>().is_some());
+    assert_eq!(
+      err.to_string(),
+      format!(
+        "Input too large: {} bytes exceeds the limit of 1024 bytes",
+        html.len()
+      )
+    );
+    // The default limit comes from FIRECRAWL_NATIVE_MAX_INPUT_BYTES.
+    assert_eq!(_max_input_bytes(None), 64 * 1024 * 1024);
+    assert_eq!(_max_input_bytes(Some(" 2048 ")), 2048);
+    assert_eq!(_max_input_bytes(Some("lots")), 64 * 1024 * 1024);
+    assert!(_check_input_size(*MAX_INPUT_BYTES + 1, None).is_err());
+    assert!(_check_input_size(*MAX_INPUT_BYTES, None).is_ok());
+
+    // Through an entry point, the limit is reported with its own status.
+    let oversized = "a".repeat(1025);
+    let options = ExtractLinksOptions {
+      max_input_bytes: Some(1024),
+      ..Default::default()
+    };
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let err = runtime
+      .block_on(extract_links(Some(oversized), Some(options)))
+      .unwrap_err();
+    assert!(matches!(err.status, napi::Status::InvalidArg));
+    assert!(err.reason.starts_with("Input too large: "));
+
+    let mut opts = transform_opts(&html, "https://example.com/");
+    opts.max_output_bytes = Some(100);
+    let (out, stats) = _transform_html_with_stats(opts).unwrap();
+    assert!(stats.truncated);
+    assert!(out.len() <= 100);
+    assert_eq!(
+      out,
+      format!("<html><body>{}</body></html>", "<p>x</p>".repeat(9))
+    );
+
+    let mut opts = transform_opts(
+      "<div><p>first paragraph</p><p>second paragraph</p></div><p>after</p>",
+      "https://example.com/",
+    );
+    opts.output = Some("body".to_string());
+    opts.max_output_bytes = Some(40);
+    let (out, stats) = _transform_html_with_stats(opts).unwrap();
+    assert!(stats.truncated);
+    assert_eq!(out, "<div><p>first paragraph</p></div>");
+
+    let mut opts = transform_opts("<p>short</p>", "https://example.com/");
+    opts.max_output_bytes = Some(1000);
+    assert!(!_transform_html_with_stats(opts).unwrap().1.truncated);
+  }
+
+  #[test]
+  fn test_transform_html_prune_empty_elements() {
+    let html = r#"<html><body>
+      <div class="wrap"><div><span> </span><nav>Menu</nav></div><p>Text</p></div>
+      <ul><li><nav>Links</nav></li></ul>
+      <div id="section-2"></div>
+      <table><tr><td></td><td>1</td></tr></table>
+      <p><br></p><div><img src="/a.png"></div><hr>
+      <svg viewBox="0 0 10 10"><g><path d="M0 0h10"></path><use href="/icons.svg"></use></g></svg>
+      <math><mi>x</mi><mspace></mspace></math>
+      <object data="/movie.swf"><param name="quality" value="high"></object>
+      <div><link rel="stylesheet" href="/a.css"></div>
+    </body></html>"#;
+
+    let mut opts = transform_opts(html, "https://example.com/");
+    opts.exclude_tags = vec!["nav".to_string()];
+    opts.output = Some("body".to_string());
+    let (out, stats) = _transform_html_with_stats(opts).unwrap();
+    let out = out.split_whitespace().collect::<String>();
+    assert_eq!(
+      out,
+      r#"<divclass="wrap"><p>Text</p></div><divid="section-2"></div><table><tbody><tr><td></td><td>1</td></tr></tbody></table><p><br></p><div><imgsrc="https://example.com/a.png"></div><hr><svgviewBox="001010"><g><pathd="M00h10"></path><usehref="/icons.svg"></use></g></svg><math><mi>x</mi><mspace></mspace></math><objectdata="/movie.swf"><paramname="quality"value="high"></object><div><linkrel="stylesheet"href="/a.css"></div>"#
+    );
+    // The inner div and span, and the ul with its li.
+    assert_eq!(stats.removed_empty_elements, 4);
+
+    let mut opts = transform_opts(html, "https://example.com/");
+    opts.exclude_tags = vec!["nav".to_string()];
+    opts.prune_keep_anchors = Some(false);
+    assert!(!_transform_html_inner(opts).unwrap().contains("section-2"));
+
+    let mut opts = transform_opts(html, "https://example.com/");
+    opts.exclude_tags = vec!["nav".to_string()];
+    opts.prune_empty_elements = Some(false);
+    assert!(_transform_html_inner(opts)
+      .unwrap()
+      .contains("<ul><li></li></ul>"));
+  }
+
+  #[test]
+  fn test_extract_metadata_amp() {
+    let amp =
+      _extract_metadata(r#"<html ⚡ lang="en"><head><title>A</title></head></html>"#).unwrap();
+    assert_eq!(amp.get("isAmp"), Some(&Value::Bool(true)));
+    assert_eq!(amp.get("ampUrl"), None);
+
+    let amp = _extract_metadata(r#"<html AMP><head></head></html>"#).unwrap();
+    assert_eq!(amp.get("isAmp"), Some(&Value::Bool(true)));
+
+    let regular = _extract_metadata(
+      r#"<html><head><link rel="canonical" href="https://example.com/news/story"><link rel="amphtml" href="amp/story"></head></html>"#,
+    )
+    .unwrap();
+    assert_eq!(regular.get("isAmp"), Some(&Value::Bool(false)));
+    assert_eq!(
+      regular.get("ampUrl"),
+      Some(&Value::String(
+        "https://example.com/news/amp/story".to_string()
+      ))
+    );
+  }
+
+  #[test]
+  fn test_extract_navigation_tree() {
+    let html = r##"<html><body>
+      <nav aria-label="Main"><ul>
+        <li><a href="/">Home</a></li>
+        <li><span>Products</span>
+          <ul class="submenu">
+            <li><a href="/products/a">Widget <b>A</b></a></li>
+            <li><a href="/products/b">Wid<i>get</i> B</a>
+              <ol><li><a href="/products/b/specs">Specs</a></li></ol>
+            </li>
+          </ul>
+        </li>
+        <li><a href="#" class="toggle">More</a></li>
+      </ul></nav>
+      <div class="footer-menu"><a href="https://other.example/terms">Terms</a> <a href="/privacy">Privacy</a></div>
+      <ul><li><a href="/not-nav">Not navigation</a></li></ul>
+    </body></html>"##;
+
+    let tree = _extract_navigation_tree(html, "https://example.com/docs/").unwrap();
+    let summary = |x: &NavNode| (x.text.clone(), x.url.clone(), x.children.len());
+    assert_eq!(
+      tree.iter().map(summary).collect::<Vec<_>>(),
+      vec![
+        (
+          "Home".to_string(),
+          Some("https://example.com/".to_string()),
+          0
+        ),
+        ("Products".to_string(), None, 2),
+        ("More".to_string(), None, 0),
+        (
+          "Terms".to_string(),
+          Some("https://other.example/terms".to_string()),
+          0
+        ),
+        (
+          "Privacy".to_string(),
+          Some("https://example.com/privacy".to_string()),
+          0
+        ),
+      ]
+    );
+
+    let products = &tree[1].children;
+    assert_eq!(products[0].text, "Widget A");
+    // Markup inside a word doesn't split it.
+    assert_eq!(products[1].text, "Widget B");
+    assert_eq!(
+      products[1].url.as_deref(),
+      Some("https://example.com/products/b")
+    );
+    assert_eq!(products[1].children[0].text, "Specs");
+    assert!(products[1].children[0].children.is_empty());
+  }
+
+  #[test]
+  fn test_invalid_selectors_are_reported_and_skipped() {
+    let html =
+      r#"<html><body><div class="a"><p>A</p></div><div class="b"><p>B</p></div></body></html>"#;
+
+    let mut opts = transform_opts(html, "https://example.com/");
+    opts.include_tags = vec![".a".to_string(), "p[".to_string()];
+    opts.exclude_tags = vec!["div:nth(".to_string(), "p[".to_string()];
+    let (out, stats) = _transform_html_with_stats(opts).unwrap();
+    assert!(out.contains("<p>A</p>"));
+    assert!(!out.contains("<p>B</p>"));
+    assert_eq!(stats.invalid_selectors, ["p[", "div:nth("]);
+
+    let options = ExtractAttributesOptions {
+      selectors: vec![
+        AttributeSelector {
+          selector: "div[".to_string(),
+          attribute: "class".to_string(),
+        },
+        AttributeSelector {
+          selector: "div".to_string(),
+          attribute: "class".to_string(),
+        },
+      ],
+      timeout_ms: None,
+    };
+    let results = _extract_attributes(html, &options).unwrap();
+    assert!(results[0].values.is_empty());
+    assert_eq!(results[0].invalid_selectors, ["div["]);
+    assert_eq!(results[1].values, ["a", "b"]);
+    assert!(results[1].invalid_selectors.is_empty());
+    let mut opts = transform_opts(html, "https://example.com/");
+    opts.include_tags = vec!["p[".to_string()];
+    let (out, stats) = _transform_html_with_stats(opts).unwrap();
+    assert!(out.contains("<p>A</p>"));
+    assert!(out.contains("<p>B</p>"));
+    assert_eq!(stats.invalid_selectors, ["p["]);
+  }
+
+  #[test]
+  fn test_extract_language_alternates() {
+    let html = r#"<html lang="en-us"><head>
+      <meta http-equiv="Content-Language" content="en-US, fr">
+      <meta property="og:locale" content="en_US">
+      <meta property="og:locale:alternate" content="pt_BR">
+      <link rel="alternate" hreflang="en-US" href="/en/">
+      <link rel="alternate" hreflang="pt-br" href="https://example.com/pt/">
+      <link rel="alternate" hreflang="zh-hant-tw" href="/zh/">
+      <link rel="alternate" hreflang="x-default" href="/">
+    </head></html>"#;
+
+    let signals = _extract_language_alternates(html, "https://example.com/page").unwrap();
+    let summary: Vec<_> = signals
+      .iter()
+      .map(|x| (x.lang.as_str(), x.source.as_str(), x.url.as_deref()))
+      .collect();
+    assert_eq!(
+      summary,
+      vec![
+        (
+          "en-US",
+          "html_lang,content_language,og_locale,hreflang",
+          Some("https://example.com/en/")
+        ),
+        ("fr", "content_language", None),
+        (
+          "pt-BR",
+          "og_locale_alternate,hreflang",
+          Some("https://example.com/pt/")
+        ),
+        ("zh-Hant-TW", "hreflang", Some("https://example.com/zh/")),
+        ("x-default", "hreflang", Some("https://example.com/")),
+      ]
+    );
+    assert_eq!(
+      _normalize_language_tag("sr-latn-rs").as_deref(),
+      Some("sr-Latn-RS")
+    );
+    assert_eq!(
+      _normalize_language_tag("de-1996").as_deref(),
+      Some("de-1996")
+    );
+    assert_eq!(_normalize_language_tag("en-éé").as_deref(), Some("en-éé"));
+    assert_eq!(
+      _normalize_language_tag("en-中文").as_deref(),
+      Some("en-中文")
+    );
+  }
+
+  #[test]
+  fn test_transform_html_reports_omce_matches() {
+    let html = r#"<html><body><main><aside>Related links</aside><p>Story text</p><aside>More links</aside></main></body></html>"#;
+    let document = parse_html().one(html);
+    let aside = document.select_first("aside").unwrap();
+    let matched = get_node_signature(aside.as_node(), SignatureMode::Exact);
+    let stale = "sig:exact:stale".to_string();
+
+    let mut opts = transform_opts(html, "https://example.com/");
+    opts.only_main_content = true;
+    opts.omce_signatures = Some(vec![stale.clone(), matched.clone(), stale.clone()]);
+    let (out, stats) = _transform_html_with_stats(opts).unwrap();
+
+    assert!(!out.contains("links"));
+    assert!(out.contains("Story text"));
+    let matches: Vec<_> = stats
+      .omce_matches
+      .iter()
+      .map(|x| (x.signature.as_str(), x.removed_count))
+      .collect();
+    assert_eq!(matches, vec![(stale.as_str(), 0), (matched.as_str(), 2)]);
+    assert_eq!(stats.removed_omce, 2);
+  }
+
+  #[test]
+  fn test_compute_boilerplate_candidates() {
+    let footer_text = "Copyright Example Inc. All rights reserved. Terms and privacy.";
+    let article_text = "A long article paragraph that is the real content of the page. ".repeat(4);
+    let html = format!(
+      r#"<html><body><div id="page"><div class="wrap"><article><p>{article_text}</p><p>{article_text}</p></article></div>
+      <footer><p>{footer_text}</p></footer></div><p>short</p></body></html>"#
+    );
+
+    let candidates =
+      _compute_boilerplate_candidates(&html, "exact", BoilerplateCandidateOptions::default())
+        .unwrap();
+    let summary: Vec<_> = candidates
+      .iter()
+      .map(|x| (x.tag_name.as_str(), x.depth))
+      .collect();
+    // div#page and the article hold two candidates each, so they're reported; html,
+    // body, div.wrap and the footer only wrap a single block and give way to it.
+    assert_eq!(
+      summary,
+      vec![("div", 2), ("article", 4), ("p", 5), ("p", 5), ("p", 4)]
+    );
+    assert_eq!(candidates[4].text_length, footer_text.len() as u32);
+
+    let document = _parse_html(&html).unwrap();
+    let footer_p = document.select_first("footer p").unwrap();
+    assert_eq!(
+      candidates[4].signature,
+      get_node_signature(footer_p.as_node(), SignatureMode::Exact)
+    );
+
+    let options = BoilerplateCandidateOptions {
+      min_text_length: Some(1000),
+    };
+    assert!(_compute_boilerplate_candidates(&html, "exact", options)
+      .unwrap()
+      .is_empty());
+    assert!(_compute_boilerplate_candidates(
+      &html,
+      "fuzzy",
+      BoilerplateCandidateOptions::default()
+    )
+    .is_err());
+  }
+
+  #[test]
+  fn test_extract_forms_values_and_association() {
+    let html = r#"<html><body>
+      <form id="filters" action="/search">
+        <select name="brand" multiple>
+          <option value="acme" selected>Acme</option>
+          <option>Globex</option>
+          <option value="initech" selected>Initech</option>
+        </select>
+        <input type="checkbox" name="in_stock" checked>
+        <textarea name="notes">Blue only</textarea>
+        <input type="radio" name="sort" value="price">
+      </form>
+      <input name="q" form="filters" value="kettle">
+      <form id="other"><input name="moved" form="missing"></form>
+      <input type="search" name="site_search" placeholder="Search">
+    </body></html>"#;
+
+    let forms = _extract_forms(html, "https://example.com/").unwrap();
+    assert_eq!(forms.len(), 3);
+
+    let filters = &forms[0];
+    assert!(!filters.unassociated);
+    let names: Vec<_> = filters.fields.iter().map(|x| x.name.as_deref()).collect();
+    assert_eq!(
+      names,
+      vec![
+        Some("brand"),
+        Some("in_stock"),
+        Some("notes"),
+        Some("sort"),
+        Some("q")
+      ]
+    );
+    let brand = &filters.fields[0];
+    assert!(brand.multiple);
+    assert_eq!(brand.options, vec!["acme", "Globex", "initech"]);
+    assert_eq!(brand.value.as_deref(), Some("acme"));
+    assert_eq!(brand.selected_values, vec!["acme", "initech"]);
+    assert_eq!(filters.fields[1].value.as_deref(), Some("on"));
+    assert!(filters.fields[1].checked);
+    assert_eq!(filters.fields[2].value.as_deref(), Some("Blue only"));
+    assert!(!filters.fields[2].checked);
+    assert_eq!(filters.fields[3].value.as_deref(), Some("price"));
+    assert!(!filters.fields[3].checked);
+    assert_eq!(filters.fields[4].value.as_deref(), Some("kettle"));
+
+    assert!(forms[1].fields.is_empty());
+
+    let unassociated = &forms[2];
+    assert!(unassociated.unassociated);
+    assert_eq!(unassociated.action, None);
+    let names: Vec<_> = unassociated
+      .fields
+      .iter()
+      .map(|x| (x.name.as_deref(), x.field_type.as_str()))
+      .collect();
+    assert_eq!(
+      names,
+      vec![(Some("moved"), "text"), (Some("site_search"), "search")]
+    );
+  }
+
+  #[test]
+  fn test_extract_iframe_embeds() {
+    let html = r#"<html><body>
+      <iframe src="//www.youtube-nocookie.com/embed/dQw4w9WgXcQ" width="560" height="315"></iframe>
+      <iframe src="https://www.google.com/maps/embed?pb=abc" width="100%"></iframe>
+      <iframe src="about:blank" data-src="https://player.vimeo.com/video/76979871"></iframe>
+      <iframe data-src="/widgets/chat"></iframe>
+      <iframe src="https://www.google.com/recaptcha/api2/anchor"></iframe>
+      <iframe src="https://fast.wistia.net/embed/iframe/abc123"></iframe>
+      <iframe src="https://open.spotify.com/embed/track/1"></iframe>
+      <iframe src="javascript:void(0)"></iframe>
+      <iframe></iframe>
+    </body></html>"#;
+
+    let embeds = _extract_iframe_embeds(html, "http://example.com/page").unwrap();
+    let summary: Vec<_> = embeds
+      .iter()
+      .map(|x| (x.url.as_str(), x.provider.as_deref()))
+      .collect();
+    assert_eq!(
+      summary,
+      vec![
+        (
+          "http://www.youtube-nocookie.com/embed/dQw4w9WgXcQ",
+          Some("youtube")
+        ),
+        (
+          "https://www.google.com/maps/embed?pb=abc",
+          Some("google_maps")
+        ),
+        ("https://player.vimeo.com/video/76979871", Some("vimeo")),
+        ("http://example.com/widgets/chat", None),
+        ("https://www.google.com/recaptcha/api2/anchor", None),
+        (
+          "https://fast.wistia.net/embed/iframe/abc123",
+          Some("wistia")
+        ),
+        ("https://open.spotify.com/embed/track/1", Some("spotify")),
+      ]
+    );
+    assert_eq!(embeds[0].width.as_deref(), Some("560"));
+    assert_eq!(embeds[0].height.as_deref(), Some("315"));
+    assert_eq!(embeds[1].width.as_deref(), Some("100%"));
+    assert_eq!(embeds[1].height, None);
+  }
+
+  #[test]
+  fn test_extract_code_blocks() {
+    let html = r#"<html><body>
+<p>Run <code>cargo build</code> first.</p>
+<pre class="lang-rust"><code>fn main() {
+    println!("hi");
+}</code></pre>
+<pre><code class="language-js hljs">let x = 1;</code></pre>
+<pre><code>plain</code></pre>
+<code>  </code>
+</body></html>"#;
+
+    let blocks = _extract_code_blocks(html).unwrap();
+    let summary: Vec<(&str, Option<&str>, bool)> = blocks
+      .iter()
+      .map(|x| (x.code.as_str(), x.language.as_deref(), x.is_block))
+      .collect();
+    assert_eq!(
+      summary,
+      vec![
+        ("cargo build", None, false),
+        ("fn main() {\n    println!(\"hi\");\n}", Some("rust"), true),
+        ("let x = 1;", Some("js"), true),
+        ("plain", None, true),
+      ]
+    );
+  }
+
+  #[test]
+  fn test_extract_definition_lists() {
+    let html = r#"<html><body>
+<dl>
+  <dt>HTML</dt>
+  <dd>HyperText
+      Markup Language</dd>
+  <dt>Colour</dt><dt>Color</dt>
+  <dd>A visual property.</dd>
+  <dd>Also a verb.</dd>
+  <div><dt>Tea</dt><dd>A drink.</dd></div>
+  <dt>Orphan</dt>
+</dl>
+</body></html>"#;
+
+    let items = _extract_definition_lists(html).unwrap();
+    let summary: Vec<(&str, Vec<&str>)> = items
+      .iter()
+      .map(|x| {
+        (
+          x.term.as_str(),
+          x.definitions.iter().map(String::as_str).collect(),
+        )
+      })
+      .collect();
+    assert_eq!(
+      summary,
+      vec![
+        ("HTML", vec!["HyperText Markup Language"]),
+        ("Colour", vec!["A visual property.", "Also a verb."]),
+        ("Color", vec!["A visual property.", "Also a verb."]),
+        ("Tea", vec!["A drink."]),
+        ("Orphan", vec![]),
+      ]
+    );
+  }
+
+  #[test]
+  fn test_from_bytes_wrappers() {
+    // A windows-1251 page declared only in the Content-Type header: "Привет" in the
+    // title and "Главная" in the link.
+    let mut body = b"<html><head><title>\xcf\xf0\xe8\xe2\xe5\xf2</title></head><body>".to_vec();
+    body.extend_from_slice(
+      b"<p><a href=\"/\xe3\">\xc3\xeb\xe0\xe2\xed\xe0\xff</a><img src=\"/logo.png\"></p></body></html>",
+    );
+    let header = || Some("text/html; charset=windows-1251".to_string());
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+
+    let links = runtime
+      .block_on(extract_links_from_bytes(
+        body.clone().into(),
+        header(),
+        None,
+      ))
+      .unwrap();
+    assert_eq!(links.charset, "windows-1251");
+    assert_eq!(links.links, vec!["/г".to_string()]);
+
+    let metadata = runtime
+      .block_on(extract_metadata_from_bytes(body.clone().into(), header()))
+      .unwrap();
+    assert_eq!(metadata.charset, "windows-1251");
+    assert_eq!(
+      metadata.metadata.get("title"),
+      Some(&Value::String("Привет".to_string()))
+    );
+
+    let images = runtime
+      .block_on(extract_images_from_bytes(
+        body.clone().into(),
+        header(),
+        "https://example.com/".to_string(),
+      ))
+      .unwrap();
+    assert_eq!(images.images, vec!["https://example.com/logo.png"]);
+
+    let transformed = runtime
+      .block_on(transform_html_from_bytes(
+        body.clone().into(),
+        header(),
+        transform_opts("ignored", "https://example.com/"),
+      ))
+      .unwrap();
+    assert_eq!(transformed.charset, "windows-1251");
+    assert!(transformed.html.contains("Главная"));
+    assert!(!transformed.html.contains("ignored"));
+
+    // Without a header or declaration, UTF-8 is detected from the bytes themselves.
+    let links = runtime
+      .block_on(extract_links_from_bytes(
+        "<a href=\"/café\">Café</a>".as_bytes().to_vec().into(),
+        None,
+        None,
+      ))
+      .unwrap();
+    assert_eq!(links.charset, "UTF-8");
+    assert_eq!(links.links, vec!["/café".to_string()]);
+
+    // The size limit is checked before the body is decoded.
+    let options = ExtractLinksOptions {
+      max_input_bytes: Some(16),
+      ..Default::default()
+    };
+    let err = runtime
+      .block_on(extract_links_from_bytes(
+        body.into(),
+        header(),
+        Some(options),
+      ))
+      .err()
+      .unwrap();
+    assert!(matches!(err.status, napi::Status::InvalidArg));
+  }
 }
@@ -4,15 +4,19 @@ pub use crate::crawler::*;
 pub use crate::engpicker::*;
 pub use crate::html::*;
 pub use crate::pdf::*;
+pub use crate::robots::*;
 pub use crate::utils::*;
 
 pub use crate::document::{DocumentConverter, DocumentType};
 
+mod charset;
 mod crawler;
 mod document;
 mod engpicker;
 mod html;
 mod pdf;
+mod readability;
+mod robots;
 mod utils;
 
 pub use napi::bindgen_prelude::*;
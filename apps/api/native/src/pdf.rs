@@ -1,9 +1,19 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::sync::LazyLock;
+
+use chrono::{FixedOffset, NaiveDate, NaiveTime, SecondsFormat};
+use lopdf::{decode_text_string, Dictionary, Document, Object, ObjectId};
 use napi::bindgen_prelude::*;
 use napi_derive::napi;
 use pdf_inspector::{
   PdfOptions, PdfType,
   process_pdf_with_options as rust_process_pdf,
 };
+use regex::Regex;
+use serde::Serialize;
+use tokio::task;
 
 #[napi(object)]
 pub struct PdfProcessResult {
@@ -26,6 +36,74 @@ fn pdf_type_str(t: PdfType) -> &'static str {
   }
 }
 
+/// Error reason prefix for encrypted documents opened without a password.
+const PDF_PASSWORD_REQUIRED: &str = "PDF_PASSWORD_REQUIRED";
+/// Error reason prefix for encrypted documents opened with the wrong password.
+const PDF_INVALID_PASSWORD: &str = "PDF_INVALID_PASSWORD";
+
+/// lopdf drops `/Encrypt` from the trailer once it has decrypted a document, so check
+/// both the trailer and whether decryption happened.
+fn is_encrypted(document: &Document) -> bool {
+  document.encryption_state.is_some() || document.trailer.has(b"Encrypt")
+}
+
+/// Load a document, decrypting it if needed. The empty user password is always tried
+/// first, which opens documents that only carry an owner password.
+fn load_pdf(path: &str, password: Option<&str>) -> Result<Document> {
+  let document = match password {
+    Some(password) => Document::load_with_password(path, password),
+    None => Document::load(path),
+  }
+  .map_err(|e| match e {
+    lopdf::Error::InvalidPassword => Error::new(
+      Status::InvalidArg,
+      format!("{PDF_INVALID_PASSWORD}: The password for this PDF is incorrect"),
+    ),
+    e => Error::new(Status::GenericFailure, format!("Failed to load PDF: {e}")),
+  })?;
+
+  // lopdf leaves the objects unparsed when it can't decrypt the document.
+  if document.trailer.has(b"Encrypt") && document.encryption_state.is_none() {
+    return Err(Error::new(
+      Status::InvalidArg,
+      format!("{PDF_PASSWORD_REQUIRED}: This PDF is encrypted and requires a password"),
+    ));
+  }
+
+  Ok(document)
+}
+
+/// pdf-inspector can't decrypt, so an encrypted document is decrypted with lopdf and
+/// written to a temporary copy for it. Returns `None` when `path` can be used as it
+/// is. Unencrypted files are only checked with `trailer_has_encrypt`, which parses no
+/// objects, so pdf-inspector's run is the only parse; encrypted ones are loaded in
+/// full once to decrypt them.
+fn inspector_input(path: &str, password: Option<&str>) -> Result<Option<tempfile::NamedTempFile>> {
+  // A file that can't be read is left for pdf-inspector to report on.
+  if !trailer_has_encrypt(path).unwrap_or(false) {
+    return Ok(None);
+  }
+
+  let document = load_pdf(path, password)?;
+  let pages = document.get_pages().len() as u32;
+  write_page_range(&document, 1, pages).map(Some)
+}
+
+/// Run pdf-inspector on `path`, decrypting it first when needed.
+fn run_inspector(
+  path: &str,
+  password: Option<&str>,
+  options: PdfOptions,
+  context: &str,
+) -> Result<pdf_inspector::PdfProcessResult> {
+  let file = inspector_input(path, password)?;
+  let path = file
+    .as_ref()
+    .map_or_else(|| path.into(), |x| x.path().to_string_lossy());
+  rust_process_pdf(&path, options)
+    .map_err(|e| Error::new(Status::GenericFailure, format!("{context}: {e}")))
+}
+
 fn to_napi_result(result: pdf_inspector::PdfProcessResult) -> PdfProcessResult {
   PdfProcessResult {
     pdf_type: pdf_type_str(result.pdf_type).to_string(),
@@ -40,28 +118,2424 @@ fn to_napi_result(result: pdf_inspector::PdfProcessResult) -> PdfProcessResult {
 }
 
 /// Process a PDF file: detect type, extract text + markdown if text-based.
+/// `password` opens encrypted documents.
 #[napi]
-pub fn process_pdf(path: String) -> Result<PdfProcessResult> {
-  let result = rust_process_pdf(&path, PdfOptions::new()).map_err(|e| {
-    Error::new(
-      Status::GenericFailure,
-      format!("Failed to process PDF: {e}"),
-    )
-  })?;
+pub fn process_pdf(path: String, password: Option<String>) -> Result<PdfProcessResult> {
+  let result = run_inspector(
+    &path,
+    password.as_deref(),
+    PdfOptions::new(),
+    "Failed to process PDF",
+  )?;
 
   Ok(to_napi_result(result))
 }
 
 /// Fast metadata-only detection: page count, title, type, confidence.
 /// Skips text extraction, markdown generation, and layout analysis.
+/// `password` opens encrypted documents.
+#[napi]
+pub fn detect_pdf(path: String, password: Option<String>) -> Result<PdfProcessResult> {
+  let result = run_inspector(
+    &path,
+    password.as_deref(),
+    PdfOptions::detect_only(),
+    "Failed to detect PDF",
+  )?;
+
+  Ok(to_napi_result(result))
+}
+
+#[derive(Default)]
+#[napi(object)]
+pub struct PdfExtractionOptions {
+  /// First page to extract, 1-based and inclusive. Defaults to the first page.
+  pub start_page: Option<i32>,
+  /// Last page to extract, 1-based and inclusive. Defaults to the last page.
+  pub end_page: Option<i32>,
+  /// Password for encrypted documents.
+  pub password: Option<String>,
+  /// Text quality score below which the output is flagged as likely garbled.
+  /// Defaults to 0.5.
+  pub garbled_threshold: Option<f64>,
+  /// Drop headers, footers and page numbers repeated across pages. Only the first and
+  /// last lines of each page are considered. Defaults to false.
+  pub remove_repeated_lines: Option<bool>,
+  /// Rejoin words hyphenated across lines and merge hard-wrapped lines into
+  /// paragraphs. Defaults to true.
+  pub reflow: Option<bool>,
+  /// Put `---` and a `<!-- page N -->` comment before the content of every page but
+  /// the first, so the markdown can be split by page. Defaults to false.
+  pub insert_page_breaks: Option<bool>,
+}
+
+#[napi(object)]
+pub struct PdfExtractionResult {
+  pub pdf_type: String,
+  pub markdown: Option<String>,
+  /// Total number of pages in the document, regardless of the extracted range.
+  pub page_count: i32,
+  pub pages_extracted: i32,
+  pub processing_time_ms: f64,
+  pub title: Option<String>,
+  /// Quality of the extracted text from 0 (garbage) to 1, see `assess_pdf_text_quality`.
+  pub text_quality_score: f64,
+  /// Whether the text looks like mojibake and the document should be OCRed instead.
+  pub likely_garbled: bool,
+  /// Distinct lines dropped by `remove_repeated_lines`, in order of first removal.
+  pub removed_boilerplate: Vec<String>,
+  /// 1-based pages whose start couldn't be found when splitting the markdown per page,
+  /// such as scanned ones; their content, if any, is left with the page before. Only
+  /// filled when `insert_page_breaks` or `remove_repeated_lines` is set.
+  pub unlocated_pages: Vec<i32>,
+}
+
+#[napi(object)]
+pub struct PdfTextQuality {
+  /// Overall quality from 0 (garbage) to 1.
+  pub score: f64,
+  pub likely_garbled: bool,
+  /// Share of U+FFFD replacement characters among non-whitespace characters.
+  pub replacement_char_ratio: f64,
+  /// Share of control and private-use characters among non-whitespace characters.
+  pub non_printable_ratio: f64,
+  /// Share of words found in a small list of common English words.
+  pub dictionary_hit_rate: f64,
+  pub average_token_length: f64,
+}
+
+const DEFAULT_GARBLED_THRESHOLD: f64 = 0.5;
+
+/// Frequent English words, enough to tell real prose from symbol-font mojibake.
+const COMMON_WORDS: [&str; 100] = [
+  "the", "of", "and", "to", "a", "in", "is", "it", "you", "that", "he", "was", "for", "on", "are",
+  "with", "as", "i", "his", "they", "be", "at", "one", "have", "this", "from", "or", "had", "by",
+  "not", "but", "what", "all", "were", "we", "when", "your", "can", "said", "there", "use", "an",
+  "each", "which", "she", "do", "how", "their", "if", "will", "up", "other", "about", "out",
+  "many", "then", "them", "these", "so", "some", "her", "would", "make", "like", "him", "into",
+  "time", "has", "look", "two", "more", "go", "see", "no", "way", "could", "people", "my", "than",
+  "first", "been", "who", "its", "now", "may", "only", "new", "also", "after", "any", "our",
+  "over", "such", "year", "most", "should", "under", "between", "both", "page",
+];
+
+fn is_non_printable(c: char) -> bool {
+  (c.is_control() && !c.is_whitespace())
+    || matches!(
+      c,
+      '\u{E000}'..='\u{F8FF}' | '\u{F0000}'..='\u{FFFFD}' | '\u{100000}'..='\u{10FFFD}'
+    )
+}
+
+fn _assess_text_quality(text: &str, threshold: f64) -> PdfTextQuality {
+  let characters = text.chars().filter(|c| !c.is_whitespace()).count();
+  let ratio = |count: usize| {
+    if characters == 0 {
+      0.0
+    } else {
+      count as f64 / characters as f64
+    }
+  };
+  let replacement_char_ratio = ratio(text.chars().filter(|&c| c == '\u{FFFD}').count());
+  let non_printable_ratio = ratio(text.chars().filter(|&c| is_non_printable(c)).count());
+
+  let tokens: Vec<&str> = text.split_whitespace().collect();
+  let average_token_length = if tokens.is_empty() {
+    0.0
+  } else {
+    tokens.iter().map(|x| x.chars().count()).sum::<usize>() as f64 / tokens.len() as f64
+  };
+
+  let words: Vec<String> = tokens
+    .iter()
+    .map(|x| {
+      x.trim_matches(|c: char| !c.is_alphanumeric())
+        .to_lowercase()
+    })
+    .filter(|x| !x.is_empty())
+    .collect();
+  let dictionary_hit_rate = if words.is_empty() {
+    0.0
+  } else {
+    words
+      .iter()
+      .filter(|x| COMMON_WORDS.contains(&x.as_str()))
+      .count() as f64
+      / words.len() as f64
+  };
+
+  // Broken characters weigh most; the word-level signals only count once there is
+  // enough text to judge, and are capped so non-English documents aren't flagged on
+  // their own.
+  let mut score = 1.0;
+  score -= (replacement_char_ratio * 5.0).min(1.0) * 0.4;
+  score -= (non_printable_ratio * 5.0).min(1.0) * 0.4;
+  if words.len() >= 20 {
+    score -= (1.0 - (dictionary_hit_rate / 0.15).min(1.0)) * 0.2;
+  }
+  if tokens.len() >= 20 && !(2.0..=15.0).contains(&average_token_length) {
+    score -= 0.1;
+  }
+  let score = if characters == 0 {
+    0.0
+  } else {
+    score.clamp(0.0, 1.0)
+  };
+
+  PdfTextQuality {
+    score,
+    likely_garbled: score < threshold,
+    replacement_char_ratio,
+    non_printable_ratio,
+    dictionary_hit_rate,
+    average_token_length,
+  }
+}
+
+/// Score extracted PDF text for signs of broken encoding (missing ToUnicode maps,
+/// symbol fonts), so callers can fall back to OCR.
 #[napi]
-pub fn detect_pdf(path: String) -> Result<PdfProcessResult> {
-  let result = rust_process_pdf(&path, PdfOptions::detect_only()).map_err(|e| {
+pub fn assess_pdf_text_quality(text: String, threshold: Option<f64>) -> PdfTextQuality {
+  _assess_text_quality(&text, threshold.unwrap_or(DEFAULT_GARBLED_THRESHOLD))
+}
+
+static PAGE_NUMBER_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+  Regex::new(r"(?i)^[-–—\s]*(?:page\s+)?\d{1,4}(?:\s*(?:of|/)\s*\d{1,4})?[-–—\s]*$")
+    .expect("PAGE_NUMBER_REGEX is a valid static regex pattern")
+});
+
+/// Number of non-empty lines at the top and bottom of each page checked for headers
+/// and footers.
+const BOILERPLATE_EDGE_LINES: usize = 2;
+
+/// Repeated lines are only detected in documents with at least this many pages.
+const MIN_BOILERPLATE_PAGES: usize = 3;
+
+/// Comparison key for header/footer detection: markdown emphasis and heading markers
+/// are dropped, digits are masked so "Page 3 of 9" matches "Page 4 of 9", and case
+/// and whitespace are normalized.
+fn boilerplate_key(line: &str) -> String {
+  let line = line
+    .trim()
+    .trim_start_matches(['#', '>'])
+    .trim_matches(['*', '_', ' ']);
+  line
+    .split_whitespace()
+    .collect::<Vec<_>>()
+    .join(" ")
+    .to_lowercase()
+    .chars()
+    .map(|c| if c.is_ascii_digit() { '#' } else { c })
+    .collect()
+}
+
+/// Keys of lines found near the top or bottom of more than half of `page_texts`.
+fn repeated_page_lines(page_texts: &[String]) -> HashSet<String> {
+  if page_texts.len() < MIN_BOILERPLATE_PAGES {
+    return HashSet::new();
+  }
+
+  let mut counts: HashMap<String, usize> = HashMap::new();
+  for text in page_texts {
+    let lines: Vec<&str> = text.lines().filter(|x| !x.trim().is_empty()).collect();
+    let head = lines.iter().take(BOILERPLATE_EDGE_LINES);
+    let tail = lines
+      .iter()
+      .skip(BOILERPLATE_EDGE_LINES)
+      .rev()
+      .take(BOILERPLATE_EDGE_LINES);
+    // Count each key once per page so a line repeated within a page doesn't qualify.
+    let keys: HashSet<String> = head
+      .chain(tail)
+      .map(|x| boilerplate_key(x))
+      .filter(|x| !x.is_empty())
+      .collect();
+    for key in keys {
+      *counts.entry(key).or_default() += 1;
+    }
+  }
+
+  counts
+    .into_iter()
+    .filter(|(_, count)| count * 2 > page_texts.len())
+    .map(|(key, _)| key)
+    .collect()
+}
+
+/// Remove lines matching `repeated` keys and standalone page numbers from the top and
+/// bottom `BOILERPLATE_EDGE_LINES` lines of a single page's `markdown`, returning the
+/// cleaned markdown and the distinct removed lines. Matching lines elsewhere on the
+/// page, such as a year or a table value, are content and are kept.
+fn remove_boilerplate_lines(markdown: &str, repeated: &HashSet<String>) -> (String, Vec<String>) {
+  let mut kept: Vec<&str> = Vec::new();
+  let mut removed: Vec<String> = Vec::new();
+  let mut skip_blank = false;
+  let content_lines = markdown.lines().filter(|x| !x.trim().is_empty()).count();
+  let mut content_index = 0;
+
+  for line in markdown.lines() {
+    let trimmed = line.trim();
+    let at_edge = !trimmed.is_empty() && {
+      content_index += 1;
+      content_index <= BOILERPLATE_EDGE_LINES
+        || content_index + BOILERPLATE_EDGE_LINES > content_lines
+    };
+    if at_edge
+      && (PAGE_NUMBER_REGEX.is_match(trimmed) || repeated.contains(&boilerplate_key(trimmed)))
+    {
+      if !removed.iter().any(|x| x == trimmed) {
+        removed.push(trimmed.to_string());
+      }
+      skip_blank = true;
+      continue;
+    }
+    // Don't leave a double blank line where a removed line used to be.
+    if trimmed.is_empty() && skip_blank && kept.last().is_none_or(|x| x.trim().is_empty()) {
+      continue;
+    }
+    skip_blank = false;
+    kept.push(line);
+  }
+  // Nor a trailing one before a removed footer.
+  if skip_blank {
+    while kept.last().is_some_and(|x| x.trim().is_empty()) {
+      kept.pop();
+    }
+  }
+
+  let mut out = kept.join("\n");
+  if markdown.ends_with('\n') && !out.is_empty() {
+    out.push('\n');
+  }
+  (out, removed)
+}
+
+static LIST_ITEM_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+  Regex::new(r"^\s*(?:[-*+•]|\d{1,3}[.)])\s")
+    .expect("LIST_ITEM_REGEX is a valid static regex pattern")
+});
+
+/// Paragraphs whose longest line is shorter than this are left alone, which keeps
+/// poetry, addresses and other deliberately short lines intact.
+const MIN_REFLOW_WIDTH: usize = 40;
+
+/// A line is treated as wrapped when it fills at least this share of the paragraph's
+/// longest line.
+const REFLOW_FILL_RATIO: f64 = 0.7;
+
+/// Lines that are never merged with their neighbours.
+fn is_structural_line(line: &str) -> bool {
+  let trimmed = line.trim_start();
+  line.starts_with("    ")
+    || line.starts_with('\t')
+    || trimmed.starts_with('#')
+    || trimmed.starts_with('|')
+    || trimmed.starts_with('>')
+    || trimmed.starts_with("---")
+    || trimmed.starts_with("***")
+}
+
+fn is_code_like(line: &str) -> bool {
+  line.trim_end().ends_with(['{', '}', ';'])
+}
+
+/// Whether `line` ends in a word broken with a hyphen, like "extrac-".
+fn ends_with_broken_word(line: &str) -> bool {
+  let mut chars = line.trim_end().chars().rev();
+  chars.next() == Some('-') && chars.next().is_some_and(|c| c.is_alphabetic())
+}
+
+fn reflow_paragraph(lines: &[&str], out: &mut Vec<String>) {
+  let width = lines
+    .iter()
+    .map(|x| x.trim_end().chars().count())
+    .max()
+    .unwrap_or(0);
+  let mut current: Option<String> = None;
+  let mut previous = "";
+
+  for &line in lines {
+    let next = line.trim();
+    current = match current {
+      Some(mut merged)
+        if ends_with_broken_word(previous)
+          && next.chars().next().is_some_and(|c| c.is_lowercase()) =>
+      {
+        merged.truncate(merged.trim_end().len() - 1);
+        merged.push_str(next);
+        Some(merged)
+      }
+      Some(mut merged)
+        if width >= MIN_REFLOW_WIDTH
+          && previous.trim_end().chars().count() as f64 >= width as f64 * REFLOW_FILL_RATIO
+          && !is_code_like(previous)
+          && !is_code_like(next) =>
+      {
+        merged.truncate(merged.trim_end().len());
+        // Keep real compounds such as "Anglo-\nSaxon" together.
+        if !ends_with_broken_word(previous) {
+          merged.push(' ');
+        }
+        merged.push_str(next);
+        Some(merged)
+      }
+      Some(merged) => {
+        out.push(merged);
+        Some(line.to_string())
+      }
+      None => Some(line.to_string()),
+    };
+    previous = line;
+  }
+
+  out.extend(current);
+}
+
+/// Undo the physical line breaks of the PDF layout: words hyphenated across lines are
+/// rejoined and wrapped lines are merged into paragraphs. Blank lines, headings, list
+/// items, tables, quotes and code are kept as they are.
+fn reflow_markdown(markdown: &str) -> String {
+  let mut out: Vec<String> = Vec::new();
+  let mut paragraph: Vec<&str> = Vec::new();
+  let mut in_fence = false;
+
+  for line in markdown.lines() {
+    let trimmed = line.trim_start();
+    if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+      reflow_paragraph(&paragraph, &mut out);
+      paragraph.clear();
+      in_fence = !in_fence;
+      out.push(line.to_string());
+    } else if in_fence || line.trim().is_empty() || is_structural_line(line) {
+      reflow_paragraph(&paragraph, &mut out);
+      paragraph.clear();
+      out.push(line.to_string());
+    } else {
+      // A list item starts a new paragraph, so it can absorb its own continuation
+      // lines but is never appended to the line before it.
+      if LIST_ITEM_REGEX.is_match(line) {
+        reflow_paragraph(&paragraph, &mut out);
+        paragraph.clear();
+      }
+      paragraph.push(line);
+    }
+  }
+  reflow_paragraph(&paragraph, &mut out);
+
+  let mut text = out.join("\n");
+  if markdown.ends_with('\n') && !text.is_empty() {
+    text.push('\n');
+  }
+  text
+}
+
+/// Resolve the requested 1-based page range against the document's pages.
+fn resolve_page_range(
+  pages: &BTreeMap<u32, ObjectId>,
+  options: &PdfExtractionOptions,
+) -> Result<(u32, u32)> {
+  let page_count = pages.len() as i32;
+  let start = options.start_page.unwrap_or(1);
+  let end = options.end_page.unwrap_or(page_count).min(page_count);
+
+  if start < 1 || start > page_count || end < start {
+    return Err(Error::new(
+      Status::InvalidArg,
+      format!("Invalid page range {start}..{end} for a document with {page_count} pages"),
+    ));
+  }
+
+  Ok((start as u32, end as u32))
+}
+
+/// Separator put before the markdown of page `page` (1-based) by `insert_page_breaks`.
+fn page_break(page: u32) -> String {
+  format!("\n\n---\n\n<!-- page {page} -->\n\n")
+}
+
+/// Join the markdown of consecutive pages, starting at `first_page`, with page-break
+/// markers between them.
+fn join_pages(first_page: u32, pages: &[String]) -> String {
+  let mut out = String::new();
+  for (i, markdown) in pages.iter().enumerate() {
+    if i > 0 {
+      out.push_str(&page_break(first_page + i as u32));
+    }
+    out.push_str(markdown.trim());
+  }
+  out
+}
+
+/// Letters and digits of `text`, lowercased, so text from lopdf and pdf-inspector's
+/// markdown can be compared regardless of spacing and markup.
+fn alphanumeric_key(text: &str) -> String {
+  text
+    .chars()
+    .filter(|c| c.is_alphanumeric())
+    .flat_map(char::to_lowercase)
+    .collect()
+}
+
+/// Page texts shorter than this (in letters and digits) are too ambiguous to locate.
+const MIN_PAGE_ANCHOR_LENGTH: usize = 6;
+
+/// Split the markdown of a run over several pages into one chunk per page, using the
+/// first substantial line of each page's text (`page_texts`, in page order) to find
+/// the markdown line the page starts at. A page whose start can't be found, such as a
+/// scanned one, gets an empty chunk and its content stays with the page before; the
+/// 0-based indices of those pages are returned alongside the chunks.
+fn split_markdown_pages(markdown: &str, page_texts: &[String]) -> (Vec<String>, Vec<usize>) {
+  let lines: Vec<&str> = markdown.lines().collect();
+  let keys: Vec<String> = lines.iter().map(|x| alphanumeric_key(x)).collect();
+
+  let mut starts = vec![0];
+  let mut cursor = 0;
+  for text in page_texts.iter().skip(1) {
+    let anchor = text
+      .lines()
+      .map(alphanumeric_key)
+      .find(|x| x.chars().count() >= MIN_PAGE_ANCHOR_LENGTH);
+    // Search past the start of the current page, whose first line may be a running
+    // header identical to the anchor.
+    let found = anchor.and_then(|anchor| {
+      (cursor + 1..lines.len()).find(|&i| {
+        keys[i].starts_with(&anchor)
+          || (keys[i].len() >= MIN_PAGE_ANCHOR_LENGTH && anchor.starts_with(&keys[i]))
+      })
+    });
+    match found {
+      Some(i) => {
+        cursor = i;
+        starts.push(i);
+      }
+      None => starts.push(usize::MAX),
+    }
+  }
+
+  let unlocated: Vec<usize> = (0..starts.len())
+    .filter(|&i| starts[i] == usize::MAX)
+    .collect();
+
+  // Pages that weren't found start where the next found page does.
+  let mut next = lines.len();
+  for start in starts.iter_mut().rev() {
+    if *start == usize::MAX {
+      *start = next;
+    } else {
+      next = *start;
+    }
+  }
+
+  let pages = starts
+    .iter()
+    .enumerate()
+    .map(|(i, &start)| {
+      let end = starts.get(i + 1).copied().unwrap_or(lines.len());
+      lines[start..end].join("\n")
+    })
+    .collect();
+  (pages, unlocated)
+}
+
+/// Write the pages `start..=end` of `document` to a temporary PDF. pdf-inspector works
+/// on whole files, so page ranges are processed from such a copy. lopdf has already
+/// decrypted encrypted documents, so the copy is written out unencrypted.
+fn write_page_range(document: &Document, start: u32, end: u32) -> Result<tempfile::NamedTempFile> {
+  let mut document = document.clone();
+  let outside: Vec<u32> = document
+    .get_pages()
+    .keys()
+    .copied()
+    .filter(|&page| page < start || page > end)
+    .collect();
+  document.delete_pages(&outside);
+  document.prune_objects();
+
+  let file = tempfile::Builder::new()
+    .suffix(".pdf")
+    .tempfile()
+    .map_err(|e| {
+      Error::new(
+        Status::GenericFailure,
+        format!("Failed to create temporary PDF: {e}"),
+      )
+    })?;
+  document.save(file.path()).map_err(|e| {
     Error::new(
       Status::GenericFailure,
-      format!("Failed to detect PDF: {e}"),
+      format!("Failed to write page range: {e}"),
     )
   })?;
 
-  Ok(to_napi_result(result))
+  Ok(file)
+}
+
+fn _extract_pdf_to_markdown(
+  path: &str,
+  options: &PdfExtractionOptions,
+) -> Result<PdfExtractionResult> {
+  let document = load_pdf(path, options.password.as_deref())?;
+  let pages = document.get_pages();
+  let (start, end) = resolve_page_range(&pages, options)?;
+  let encrypted = is_encrypted(&document);
+
+  let remove_repeated_lines = options.remove_repeated_lines.unwrap_or(false);
+  let insert_page_breaks = options.insert_page_breaks.unwrap_or(false);
+  let page_texts: Vec<String> = if remove_repeated_lines || insert_page_breaks {
+    (start..=end)
+      .map(|page| document.extract_text(&[page]).unwrap_or_default())
+      .collect()
+  } else {
+    Vec::new()
+  };
+  let repeated = remove_repeated_lines.then(|| repeated_page_lines(&page_texts));
+
+  let process = |path: &str| {
+    rust_process_pdf(path, PdfOptions::new()).map_err(|e| {
+      Error::new(
+        Status::GenericFailure,
+        format!("Failed to process PDF: {e}"),
+      )
+    })
+  };
+
+  let result = if start == 1 && end as usize == pages.len() && !encrypted {
+    process(path)?
+  } else {
+    let file = write_page_range(&document, start, end)?;
+    process(&file.path().to_string_lossy())?
+  };
+
+  let mut removed_boilerplate: Vec<String> = Vec::new();
+  let mut strip = |markdown: String| match &repeated {
+    Some(repeated) => {
+      let (cleaned, removed) = remove_boilerplate_lines(&markdown, repeated);
+      for line in removed {
+        if !removed_boilerplate.contains(&line) {
+          removed_boilerplate.push(line);
+        }
+      }
+      cleaned
+    }
+    None => markdown,
+  };
+  let reflow = |markdown: String| {
+    if options.reflow.unwrap_or(true) {
+      reflow_markdown(&markdown)
+    } else {
+      markdown
+    }
+  };
+
+  // pdf-inspector doesn't report where pages end in its markdown, so the single run
+  // over the whole range is split where each page's text starts. Headers and footers
+  // are only looked for at the edges of those pages.
+  let mut unlocated_pages = Vec::new();
+  let markdown = result.markdown.map(|markdown| {
+    if !insert_page_breaks && repeated.is_none() {
+      return reflow(markdown);
+    }
+    let (pages, unlocated) = split_markdown_pages(&markdown, &page_texts);
+    unlocated_pages = unlocated
+      .into_iter()
+      .map(|i| (start as usize + i) as i32)
+      .collect();
+    let pages: Vec<String> = pages.into_iter().map(&mut strip).collect();
+    if insert_page_breaks {
+      let pages: Vec<String> = pages.into_iter().map(reflow).collect();
+      join_pages(start, &pages)
+    } else {
+      reflow(pages.join("\n"))
+    }
+  });
+
+  let quality = _assess_text_quality(
+    markdown.as_deref().unwrap_or_default(),
+    options
+      .garbled_threshold
+      .unwrap_or(DEFAULT_GARBLED_THRESHOLD),
+  );
+
+  Ok(PdfExtractionResult {
+    pdf_type: pdf_type_str(result.pdf_type).to_string(),
+    markdown,
+    page_count: pages.len() as i32,
+    pages_extracted: (end - start + 1) as i32,
+    processing_time_ms: result.processing_time_ms as f64,
+    title: result.title,
+    text_quality_score: quality.score,
+    likely_garbled: quality.likely_garbled,
+    removed_boilerplate,
+    unlocated_pages,
+  })
+}
+
+/// Extract the whole document as markdown, ignoring the page range in `options`.
+#[napi]
+pub fn extract_pdf_to_markdown(
+  path: String,
+  options: Option<PdfExtractionOptions>,
+) -> Result<PdfExtractionResult> {
+  _extract_pdf_to_markdown(
+    &path,
+    &PdfExtractionOptions {
+      start_page: None,
+      end_page: None,
+      ..options.unwrap_or_default()
+    },
+  )
+}
+
+/// Extract only the pages in `options` as markdown. `page_count` still reports the
+/// total number of pages in the document.
+#[napi]
+pub fn extract_pdf_range_to_markdown(
+  path: String,
+  options: PdfExtractionOptions,
+) -> Result<PdfExtractionResult> {
+  _extract_pdf_to_markdown(&path, &options)
+}
+
+/// Alias of `extract_pdf_range_to_markdown`.
+#[napi]
+pub fn extract_pdf_to_markdown_with_options(
+  path: String,
+  options: PdfExtractionOptions,
+) -> Result<PdfExtractionResult> {
+  extract_pdf_range_to_markdown(path, options)
+}
+
+#[derive(Serialize)]
+#[napi(object)]
+pub struct PDFMetadata {
+  pub num_pages: i32,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub title: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub author: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub creator: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub producer: Option<String>,
+  /// Creation date as an ISO 8601 string.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub creation_date: Option<String>,
+  /// The raw `/CreationDate` value, set only when it couldn't be parsed.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub creation_date_raw: Option<String>,
+  /// Modification date as an ISO 8601 string.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub modification_date: Option<String>,
+  /// The raw `/ModDate` value, set only when it couldn't be parsed.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub modification_date_raw: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub subject: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub keywords: Option<String>,
+  /// PDF version from the file header, e.g. "1.7".
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub pdf_version: Option<String>,
+  pub is_encrypted: bool,
+  /// Width of the first page's media box, in points.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub page_width_pts: Option<f64>,
+  /// Height of the first page's media box, in points.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub page_height_pts: Option<f64>,
+}
+
+static PDF_DATE_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+  Regex::new(
+    r"^(?:D:)?(\d{4})(\d{2})?(\d{2})?(\d{2})?(\d{2})?(\d{2})?(?:Z(?:00'?(?:00'?)?)?|([+-])(\d{2})'?(?:(\d{2})'?)?)?$",
+  )
+  .expect("PDF_DATE_REGEX is a valid static regex pattern")
+});
+
+/// Convert a PDF date (`D:YYYYMMDDHHmmSSOHH'mm'`, everything after the year optional)
+/// to ISO 8601. Dates without a time zone are returned without an offset.
+fn parse_pdf_date(raw: &str) -> Option<String> {
+  let caps = PDF_DATE_REGEX.captures(raw.trim())?;
+  let field = |i: usize, default: u32| {
+    caps
+      .get(i)
+      .map_or(Some(default), |x| x.as_str().parse::<u32>().ok())
+  };
+
+  let date = NaiveDate::from_ymd_opt(field(1, 0)? as i32, field(2, 1)?, field(3, 1)?)?;
+  let time = NaiveTime::from_hms_opt(field(4, 0)?, field(5, 0)?, field(6, 0)?)?;
+  let datetime = date.and_time(time);
+
+  let has_offset = caps.get(7).is_some() || raw.contains('Z');
+  if !has_offset {
+    return Some(datetime.format("%Y-%m-%dT%H:%M:%S").to_string());
+  }
+
+  let seconds = (field(8, 0)? * 3600 + field(9, 0)? * 60) as i32;
+  let offset = match caps.get(7).map(|x| x.as_str()) {
+    Some("-") => FixedOffset::west_opt(seconds)?,
+    _ => FixedOffset::east_opt(seconds)?,
+  };
+  let datetime = datetime.and_local_timezone(offset).single()?;
+  Some(datetime.to_rfc3339_opts(SecondsFormat::Secs, true))
+}
+
+/// Split a raw PDF date into its parsed ISO 8601 form, or the raw value when it
+/// couldn't be parsed.
+fn split_pdf_date(raw: Option<String>) -> (Option<String>, Option<String>) {
+  match raw {
+    Some(raw) => match parse_pdf_date(&raw) {
+      Some(parsed) => (Some(parsed), None),
+      None => (None, Some(raw)),
+    },
+    None => (None, None),
+  }
+}
+
+fn info_dictionary(document: &Document) -> Option<&Dictionary> {
+  let info = document.trailer.get(b"Info").ok()?;
+  document.dereference(info).ok()?.1.as_dict().ok()
+}
+
+fn info_string(info: Option<&Dictionary>, key: &[u8]) -> Option<String> {
+  let value = decode_text_string(info?.get(key).ok()?).ok()?;
+  let value = value.trim();
+  (!value.is_empty()).then(|| value.to_string())
+}
+
+/// Look up a page attribute, following `/Parent` links for inherited attributes.
+fn inherited_page_attribute<'a>(
+  document: &'a Document,
+  page_id: ObjectId,
+  key: &[u8],
+) -> Option<&'a Object> {
+  let mut dictionary = document.get_dictionary(page_id).ok()?;
+  // Bound the walk in case of a cyclic page tree.
+  for _ in 0..32 {
+    if let Ok(value) = dictionary.get(key) {
+      return document.dereference(value).ok().map(|(_, x)| x);
+    }
+    let parent = dictionary
+      .get(b"Parent")
+      .and_then(Object::as_reference)
+      .ok()?;
+    dictionary = document.get_dictionary(parent).ok()?;
+  }
+  None
+}
+
+/// Width and height of a page's media box, in points.
+fn page_size(document: &Document, page_id: ObjectId) -> Option<(f64, f64)> {
+  let media_box = inherited_page_attribute(document, page_id, b"MediaBox")?
+    .as_array()
+    .ok()?;
+  let coords: Vec<f64> = media_box
+    .iter()
+    .filter_map(|x| x.as_float().ok().map(f64::from))
+    .collect();
+  match coords[..] {
+    [x0, y0, x1, y1] => Some(((x1 - x0).abs(), (y1 - y0).abs())),
+    _ => None,
+  }
+}
+
+fn _get_pdf_metadata(path: &str, password: Option<&str>) -> Result<PDFMetadata> {
+  let document = load_pdf(path, password)?;
+  let pages = document.get_pages();
+  let info = info_dictionary(&document);
+  let size = pages
+    .values()
+    .next()
+    .and_then(|&page_id| page_size(&document, page_id));
+  let (creation_date, creation_date_raw) = split_pdf_date(info_string(info, b"CreationDate"));
+  let (modification_date, modification_date_raw) = split_pdf_date(info_string(info, b"ModDate"));
+
+  Ok(PDFMetadata {
+    num_pages: pages.len() as i32,
+    title: info_string(info, b"Title"),
+    author: info_string(info, b"Author"),
+    creator: info_string(info, b"Creator"),
+    producer: info_string(info, b"Producer"),
+    creation_date,
+    creation_date_raw,
+    modification_date,
+    modification_date_raw,
+    subject: info_string(info, b"Subject"),
+    keywords: info_string(info, b"Keywords"),
+    pdf_version: (!document.version.is_empty()).then(|| document.version.clone()),
+    is_encrypted: is_encrypted(&document),
+    page_width_pts: size.map(|(width, _)| width),
+    page_height_pts: size.map(|(_, height)| height),
+  })
+}
+
+/// Read the document information dictionary and first-page dimensions without
+/// extracting any text.
+#[napi]
+pub fn get_pdf_metadata(path: String, password: Option<String>) -> Result<PDFMetadata> {
+  _get_pdf_metadata(&path, password.as_deref())
+}
+
+/// How far from the end of a file the final `startxref` is looked for.
+const STARTXREF_SEARCH_BYTES: u64 = 1024;
+
+static STARTXREF_REGEX: LazyLock<regex::bytes::Regex> = LazyLock::new(|| {
+  regex::bytes::Regex::new(r"startxref\s+(\d+)")
+    .expect("STARTXREF_REGEX is a valid static regex pattern")
+});
+
+/// The `/Encrypt` key itself, not `/EncryptMetadata` and the like.
+static ENCRYPT_KEY_REGEX: LazyLock<regex::bytes::Regex> = LazyLock::new(|| {
+  regex::bytes::Regex::new(r"/Encrypt[\x00\t\n\x0c\r /<\[0-9]")
+    .expect("ENCRYPT_KEY_REGEX is a valid static regex pattern")
+});
+
+/// Whether the last cross-reference section of the file, which carries the trailer,
+/// has an `/Encrypt` entry. Only the bytes from the final `startxref` offset to the end
+/// of the file are read, and no object is parsed. An update's trailer repeats the
+/// entries of the one before, so earlier sections don't need checking. Without a
+/// usable `startxref` the whole file is scanned.
+fn trailer_has_encrypt(path: &str) -> std::io::Result<bool> {
+  let mut file = File::open(path)?;
+  let len = file.metadata()?.len();
+  file.seek(SeekFrom::Start(len.saturating_sub(STARTXREF_SEARCH_BYTES)))?;
+  let mut tail = Vec::new();
+  file.read_to_end(&mut tail)?;
+
+  let start = STARTXREF_REGEX
+    .captures_iter(&tail)
+    .last()
+    .and_then(|x| std::str::from_utf8(&x[1]).ok()?.parse::<u64>().ok())
+    .filter(|&x| x < len)
+    .unwrap_or(0);
+  file.seek(SeekFrom::Start(start))?;
+  let mut section = Vec::new();
+  file.read_to_end(&mut section)?;
+
+  Ok(ENCRYPT_KEY_REGEX.is_match(&section))
+}
+
+fn _detect_pdf_encryption(path: &str) -> Result<bool> {
+  trailer_has_encrypt(path).map_err(|e| {
+    Error::new(
+      Status::GenericFailure,
+      format!("Failed to read PDF trailer: {e}"),
+    )
+  })
+}
+
+/// Check whether a PDF is encrypted by looking for `/Encrypt` in its trailer. Only the
+/// last cross-reference section is read and no object is parsed, so this is much
+/// cheaper than `detect_pdf`. The password isn't checked: documents that open with the
+/// empty user password are reported as encrypted too.
+#[napi]
+pub fn detect_pdf_encryption(path: String) -> Result<bool> {
+  _detect_pdf_encryption(&path)
+}
+
+#[napi(object)]
+pub struct PdfBookmark {
+  pub title: String,
+  /// 1-based page the bookmark points at, or 0 when its destination can't be resolved.
+  pub page: i32,
+  pub children: Vec<PdfBookmark>,
+}
+
+/// Limits on outline traversal, guarding against malformed or cyclic outline trees.
+const MAX_OUTLINE_DEPTH: usize = 64;
+const MAX_NAME_TREE_DEPTH: usize = 32;
+const MAX_DESTINATION_DEPTH: usize = 4;
+
+struct OutlineItem {
+  title: String,
+  page: Option<u32>,
+  children: Vec<OutlineItem>,
+}
+
+/// Find `key` in a name tree such as `/Names /Dests`.
+fn name_tree_lookup<'a>(
+  document: &'a Document,
+  node: &'a Dictionary,
+  key: &[u8],
+  depth: usize,
+) -> Option<&'a Object> {
+  if depth > MAX_NAME_TREE_DEPTH {
+    return None;
+  }
+
+  if let Ok(names) = node.get(b"Names").and_then(Object::as_array) {
+    for pair in names.chunks(2) {
+      if let [name, value] = pair {
+        if name.as_str().ok() == Some(key) {
+          return document.dereference(value).ok().map(|(_, x)| x);
+        }
+      }
+    }
+  }
+
+  let kids = node.get(b"Kids").and_then(Object::as_array).ok()?;
+  kids.iter().find_map(|kid| {
+    let kid = document.dereference(kid).ok()?.1.as_dict().ok()?;
+    name_tree_lookup(document, kid, key, depth + 1)
+  })
+}
+
+/// Look up a named destination, either in the catalog's `/Dests` dictionary (PDF 1.1)
+/// or in the `/Names /Dests` name tree.
+fn named_destination<'a>(document: &'a Document, name: &[u8]) -> Option<&'a Object> {
+  let catalog = document.catalog().ok()?;
+
+  if let Ok(dests) = document.get_dict_in_dict(catalog, b"Dests") {
+    if let Ok(value) = dests.get(name) {
+      return document.dereference(value).ok().map(|(_, x)| x);
+    }
+  }
+
+  let names = document.get_dict_in_dict(catalog, b"Names").ok()?;
+  let tree = document.get_dict_in_dict(names, b"Dests").ok()?;
+  name_tree_lookup(document, tree, name, 0)
+}
+
+/// Resolve an explicit or named destination to a 1-based page number.
+fn destination_page(
+  document: &Document,
+  pages_by_id: &HashMap<ObjectId, u32>,
+  destination: &Object,
+  depth: usize,
+) -> Option<u32> {
+  if depth > MAX_DESTINATION_DEPTH {
+    return None;
+  }
+
+  match document.dereference(destination).ok()?.1 {
+    Object::Array(array) => match array.first()? {
+      Object::Reference(id) => pages_by_id.get(id).copied(),
+      // Remote destinations address pages by 0-based index.
+      Object::Integer(index) => u32::try_from(*index).ok().map(|x| x + 1),
+      _ => None,
+    },
+    Object::Dictionary(dictionary) => {
+      destination_page(document, pages_by_id, dictionary.get(b"D").ok()?, depth + 1)
+    }
+    Object::Name(name) | Object::String(name, _) => {
+      let target = named_destination(document, name)?;
+      destination_page(document, pages_by_id, target, depth + 1)
+    }
+    _ => None,
+  }
+}
+
+/// Destination of an outline item or link annotation, from `/Dest` or a `/GoTo` action.
+fn item_destination<'a>(document: &'a Document, item: &'a Dictionary) -> Option<&'a Object> {
+  if let Ok(destination) = item.get(b"Dest") {
+    return Some(destination);
+  }
+
+  let action = document
+    .dereference(item.get(b"A").ok()?)
+    .ok()?
+    .1
+    .as_dict()
+    .ok()?;
+  if action.get(b"S").and_then(Object::as_name).ok()? != b"GoTo" {
+    return None;
+  }
+  action.get(b"D").ok()
+}
+
+fn read_outline_items(
+  document: &Document,
+  pages_by_id: &HashMap<ObjectId, u32>,
+  first: Option<ObjectId>,
+  depth: usize,
+  visited: &mut HashSet<ObjectId>,
+) -> Vec<OutlineItem> {
+  let mut items = Vec::new();
+  if depth > MAX_OUTLINE_DEPTH {
+    return items;
+  }
+
+  let mut next = first;
+  while let Some(id) = next {
+    if !visited.insert(id) {
+      break;
+    }
+    let Ok(item) = document.get_dictionary(id) else {
+      break;
+    };
+
+    let title = item
+      .get(b"Title")
+      .ok()
+      .and_then(|x| document.dereference(x).ok())
+      .and_then(|(_, x)| decode_text_string(x).ok())
+      .unwrap_or_default();
+    let page =
+      item_destination(document, item).and_then(|x| destination_page(document, pages_by_id, x, 0));
+    let first_child = item.get(b"First").and_then(Object::as_reference).ok();
+
+    items.push(OutlineItem {
+      title: title.trim().to_string(),
+      page,
+      children: read_outline_items(document, pages_by_id, first_child, depth + 1, visited),
+    });
+
+    next = item.get(b"Next").and_then(Object::as_reference).ok();
+  }
+
+  items
+}
+
+/// Read the document outline (bookmarks). Returns an empty list when there is none.
+fn read_outline(document: &Document) -> Vec<OutlineItem> {
+  let pages_by_id: HashMap<ObjectId, u32> = document
+    .get_pages()
+    .into_iter()
+    .map(|(number, id)| (id, number))
+    .collect();
+
+  let first = document
+    .catalog()
+    .and_then(|catalog| document.get_dict_in_dict(catalog, b"Outlines"))
+    .and_then(|outlines| outlines.get(b"First"))
+    .and_then(Object::as_reference)
+    .ok();
+
+  read_outline_items(document, &pages_by_id, first, 0, &mut HashSet::new())
+}
+
+fn to_pdf_bookmarks(items: Vec<OutlineItem>) -> Vec<PdfBookmark> {
+  items
+    .into_iter()
+    .map(|item| PdfBookmark {
+      title: item.title,
+      page: item.page.map_or(0, |x| x as i32),
+      children: to_pdf_bookmarks(item.children),
+    })
+    .collect()
+}
+
+/// Extract the document outline as a tree of bookmarks.
+#[napi]
+pub fn extract_pdf_bookmarks(path: String) -> Result<Vec<PdfBookmark>> {
+  let document = load_pdf(&path, None)?;
+  Ok(to_pdf_bookmarks(read_outline(&document)))
+}
+
+#[derive(Default)]
+#[napi(object)]
+pub struct PdfTypeOptions {
+  /// Return per-page details for the sampled pages in `pages`. Defaults to false.
+  pub include_page_details: Option<bool>,
+  /// Sample every page, ignoring `max_pages_to_sample`. Defaults to false.
+  ///
+  /// Setting any of the sampling options below makes `pdf_type` and `confidence`
+  /// come from the sampled pages instead of pdf-inspector.
+  pub sample_all_pages: Option<bool>,
+  /// Number of pages to sample, at least 1. Defaults to 10, so on longer documents the
+  /// per-page fields cover only part of the document.
+  pub max_pages_to_sample: Option<u32>,
+  /// Which pages to sample: "first" (default) takes the leading pages, "spread" spaces
+  /// samples evenly across the document, and "random" picks pages pseudo-randomly with
+  /// a seed derived from the page count, so repeated runs sample the same pages.
+  pub sample_strategy: Option<String>,
+  /// Minimum non-whitespace characters for a page to count as having text. Defaults
+  /// to 20.
+  pub text_threshold: Option<u32>,
+}
+
+#[napi(object)]
+pub struct PdfPageDetail {
+  /// 1-based page number.
+  pub page: i32,
+  pub has_text: bool,
+  /// Non-whitespace characters of extracted text.
+  pub text_length: i32,
+  pub image_count: i32,
+  /// Fraction of the page area covered by drawn images, capped at 1.
+  pub image_coverage: f64,
+}
+
+#[napi(object)]
+pub struct PdfTypeResult {
+  /// pdf-inspector's classification of the whole document, the same as `detect_pdf`
+  /// reports. With sampling options set it is "TextBased" when every sampled page is
+  /// a text page, "Scanned" when none is, and "Mixed" otherwise.
+  pub pdf_type: String,
+  pub page_count: i32,
+  pub pages_sampled: i32,
+  pub pages_with_text: i32,
+  /// pdf-inspector's confidence in `pdf_type`, or with sampling options set the
+  /// fraction of the document's pages that were sampled.
+  pub confidence: f64,
+  /// "text", "image" or "empty" for each sampled page, in the same order as
+  /// `sampled_page_indices`.
+  pub page_types: Vec<String>,
+  /// 0-based indices of the sampled pages.
+  pub sampled_page_indices: Vec<i32>,
+  /// Details for each sampled page, when `include_page_details` is set.
+  pub pages: Option<Vec<PdfPageDetail>>,
+}
+
+/// Default number of pages sampled by `detect_pdf_type`.
+const MAX_SAMPLED_PAGES: usize = 10;
+/// Minimum number of non-whitespace characters for a page to count as having text.
+const MIN_PAGE_TEXT_LENGTH: usize = 20;
+
+/// Image XObjects available to a page, keyed by resource name, with their width and
+/// height.
+fn page_image_xobjects(document: &Document, page_id: ObjectId) -> HashMap<Vec<u8>, (i64, i64)> {
+  let Some(resources) =
+    inherited_page_attribute(document, page_id, b"Resources").and_then(|x| x.as_dict().ok())
+  else {
+    return HashMap::new();
+  };
+  let Ok(xobjects) = document.get_dict_in_dict(resources, b"XObject") else {
+    return HashMap::new();
+  };
+
+  xobjects
+    .iter()
+    .filter_map(|(name, value)| {
+      let dictionary = &document.dereference(value).ok()?.1.as_stream().ok()?.dict;
+      if dictionary.get(b"Subtype").and_then(Object::as_name).ok()? != b"Image" {
+        return None;
+      }
+      let width = dictionary.get(b"Width").and_then(Object::as_i64).ok()?;
+      let height = dictionary.get(b"Height").and_then(Object::as_i64).ok()?;
+      Some((name.clone(), (width, height)))
+    })
+    .collect()
+}
+
+/// Width and height of every image XObject the page draws. Falls back to every image
+/// in the page's resources when the content stream can't be decoded.
+fn page_images(document: &Document, page_id: ObjectId) -> Vec<(i64, i64)> {
+  let images = page_image_xobjects(document, page_id);
+  if images.is_empty() {
+    return Vec::new();
+  }
+
+  // Resources are often shared between pages, so only count what this page draws.
+  let drawn: Option<HashSet<Vec<u8>>> =
+    document
+      .get_and_decode_page_content(page_id)
+      .ok()
+      .map(|content| {
+        content
+          .operations
+          .iter()
+          .filter(|x| x.operator == "Do")
+          .filter_map(|x| x.operands.first()?.as_name().ok().map(<[u8]>::to_vec))
+          .collect()
+      });
+
+  images
+    .into_iter()
+    .filter(|(name, _)| drawn.as_ref().is_none_or(|x| x.contains(name)))
+    .map(|(_, size)| size)
+    .collect()
+}
+
+/// Fraction of the page area covered by images drawn directly from its content stream.
+/// Images are drawn into the unit square, so each covers the absolute determinant of
+/// the current transformation matrix.
+fn page_image_coverage(document: &Document, page_id: ObjectId) -> f64 {
+  let images = page_image_xobjects(document, page_id);
+  let Some((width, height)) = page_size(document, page_id) else {
+    return 0.0;
+  };
+  let Ok(content) = document.get_and_decode_page_content(page_id) else {
+    return 0.0;
+  };
+  if images.is_empty() || width * height <= 0.0 {
+    return 0.0;
+  }
+
+  let mut determinant = 1.0;
+  let mut stack = Vec::new();
+  let mut covered = 0.0;
+
+  for operation in content.operations {
+    match operation.operator.as_str() {
+      "q" => stack.push(determinant),
+      "Q" => determinant = stack.pop().unwrap_or(1.0),
+      "cm" => {
+        let m: Vec<f64> = operation
+          .operands
+          .iter()
+          .filter_map(|x| x.as_float().ok().map(f64::from))
+          .collect();
+        if let [a, b, c, d, _, _] = m[..] {
+          determinant *= a * d - b * c;
+        }
+      }
+      "Do" => {
+        let name = operation.operands.first().and_then(|x| x.as_name().ok());
+        if name.is_some_and(|x| images.contains_key(x)) {
+          covered += f64::abs(determinant);
+        }
+      }
+      _ => {}
+    }
+  }
+
+  (covered / (width * height)).min(1.0)
+}
+
+/// Non-whitespace characters of text extracted from a page.
+fn page_text_length(document: &Document, page: u32) -> usize {
+  document
+    .extract_text(&[page])
+    .map(|text| text.chars().filter(|c| !c.is_whitespace()).count())
+    .unwrap_or(0)
+}
+
+struct PageSample {
+  page: u32,
+  text_length: usize,
+  has_text: bool,
+  images: usize,
+}
+
+impl PageSample {
+  fn new(document: &Document, page: u32, page_id: ObjectId, text_threshold: usize) -> Self {
+    let text_length = page_text_length(document, page);
+    PageSample {
+      page,
+      text_length,
+      has_text: text_length >= text_threshold,
+      images: page_images(document, page_id).len(),
+    }
+  }
+
+  fn page_type(&self) -> &'static str {
+    if self.has_text {
+      "text"
+    } else if self.images > 0 {
+      "image"
+    } else {
+      "empty"
+    }
+  }
+}
+
+/// Pick `count` of `total` 0-based page indices, in ascending order.
+fn sample_page_indices(total: usize, count: usize, strategy: &str) -> Vec<usize> {
+  let count = count.min(total);
+  match strategy {
+    "spread" if count > 1 => (0..count).map(|i| i * (total - 1) / (count - 1)).collect(),
+    "random" => {
+      // xorshift64, seeded from the page count so results are reproducible.
+      let mut state = (total as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1;
+      let mut picked = BTreeSet::new();
+      while picked.len() < count {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        picked.insert((state % total as u64) as usize);
+      }
+      picked.into_iter().collect()
+    }
+    _ => (0..count).collect(),
+  }
+}
+
+/// Classify a document from how many of its sampled pages are text pages.
+fn sampled_pdf_type(text_pages: usize, sampled: usize) -> PdfType {
+  if text_pages == sampled {
+    PdfType::TextBased
+  } else if text_pages == 0 {
+    PdfType::Scanned
+  } else {
+    PdfType::Mixed
+  }
+}
+
+/// Classify a document with pdf-inspector, as `detect_pdf` does. pdf-inspector can't
+/// decrypt, so encrypted documents are classified from the copy lopdf decrypted.
+fn inspect_pdf_type(path: &str, document: &Document) -> Result<(PdfType, f64)> {
+  let classify = |path: &str| {
+    rust_process_pdf(path, PdfOptions::detect_only())
+      .map_err(|e| Error::new(Status::GenericFailure, format!("Failed to detect PDF: {e}")))
+  };
+  let result = if is_encrypted(document) {
+    let file = write_page_range(document, 1, document.get_pages().len() as u32)?;
+    classify(&file.path().to_string_lossy())?
+  } else {
+    classify(path)?
+  };
+
+  Ok((result.pdf_type, result.confidence as f64))
+}
+
+/// Sample the pages of `document` for the per-page breakdown. Without sampling options
+/// the document type comes from `classify`, which is only called once the options and
+/// document are valid; with them it comes from the sample, so it can't contradict
+/// `page_types`.
+fn _detect_pdf_type(
+  document: &Document,
+  options: &PdfTypeOptions,
+  classify: impl FnOnce() -> Result<(PdfType, f64)>,
+) -> Result<PdfTypeResult> {
+  let strategy = options.sample_strategy.as_deref().unwrap_or("first");
+  if !matches!(strategy, "first" | "spread" | "random") {
+    return Err(Error::new(
+      Status::InvalidArg,
+      format!("Invalid sample_strategy: {strategy}"),
+    ));
+  }
+  if options.max_pages_to_sample == Some(0) {
+    return Err(Error::new(
+      Status::InvalidArg,
+      "max_pages_to_sample must be at least 1",
+    ));
+  }
+  let text_threshold = options
+    .text_threshold
+    .map_or(MIN_PAGE_TEXT_LENGTH, |x| x as usize);
+
+  let pages = document.get_pages();
+  if pages.is_empty() {
+    return Err(Error::new(Status::InvalidArg, "PDF has no pages"));
+  }
+  let all_pages: Vec<(u32, ObjectId)> = pages.iter().map(|(&page, &id)| (page, id)).collect();
+  let count = if options.sample_all_pages.unwrap_or(false) {
+    all_pages.len()
+  } else {
+    options
+      .max_pages_to_sample
+      .map_or(MAX_SAMPLED_PAGES, |x| x as usize)
+  };
+  let sampled: Vec<(u32, ObjectId)> = sample_page_indices(all_pages.len(), count, strategy)
+    .into_iter()
+    .map(|i| all_pages[i])
+    .collect();
+
+  let samples: Vec<PageSample> = sampled
+    .iter()
+    .map(|&(page, id)| PageSample::new(document, page, id, text_threshold))
+    .collect();
+  let text_pages = samples.iter().filter(|x| x.page_type() == "text").count();
+  let sampling = options.sample_all_pages.is_some()
+    || options.max_pages_to_sample.is_some()
+    || options.sample_strategy.is_some()
+    || options.text_threshold.is_some();
+  let (pdf_type, confidence) = if sampling {
+    (
+      sampled_pdf_type(text_pages, samples.len()),
+      samples.len() as f64 / pages.len() as f64,
+    )
+  } else {
+    classify()?
+  };
+
+  let details = options.include_page_details.unwrap_or(false).then(|| {
+    samples
+      .iter()
+      .zip(&sampled)
+      .map(|(sample, &(_, page_id))| PdfPageDetail {
+        page: sample.page as i32,
+        has_text: sample.has_text,
+        text_length: sample.text_length as i32,
+        image_count: sample.images as i32,
+        image_coverage: page_image_coverage(document, page_id),
+      })
+      .collect()
+  });
+
+  Ok(PdfTypeResult {
+    pdf_type: pdf_type_str(pdf_type).to_string(),
+    page_count: pages.len() as i32,
+    pages_sampled: samples.len() as i32,
+    pages_with_text: text_pages as i32,
+    confidence,
+    page_types: samples.iter().map(|x| x.page_type().to_string()).collect(),
+    sampled_page_indices: samples.iter().map(|x| x.page as i32 - 1).collect(),
+    pages: details,
+  })
+}
+
+/// Classify a PDF with pdf-inspector, as `detect_pdf` does, and report the type of a
+/// sample of its pages so mixed documents can be OCRed per page. When sampling options
+/// are set the document is classified from the sample instead.
+#[napi]
+pub fn detect_pdf_type(
+  path: String,
+  password: Option<String>,
+  options: Option<PdfTypeOptions>,
+) -> Result<PdfTypeResult> {
+  let document = load_pdf(&path, password.as_deref())?;
+  _detect_pdf_type(&document, &options.unwrap_or_default(), || {
+    inspect_pdf_type(&path, &document)
+  })
+}
+
+#[napi(object)]
+pub struct PdfPageImageInfo {
+  /// 1-based page number.
+  pub page: i32,
+  pub image_count: i32,
+  pub max_width_px: Option<i32>,
+  pub max_height_px: Option<i32>,
+}
+
+fn _extract_pdf_images_info(document: &Document) -> Vec<PdfPageImageInfo> {
+  document
+    .get_pages()
+    .into_iter()
+    .map(|(page, page_id)| {
+      let images = page_images(document, page_id);
+      PdfPageImageInfo {
+        page: page as i32,
+        image_count: images.len() as i32,
+        max_width_px: images.iter().map(|&(width, _)| width as i32).max(),
+        max_height_px: images.iter().map(|&(_, height)| height as i32).max(),
+      }
+    })
+    .collect()
+}
+
+/// Report how many images each page draws and the largest image dimensions, to help
+/// calibrate OCR before routing a document to it.
+#[napi]
+pub fn extract_pdf_images_info(path: String) -> Result<Vec<PdfPageImageInfo>> {
+  let document = load_pdf(&path, None)?;
+  Ok(_extract_pdf_images_info(&document))
+}
+
+#[napi(object)]
+pub struct PdfOutlineEntry {
+  pub title: String,
+  /// 1-based page, or null when the destination is broken or can't be resolved.
+  pub page: Option<i32>,
+  /// Depth in the outline tree; top-level entries are level 1.
+  pub level: i32,
+}
+
+fn flatten_outline(items: Vec<OutlineItem>, level: i32, out: &mut Vec<PdfOutlineEntry>) {
+  for item in items {
+    out.push(PdfOutlineEntry {
+      title: item.title,
+      page: item.page.map(|x| x as i32),
+      level,
+    });
+    flatten_outline(item.children, level + 1, out);
+  }
+}
+
+/// Extract the document outline as a flat, pre-order list of entries with their depth,
+/// suitable for building a table of contents.
+#[napi]
+pub fn extract_pdf_outline(path: String) -> Result<Vec<PdfOutlineEntry>> {
+  let document = load_pdf(&path, None)?;
+  let mut entries = Vec::new();
+  flatten_outline(read_outline(&document), 1, &mut entries);
+  Ok(entries)
+}
+
+#[napi(object)]
+pub struct PdfLink {
+  pub url: String,
+  /// 1-based page the link annotation sits on.
+  pub page: i32,
+}
+
+fn _extract_pdf_links(document: &Document) -> Vec<PdfLink> {
+  let mut links = Vec::new();
+
+  for (page, page_id) in document.get_pages() {
+    let Ok(annotations) = document.get_page_annotations(page_id) else {
+      continue;
+    };
+
+    for annotation in annotations {
+      if annotation.get(b"Subtype").and_then(Object::as_name).ok() != Some(b"Link") {
+        continue;
+      }
+      let Some(action) = annotation
+        .get(b"A")
+        .ok()
+        .and_then(|x| document.dereference(x).ok())
+        .and_then(|(_, x)| x.as_dict().ok())
+      else {
+        continue;
+      };
+      // Only external links; `/GoTo` and friends point inside the document.
+      if action.get(b"S").and_then(Object::as_name).ok() != Some(b"URI") {
+        continue;
+      }
+      let Some(uri) = action
+        .get(b"URI")
+        .ok()
+        .and_then(|x| document.dereference(x).ok())
+        .and_then(|(_, x)| x.as_str().ok())
+      else {
+        continue;
+      };
+
+      let url = String::from_utf8_lossy(uri).trim().to_string();
+      if !url.is_empty() {
+        links.push(PdfLink {
+          url,
+          page: page as i32,
+        });
+      }
+    }
+  }
+
+  links
+}
+
+/// Extract the URI link annotations of every page, in page order.
+#[napi]
+pub fn extract_pdf_links(path: String) -> Result<Vec<PdfLink>> {
+  let document = load_pdf(&path, None)?;
+  Ok(_extract_pdf_links(&document))
+}
+
+/// pdf-inspector and lopdf's loaders work on paths, so PDFs received as bytes are
+/// written to a temporary file for the duration of `f`. The file is removed when it
+/// goes out of scope, whether or not `f` succeeds.
+fn with_temp_pdf<T>(data: &[u8], f: impl FnOnce(&str) -> Result<T>) -> Result<T> {
+  let temp_error = |e: std::io::Error| {
+    Error::new(
+      Status::GenericFailure,
+      format!("Failed to write temporary PDF: {e}"),
+    )
+  };
+  let mut file = tempfile::Builder::new()
+    .suffix(".pdf")
+    .tempfile()
+    .map_err(temp_error)?;
+  file
+    .write_all(data)
+    .and_then(|_| file.flush())
+    .map_err(temp_error)?;
+
+  f(&file.path().to_string_lossy())
+}
+
+/// Run `f` on a blocking thread with `data` written to a temporary file.
+async fn with_temp_pdf_async<T: Send + 'static>(
+  name: &str,
+  data: Buffer,
+  f: impl FnOnce(&str) -> Result<T> + Send + 'static,
+) -> Result<T> {
+  let data = data.to_vec();
+  task::spawn_blocking(move || with_temp_pdf(&data, f))
+    .await
+    .map_err(|e| Error::new(Status::GenericFailure, format!("{name} join error: {e}")))?
+}
+
+/// `detect_pdf_type` for a PDF held in memory.
+#[napi]
+pub fn detect_pdf_type_from_bytes(
+  data: Buffer,
+  password: Option<String>,
+  options: Option<PdfTypeOptions>,
+) -> Result<PdfTypeResult> {
+  with_temp_pdf(&data, |path| {
+    detect_pdf_type(path.to_string(), password, options)
+  })
+}
+
+/// `detect_pdf_type_from_bytes` on a worker thread.
+#[napi]
+pub async fn detect_pdf_type_from_bytes_async(
+  data: Buffer,
+  password: Option<String>,
+  options: Option<PdfTypeOptions>,
+) -> Result<PdfTypeResult> {
+  with_temp_pdf_async("detect_pdf_type_from_bytes_async", data, |path| {
+    detect_pdf_type(path.to_string(), password, options)
+  })
+  .await
+}
+
+/// `extract_pdf_to_markdown` for a PDF held in memory.
+#[napi]
+pub fn extract_pdf_to_markdown_from_bytes(
+  data: Buffer,
+  options: Option<PdfExtractionOptions>,
+) -> Result<PdfExtractionResult> {
+  with_temp_pdf(&data, |path| {
+    extract_pdf_to_markdown(path.to_string(), options)
+  })
+}
+
+/// `extract_pdf_to_markdown_from_bytes` on a worker thread.
+#[napi]
+pub async fn extract_pdf_to_markdown_from_bytes_async(
+  data: Buffer,
+  options: Option<PdfExtractionOptions>,
+) -> Result<PdfExtractionResult> {
+  with_temp_pdf_async("extract_pdf_to_markdown_from_bytes_async", data, |path| {
+    extract_pdf_to_markdown(path.to_string(), options)
+  })
+  .await
+}
+
+/// `get_pdf_metadata` for a PDF held in memory.
+#[napi]
+pub fn get_pdf_metadata_from_bytes(data: Buffer, password: Option<String>) -> Result<PDFMetadata> {
+  with_temp_pdf(&data, |path| _get_pdf_metadata(path, password.as_deref()))
+}
+
+/// `get_pdf_metadata_from_bytes` on a worker thread.
+#[napi]
+pub async fn get_pdf_metadata_from_bytes_async(
+  data: Buffer,
+  password: Option<String>,
+) -> Result<PDFMetadata> {
+  with_temp_pdf_async("get_pdf_metadata_from_bytes_async", data, move |path| {
+    _get_pdf_metadata(path, password.as_deref())
+  })
+  .await
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use lopdf::{dictionary, EncryptionState, EncryptionVersion, Permissions, Stream, StringFormat};
+  use tempfile::NamedTempFile;
+
+  /// A page of a synthetic test PDF: optional text drawn in Helvetica and an optional
+  /// image XObject.
+  #[derive(Clone, Copy, Default)]
+  struct TestPage<'a> {
+    text: Option<&'a str>,
+    image: bool,
+  }
+
+  /// Build a PDF from `pages` with an optional Info dictionary.
+  fn build_pdf(pages: &[TestPage], info: Option<Dictionary>) -> Document {
+    let mut document = Document::with_version("1.5");
+    let pages_id = document.new_object_id();
+    let font_id = document.add_object(dictionary! {
+      "Type" => "Font",
+      "Subtype" => "Type1",
+      "BaseFont" => "Helvetica",
+      "Encoding" => "WinAnsiEncoding",
+    });
+    let image_id = document.add_object(Stream::new(
+      dictionary! {
+        "Type" => "XObject",
+        "Subtype" => "Image",
+        "Width" => 1,
+        "Height" => 1,
+        "ColorSpace" => "DeviceGray",
+        "BitsPerComponent" => 8,
+      },
+      vec![0],
+    ));
+
+    let mut kids = Vec::new();
+    for page in pages {
+      let mut operations = String::new();
+      if let Some(text) = page.text {
+        operations.push_str(&format!("BT /F1 12 Tf 72 720 Td ({text}) Tj ET\n"));
+      }
+      if page.image {
+        operations.push_str("q 200 0 0 200 72 300 cm /Im1 Do Q\n");
+      }
+      let content_id = document.add_object(Stream::new(dictionary! {}, operations.into_bytes()));
+      let page_id = document.add_object(dictionary! {
+        "Type" => "Page",
+        "Parent" => pages_id,
+        "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+        "Contents" => content_id,
+        "Resources" => dictionary! {
+          "Font" => dictionary! { "F1" => font_id },
+          "XObject" => dictionary! { "Im1" => image_id },
+        },
+      });
+      kids.push(page_id.into());
+    }
+
+    let count = kids.len() as i64;
+    document.objects.insert(
+      pages_id,
+      Object::Dictionary(dictionary! {
+        "Type" => "Pages",
+        "Kids" => kids,
+        "Count" => count,
+      }),
+    );
+    let catalog_id = document.add_object(dictionary! {
+      "Type" => "Catalog",
+      "Pages" => pages_id,
+    });
+    document.trailer.set("Root", catalog_id);
+    if let Some(info) = info {
+      let info_id = document.add_object(info);
+      document.trailer.set("Info", info_id);
+    }
+    document
+  }
+
+  fn save_pdf(mut document: Document) -> NamedTempFile {
+    let file = tempfile::Builder::new().suffix(".pdf").tempfile().unwrap();
+    document.save(file.path()).unwrap();
+    file
+  }
+
+  fn write_pdf(pages: &[TestPage], info: Option<Dictionary>) -> NamedTempFile {
+    save_pdf(build_pdf(pages, info))
+  }
+
+  /// Build a one-page PDF encrypted with the given user password and owner password "owner".
+  fn write_encrypted_pdf(user_password: &str) -> NamedTempFile {
+    let mut document = build_pdf(
+      &[TestPage {
+        text: Some("Confidential quarterly figures for the board"),
+        image: false,
+      }],
+      None,
+    );
+    document.trailer.set(
+      "ID",
+      vec![
+        Object::string_literal("0123456789abcdef"),
+        Object::string_literal("0123456789abcdef"),
+      ],
+    );
+    let state = EncryptionState::try_from(EncryptionVersion::V2 {
+      document: &document,
+      owner_password: "owner",
+      user_password,
+      key_length: 128,
+      permissions: Permissions::default(),
+    })
+    .unwrap();
+    document.encrypt(&state).unwrap();
+    save_pdf(document)
+  }
+
+  /// Build a one-page PDF with an optional Info dictionary.
+  fn write_test_pdf(info: Option<Dictionary>) -> NamedTempFile {
+    write_pdf(&[TestPage::default()], info)
+  }
+
+  fn text(value: &str) -> Object {
+    Object::String(value.as_bytes().to_vec(), StringFormat::Literal)
+  }
+
+  #[test]
+  fn test_parse_pdf_date() {
+    assert_eq!(
+      parse_pdf_date("D:20230415103000+02'00'").as_deref(),
+      Some("2023-04-15T10:30:00+02:00")
+    );
+    assert_eq!(
+      parse_pdf_date("D:20230415103000Z").as_deref(),
+      Some("2023-04-15T10:30:00Z")
+    );
+    assert_eq!(
+      parse_pdf_date("D:2023").as_deref(),
+      Some("2023-01-01T00:00:00")
+    );
+    assert_eq!(parse_pdf_date("D:20231345"), None);
+    assert_eq!(parse_pdf_date("yesterday"), None);
+  }
+
+  #[test]
+  fn test_get_pdf_metadata_full_info() {
+    let file = write_test_pdf(Some(dictionary! {
+      "Title" => text("Annual Report"),
+      "Author" => text("Jane Doe"),
+      "Subject" => text("Finances"),
+      "Keywords" => text("report, finance"),
+      "Creator" => text("Writer"),
+      "Producer" => text("LibreOffice 7.5"),
+      "CreationDate" => text("D:20230415103000-05'00'"),
+      "ModDate" => text("last tuesday"),
+    }));
+
+    let metadata = _get_pdf_metadata(&file.path().to_string_lossy(), None).unwrap();
+    assert_eq!(metadata.num_pages, 1);
+    assert_eq!(metadata.title.as_deref(), Some("Annual Report"));
+    assert_eq!(metadata.author.as_deref(), Some("Jane Doe"));
+    assert_eq!(metadata.subject.as_deref(), Some("Finances"));
+    assert_eq!(metadata.keywords.as_deref(), Some("report, finance"));
+    assert_eq!(metadata.creator.as_deref(), Some("Writer"));
+    assert_eq!(metadata.producer.as_deref(), Some("LibreOffice 7.5"));
+    assert_eq!(
+      metadata.creation_date.as_deref(),
+      Some("2023-04-15T10:30:00-05:00")
+    );
+    assert_eq!(metadata.creation_date_raw, None);
+    assert_eq!(metadata.modification_date, None);
+    assert_eq!(
+      metadata.modification_date_raw.as_deref(),
+      Some("last tuesday")
+    );
+    assert_eq!(metadata.pdf_version.as_deref(), Some("1.5"));
+    assert!(!metadata.is_encrypted);
+    assert_eq!(metadata.page_width_pts, Some(612.0));
+    assert_eq!(metadata.page_height_pts, Some(792.0));
+  }
+
+  #[test]
+  fn test_get_pdf_metadata_without_info() {
+    let file = write_test_pdf(None);
+
+    let metadata = _get_pdf_metadata(&file.path().to_string_lossy(), None).unwrap();
+    assert_eq!(metadata.num_pages, 1);
+    assert_eq!(metadata.title, None);
+    assert_eq!(metadata.author, None);
+    assert_eq!(metadata.creation_date, None);
+    assert_eq!(metadata.creation_date_raw, None);
+    assert!(!metadata.is_encrypted);
+
+    let json = serde_json::to_value(&metadata).unwrap();
+    let keys: Vec<_> = json.as_object().unwrap().keys().cloned().collect();
+    assert_eq!(
+      keys,
+      vec![
+        "is_encrypted",
+        "num_pages",
+        "page_height_pts",
+        "page_width_pts",
+        "pdf_version",
+      ]
+    );
+  }
+
+  #[test]
+  fn test_detect_pdf_type_page_types() {
+    let text = TestPage {
+      text: Some("Quarterly results exceeded expectations across all regions"),
+      image: false,
+    };
+    let scanned = TestPage {
+      text: None,
+      image: true,
+    };
+    let file = write_pdf(&[text, scanned, TestPage::default(), text], None);
+
+    let document = Document::load(file.path()).unwrap();
+    let result = _detect_pdf_type(&document, &PdfTypeOptions::default(), || {
+      Ok((PdfType::ImageBased, 0.8))
+    })
+    .unwrap();
+    assert_eq!(result.pdf_type, "ImageBased");
+    assert_eq!(result.confidence, 0.8);
+    assert_eq!(result.page_count, 4);
+    assert_eq!(result.pages_sampled, 4);
+    assert_eq!(result.pages_with_text, 2);
+    assert_eq!(result.page_types, vec!["text", "image", "empty", "text"]);
+    assert_eq!(result.sampled_page_indices, vec![0, 1, 2, 3]);
+
+    // With sampling options the type follows the sampled pages, not pdf-inspector.
+    let options = |max_pages_to_sample, text_threshold| PdfTypeOptions {
+      max_pages_to_sample,
+      text_threshold,
+      ..Default::default()
+    };
+    let classify = || -> Result<(PdfType, f64)> { panic!("classified a sampled document") };
+    let result = _detect_pdf_type(&document, &options(Some(1), None), classify).unwrap();
+    assert_eq!(result.page_types, vec!["text"]);
+    assert_eq!(result.pdf_type, "TextBased");
+    assert_eq!(result.confidence, 0.25);
+    let result = _detect_pdf_type(&document, &options(None, Some(1000)), classify).unwrap();
+    assert_eq!(result.pages_with_text, 0);
+    assert_eq!(result.pdf_type, "Scanned");
+    assert_eq!(result.confidence, 1.0);
+
+    // Without pages there is nothing to classify.
+    let empty = build_pdf(&[], None);
+    let error = _detect_pdf_type(&empty, &PdfTypeOptions::default(), || {
+      panic!("classified an empty document")
+    })
+    .err()
+    .unwrap();
+    assert_eq!(error.reason, "PDF has no pages");
+  }
+
+  #[test]
+  fn test_encrypted_pdf_passwords() {
+    // Owner-password-only documents open with the empty user password.
+    let owner_only = write_encrypted_pdf("");
+    let path = owner_only.path().to_string_lossy().to_string();
+    let metadata = _get_pdf_metadata(&path, None).unwrap();
+    assert!(metadata.is_encrypted);
+    assert!(_detect_pdf_encryption(&path).unwrap());
+    let plain = write_pdf(&[TestPage::default()], None);
+    assert!(!_detect_pdf_encryption(&plain.path().to_string_lossy()).unwrap());
+    assert!(_detect_pdf_encryption("/nonexistent/file.pdf").is_err());
+
+    let locked = write_encrypted_pdf("secret");
+    let path = locked.path().to_string_lossy().to_string();
+    let error = load_pdf(&path, None).unwrap_err();
+    assert!(error.reason.starts_with(PDF_PASSWORD_REQUIRED));
+    let error = load_pdf(&path, Some("wrong")).unwrap_err();
+    assert!(error.reason.starts_with(PDF_INVALID_PASSWORD));
+
+    // pdf-inspector gets the file itself, a decrypted copy, or the password error.
+    let plain = write_pdf(&[TestPage::default()], None);
+    assert!(inspector_input(&plain.path().to_string_lossy(), None)
+      .unwrap()
+      .is_none());
+    let copy = inspector_input(&owner_only.path().to_string_lossy(), None)
+      .unwrap()
+      .unwrap();
+    assert!(!is_encrypted(&Document::load(copy.path()).unwrap()));
+    let copy = inspector_input(&path, Some("secret")).unwrap().unwrap();
+    assert!(!is_encrypted(&Document::load(copy.path()).unwrap()));
+    for (password, prefix) in [
+      (None, PDF_PASSWORD_REQUIRED),
+      (Some("wrong"), PDF_INVALID_PASSWORD),
+    ] {
+      let error = process_pdf(path.clone(), password.map(str::to_string))
+        .err()
+        .unwrap();
+      assert!(matches!(error.status, Status::InvalidArg));
+      assert!(error.reason.starts_with(prefix));
+    }
+
+    let document = load_pdf(&path, Some("secret")).unwrap();
+    let result = _detect_pdf_type(&document, &PdfTypeOptions::default(), || {
+      Ok((PdfType::TextBased, 1.0))
+    })
+    .unwrap();
+    assert_eq!(result.page_types, vec!["text"]);
+  }
+
+  #[test]
+  fn test_extract_pdf_links() {
+    let mut document = build_pdf(&[TestPage::default(), TestPage::default()], None);
+    let pages = document.get_pages();
+
+    let uri = document.add_object(dictionary! {
+      "Type" => "Annot",
+      "Subtype" => "Link",
+      "Rect" => vec![0.into(), 0.into(), 100.into(), 20.into()],
+      "A" => dictionary! {
+        "S" => "URI",
+        "URI" => Object::string_literal("https://example.com/report"),
+      },
+    });
+    let internal = document.add_object(dictionary! {
+      "Type" => "Annot",
+      "Subtype" => "Link",
+      "Rect" => vec![0.into(), 40.into(), 100.into(), 60.into()],
+      "A" => dictionary! {
+        "S" => "GoTo",
+        "D" => vec![pages[&1].into(), "Fit".into()],
+      },
+    });
+    document
+      .get_dictionary_mut(pages[&2])
+      .unwrap()
+      .set("Annots", vec![internal.into(), uri.into()]);
+
+    let file = save_pdf(document);
+    let links = _extract_pdf_links(&Document::load(file.path()).unwrap());
+    assert_eq!(links.len(), 1);
+    assert_eq!(links[0].url, "https://example.com/report");
+    assert_eq!(links[0].page, 2);
+  }
+
+  #[test]
+  fn test_extract_pdf_outline() {
+    let mut document = build_pdf(&[TestPage::default(), TestPage::default()], None);
+    let pages = document.get_pages();
+
+    let outlines_id = document.new_object_id();
+    let chapter_id = document.new_object_id();
+    let section_id = document.new_object_id();
+    let broken_id = document.new_object_id();
+    document.objects.insert(
+      chapter_id,
+      Object::Dictionary(dictionary! {
+        "Title" => Object::string_literal("Chapter 1"),
+        "Parent" => outlines_id,
+        "First" => section_id,
+        "Last" => section_id,
+        "Next" => broken_id,
+        "Dest" => vec![pages[&1].into(), "Fit".into()],
+      }),
+    );
+    document.objects.insert(
+      section_id,
+      Object::Dictionary(dictionary! {
+        "Title" => Object::string_literal("Section 1.1"),
+        "Parent" => chapter_id,
+        "A" => dictionary! {
+          "S" => "GoTo",
+          "D" => vec![pages[&2].into(), "Fit".into()],
+        },
+      }),
+    );
+    document.objects.insert(
+      broken_id,
+      Object::Dictionary(dictionary! {
+        "Title" => Object::string_literal("Appendix"),
+        "Parent" => outlines_id,
+        "Dest" => Object::string_literal("missing-destination"),
+      }),
+    );
+    document.objects.insert(
+      outlines_id,
+      Object::Dictionary(dictionary! {
+        "Type" => "Outlines",
+        "First" => chapter_id,
+        "Last" => broken_id,
+      }),
+    );
+    let catalog_id = document
+      .trailer
+      .get(b"Root")
+      .unwrap()
+      .as_reference()
+      .unwrap();
+    document
+      .get_dictionary_mut(catalog_id)
+      .unwrap()
+      .set("Outlines", outlines_id);
+
+    let file = save_pdf(document);
+    let entries = extract_pdf_outline(file.path().to_string_lossy().to_string()).unwrap();
+    let entries: Vec<_> = entries
+      .into_iter()
+      .map(|x| (x.title, x.page, x.level))
+      .collect();
+    assert_eq!(
+      entries,
+      vec![
+        ("Chapter 1".to_string(), Some(1), 1),
+        ("Section 1.1".to_string(), Some(2), 2),
+        ("Appendix".to_string(), None, 1),
+      ]
+    );
+  }
+
+  #[test]
+  fn test_detect_pdf_type_page_details() {
+    let pages: Vec<TestPage> = (0..12)
+      .map(|i| TestPage {
+        text: (i % 2 == 0).then_some("Text layer present on this page of the report"),
+        image: i % 2 == 1,
+      })
+      .collect();
+    let file = write_pdf(&pages, None);
+    let document = Document::load(file.path()).unwrap();
+    let classify = || Ok((PdfType::Mixed, 1.0));
+
+    let sampled = _detect_pdf_type(
+      &document,
+      &PdfTypeOptions {
+        include_page_details: Some(true),
+        ..Default::default()
+      },
+      classify,
+    )
+    .unwrap();
+    let details = sampled.pages.unwrap();
+    assert_eq!(details.len(), MAX_SAMPLED_PAGES);
+    assert!(details[0].has_text);
+    assert_eq!(details[0].image_count, 0);
+    assert!(!details[1].has_text);
+    assert_eq!(details[1].image_count, 1);
+    // A 200x200pt image on a 612x792pt page.
+    let expected = 200.0 * 200.0 / (612.0 * 792.0);
+    assert!((details[1].image_coverage - expected).abs() < 1e-9);
+
+    let all = _detect_pdf_type(
+      &document,
+      &PdfTypeOptions {
+        include_page_details: Some(true),
+        sample_all_pages: Some(true),
+        ..Default::default()
+      },
+      classify,
+    )
+    .unwrap();
+    assert_eq!(all.pages_sampled, 12);
+    assert_eq!(all.pdf_type, "Mixed");
+    assert_eq!(all.confidence, 1.0);
+    let error = _detect_pdf_type(
+      &document,
+      &PdfTypeOptions {
+        max_pages_to_sample: Some(0),
+        ..Default::default()
+      },
+      classify,
+    )
+    .err()
+    .unwrap();
+    assert!(matches!(error.status, Status::InvalidArg));
+    assert_eq!(all.pages.unwrap().len(), 12);
+    assert!(
+      _detect_pdf_type(&document, &PdfTypeOptions::default(), classify)
+        .unwrap()
+        .pages
+        .is_none()
+    );
+  }
+
+  #[test]
+  fn test_sample_page_indices() {
+    assert_eq!(
+      sample_page_indices(100, 10, "first"),
+      (0..10).collect::<Vec<_>>()
+    );
+    assert_eq!(
+      sample_page_indices(100, 5, "spread"),
+      vec![0, 24, 49, 74, 99]
+    );
+    assert_eq!(sample_page_indices(3, 10, "spread"), vec![0, 1, 2]);
+
+    let random = sample_page_indices(100, 10, "random");
+    assert_eq!(random.len(), 10);
+    assert!(random.windows(2).all(|x| x[0] < x[1]));
+    assert_eq!(random, sample_page_indices(100, 10, "random"));
+  }
+
+  #[test]
+  fn test_assess_text_quality() {
+    let prose = "The committee reviewed the annual budget and found that most of the \
+      spending was in line with the plan. Some items, such as travel, were over the \
+      limit and will be cut in the next year.";
+    let quality = _assess_text_quality(prose, DEFAULT_GARBLED_THRESHOLD);
+    assert!(quality.score > 0.9, "{}", quality.score);
+    assert!(!quality.likely_garbled);
+
+    let mojibake = "\u{F0B7}\u{F0A7} \u{FFFD}\u{FFFD}\u{FFFD} \u{E012}\u{E013}\u{E014} \
+      \u{FFFD}\u{FFFD} \u{F0B7}\u{F0B7}\u{F0B7} ab\u{FFFD}cd";
+    let quality = _assess_text_quality(mojibake, DEFAULT_GARBLED_THRESHOLD);
+    assert!(quality.likely_garbled, "{}", quality.score);
+    assert!(quality.replacement_char_ratio > 0.2);
+    assert!(quality.non_printable_ratio > 0.2);
+
+    assert!(_assess_text_quality("", DEFAULT_GARBLED_THRESHOLD).likely_garbled);
+    assert!(!_assess_text_quality(mojibake, 0.0).likely_garbled);
+  }
+
+  #[test]
+  fn test_remove_repeated_lines() {
+    let pages: Vec<String> = (1..=4)
+      .map(|page| {
+        format!(
+          "CONFIDENTIAL - Acme Corp\nSection {page} heading\nBody text for page {page}.\nMore body text.\nPage {page} of 4\n"
+        )
+      })
+      .collect();
+    let repeated = repeated_page_lines(&pages);
+    assert!(repeated.contains("confidential - acme corp"));
+    assert!(repeated.contains("page # of #"));
+    assert!(!repeated.contains("body text for page #."));
+
+    // Too few pages to tell boilerplate from content.
+    assert!(repeated_page_lines(&pages[..2]).is_empty());
+
+    let repeated = HashSet::from(["confidential - acme corp".to_string()]);
+    let first = "**CONFIDENTIAL - Acme Corp**\n\n# Introduction\n\nFirst page text.\n\n2024\n\nMore text.\n\nPage 1 of 4\n";
+    let (cleaned, removed) = remove_boilerplate_lines(first, &repeated);
+    assert_eq!(
+      cleaned,
+      "# Introduction\n\nFirst page text.\n\n2024\n\nMore text.\n"
+    );
+    assert_eq!(removed, vec!["**CONFIDENTIAL - Acme Corp**", "Page 1 of 4"]);
+
+    // Numbers and repeated lines in the middle of a page are content.
+    let second = "- 2 -\n\nCONFIDENTIAL - Acme Corp\n\nSecond page text.\n\n12\n\nCONFIDENTIAL - Acme Corp\n\nClosing line.\n\nLast line.\n";
+    let (cleaned, removed) = remove_boilerplate_lines(second, &repeated);
+    assert_eq!(
+      cleaned,
+      "Second page text.\n\n12\n\nCONFIDENTIAL - Acme Corp\n\nClosing line.\n\nLast line.\n"
+    );
+    assert_eq!(removed, vec!["- 2 -", "CONFIDENTIAL - Acme Corp"]);
+  }
+
+  #[test]
+  fn test_reflow_markdown() {
+    let academic = "## 2 Related Work\n\nPrior approaches to web information extrac-\ntion rely on hand-written wrappers for each\nsite, which break whenever the page layout\nchanges, as noted by earlier surveys.\n\nA second paragraph stays separate.\n";
+    assert_eq!(
+      reflow_markdown(academic),
+      "## 2 Related Work\n\nPrior approaches to web information extraction rely on hand-written wrappers for each site, which break whenever the page layout changes, as noted by earlier surveys.\n\nA second paragraph stays separate.\n"
+    );
+
+    // A capitalized continuation keeps its hyphen.
+    assert_eq!(
+      reflow_markdown("Results were reported separately for the Anglo-\nSaxon corpus only."),
+      "Results were reported separately for the Anglo-Saxon corpus only."
+    );
+
+    let lists = "- first item wraps onto a second line because it is long\n  and continues here\n- second item\n1. numbered item\n";
+    assert_eq!(
+      reflow_markdown(lists),
+      "- first item wraps onto a second line because it is long and continues here\n- second item\n1. numbered item\n"
+    );
+
+    let poem = "The woods are lovely, dark and deep,\nBut I have promises to keep,\nAnd miles to go before I sleep.\n";
+    assert_eq!(reflow_markdown(poem), poem);
+
+    let code = "```\nfn main() {\n    let extrac-\n    tion = 1;\n}\n```\nif (ready) {\n  start_the_engine_with_a_long_argument(x);\n}\n";
+    assert_eq!(reflow_markdown(code), code);
+  }
+
+  #[test]
+  fn test_split_markdown_pages() {
+    let markdown = "# Annual Report\n\nIntro text on the first page.\n\n## Results\n\nRevenue grew strongly this year.\n\nClosing remarks on the last page.\n";
+    let page_texts = vec![
+      "Annual Report\nIntro text on the first\npage.".to_string(),
+      "Results\nRevenue grew strongly".to_string(),
+      String::new(),
+      "Closing   remarks on the\nlast page".to_string(),
+    ];
+    assert_eq!(
+      split_markdown_pages(markdown, &page_texts),
+      (
+        vec![
+          "# Annual Report\n\nIntro text on the first page.\n".to_string(),
+          "## Results\n\nRevenue grew strongly this year.\n".to_string(),
+          String::new(),
+          "Closing remarks on the last page.".to_string(),
+        ],
+        vec![2]
+      )
+    );
+
+    // A page whose text isn't in the markdown leaves its content with the page before.
+    let page_texts = vec![
+      "Annual Report".to_string(),
+      "Results\nRevenue grew strongly".to_string(),
+      "Appendix tables and figures".to_string(),
+    ];
+    let (pages, unlocated) = split_markdown_pages(markdown, &page_texts);
+    assert_eq!(unlocated, vec![2]);
+    assert_eq!(pages[2], "");
+    assert!(pages[1].ends_with("Closing remarks on the last page."));
+
+    // A running header repeated at the top of every page.
+    let markdown = "ACME Corp\n\nFirst.\n\nACME Corp\n\nSecond.";
+    let page_texts = vec![
+      "ACME Corp\nFirst.".to_string(),
+      "ACME Corp\nSecond.".to_string(),
+    ];
+    let (pages, unlocated) = split_markdown_pages(markdown, &page_texts);
+    assert_eq!(pages, vec!["ACME Corp\n\nFirst.\n", "ACME Corp\n\nSecond."]);
+    assert!(unlocated.is_empty());
+
+    assert_eq!(
+      split_markdown_pages("Only page.", &["Only page.".to_string()]).0,
+      vec!["Only page."]
+    );
+  }
+
+  #[test]
+  fn test_join_pages() {
+    let pages = vec![
+      "# Intro\n\nFirst page.\n".to_string(),
+      String::new(),
+      "\nThird page.".to_string(),
+    ];
+    assert_eq!(
+      join_pages(1, &pages),
+      "# Intro\n\nFirst page.\n\n---\n\n<!-- page 2 -->\n\n\n\n---\n\n<!-- page 3 -->\n\nThird page."
+    );
+    assert_eq!(
+      join_pages(4, &pages[..2]),
+      "# Intro\n\nFirst page.\n\n---\n\n<!-- page 5 -->\n\n"
+    );
+    assert_eq!(join_pages(1, &pages[..1]), "# Intro\n\nFirst page.");
+  }
+
+  #[test]
+  fn test_pdf_functions_from_bytes() {
+    let file = write_pdf(
+      &[TestPage {
+        text: Some("Quarterly results exceeded expectations across all regions"),
+        image: false,
+      }],
+      Some(dictionary! { "Title" => text("Quarterly report") }),
+    );
+    let data = std::fs::read(file.path()).unwrap();
+
+    let mut temp_path = None;
+    let result = with_temp_pdf(&data, |path| {
+      temp_path = Some(path.to_string());
+      let document = load_pdf(path, None)?;
+      _detect_pdf_type(&document, &PdfTypeOptions::default(), || {
+        Ok((PdfType::TextBased, 1.0))
+      })
+    })
+    .unwrap();
+    assert_eq!(result.page_types, vec!["text"]);
+    assert!(!std::path::Path::new(&temp_path.unwrap()).exists());
+
+    let metadata = with_temp_pdf(&data, |path| _get_pdf_metadata(path, None)).unwrap();
+    assert_eq!(metadata.title.as_deref(), Some("Quarterly report"));
+
+    // The temporary file is cleaned up on failure too.
+    let mut temp_path = None;
+    let error = with_temp_pdf(b"not a pdf", |path| {
+      temp_path = Some(path.to_string());
+      load_pdf(path, None)
+    })
+    .unwrap_err();
+    assert!(error.reason.starts_with("Failed to load PDF"));
+    assert!(!std::path::Path::new(&temp_path.unwrap()).exists());
+  }
+
+  #[test]
+  fn test_extract_pdf_page_range() {
+    let texts = [
+      "First page of the report",
+      "Second page of the report",
+      "Third page of the report",
+      "Fourth page of the report",
+    ];
+    let pages: Vec<TestPage> = texts
+      .iter()
+      .map(|&text| TestPage {
+        text: Some(text),
+        image: false,
+      })
+      .collect();
+    let document = build_pdf(&pages, None);
+    let page_ids = document.get_pages();
+
+    let range = |start_page: Option<i32>, end_page: Option<i32>| {
+      resolve_page_range(
+        &page_ids,
+        &PdfExtractionOptions {
+          start_page,
+          end_page,
+          ..Default::default()
+        },
+      )
+    };
+    assert_eq!(range(None, None).unwrap(), (1, 4));
+    assert_eq!(range(Some(2), Some(3)).unwrap(), (2, 3));
+    // The end is clamped to the last page.
+    assert_eq!(range(Some(3), Some(10)).unwrap(), (3, 4));
+    for (start, end) in [(Some(0), None), (Some(5), None), (Some(3), Some(2))] {
+      let error = range(start, end).unwrap_err();
+      assert!(matches!(error.status, Status::InvalidArg));
+    }
+
+    let file = write_page_range(&document, 2, 3).unwrap();
+    let copy = Document::load(file.path()).unwrap();
+    assert_eq!(copy.get_pages().len(), 2);
+    assert!(copy.extract_text(&[1]).unwrap().contains(texts[1]));
+    assert!(copy.extract_text(&[2]).unwrap().contains(texts[2]));
+
+    // An invalid range is refused before the document is processed.
+    let file = save_pdf(document);
+    let error = extract_pdf_range_to_markdown(
+      file.path().to_string_lossy().to_string(),
+      PdfExtractionOptions {
+        start_page: Some(5),
+        ..Default::default()
+      },
+    )
+    .err()
+    .unwrap();
+    assert_eq!(
+      error.reason,
+      "Invalid page range 5..4 for a document with 4 pages"
+    );
+  }
 }
@@ -0,0 +1,178 @@
+//! Simplified readability-style content scoring over a kuchikiki tree.
+//!
+//! Paragraph-like elements are scored by their text length and comma count, and the
+//! score is propagated to their parent and grandparent. Candidates are then weighted
+//! by tag, class/id hints, and link density; the best-scoring subtree is taken to be
+//! the main content of the page.
+
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::LazyLock;
+
+use kuchikiki::{Node, NodeRef};
+use regex::Regex;
+
+static POSITIVE_HINT_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+  Regex::new(r"(?i)article|body|content|entry|hentry|h-entry|main|page|post|text|blog|story")
+    .expect("POSITIVE_HINT_REGEX is a valid static regex pattern")
+});
+
+static NEGATIVE_HINT_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+  Regex::new(
+    r"(?i)-ad-|hidden|banner|combx|comment|com-|contact|footer|footnote|masthead|media|meta|modal|nav|outbrain|promo|related|scroll|share|shoutbox|sidebar|skyscraper|sponsor|shopping|social|tags|tool|widget",
+  )
+  .expect("NEGATIVE_HINT_REGEX is a valid static regex pattern")
+});
+
+/// Elements whose own text is scored.
+const SCORED_TAGS: [&str; 6] = ["p", "pre", "td", "blockquote", "section", "div"];
+
+/// Elements considered block-level when deciding whether a div carries its own text.
+const BLOCK_TAGS: [&str; 14] = [
+  "address",
+  "article",
+  "blockquote",
+  "div",
+  "dl",
+  "figure",
+  "form",
+  "ol",
+  "p",
+  "pre",
+  "section",
+  "table",
+  "ul",
+  "main",
+];
+
+/// Text shorter than this is ignored when scoring.
+const MIN_TEXT_LENGTH: usize = 25;
+
+fn tag_name(node: &NodeRef) -> Option<String> {
+  node.as_element().map(|e| e.name.local.to_string())
+}
+
+fn tag_weight(tag: &str) -> f64 {
+  match tag {
+    "article" | "main" => 10.0,
+    "div" => 5.0,
+    "pre" | "td" | "blockquote" => 3.0,
+    "address" | "ol" | "ul" | "dl" | "dd" | "dt" | "li" | "form" => -3.0,
+    "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "th" => -5.0,
+    _ => 0.0,
+  }
+}
+
+fn class_weight(node: &NodeRef) -> f64 {
+  let Some(element) = node.as_element() else {
+    return 0.0;
+  };
+  let attrs = element.attributes.borrow();
+  let mut weight = 0.0;
+
+  for value in [attrs.get("class"), attrs.get("id")].into_iter().flatten() {
+    if NEGATIVE_HINT_REGEX.is_match(value) {
+      weight -= 25.0;
+    }
+    if POSITIVE_HINT_REGEX.is_match(value) {
+      weight += 25.0;
+    }
+  }
+
+  weight
+}
+
+/// Length of the normalized text content of `node`.
+pub(crate) fn text_length(node: &NodeRef) -> usize {
+  node
+    .text_contents()
+    .split_whitespace()
+    .map(|x| x.len() + 1)
+    .sum()
+}
+
+/// Fraction of the text of `node` that sits inside links.
+pub(crate) fn link_density(node: &NodeRef) -> f64 {
+  let total = text_length(node);
+  if total == 0 {
+    return 0.0;
+  }
+
+  let linked: usize = node
+    .descendants()
+    .filter(|x| tag_name(x).as_deref() == Some("a"))
+    .map(|x| text_length(&x))
+    .sum();
+
+  linked as f64 / total as f64
+}
+
+fn has_block_children(node: &NodeRef) -> bool {
+  node
+    .children()
+    .any(|x| tag_name(&x).is_some_and(|t| BLOCK_TAGS.contains(&t.as_str())))
+}
+
+struct Candidate {
+  node: NodeRef,
+  score: f64,
+}
+
+/// Find the subtree of `root` most likely to hold the main content, or `None` when
+/// nothing on the page carries enough text to score.
+pub(crate) fn find_main_content(root: &NodeRef) -> Option<NodeRef> {
+  let mut candidates: Vec<Candidate> = Vec::new();
+  let mut index: HashMap<*const Node, usize> = HashMap::new();
+
+  let mut add_score = |node: &NodeRef, score: f64| {
+    let key = Rc::as_ptr(&node.0);
+    let i = *index.entry(key).or_insert_with(|| {
+      let base = tag_name(node).map(|t| tag_weight(&t)).unwrap_or(0.0) + class_weight(node);
+      candidates.push(Candidate {
+        node: node.clone(),
+        score: base,
+      });
+      candidates.len() - 1
+    });
+    candidates[i].score += score;
+  };
+
+  for node in root.descendants() {
+    let Some(tag) = tag_name(&node) else {
+      continue;
+    };
+    if !SCORED_TAGS.contains(&tag.as_str()) {
+      continue;
+    }
+    // Containers only count when they hold text directly rather than through blocks.
+    if matches!(tag.as_str(), "div" | "section") && has_block_children(&node) {
+      continue;
+    }
+
+    let text = node.text_contents();
+    let length = text.trim().len();
+    if length < MIN_TEXT_LENGTH {
+      continue;
+    }
+
+    let score = 1.0 + text.matches(',').count() as f64 + (length as f64 / 100.0).min(3.0);
+
+    let mut ancestors = node.ancestors().filter(|x| x.as_element().is_some());
+    if let Some(parent) = ancestors.next() {
+      add_score(&parent, score);
+      if let Some(grandparent) = ancestors.next() {
+        add_score(&grandparent, score / 2.0);
+      }
+    }
+  }
+
+  candidates
+    .into_iter()
+    .filter(|x| !matches!(tag_name(&x.node).as_deref(), Some("html" | "body")))
+    .map(|x| {
+      let score = x.score * (1.0 - link_density(&x.node));
+      (x.node, score)
+    })
+    .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+    .map(|(node, _)| node)
+}
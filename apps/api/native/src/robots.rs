@@ -0,0 +1,578 @@
+//! robots.txt parsing and matching following RFC 9309 and Google's reference parser.
+//!
+//! Rules are grouped by the user-agent lines that precede them; a crawler obeys the
+//! groups naming its product token, or the `*` groups when none do. Within the
+//! chosen groups the longest matching pattern decides, and `allow` wins ties.
+
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+use serde::Serialize;
+use tokio::task;
+use url::Url;
+
+#[derive(Serialize, Clone)]
+#[napi(object)]
+pub struct RobotsRule {
+  pub allow: bool,
+  /// Path pattern with non-ASCII characters percent-encoded. `*` matches any run of
+  /// characters and a trailing `$` anchors the end of the URL.
+  pub path: String,
+}
+
+#[derive(Serialize, Default)]
+#[napi(object)]
+pub struct RobotsGroup {
+  /// User agents the group applies to, as written; `*` for every crawler.
+  pub user_agents: Vec<String>,
+  pub rules: Vec<RobotsRule>,
+  pub crawl_delay: Option<f64>,
+}
+
+#[derive(Serialize, Default)]
+#[napi(object)]
+pub struct RobotsRules {
+  pub groups: Vec<RobotsGroup>,
+  /// `Sitemap:` URLs, which apply to the whole file wherever they appear.
+  pub sitemaps: Vec<String>,
+}
+
+/// Content past this size is ignored, like Google does.
+const MAX_ROBOTS_TXT_BYTES: usize = 500 * 1024;
+
+/// Percent-encode non-ASCII bytes and uppercase the hex digits of existing escapes, so
+/// patterns and URL paths compare byte for byte.
+fn escape_pattern(value: &str) -> String {
+  let bytes = value.as_bytes();
+  let mut out = String::with_capacity(value.len());
+  let mut i = 0;
+
+  while i < bytes.len() {
+    let b = bytes[i];
+    if b == b'%'
+      && bytes.get(i + 1).is_some_and(u8::is_ascii_hexdigit)
+      && bytes.get(i + 2).is_some_and(u8::is_ascii_hexdigit)
+    {
+      out.push('%');
+      out.push(bytes[i + 1].to_ascii_uppercase() as char);
+      out.push(bytes[i + 2].to_ascii_uppercase() as char);
+      i += 3;
+    } else if b.is_ascii() {
+      out.push(b as char);
+      i += 1;
+    } else {
+      out.push_str(&format!("%{b:02X}"));
+      i += 1;
+    }
+  }
+
+  out
+}
+
+/// The leading `[A-Za-z_-]` run of a user agent, which is what robots.txt groups
+/// are matched against: `Googlebot/2.1 (+http://...)` becomes `Googlebot`.
+fn product_token(user_agent: &str) -> &str {
+  let user_agent = user_agent.trim_start();
+  let end = user_agent
+    .find(|c: char| !(c.is_ascii_alphabetic() || c == '_' || c == '-'))
+    .unwrap_or(user_agent.len());
+  &user_agent[..end]
+}
+
+/// Split a line into a lowercased key and its value. A missing colon is tolerated
+/// when the line is exactly a key and a value separated by whitespace.
+fn split_line(line: &str) -> Option<(String, &str)> {
+  let (key, value) = match line.split_once(':') {
+    Some(x) => x,
+    None => {
+      let (key, value) = line.split_once(char::is_whitespace)?;
+      if value.trim().contains(char::is_whitespace) {
+        return None;
+      }
+      (key, value)
+    }
+  };
+  Some((key.trim().to_ascii_lowercase(), value.trim()))
+}
+
+pub(crate) fn _parse_robots_txt(content: &str) -> RobotsRules {
+  let mut content = content.strip_prefix('\u{feff}').unwrap_or(content);
+  if content.len() > MAX_ROBOTS_TXT_BYTES {
+    let mut end = MAX_ROBOTS_TXT_BYTES;
+    while !content.is_char_boundary(end) {
+      end -= 1;
+    }
+    content = &content[..end];
+  }
+
+  let mut out = RobotsRules::default();
+  // Whether the current group has had a member line, so that the next user-agent
+  // line starts a new group instead of adding to this one.
+  let mut group_has_members = false;
+
+  for line in content.lines() {
+    let line = line.split('#').next().unwrap_or_default().trim();
+    let Some((key, value)) = split_line(line) else {
+      continue;
+    };
+
+    match key.as_str() {
+      "user-agent" | "useragent" | "user agent" => {
+        if group_has_members || out.groups.is_empty() {
+          out.groups.push(RobotsGroup::default());
+          group_has_members = false;
+        }
+        if let Some(group) = out.groups.last_mut() {
+          group.user_agents.push(value.to_string());
+        }
+      }
+      "allow" | "disallow" | "dissallow" | "dissalow" | "disalow" => {
+        // Rules before the first user-agent line belong to no group.
+        if let Some(group) = out.groups.last_mut() {
+          group_has_members = true;
+          if !value.is_empty() {
+            group.rules.push(RobotsRule {
+              allow: key == "allow",
+              path: escape_pattern(value),
+            });
+          }
+        }
+      }
+      "crawl-delay" => {
+        if let Some(group) = out.groups.last_mut() {
+          group_has_members = true;
+          group.crawl_delay = value
+            .parse()
+            .ok()
+            .filter(|x: &f64| x.is_finite() && *x >= 0.0);
+        }
+      }
+      "sitemap" | "site-map" if !value.is_empty() => {
+        out.sitemaps.push(value.to_string());
+      }
+      _ => {}
+    }
+  }
+
+  out
+}
+
+/// Whether `pattern` matches the start of `path`, with `*` matching any run of bytes
+/// and a trailing `$` requiring the match to reach the end of `path`.
+fn pattern_matches(path: &[u8], pattern: &[u8]) -> bool {
+  // Every position in `path` the pattern read so far can end at.
+  let mut positions = vec![0];
+
+  for (i, &c) in pattern.iter().enumerate() {
+    if c == b'$' && i == pattern.len() - 1 {
+      return positions.contains(&path.len());
+    }
+    if c == b'*' {
+      let first = positions[0];
+      positions = (first..=path.len()).collect();
+      continue;
+    }
+
+    positions = positions
+      .into_iter()
+      .filter(|&p| path.get(p) == Some(&c))
+      .map(|p| p + 1)
+      .collect();
+    if positions.is_empty() {
+      return false;
+    }
+  }
+
+  true
+}
+
+/// Groups a crawler with `user_agent` obeys: those naming its product token, or the
+/// global `*` groups when none do.
+fn groups_for<'a>(rules: &'a RobotsRules, user_agent: &str) -> Vec<&'a RobotsGroup> {
+  let token = product_token(user_agent);
+  let specific: Vec<&RobotsGroup> = rules
+    .groups
+    .iter()
+    .filter(|group| {
+      !token.is_empty()
+        && group
+          .user_agents
+          .iter()
+          .any(|x| product_token(x).eq_ignore_ascii_case(token))
+    })
+    .collect();
+  if !specific.is_empty() {
+    return specific;
+  }
+
+  rules
+    .groups
+    .iter()
+    .filter(|group| group.user_agents.iter().any(|x| x.trim().starts_with('*')))
+    .collect()
+}
+
+pub(crate) fn _robots_is_allowed(rules: &RobotsRules, user_agent: &str, url: &Url) -> bool {
+  let mut path = url.path().to_string();
+  if path == "/robots.txt" {
+    return true;
+  }
+  if let Some(query) = url.query() {
+    path.push('?');
+    path.push_str(query);
+  }
+  let path = escape_pattern(&path);
+
+  let mut allow = None;
+  let mut disallow = None;
+  for rule in groups_for(rules, user_agent)
+    .into_iter()
+    .flat_map(|x| &x.rules)
+  {
+    let mut length =
+      pattern_matches(path.as_bytes(), rule.path.as_bytes()).then_some(rule.path.len());
+    // An allowed `/dir/index.html` allows the directory itself too.
+    if rule.allow && length.is_none() {
+      if let Some(dir) = ["index.html", "index.htm"]
+        .iter()
+        .find_map(|x| rule.path.strip_suffix(x))
+        .filter(|x| x.ends_with('/'))
+      {
+        let pattern = format!("{dir}$");
+        length = pattern_matches(path.as_bytes(), pattern.as_bytes()).then_some(pattern.len());
+      }
+    }
+
+    let best = if rule.allow {
+      &mut allow
+    } else {
+      &mut disallow
+    };
+    if length > *best {
+      *best = length;
+    }
+  }
+
+  allow >= disallow
+}
+
+/// Parse robots.txt into its user-agent groups, rules, crawl delays and sitemaps.
+#[napi]
+pub fn parse_robots_txt(content: String) -> RobotsRules {
+  _parse_robots_txt(&content)
+}
+
+/// Whether robots.txt `content` lets a crawler with `user_agent` fetch `url`. The
+/// user agent may be a full header value; only its product token is matched.
+#[napi]
+pub fn robots_is_allowed(content: String, user_agent: String, url: String) -> Result<bool> {
+  let url =
+    Url::parse(&url).map_err(|e| Error::new(Status::InvalidArg, format!("Invalid URL: {e}")))?;
+  Ok(_robots_is_allowed(
+    &_parse_robots_txt(&content),
+    &user_agent,
+    &url,
+  ))
+}
+
+/// Keep the URLs robots.txt `content` lets a crawler with `user_agent` fetch, in
+/// order. URLs that fail to parse are dropped.
+#[napi]
+pub async fn robots_filter_urls(
+  content: String,
+  user_agent: String,
+  urls: Vec<String>,
+) -> Result<Vec<String>> {
+  task::spawn_blocking(move || {
+    let rules = _parse_robots_txt(&content);
+    urls
+      .into_iter()
+      .filter(|x| Url::parse(x).is_ok_and(|url| _robots_is_allowed(&rules, &user_agent, &url)))
+      .collect()
+  })
+  .await
+  .map_err(|e| {
+    napi::Error::new(
+      napi::Status::GenericFailure,
+      format!("robots_filter_urls join error: {e}"),
+    )
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn allowed(robots_txt: &str, user_agent: &str, url: &str) -> bool {
+    _robots_is_allowed(
+      &_parse_robots_txt(robots_txt),
+      user_agent,
+      &Url::parse(url).unwrap(),
+    )
+  }
+
+  // The cases below follow the test suite of Google's robots.txt parser
+  // (github.com/google/robotstxt, robots_test.cc).
+
+  #[test]
+  fn test_line_syntax() {
+    let url = "http://foo.bar/x/y";
+    assert!(!allowed("user-agent: FooBot\ndisallow: /\n", "FooBot", url));
+    assert!(allowed("foo: FooBot\nbar: /\n", "FooBot", url));
+    assert!(!allowed("user-agent FooBot\ndisallow /\n", "FooBot", url));
+  }
+
+  #[test]
+  fn test_groups() {
+    let robots_txt = "allow: /foo/bar/\n\nuser-agent: FooBot\ndisallow: /\nallow: /x/\nuser-agent: BarBot\ndisallow: /\nallow: /y/\n\n\nallow: /w/\nuser-agent: BazBot\n\nuser-agent: FooBot\nallow: /z/\ndisallow: /\n";
+    let (w, x, y, z, foo) = (
+      "http://foo.bar/w/a",
+      "http://foo.bar/x/b",
+      "http://foo.bar/y/c",
+      "http://foo.bar/z/d",
+      "http://foo.bar/foo/bar/",
+    );
+
+    assert!(allowed(robots_txt, "FooBot", x));
+    assert!(allowed(robots_txt, "FooBot", z));
+    assert!(!allowed(robots_txt, "FooBot", y));
+    assert!(allowed(robots_txt, "BarBot", y));
+    assert!(allowed(robots_txt, "BarBot", w));
+    assert!(!allowed(robots_txt, "BarBot", z));
+    assert!(allowed(robots_txt, "BazBot", z));
+
+    // Rules before the first user-agent line are ignored.
+    assert!(!allowed(robots_txt, "FooBot", foo));
+    assert!(!allowed(robots_txt, "BarBot", foo));
+    assert!(!allowed(robots_txt, "BazBot", foo));
+  }
+
+  #[test]
+  fn test_groups_other_rules() {
+    let url = "http://foo.bar/";
+    let sitemap =
+      "User-agent: BarBot\nSitemap: https://foo.bar/sitemap\nUser-agent: *\nDisallow: /\n";
+    assert!(!allowed(sitemap, "FooBot", url));
+    assert!(!allowed(sitemap, "BarBot", url));
+
+    let unknown = "User-agent: FooBot\nInvalid-Unknown-Line: unknown\nUser-agent: *\nDisallow: /\n";
+    assert!(!allowed(unknown, "FooBot", url));
+    assert!(!allowed(unknown, "BarBot", url));
+  }
+
+  #[test]
+  fn test_case_insensitive_names_and_agents() {
+    let url_allowed = "http://foo.bar/x/y";
+    let url_disallowed = "http://foo.bar/a/b";
+
+    for robots_txt in [
+      "USER-AGENT: FooBot\nALLOW: /x/\nDISALLOW: /\n",
+      "user-agent: FooBot\nallow: /x/\ndisallow: /\n",
+      "uSeR-aGeNt: FooBot\nAlLoW: /x/\ndIsAlLoW: /\n",
+    ] {
+      assert!(allowed(robots_txt, "FooBot", url_allowed));
+      assert!(!allowed(robots_txt, "FooBot", url_disallowed));
+    }
+
+    for robots_txt in [
+      "User-Agent: FOO BAR\nAllow: /x/\nDisallow: /\n",
+      "User-Agent: foo bar\nAllow: /x/\nDisallow: /\n",
+      "User-Agent: FoO bAr\nAllow: /x/\nDisallow: /\n",
+    ] {
+      assert!(allowed(robots_txt, "Foo", url_allowed));
+      assert!(!allowed(robots_txt, "Foo", url_disallowed));
+      assert!(allowed(robots_txt, "foo", url_allowed));
+      assert!(!allowed(robots_txt, "foo", url_disallowed));
+    }
+
+    // Only the product token of a full user agent string is matched.
+    assert!(!allowed(
+      "user-agent: FooBot\ndisallow: /\n",
+      "FooBot/2.1 (+http://foo.bar/bot)",
+      url_allowed
+    ));
+  }
+
+  #[test]
+  fn test_global_groups() {
+    let url = "http://foo.bar/x/y";
+    assert!(allowed("", "FooBot", url));
+
+    let robots_txt = "user-agent: *\nallow: /\nuser-agent: FooBot\ndisallow: /\n";
+    assert!(!allowed(robots_txt, "FooBot", url));
+    assert!(allowed(robots_txt, "BarBot", url));
+    assert!(allowed("user-agent: FooBot\ndisallow: /\n", "BarBot", url));
+
+    // A specific group replaces the global one rather than adding to it.
+    let specific = "User-agent: *\nDisallow: /x/\nUser-agent: FooBot\nDisallow: /y/\n";
+    assert!(allowed(specific, "FooBot", "http://foo.bar/x/page"));
+    assert!(!allowed(specific, "FooBot", "http://foo.bar/y/page"));
+    assert!(!allowed(specific, "BarBot", "http://foo.bar/x/page"));
+  }
+
+  #[test]
+  fn test_values_case_sensitive() {
+    let url = "http://foo.bar/x/y";
+    assert!(!allowed(
+      "user-agent: FooBot\ndisallow: /x/\n",
+      "FooBot",
+      url
+    ));
+    assert!(allowed(
+      "user-agent: FooBot\ndisallow: /X/\n",
+      "FooBot",
+      url
+    ));
+  }
+
+  #[test]
+  fn test_longest_match() {
+    let url = "http://foo.bar/x/page.html";
+    assert!(allowed(
+      "user-agent: FooBot\ndisallow: /x/page.html\nallow: /x/page.html\n",
+      "FooBot",
+      url
+    ));
+    assert!(!allowed(
+      "user-agent: FooBot\nallow: /x/page.html\ndisallow: /x/\n",
+      "FooBot",
+      "http://foo.bar/x/"
+    ));
+    assert!(allowed(
+      "user-agent: FooBot\nallow: /x/page.html\ndisallow: /x/\n",
+      "FooBot",
+      url
+    ));
+    assert!(allowed(
+      "user-agent: FooBot\nallow: \ndisallow: \n",
+      "FooBot",
+      url
+    ));
+    assert!(allowed(
+      "user-agent: FooBot\nallow: /\ndisallow: /\n",
+      "FooBot",
+      url
+    ));
+
+    let dirs = "user-agent: FooBot\ndisallow: /x\nallow: /x/\n";
+    assert!(!allowed(dirs, "FooBot", "http://foo.bar/x"));
+    assert!(allowed(dirs, "FooBot", "http://foo.bar/x/"));
+
+    let wildcard = "user-agent: FooBot\nallow: /page\ndisallow: /*.html\n";
+    assert!(!allowed(wildcard, "FooBot", "http://foo.bar/page.html"));
+    assert!(allowed(wildcard, "FooBot", "http://foo.bar/page"));
+
+    let longer = "user-agent: FooBot\nallow: /x/page.\ndisallow: /*.html\n";
+    assert!(allowed(longer, "FooBot", url));
+    assert!(!allowed(longer, "FooBot", "http://foo.bar/x/y.html"));
+
+    let root = "User-agent: FooBot\nAllow: /$\nDisallow: /\n";
+    assert!(allowed(root, "FooBot", "http://foo.bar/"));
+    assert!(!allowed(root, "FooBot", "http://foo.bar/page.html"));
+  }
+
+  #[test]
+  fn test_encoding() {
+    let query =
+      "User-agent: FooBot\nDisallow: /\nAllow: /foo/bar?qux=taz&baz=http://foo.bar?tar&par\n";
+    assert!(allowed(
+      query,
+      "FooBot",
+      "http://foo.bar/foo/bar?qux=taz&baz=http://foo.bar?tar&par"
+    ));
+
+    let utf8 = "User-agent: FooBot\nDisallow: /\nAllow: /foo/bar/ツ\n";
+    assert!(allowed(utf8, "FooBot", "http://foo.bar/foo/bar/%E3%83%84"));
+
+    let encoded = "User-agent: FooBot\nDisallow: /\nAllow: /foo/bar/%E3%83%84\n";
+    assert!(allowed(
+      encoded,
+      "FooBot",
+      "http://foo.bar/foo/bar/%E3%83%84"
+    ));
+    assert!(allowed(
+      encoded,
+      "FooBot",
+      "http://foo.bar/foo/bar/%e3%83%84"
+    ));
+
+    // Escaped unreserved characters are not decoded.
+    let unreserved = "User-agent: FooBot\nDisallow: /\nAllow: /foo/bar/%62%61%7A\n";
+    assert!(!allowed(unreserved, "FooBot", "http://foo.bar/foo/bar/baz"));
+    assert!(allowed(
+      unreserved,
+      "FooBot",
+      "http://foo.bar/foo/bar/%62%61%7A"
+    ));
+  }
+
+  #[test]
+  fn test_special_characters() {
+    let wildcard = "User-agent: FooBot\nDisallow: /foo/bar/quz\nAllow: /foo/*/qux\n";
+    assert!(!allowed(wildcard, "FooBot", "http://foo.bar/foo/bar/quz"));
+    assert!(allowed(wildcard, "FooBot", "http://foo.bar/foo/quz"));
+    assert!(allowed(wildcard, "FooBot", "http://foo.bar/foo//quz"));
+    assert!(allowed(wildcard, "FooBot", "http://foo.bar/foo/bax/quz"));
+
+    let anchored = "User-agent: FooBot\nDisallow: /foo/bar$\nAllow: /foo/bar/qux\n";
+    assert!(!allowed(anchored, "FooBot", "http://foo.bar/foo/bar"));
+    assert!(allowed(anchored, "FooBot", "http://foo.bar/foo/bar/qux"));
+    assert!(allowed(anchored, "FooBot", "http://foo.bar/foo/bar/"));
+    assert!(allowed(anchored, "FooBot", "http://foo.bar/foo/bar/baz"));
+
+    let comments = "User-agent: FooBot\n# Disallow: /\nDisallow: /foo/quz#qux\nAllow: /\n";
+    assert!(allowed(comments, "FooBot", "http://foo.bar/foo/bar"));
+    assert!(!allowed(comments, "FooBot", "http://foo.bar/foo/quz"));
+  }
+
+  #[test]
+  fn test_index_html_is_directory() {
+    let robots_txt = "User-Agent: *\nAllow: /allowed-slash/index.html\nDisallow: /\n";
+    assert!(allowed(
+      robots_txt,
+      "foobot",
+      "http://foo.com/allowed-slash/"
+    ));
+    assert!(!allowed(
+      robots_txt,
+      "foobot",
+      "http://foo.com/allowed-slash/index.htm"
+    ));
+    assert!(allowed(
+      robots_txt,
+      "foobot",
+      "http://foo.com/allowed-slash/index.html"
+    ));
+    assert!(!allowed(
+      robots_txt,
+      "foobot",
+      "http://foo.com/anyother-url"
+    ));
+  }
+
+  #[test]
+  fn test_robots_txt_always_allowed() {
+    assert!(allowed(
+      "User-agent: *\nDisallow: /\n",
+      "FooBot",
+      "http://foo.bar/robots.txt"
+    ));
+  }
+
+  #[test]
+  fn test_parse_robots_txt_directives() {
+    let rules = _parse_robots_txt(
+      "\u{feff}User-Agent: foo\nAllow: /some/path\nCrawl-delay: 2.5\nUser-Agent: bar\n\n\nSitemap: http://foo.bar/sitemap.xml\nsitemap: http://foo.bar/news.xml\nDisallow: /private\n",
+    );
+    assert_eq!(
+      rules.sitemaps,
+      vec!["http://foo.bar/sitemap.xml", "http://foo.bar/news.xml"]
+    );
+    assert_eq!(rules.groups.len(), 2);
+    assert_eq!(rules.groups[0].user_agents, vec!["foo"]);
+    assert_eq!(rules.groups[0].crawl_delay, Some(2.5));
+    assert_eq!(rules.groups[1].user_agents, vec!["bar"]);
+    assert_eq!(rules.groups[1].crawl_delay, None);
+    assert!(!rules.groups[1].rules[0].allow);
+    assert_eq!(rules.groups[1].rules[0].path, "/private");
+  }
+}
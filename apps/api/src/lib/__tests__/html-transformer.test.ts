@@ -46,10 +46,31 @@ describe("HTML Transformer", () => {
         "./local/page",
         "relative/path",
         "?param=value",
-        "#section",
       ]);
     });
 
+    it("should drop non-crawlable schemes and fragment-only links by default", async () => {
+      const html = `
+        <html>
+          <body>
+            <a href="/page">Page</a>
+            <a href="#section">Hash Link</a>
+            <a href="javascript:void(0)">JavaScript</a>
+            <a href="mailto:test@example.com">Email</a>
+            <a href="tel:+15555550100">Phone</a>
+            <a href="data:text/html,hi">Data</a>
+          </body>
+        </html>
+      `;
+      expect(await extractLinks(html)).toEqual(["/page"]);
+      expect(
+        await extractLinks(html, {
+          includeAnchorOnly: true,
+          includeMailto: true,
+        }),
+      ).toEqual(["/page", "#section", "mailto:test@example.com"]);
+    });
+
     it("should handle complex nested HTML structure", async () => {
       const html = `
         <html>
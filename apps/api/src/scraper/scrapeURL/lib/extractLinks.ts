@@ -49,7 +49,9 @@ async function extractLinksRust(
   html: string,
   baseUrl: string,
 ): Promise<string[]> {
-  const hrefs = await _extractLinks(html);
+  // mailto: links are part of the scrape output; other non-crawlable schemes and
+  // fragment-only links are dropped natively.
+  const hrefs = await _extractLinks(html, { includeMailto: true });
   const baseHref = await _extractBaseHref(html, baseUrl);
   const links: string[] = [];
 